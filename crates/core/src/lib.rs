@@ -2,7 +2,10 @@ pub mod commands;
 pub mod encoding;
 pub mod model;
 pub mod observability;
+pub mod replace;
+pub mod replay;
 pub mod safety;
+pub mod settings;
 pub mod tool;
 pub mod types;
 pub mod validation;
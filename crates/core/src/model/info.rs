@@ -1,10 +1,14 @@
-use std::{collections::HashSet, env, path::PathBuf};
+use std::{
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-use crate::encoding::read_utf8_text_with_report;
+use crate::{encoding::read_utf8_text_with_report, settings::user_config_dir};
 
 use super::capabilities::ProviderCapabilitiesOverride;
 
@@ -343,7 +347,7 @@ fn select_current_model(config: ModelConfigFile) -> Option<ModelInfo> {
 /// 读取模型配置文件。
 fn load_model_config_file() -> Result<Option<ModelConfigFile>> {
     let paths = candidate_config_paths()?;
-    let Some(config_path) = paths.into_iter().find(|path| path.exists()) else {
+    let Some(config_path) = paths.iter().find_map(|path| resolve_config_variant(path)) else {
         return Ok(None);
     };
 
@@ -358,14 +362,75 @@ fn load_model_config_file() -> Result<Option<ModelConfigFile>> {
         return Ok(Some(ModelConfigFile::default()));
     }
 
-    let value: Value = serde_json::from_str(&content)
-        .with_context(|| format!("解析模型配置 JSON 失败: {}", config_path.display()))?;
-    let config = parse_model_config_value(&value)
-        .with_context(|| format!("模型配置结构无效: {}", config_path.display()))?;
-
+    let config = parse_model_config_content(&content, &config_path)?;
     Ok(Some(config))
 }
 
+/// 判断配置路径是否为 TOML 格式（按扩展名）。
+fn is_toml_config_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+/// 在同名的 `.toml` / `.json` 两种配置中选出实际读取的文件。
+///
+/// 两者同时存在时优先 TOML 并给出警告：用户新建 TOML 后往往忘了删旧的 JSON，
+/// 静默二选一会让人误以为修改没有生效。
+fn resolve_config_variant(path: &Path) -> Option<PathBuf> {
+    let toml_path = path.with_extension("toml");
+    if toml_path.exists() {
+        if toml_path != path && path.exists() {
+            eprintln!(
+                "model config warning: {} 与 {} 同时存在，使用 TOML 配置",
+                toml_path.display(),
+                path.display()
+            );
+        }
+        return Some(toml_path);
+    }
+    path.exists().then(|| path.to_path_buf())
+}
+
+/// 计算写入模型配置时使用的路径。
+///
+/// 用户已改用 TOML（同名 `.toml` 已存在）时继续写 TOML，否则写默认的 JSON 路径。
+pub fn resolve_model_config_write_path(json_path: &Path) -> PathBuf {
+    let toml_path = json_path.with_extension("toml");
+    if toml_path.exists() {
+        toml_path
+    } else {
+        json_path.to_path_buf()
+    }
+}
+
+/// 按目标路径的扩展名把模型配置序列化为 TOML 或 JSON 文本（以换行结尾）。
+pub fn serialize_model_config(value: &Value, path: &Path) -> Result<String> {
+    if is_toml_config_path(path) {
+        return toml::to_string_pretty(value).context("序列化模型配置 TOML 失败");
+    }
+    let mut content = serde_json::to_string_pretty(value).context("序列化模型配置失败")?;
+    content.push('\n');
+    Ok(content)
+}
+
+/// 按文件格式解析配置文本。
+///
+/// TOML 先转换为与 JSON 相同的值结构，再复用同一套字段解析，
+/// 保证两种格式支持的字段与别名完全一致。
+fn parse_model_config_content(content: &str, config_path: &Path) -> Result<ModelConfigFile> {
+    let value: Value = if is_toml_config_path(config_path) {
+        let document: toml::Value = toml::from_str(content)
+            .with_context(|| format!("解析模型配置 TOML 失败: {}", config_path.display()))?;
+        serde_json::to_value(document)
+            .with_context(|| format!("转换模型配置 TOML 失败: {}", config_path.display()))?
+    } else {
+        serde_json::from_str(content)
+            .with_context(|| format!("解析模型配置 JSON 失败: {}", config_path.display()))?
+    };
+    parse_model_config_value(&value)
+        .with_context(|| format!("模型配置结构无效: {}", config_path.display()))
+}
+
 /// 列出可用配置路径候选。
 fn candidate_config_paths() -> Result<Vec<PathBuf>> {
     let mut paths = Vec::new();
@@ -385,6 +450,10 @@ fn candidate_config_paths() -> Result<Vec<PathBuf>> {
     ] {
         paths.push(current_dir.join(relative));
     }
+    // 用户全局配置优先级最低：项目内任一配置存在时都以项目为准。
+    if let Some(user_dir) = user_config_dir() {
+        paths.push(user_dir.join("model.json"));
+    }
 
     // 去重，避免同一路径被重复解析。
     let mut seen = HashSet::new();
@@ -818,6 +887,110 @@ mod tests {
         assert_eq!(current.model_name, "gemini-2.0-flash");
     }
 
+    #[test]
+    fn parse_toml_config_should_match_equivalent_json() {
+        let json_config = parse_model_config_content(
+            r#"{
+                "current_model": "gemini-2.0-flash",
+                "models": [
+                    {"provider": "openai", "model": "gpt-4o-mini", "api_key": "abc"},
+                    {"provider": "gemini", "model": "gemini-2.0-flash", "support_tools": false,
+                     "model_max_context": 1000000, "default_max_turns": 8}
+                ]
+            }"#,
+            Path::new("model.json"),
+        )
+        .expect("JSON 配置应解析成功");
+        let toml_config = parse_model_config_content(
+            r#"
+current_model = "gemini-2.0-flash"
+
+[[models]]
+provider = "openai"
+model = "gpt-4o-mini"
+api_key = "abc"
+
+[[models]]
+provider = "gemini"
+model = "gemini-2.0-flash"
+support_tools = false
+model_max_context = 1000000
+default_max_turns = 8
+"#,
+            Path::new("model.toml"),
+        )
+        .expect("TOML 配置应解析成功");
+
+        let to_value = |config: ModelConfigFile| {
+            let models = serde_json::to_value(&config.models).expect("序列化模型列表失败");
+            let current = select_current_model(config).expect("应选出当前模型");
+            (
+                models,
+                serde_json::to_value(current).expect("序列化模型失败"),
+            )
+        };
+        assert_eq!(
+            to_value(json_config),
+            to_value(toml_config),
+            "等价的 TOML 与 JSON 应得到相同的模型信息"
+        );
+
+        assert!(
+            parse_model_config_content("current_model = ", Path::new("model.toml")).is_err(),
+            "非法 TOML 应报错"
+        );
+    }
+
+    #[test]
+    fn model_config_toml_should_round_trip_and_take_precedence() {
+        let value = json!({
+            "current": {
+                "provider": "codex",
+                "model": "gpt-5.3-codex",
+                "api_url": "",
+                "token": "",
+                "support_tools": true,
+                "default_max_turns": 12
+            },
+            "models": []
+        });
+        let toml_text = serialize_model_config(&value, Path::new(".order/model.toml"))
+            .expect("序列化 TOML 失败");
+        let config = parse_model_config_content(&toml_text, Path::new("model.toml"))
+            .expect("写出的 TOML 应能读回");
+        let current = select_current_model(config).expect("应有当前模型");
+        assert_eq!(current.provider_name, "codex");
+        assert_eq!(current.default_max_turns, 12);
+        assert!(
+            serialize_model_config(&value, Path::new("model.json"))
+                .expect("序列化 JSON 失败")
+                .ends_with("}\n")
+        );
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let dir = env::temp_dir().join(format!("order-model-toml-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("创建临时目录失败");
+        let json_path = dir.join("model.json");
+        let toml_path = dir.join("model.toml");
+
+        std::fs::write(&json_path, "{}").expect("写入 JSON 失败");
+        assert_eq!(resolve_config_variant(&json_path), Some(json_path.clone()));
+        assert_eq!(resolve_model_config_write_path(&json_path), json_path);
+
+        std::fs::write(&toml_path, "").expect("写入 TOML 失败");
+        assert_eq!(
+            resolve_config_variant(&json_path),
+            Some(toml_path.clone()),
+            "两者都存在时优先 TOML"
+        );
+        assert_eq!(resolve_model_config_write_path(&json_path), toml_path);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn parse_bool_text_works() {
         assert!(parse_bool_text("true"));
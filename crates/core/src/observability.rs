@@ -105,6 +105,9 @@ pub enum AgentEvent {
         ts: String,
         trace_id: String,
         tool: String,
+        /// 调用参数（写入类工具不含正文），供 `/replay` 重建与重放调用序列；旧日志中没有该字段。
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        args: Option<serde_json::Value>,
     },
     /// tool 调用结束。
     ToolCallEnd {
@@ -0,0 +1,310 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    safety::{ExecutionGuard, PendingWriteSummary},
+    tool::{search_file::is_default_ignored_directory_name, workspace::MAX_READ_BYTES},
+};
+
+/// 每扫描多少个文件上报一次进度。
+const PROGRESS_EVERY_FILES: usize = 50;
+
+/// 单个文件的搜索命中结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceFileMatch {
+    /// 工作区相对路径（统一使用 `/` 分隔）。
+    pub path: String,
+    /// 命中次数（不重叠计数，与替换次数一致）。
+    pub match_count: usize,
+    /// 首个命中所在行（从 1 开始）及其内容，用于确认时展示。
+    pub first_line: (usize, String),
+    /// 搜索时的内容指纹，替换前据此判断文件是否已被改动。
+    pub content_hash: u64,
+}
+
+/// 已组装好、等待 stage 的单文件替换结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedReplaceEdit {
+    pub path: String,
+    /// 替换后的完整文件内容。
+    pub content: String,
+    /// 实际替换次数。
+    pub replaced: usize,
+}
+
+/// 组装替换的整体结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplacePlan {
+    pub edits: Vec<StagedReplaceEdit>,
+    /// 搜索后已被改动（或已无法读取）而跳过的文件及原因。
+    pub skipped: Vec<(String, String)>,
+    /// 是否在组装中途被取消。
+    pub cancelled: bool,
+}
+
+/// 计算文本内容指纹。
+///
+/// 只用于同一进程内“搜索后是否被改动”的比较，不需要跨进程稳定，
+/// 因此直接使用标准库哈希，避免为此引入额外依赖。
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 判断文件自搜索以来是否已被改动。
+pub fn is_stale(recorded_hash: u64, current_content: &str) -> bool {
+    content_hash(current_content) != recorded_hash
+}
+
+/// 在工作区内递归搜索包含 `pattern` 的文本文件。
+///
+/// 跳过规则与 `SearchFileTool` 一致（`.git`、`target`、`.order` 等噪声目录），
+/// 另外跳过超过读取上限或非 UTF-8 的文件：这类文件不适合做文本替换。
+/// `cancel` 被置位时立即以错误返回；`on_progress` 定期收到已扫描的文件数。
+pub fn search_workspace(
+    workspace_root: &Path,
+    pattern: &str,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize),
+) -> Result<Vec<ReplaceFileMatch>> {
+    if pattern.is_empty() {
+        return Err(anyhow!("搜索内容不能为空"));
+    }
+
+    let mut matches = Vec::new();
+    let mut scanned = 0;
+    let mut pending_dirs = vec![workspace_root.to_path_buf()];
+    while let Some(dir) = pending_dirs.pop() {
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries = read_dir.flatten().collect::<Vec<_>>();
+        // 按名称排序，保证结果顺序稳定，逐个确认时不会因文件系统顺序而跳动。
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries.into_iter().rev() {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(anyhow!("搜索已取消"));
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                if !is_default_ignored_directory_name(&name.to_string_lossy()) {
+                    pending_dirs.push(path);
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+
+            scanned += 1;
+            if scanned % PROGRESS_EVERY_FILES == 0 {
+                on_progress(scanned);
+            }
+            let too_large = entry
+                .metadata()
+                .map(|metadata| metadata.len() > MAX_READ_BYTES)
+                .unwrap_or(true);
+            if too_large {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Some(file_match) = match_file(workspace_root, &path, &content, pattern) {
+                matches.push(file_match);
+            }
+        }
+    }
+    on_progress(scanned);
+    matches.sort_by(|left, right| left.path.cmp(&right.path));
+    Ok(matches)
+}
+
+// 统计单个文件的命中情况。
+fn match_file(
+    workspace_root: &Path,
+    path: &Path,
+    content: &str,
+    pattern: &str,
+) -> Option<ReplaceFileMatch> {
+    let match_count = content.matches(pattern).count();
+    if match_count == 0 {
+        return None;
+    }
+    let first_line = content
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.contains(pattern))
+        .map(|(index, line)| (index + 1, line.trim().to_string()))
+        .unwrap_or_default();
+    let relative = path.strip_prefix(workspace_root).unwrap_or(path);
+    Some(ReplaceFileMatch {
+        path: relative.to_string_lossy().replace('\\', "/"),
+        match_count,
+        first_line,
+        content_hash: content_hash(content),
+    })
+}
+
+/// 基于用户确认的文件组装替换内容。
+///
+/// 每个文件都重新读取并与搜索时的指纹比对：搜索之后被改动过的文件直接跳过，
+/// 避免把基于旧内容的替换覆盖到用户（或模型）的新改动上。
+pub fn assemble_replace_plan(
+    workspace_root: &Path,
+    accepted: &[ReplaceFileMatch],
+    pattern: &str,
+    replacement: &str,
+    cancel: &AtomicBool,
+) -> ReplacePlan {
+    let mut plan = ReplacePlan::default();
+    for file_match in accepted {
+        if cancel.load(Ordering::Relaxed) {
+            plan.cancelled = true;
+            return plan;
+        }
+        let path = workspace_root.join(&file_match.path);
+        let current = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) => {
+                plan.skipped
+                    .push((file_match.path.clone(), format!("读取失败：{error}")));
+                continue;
+            }
+        };
+        if is_stale(file_match.content_hash, &current) {
+            plan.skipped
+                .push((file_match.path.clone(), "搜索后文件已被修改".to_string()));
+            continue;
+        }
+        let replaced = current.matches(pattern).count();
+        if replaced == 0 {
+            continue;
+        }
+        plan.edits.push(StagedReplaceEdit {
+            path: file_match.path.clone(),
+            content: current.replace(pattern, replacement),
+            replaced,
+        });
+    }
+    plan
+}
+
+/// 将替换结果统一 stage 到同一个 trace_id 下。
+///
+/// 复用写入确认与快照流程：确认后一次性落盘，`/rollback <trace_id>` 可整体回滚。
+/// 任一文件 stage 失败时撤销整个 trace，避免留下只有部分文件的待确认写入。
+pub fn stage_replace_plan(
+    guard: &ExecutionGuard,
+    trace_id: &str,
+    edits: &[StagedReplaceEdit],
+) -> Result<Vec<PendingWriteSummary>> {
+    let mut summaries = Vec::with_capacity(edits.len());
+    for edit in edits {
+        match guard.stage_write(trace_id, &edit.path, &edit.content, false) {
+            Ok(summary) => summaries.push(summary),
+            Err(error) => {
+                let _ = guard.reject_pending_writes(trace_id);
+                return Err(error.context(format!("暂存替换失败：{}", edit.path)));
+            }
+        }
+    }
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::atomic::AtomicBool,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{assemble_replace_plan, is_stale, search_workspace};
+
+    fn temp_workspace() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let root =
+            std::env::temp_dir().join(format!("order-replace-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(root.join("src")).expect("创建临时目录失败");
+        fs::create_dir_all(root.join("target")).expect("创建临时目录失败");
+        root
+    }
+
+    #[test]
+    fn assemble_replace_plan_should_build_edits_and_skip_stale_files() {
+        let root = temp_workspace();
+        fs::write(root.join("src/a.rs"), "let old = old_value;\n").expect("写入文件失败");
+        fs::write(root.join("src/b.rs"), "fn old() {}\n").expect("写入文件失败");
+        fs::write(root.join("target/c.rs"), "old\n").expect("写入文件失败");
+        fs::write(root.join("README.md"), "nothing here\n").expect("写入文件失败");
+
+        let cancel = AtomicBool::new(false);
+        let mut progress = Vec::new();
+        let matches = search_workspace(&root, "old", &cancel, |scanned| progress.push(scanned))
+            .expect("搜索失败");
+        assert_eq!(
+            matches
+                .iter()
+                .map(|item| (item.path.as_str(), item.match_count))
+                .collect::<Vec<_>>(),
+            vec![("src/a.rs", 2), ("src/b.rs", 1)],
+            "应跳过 target 目录并按路径排序"
+        );
+        assert_eq!(
+            matches[0].first_line,
+            (1, "let old = old_value;".to_string())
+        );
+        assert_eq!(progress.last(), Some(&3));
+
+        // 搜索之后 b.rs 被改动，应跳过并给出原因。
+        fs::write(root.join("src/b.rs"), "fn old() {}\nfn old2() {}\n").expect("写入文件失败");
+        let plan = assemble_replace_plan(&root, &matches, "old", "new", &cancel);
+        assert!(!plan.cancelled);
+        assert_eq!(plan.edits.len(), 1);
+        assert_eq!(plan.edits[0].path, "src/a.rs");
+        assert_eq!(plan.edits[0].content, "let new = new_value;\n");
+        assert_eq!(plan.edits[0].replaced, 2);
+        assert_eq!(
+            plan.skipped,
+            vec![("src/b.rs".to_string(), "搜索后文件已被修改".to_string())]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn assemble_replace_plan_should_stop_when_cancelled() {
+        let root = temp_workspace();
+        fs::write(root.join("src/a.rs"), "old\n").expect("写入文件失败");
+        let cancel = AtomicBool::new(false);
+        let matches = search_workspace(&root, "old", &cancel, |_| {}).expect("搜索失败");
+
+        let cancelled = AtomicBool::new(true);
+        let plan = assemble_replace_plan(&root, &matches, "old", "new", &cancelled);
+        assert!(plan.cancelled);
+        assert!(plan.edits.is_empty());
+        assert!(search_workspace(&root, "old", &cancelled, |_| {}).is_err());
+
+        assert!(!is_stale(matches[0].content_hash, "old\n"));
+        assert!(is_stale(matches[0].content_hash, "old\n\n"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
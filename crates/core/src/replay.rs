@@ -0,0 +1,277 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use rig::tool::Tool;
+use serde_json::Value;
+
+use crate::{
+    encoding::read_utf8_text_with_report,
+    observability::AgentEvent,
+    tool::{
+        read::{ReadTool, ReadToolArgs},
+        search_file::{SearchFileTool, SearchFileToolArgs},
+    },
+};
+
+/// 允许重放的只读工具。
+///
+/// 写入类工具一律不重放；命令工具同样可能修改工作区，也不在此列。
+const READ_ONLY_TOOLS: &[&str] = &[ReadTool::NAME, SearchFileTool::NAME];
+
+/// 日志中记录的一次工具调用。
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedToolCall {
+    pub tool: String,
+    /// 调用参数；旧版本日志没有记录参数，此时为 `None`，无法重放。
+    pub args: Option<Value>,
+    /// 调用是否成功；缺少对应结束事件（例如进程中途退出）时为 `None`。
+    pub ok: Option<bool>,
+    pub duration_ms: Option<u128>,
+    pub error: Option<String>,
+}
+
+impl RecordedToolCall {
+    // 是否属于允许重放的只读工具。
+    pub fn is_read_only(&self) -> bool {
+        READ_ONLY_TOOLS.contains(&self.tool.as_str())
+    }
+
+    /// 单行展示的参数摘要，例如 `path=src/lib.rs keyword=foo`。
+    pub fn args_summary(&self) -> String {
+        let Some(Value::Object(args)) = self.args.as_ref() else {
+            return "（未记录参数）".to_string();
+        };
+        args.iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| match value {
+                Value::String(text) => format!("{key}={text}"),
+                other => format!("{key}={other}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 从事件流中重建指定 trace 的工具调用序列。
+///
+/// 结束事件只带工具名，这里按“同名工具先开始先结束”配对；
+/// 没有对应开始事件的结束事件（例如能力缓存回写失败的记录）不属于工具调用，直接忽略。
+pub fn tool_sequence_for_trace(events: &[AgentEvent], trace_id: &str) -> Vec<RecordedToolCall> {
+    let mut calls: Vec<RecordedToolCall> = Vec::new();
+    for event in events {
+        match event {
+            AgentEvent::ToolCallStart {
+                trace_id: event_trace,
+                tool,
+                args,
+                ..
+            } if event_trace == trace_id => calls.push(RecordedToolCall {
+                tool: tool.clone(),
+                args: args.clone(),
+                ok: None,
+                duration_ms: None,
+                error: None,
+            }),
+            AgentEvent::ToolCallEnd {
+                trace_id: event_trace,
+                tool,
+                ok,
+                duration_ms,
+                error,
+                ..
+            } if event_trace == trace_id => {
+                if let Some(call) = calls
+                    .iter_mut()
+                    .find(|call| call.tool == *tool && call.ok.is_none())
+                {
+                    call.ok = Some(*ok);
+                    call.duration_ms = Some(*duration_ms);
+                    call.error = error.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    calls
+}
+
+/// 最近一次发生过工具调用的 trace_id。
+pub fn last_tool_trace_id(events: &[AgentEvent]) -> Option<String> {
+    events.iter().rev().find_map(|event| match event {
+        AgentEvent::ToolCallStart { trace_id, .. } => Some(trace_id.clone()),
+        _ => None,
+    })
+}
+
+/// 筛选出可以重放的调用：只读工具且日志中记录了参数。
+pub fn read_only_replay_plan(calls: &[RecordedToolCall]) -> Vec<&RecordedToolCall> {
+    calls
+        .iter()
+        .filter(|call| call.is_read_only() && call.args.is_some())
+        .collect()
+}
+
+/// 按时间顺序读取 `.order/logs/` 下的全部事件。
+///
+/// 日志文件名为 `agent-YYYYMMDD.log`，按文件名排序即按日期排序；
+/// 无法解析的行直接跳过，`/status` 已负责提示日志损坏。
+pub fn load_logged_events(workspace_root: &Path) -> Result<Vec<AgentEvent>> {
+    let logs_dir = workspace_root.join(".order").join("logs");
+    if !logs_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = fs::read_dir(&logs_dir)
+        .with_context(|| format!("读取日志目录失败: {}", logs_dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("agent-") && name.ends_with(".log"))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut events = Vec::new();
+    for path in paths {
+        let (content, _) = read_utf8_text_with_report(&path)
+            .with_context(|| format!("读取日志失败: {}", path.display()))?;
+        events.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .filter_map(|line| serde_json::from_str::<AgentEvent>(line).ok()),
+        );
+    }
+    Ok(events)
+}
+
+/// 重放一次只读工具调用，返回结果摘要。
+///
+/// 非只读工具在这里再次拒绝，即使调用方绕过了 [`read_only_replay_plan`] 也不会产生写入。
+pub async fn replay_read_only_call(call: &RecordedToolCall) -> Result<String> {
+    if !call.is_read_only() {
+        return Err(anyhow!("{} 不是只读工具，拒绝重放", call.tool));
+    }
+    let args = call
+        .args
+        .clone()
+        .ok_or_else(|| anyhow!("日志中没有记录 {} 的参数，无法重放", call.tool))?;
+
+    match call.tool.as_str() {
+        ReadTool::NAME => {
+            let args: ReadToolArgs =
+                serde_json::from_value(args).context("解析 ReadTool 参数失败")?;
+            let content = ReadTool.call(args).await?;
+            Ok(format!(
+                "{} 行，{} 字节",
+                content.lines().count(),
+                content.len()
+            ))
+        }
+        SearchFileTool::NAME => {
+            let args: SearchFileToolArgs =
+                serde_json::from_value(args).context("解析 SearchFileTool 参数失败")?;
+            let matches = SearchFileTool.call(args).await?;
+            Ok(format!("{} 条匹配", matches.len()))
+        }
+        other => Err(anyhow!("{other} 不是只读工具，拒绝重放")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{last_tool_trace_id, read_only_replay_plan, tool_sequence_for_trace};
+    use crate::observability::AgentEvent;
+
+    fn start(trace_id: &str, tool: &str, args: Option<serde_json::Value>) -> AgentEvent {
+        AgentEvent::ToolCallStart {
+            ts: "2026-02-08T10:00:00+08:00".to_string(),
+            trace_id: trace_id.to_string(),
+            tool: tool.to_string(),
+            args,
+        }
+    }
+
+    fn end(trace_id: &str, tool: &str, ok: bool) -> AgentEvent {
+        AgentEvent::ToolCallEnd {
+            ts: "2026-02-08T10:00:01+08:00".to_string(),
+            trace_id: trace_id.to_string(),
+            tool: tool.to_string(),
+            ok,
+            duration_ms: 5,
+            error: (!ok).then(|| "失败".to_string()),
+        }
+    }
+
+    #[test]
+    fn tool_sequence_should_pair_events_for_trace() {
+        let events = [
+            start("t1", "ReadTool", Some(json!({ "path": "a.rs" }))),
+            start("t2", "ReadTool", Some(json!({ "path": "other.rs" }))),
+            end("t1", "ReadTool", true),
+            start(
+                "t1",
+                "SearchFileTool",
+                Some(json!({ "path": ".", "keyword": "foo" })),
+            ),
+            end("t1", "capability_cache_writeback", false),
+            end("t1", "SearchFileTool", false),
+            start("t1", "WriteTool", Some(json!({ "path": "a.rs" }))),
+        ];
+
+        let calls = tool_sequence_for_trace(&events, "t1");
+        assert_eq!(
+            calls
+                .iter()
+                .map(|call| (call.tool.as_str(), call.ok))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ReadTool", Some(true)),
+                ("SearchFileTool", Some(false)),
+                ("WriteTool", None),
+            ],
+            "应只包含该 trace 的调用，并忽略没有开始事件的结束记录"
+        );
+        assert_eq!(calls[1].args_summary(), "keyword=foo path=.");
+        assert_eq!(calls[1].error.as_deref(), Some("失败"));
+        assert_eq!(last_tool_trace_id(&events).as_deref(), Some("t1"));
+    }
+
+    #[test]
+    fn replay_plan_should_keep_only_read_only_calls_with_args() {
+        let events = [
+            start("t1", "ReadTool", Some(json!({ "path": "a.rs" }))),
+            start("t1", "WriteTool", Some(json!({ "path": "a.rs" }))),
+            start("t1", "CommandTool", Some(json!({ "program": "ls" }))),
+            start("t1", "SearchFileTool", None),
+            start(
+                "t1",
+                "SearchFileTool",
+                Some(json!({ "path": "src", "keyword": "x" })),
+            ),
+        ];
+        let calls = tool_sequence_for_trace(&events, "t1");
+
+        let plan = read_only_replay_plan(&calls);
+        assert_eq!(
+            plan.iter()
+                .map(|call| call.tool.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ReadTool", "SearchFileTool"],
+            "写入与命令工具不重放，缺少参数的旧日志也无法重放"
+        );
+        assert_eq!(calls[3].args_summary(), "（未记录参数）");
+    }
+
+    #[tokio::test]
+    async fn replay_should_refuse_write_tools() {
+        let events = [start("t1", "WriteTool", Some(json!({ "path": "a.rs" })))];
+        let calls = tool_sequence_for_trace(&events, "t1");
+        assert!(super::replay_read_only_call(&calls[0]).await.is_err());
+    }
+}
@@ -409,18 +409,22 @@ fn snapshot_trace_dir(workspace_root: &Path, trace_id: &str) -> PathBuf {
 
 /// 当前进程是否启用“保留写入快照”。
 fn keep_snapshots_enabled() -> bool {
-    match env::var("ORDER_KEEP_SNAPSHOTS") {
-        Ok(value) => parse_env_truthy(&value),
-        Err(_) => false,
-    }
+    env_flag(env::var("ORDER_KEEP_SNAPSHOTS").ok().as_deref(), false)
 }
 
-/// 将环境变量文本解析为布尔值（真值集合）。
-fn parse_env_truthy(value: &str) -> bool {
-    matches!(
-        value.trim().to_ascii_lowercase().as_str(),
-        "1" | "true" | "yes" | "on"
-    )
+/// 解析开关型环境变量。
+///
+/// `1/true/yes/on` 为开、`0/false/no/off` 为关（忽略大小写与首尾空白）；
+/// 未设置或无法识别时返回 `default`，拼写错误不会把开关翻到默认值的反面。
+pub fn env_flag(value: Option<&str>, default: bool) -> bool {
+    match value
+        .map(|value| value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("1" | "true" | "yes" | "on") => true,
+        Some("0" | "false" | "no" | "off") => false,
+        _ => default,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -683,7 +687,7 @@ fn is_suspicious_placeholder_text(trimmed: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::{
-        cleanup_snapshot_after_apply, is_suspicious_placeholder_text, parse_env_truthy,
+        cleanup_snapshot_after_apply, env_flag, is_suspicious_placeholder_text,
         validate_write_content,
     };
     use std::{
@@ -739,15 +743,27 @@ mod tests {
     }
 
     #[test]
-    fn parse_env_truthy_should_match_expected_values() {
-        assert!(parse_env_truthy("1"));
-        assert!(parse_env_truthy("true"));
-        assert!(parse_env_truthy(" YES "));
-        assert!(parse_env_truthy("On"));
-        assert!(!parse_env_truthy("0"));
-        assert!(!parse_env_truthy("false"));
-        assert!(!parse_env_truthy("off"));
-        assert!(!parse_env_truthy("random"));
+    fn env_flag_should_fall_back_to_default() {
+        assert!(env_flag(Some(" On "), false));
+        assert!(!env_flag(Some("0"), true));
+        assert!(!env_flag(Some("no"), true));
+        assert!(!env_flag(None, false), "未设置时返回默认值");
+        assert!(env_flag(None, true));
+        assert!(!env_flag(Some("random"), false), "无法识别时返回默认值");
+        assert!(env_flag(Some("random"), true));
+    }
+
+    #[test]
+    fn env_flag_should_match_expected_values() {
+        assert!(env_flag(Some("1"), false));
+        assert!(env_flag(Some("true"), false));
+        assert!(env_flag(Some(" YES "), false));
+        assert!(env_flag(Some("On"), false));
+        assert!(!env_flag(Some("0"), true));
+        assert!(!env_flag(Some("false"), true));
+        assert!(!env_flag(Some("off"), true));
+        assert!(!env_flag(Some(" OFF "), true));
+        assert!(!env_flag(Some("random"), false));
     }
 
     #[test]
@@ -0,0 +1,296 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::encoding::read_utf8_text_with_report;
+
+/// 配置文件所在层级，按优先级从低到高排列。
+///
+/// 内置默认值由配置结构体的 `Default` 提供，环境变量在文件之后叠加，
+/// 两者都没有对应文件，因此不在这里列出。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SettingsLayer {
+    /// 用户全局配置：`~/.config/order/<file>`。
+    User,
+    /// 项目配置：`<workspace>/.order/<file>`。
+    Project,
+}
+
+/// 叠加后的配置结果。
+#[derive(Debug, Clone, Default)]
+pub struct LayeredSettings {
+    /// 各层按优先级深度合并后的值。
+    pub value: Value,
+    /// 实际参与合并的文件来源，按优先级从低到高排列。
+    pub sources: Vec<(SettingsLayer, PathBuf)>,
+}
+
+impl LayeredSettings {
+    /// 将合并结果反序列化为具体配置结构。
+    ///
+    /// 目标结构需标注 `#[serde(default)]`，缺省字段即回退到内置默认值，
+    /// 这就是“默认值”这一层的实现方式。
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        let value = if self.value.is_null() {
+            Value::Object(Map::new())
+        } else {
+            self.value.clone()
+        };
+        serde_json::from_value(value).context("合并后的配置结构无效")
+    }
+}
+
+/// 用户全局配置目录。
+///
+/// 优先使用 `XDG_CONFIG_HOME`，否则回退到 `~/.config`；
+/// Windows 上同样放在用户目录下的 `.config`，保证各平台的文件布局一致。
+pub fn user_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join("order"));
+    }
+    let home = if cfg!(windows) {
+        env::var_os("USERPROFILE")
+    } else {
+        env::var_os("HOME")
+    };
+    home.filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(".config").join("order"))
+}
+
+/// 按“默认值 < 用户全局 < 项目 < 环境变量”的顺序加载并合并配置。
+///
+/// 参数：
+/// - `workspace_root`：工作区根目录，项目配置位于其下的 `.order/`；
+/// - `file_name`：配置文件名（如 `editor.json`、`validation.toml`），按扩展名选择解析格式；
+/// - `env_keys`：`(环境变量名, 配置键)` 映射，配置键支持用 `.` 表示嵌套字段。
+pub fn load_layered_settings(
+    workspace_root: &Path,
+    file_name: &str,
+    env_keys: &[(&str, &str)],
+) -> Result<LayeredSettings> {
+    let env_values = env_keys
+        .iter()
+        .map(|(name, key)| (*key, env::var(name).ok()))
+        .collect::<Vec<_>>();
+    load_layers(
+        user_config_dir().as_deref(),
+        workspace_root,
+        file_name,
+        &env_values,
+    )
+}
+
+/// 加载配置并直接反序列化为目标结构。
+pub fn load_settings<T: DeserializeOwned>(
+    workspace_root: &Path,
+    file_name: &str,
+    env_keys: &[(&str, &str)],
+) -> Result<T> {
+    load_layered_settings(workspace_root, file_name, env_keys)?.deserialize()
+}
+
+/// 将 `overlay` 深度合并进 `base`：对象逐键递归合并，其余类型整体替换。
+///
+/// 数组整体替换而不是拼接，避免项目配置无法“去掉”全局配置里的某项命令。
+pub fn merge_settings_value(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                merge_settings_value(base_map.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// 依次读取各层并合并；用户目录与环境变量由参数传入，便于测试隔离真实环境。
+fn load_layers(
+    user_dir: Option<&Path>,
+    workspace_root: &Path,
+    file_name: &str,
+    env_values: &[(&str, Option<String>)],
+) -> Result<LayeredSettings> {
+    let mut settings = LayeredSettings::default();
+    let candidates = [
+        (SettingsLayer::User, user_dir.map(|dir| dir.join(file_name))),
+        (
+            SettingsLayer::Project,
+            Some(workspace_root.join(".order").join(file_name)),
+        ),
+    ];
+    for (layer, path) in candidates {
+        let Some(path) = path.filter(|path| path.exists()) else {
+            continue;
+        };
+        let value = read_settings_file(&path)?;
+        merge_settings_value(&mut settings.value, value);
+        settings.sources.push((layer, path));
+    }
+
+    for (key, raw) in env_values {
+        let Some(raw) = raw.as_deref().map(str::trim).filter(|raw| !raw.is_empty()) else {
+            continue;
+        };
+        merge_settings_value(&mut settings.value, env_value_at(key, raw));
+    }
+    Ok(settings)
+}
+
+// 读取单个配置文件，`.toml` 按 TOML 解析，其余按 JSON 解析。
+fn read_settings_file(path: &Path) -> Result<Value> {
+    let (content, report) = read_utf8_text_with_report(path)
+        .with_context(|| format!("读取配置失败: {}", path.display()))?;
+    if report.has_warning() {
+        for warning in report.warnings_for(path) {
+            eprintln!("settings encoding warning: {warning}");
+        }
+    }
+    if content.trim().is_empty() {
+        return Ok(Value::Object(Map::new()));
+    }
+
+    let is_toml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    if is_toml {
+        let value: toml::Value = toml::from_str(&content)
+            .with_context(|| format!("解析配置失败: {}", path.display()))?;
+        serde_json::to_value(value).with_context(|| format!("转换配置失败: {}", path.display()))
+    } else {
+        serde_json::from_str(&content).with_context(|| format!("解析配置失败: {}", path.display()))
+    }
+}
+
+/// 把环境变量值放到配置键对应的嵌套位置。
+///
+/// 取值先按 JSON 解析（支持 `true`、数字与数组），失败时按普通字符串处理，
+/// 这样 `ORDER_X=above` 无需额外加引号。
+fn env_value_at(key: &str, raw: &str) -> Value {
+    let mut value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    for segment in key.rsplit('.') {
+        let mut object = Map::new();
+        object.insert(segment.to_string(), value);
+        value = Value::Object(object);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use serde_json::json;
+
+    use super::{SettingsLayer, load_layers, merge_settings_value};
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!(
+            "order-settings-{tag}-{}-{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).expect("创建临时目录失败");
+        dir
+    }
+
+    #[test]
+    fn load_layers_should_apply_precedence_across_layers() {
+        let user_dir = temp_dir("user");
+        let workspace = temp_dir("workspace");
+        fs::create_dir_all(workspace.join(".order")).expect("创建 .order 目录失败");
+        fs::write(
+            user_dir.join("editor.json"),
+            r#"{ "completion_placement": "above", "will_save_preview": true, "format": { "indent_size": 2, "tab_width": 8 } }"#,
+        )
+        .expect("写入用户配置失败");
+        fs::write(
+            workspace.join(".order").join("editor.json"),
+            r#"{ "completion_placement": "below", "format": { "indent_size": 4 } }"#,
+        )
+        .expect("写入项目配置失败");
+
+        // 仅有默认值：没有任何来源。
+        let empty = temp_dir("empty");
+        let defaults = load_layers(None, &empty, "editor.json", &[]).expect("无配置文件时应成功");
+        assert!(defaults.sources.is_empty());
+        assert!(defaults.value.is_null());
+        let _ = fs::remove_dir_all(&empty);
+
+        // 项目覆盖用户全局，未覆盖的键（含嵌套字段）保留用户全局的值。
+        let files =
+            load_layers(Some(&user_dir), &workspace, "editor.json", &[]).expect("读取配置失败");
+        assert_eq!(files.value["completion_placement"], json!("below"));
+        assert_eq!(files.value["will_save_preview"], json!(true));
+        assert_eq!(files.value["format"]["indent_size"], json!(4));
+        assert_eq!(files.value["format"]["tab_width"], json!(8));
+        assert_eq!(
+            files
+                .sources
+                .iter()
+                .map(|(layer, _)| *layer)
+                .collect::<Vec<_>>(),
+            vec![SettingsLayer::User, SettingsLayer::Project]
+        );
+
+        // 环境变量优先级最高；空值视为未设置。
+        let with_env = load_layers(
+            Some(&user_dir),
+            &workspace,
+            "editor.json",
+            &[
+                ("completion_placement", Some("auto".to_string())),
+                ("format.tab_width", Some("2".to_string())),
+                ("will_save_preview", Some("  ".to_string())),
+            ],
+        )
+        .expect("读取配置失败");
+        assert_eq!(with_env.value["completion_placement"], json!("auto"));
+        assert_eq!(with_env.value["format"]["tab_width"], json!(2));
+        assert_eq!(with_env.value["format"]["indent_size"], json!(4));
+        assert_eq!(with_env.value["will_save_preview"], json!(true));
+
+        let _ = fs::remove_dir_all(&user_dir);
+        let _ = fs::remove_dir_all(&workspace);
+    }
+
+    #[test]
+    fn load_layers_should_parse_toml_and_replace_arrays() {
+        let user_dir = temp_dir("user-toml");
+        let workspace = temp_dir("workspace-toml");
+        fs::create_dir_all(workspace.join(".order")).expect("创建 .order 目录失败");
+        fs::write(
+            user_dir.join("validation.toml"),
+            "minimal = [\"cargo check\", \"cargo test\"]\nextended = [\"cargo clippy\"]\n",
+        )
+        .expect("写入用户配置失败");
+        fs::write(
+            workspace.join(".order").join("validation.toml"),
+            "minimal = [\"cargo test -p core\"]\n",
+        )
+        .expect("写入项目配置失败");
+
+        let settings =
+            load_layers(Some(&user_dir), &workspace, "validation.toml", &[]).expect("读取配置失败");
+        assert_eq!(settings.value["minimal"], json!(["cargo test -p core"]));
+        assert_eq!(settings.value["extended"], json!(["cargo clippy"]));
+
+        let mut base = json!({ "a": [1, 2], "b": { "c": 1 } });
+        merge_settings_value(&mut base, json!({ "a": [3], "b": { "d": 2 } }));
+        assert_eq!(base, json!({ "a": [3], "b": { "c": 1, "d": 2 } }));
+
+        let _ = fs::remove_dir_all(&user_dir);
+        let _ = fs::remove_dir_all(&workspace);
+    }
+}
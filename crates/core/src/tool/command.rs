@@ -101,6 +101,10 @@ impl Tool for CommandTool {
                     ts: ts(),
                     trace_id: trace_id.clone(),
                     tool: Self::NAME.to_string(),
+                    args: Some(serde_json::json!({
+                        "program": logged_program(&args.command),
+                        "timeout_seconds": args.timeout_seconds,
+                    })),
                 },
             );
         }
@@ -195,6 +199,22 @@ fn build_shell_command(command_line: &str) -> TokioCommand {
     command
 }
 
+// 日志只记录程序名：完整命令行可能带有令牌、密码等参数，而 `/replay` 也不会重放命令工具。
+// 前置的 `KEY=value` 环境变量赋值同样可能是密钥，一并跳过。
+fn logged_program(command_line: &str) -> &str {
+    command_line
+        .split_whitespace()
+        .find(|token| {
+            !token.split_once('=').is_some_and(|(name, _)| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            })
+        })
+        .unwrap_or_default()
+}
+
 fn normalize_timeout_seconds(requested: Option<u64>) -> u64 {
     match requested {
         Some(value) if value > 0 => value.min(MAX_TIMEOUT_SECONDS),
@@ -224,9 +244,24 @@ fn truncate_output(bytes: &[u8], max_bytes: usize) -> (String, bool) {
 #[cfg(test)]
 mod tests {
     use super::{
-        DEFAULT_TIMEOUT_SECONDS, MAX_TIMEOUT_SECONDS, normalize_timeout_seconds, truncate_output,
+        DEFAULT_TIMEOUT_SECONDS, MAX_TIMEOUT_SECONDS, logged_program, normalize_timeout_seconds,
+        truncate_output,
     };
 
+    #[test]
+    fn logged_program_should_drop_arguments_and_env_assignments() {
+        assert_eq!(
+            logged_program("curl -H 'Authorization: Bearer sk-123' https://x"),
+            "curl"
+        );
+        assert_eq!(
+            logged_program("  API_TOKEN=sk-123 DEBUG=1 ./deploy.sh --force"),
+            "./deploy.sh",
+            "前置环境变量赋值不应写入日志"
+        );
+        assert_eq!(logged_program("   "), "");
+    }
+
     #[test]
     fn normalize_timeout_seconds_should_apply_default_and_cap() {
         assert_eq!(normalize_timeout_seconds(None), DEFAULT_TIMEOUT_SECONDS);
@@ -80,6 +80,7 @@ impl Tool for ReadTool {
                     ts: ts(),
                     trace_id: trace_id.clone(),
                     tool: Self::NAME.to_string(),
+                    args: Some(serde_json::json!({ "path": args.path })),
                 },
             );
         }
@@ -105,6 +105,7 @@ impl Tool for SearchFileTool {
                     ts: ts(),
                     trace_id: trace_id.clone(),
                     tool: Self::NAME.to_string(),
+                    args: Some(serde_json::json!({ "path": args.path, "keyword": args.keyword })),
                 },
             );
         }
@@ -294,7 +295,7 @@ fn should_skip_directory(search_root: &Path, candidate: &Path) -> bool {
     is_default_ignored_directory_name(name)
 }
 
-fn is_default_ignored_directory_name(name: &str) -> bool {
+pub(crate) fn is_default_ignored_directory_name(name: &str) -> bool {
     DEFAULT_IGNORED_DIR_NAMES
         .iter()
         .any(|ignored| ignored.eq_ignore_ascii_case(name))
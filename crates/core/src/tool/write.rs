@@ -87,6 +87,7 @@ impl Tool for WriteTool {
                     ts: ts(),
                     trace_id: trace_id.clone(),
                     tool: Self::NAME.to_string(),
+                    args: Some(serde_json::json!({ "path": args.path, "append": args.append })),
                 },
             );
         }
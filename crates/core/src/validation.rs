@@ -1,28 +1,60 @@
 use std::{
     collections::BTreeSet,
     fs,
-    path::Path,
+    path::{Path, PathBuf},
     process::Command,
     time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result, anyhow};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::encoding::{read_utf8_text_with_report, write_utf8_text_with_report};
 use crate::observability::{AgentEvent, log_event_best_effort, ts, workspace_root_best_effort};
+use crate::settings::load_settings;
 
-/// 自动验证配置（可选），读取自 `.order/validation.toml`。
+/// 自动验证配置（可选），按层读取 `validation.toml`（见 `settings` 模块）。
 ///
 /// 之所以允许覆盖：
 /// - 不同项目的“最小验证”差异很大；
 /// - 让用户可以把最常用的验证命令固化下来，减少反复手动输入。
 #[derive(Debug, Clone, Default, Deserialize)]
 struct ValidationConfig {
+    #[serde(default, deserialize_with = "deserialize_command_list")]
     minimal: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_command_list")]
     extended: Option<Vec<String>>,
 }
 
+/// 命令列表的两种写法：数组，或以 `;` 分隔的单个字符串。
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CommandList {
+    Many(Vec<String>),
+    One(String),
+}
+
+// 兼容 `ORDER_VALIDATION_MINIMAL=cargo test -p core` 这类未写成 JSON 数组的取值，
+// 否则整份配置反序列化失败，所有验证都会中断。
+fn deserialize_command_list<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        Option::<CommandList>::deserialize(deserializer)?.map(|list| match list {
+            CommandList::Many(commands) => commands,
+            CommandList::One(text) => text
+                .split(';')
+                .map(str::trim)
+                .filter(|command| !command.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }),
+    )
+}
+
 /// 单条验证命令的执行记录。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandReport {
@@ -41,6 +73,13 @@ pub struct StageReport {
     pub commands: Vec<CommandReport>,
 }
 
+/// 验证阶段计划执行的命令，用于在重跑失败命令后补跑此前被跳过的部分。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StagePlan {
+    pub name: String,
+    pub commands: Vec<String>,
+}
+
 /// 验证总报告（写入 `.order/reports/<trace_id>/validation.json`）。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationReport {
@@ -52,6 +91,9 @@ pub struct ValidationReport {
     pub failed_command: Option<String>,
     /// 给用户的下一步建议（尽量可执行/可回退）。
     pub suggestion: Option<String>,
+    /// 各阶段计划执行的全部命令；旧版本生成的报告没有该字段。
+    #[serde(default)]
+    pub plan: Vec<StagePlan>,
 }
 
 /// 自动验证管线。
@@ -130,14 +172,7 @@ impl ValidationPipeline {
             },
         );
 
-        let suggestion = if let Some(ref cmd) = failed_command {
-            Some(format!(
-                "验证失败：可直接复制执行复现命令：`{}`；如需快速回退请使用 `/rollback {}`",
-                cmd, trace_id
-            ))
-        } else {
-            Some("验证通过".to_string())
-        };
+        let suggestion = Some(build_suggestion(failed_command.as_deref(), trace_id));
 
         let report = ValidationReport {
             trace_id: trace_id.to_string(),
@@ -147,11 +182,229 @@ impl ValidationPipeline {
             stages,
             failed_command,
             suggestion,
+            plan: vec![
+                StagePlan {
+                    name: "minimal".to_string(),
+                    commands: minimal_commands,
+                },
+                StagePlan {
+                    name: "extended".to_string(),
+                    commands: extended_commands,
+                },
+            ],
         };
 
         write_report(&workspace_root, trace_id, &report)?;
         Ok(report)
     }
+
+    /// 重跑已归档报告中失败的命令，全部通过后继续补跑此前被跳过的命令，并原地更新报告。
+    ///
+    /// 参数：
+    /// - `trace_id`：为 `None` 时使用最近一次生成的报告。
+    ///
+    /// 返回值：更新后的报告；报告中既没有失败命令也没有未执行的命令时返回错误，
+    /// 避免“重跑”变成空操作。
+    pub fn retry_failed(&self, trace_id: Option<&str>) -> Result<ValidationReport> {
+        let workspace_root = workspace_root_best_effort();
+        let report = load_validation_report(&workspace_root, trace_id)?;
+        let mut commands = failed_commands(&report);
+        commands.extend(skipped_commands(&report));
+        if commands.is_empty() {
+            return Err(anyhow!(
+                "验证报告（trace_id={}）中没有失败或未执行的命令",
+                report.trace_id
+            ));
+        }
+
+        log_event_best_effort(
+            &workspace_root,
+            AgentEvent::ValidationStart {
+                ts: ts(),
+                trace_id: report.trace_id.clone(),
+                commands,
+            },
+        );
+        let report =
+            rerun_failed_commands(report, |command| run_command(&workspace_root, command))?;
+        log_event_best_effort(
+            &workspace_root,
+            AgentEvent::ValidationEnd {
+                ts: ts(),
+                trace_id: report.trace_id.clone(),
+                ok: report.ok,
+                duration_ms: report.duration_ms,
+                failed_command: report.failed_command.clone(),
+            },
+        );
+
+        write_report(&workspace_root, &report.trace_id, &report)?;
+        Ok(report)
+    }
+}
+
+/// 按阶段顺序列出报告中失败的命令。
+///
+/// 以各阶段的命令记录为准而不是 `failed_command`：后者只记录第一条失败，
+/// 而同一份报告可能在多个阶段都有失败记录。
+pub fn failed_commands(report: &ValidationReport) -> Vec<String> {
+    report
+        .stages
+        .iter()
+        .flat_map(|stage| stage.commands.iter())
+        .filter(|command| !command.ok)
+        .map(|command| command.command.clone())
+        .collect()
+}
+
+/// 按计划列出尚未执行的命令（此前因失败而跳过的部分）。
+///
+/// 阶段内命令按顺序执行、遇到失败即停止，所以已有记录总是计划的前缀。
+fn skipped_commands(report: &ValidationReport) -> Vec<String> {
+    report
+        .plan
+        .iter()
+        .flat_map(|plan| {
+            let executed = report
+                .stages
+                .iter()
+                .find(|stage| stage.name == plan.name)
+                .map_or(0, |stage| stage.commands.len());
+            plan.commands.iter().skip(executed).cloned()
+        })
+        .collect()
+}
+
+/// 用 `runner` 重跑报告中失败的命令，替换对应记录并重新汇总结果。
+///
+/// 失败命令全部通过后按计划补跑此前被跳过的命令（遇到失败即停止），
+/// 只有计划内的命令都执行且通过时才把报告标记为通过。旧版本报告没有记录计划，
+/// 无法得知跳过了哪些命令，因此保持未通过，避免把部分重跑误报为完整验证通过。
+fn rerun_failed_commands(
+    mut report: ValidationReport,
+    mut runner: impl FnMut(&str) -> Result<CommandReport>,
+) -> Result<ValidationReport> {
+    for command in report
+        .stages
+        .iter_mut()
+        .flat_map(|stage| stage.commands.iter_mut())
+        .filter(|command| !command.ok)
+    {
+        let rerun = runner(&command.command)?;
+        report.duration_ms = report
+            .duration_ms
+            .saturating_sub(command.duration_ms)
+            .saturating_add(rerun.duration_ms);
+        *command = rerun;
+    }
+
+    if failed_commands(&report).is_empty() {
+        run_skipped_commands(&mut report, &mut runner)?;
+    }
+
+    report.failed_command = failed_commands(&report).into_iter().next();
+    let complete = !report.plan.is_empty() && skipped_commands(&report).is_empty();
+    report.ok = report.failed_command.is_none() && complete;
+    report.suggestion = Some(match report.failed_command.as_deref() {
+        None if !complete => {
+            "失败命令重跑已通过，但该报告未记录完整的命令计划，无法补跑此前跳过的命令；请重新运行完整验证".to_string()
+        }
+        failed_command => build_suggestion(failed_command, &report.trace_id),
+    });
+    Ok(report)
+}
+
+// 按计划顺序补跑尚未执行的命令，遇到失败即停止（与 `run_stage` 一致）。
+fn run_skipped_commands(
+    report: &mut ValidationReport,
+    runner: &mut impl FnMut(&str) -> Result<CommandReport>,
+) -> Result<()> {
+    for plan in report.plan.clone() {
+        let index = match report
+            .stages
+            .iter()
+            .position(|stage| stage.name == plan.name)
+        {
+            Some(index) => index,
+            None => {
+                report.stages.push(StageReport {
+                    name: plan.name.clone(),
+                    commands: Vec::new(),
+                });
+                report.stages.len() - 1
+            }
+        };
+        let executed = report.stages[index].commands.len();
+        for command_line in plan.commands.iter().skip(executed) {
+            let command = runner(command_line)?;
+            let ok = command.ok;
+            report.duration_ms = report.duration_ms.saturating_add(command.duration_ms);
+            report.stages[index].commands.push(command);
+            if !ok {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+// 根据失败命令生成给用户的下一步建议。
+fn build_suggestion(failed_command: Option<&str>, trace_id: &str) -> String {
+    match failed_command {
+        Some(command) => format!(
+            "验证失败：可直接复制执行复现命令：`{}`；如需快速回退请使用 `/rollback {}`",
+            command, trace_id
+        ),
+        None => "验证通过".to_string(),
+    }
+}
+
+/// 读取已归档的验证报告。
+///
+/// 参数：
+/// - `trace_id`：为 `None` 时读取最近一次（按报告文件修改时间）生成的报告。
+///
+/// 返回值：报告不存在或解析失败时返回错误，由调用方决定如何提示。
+pub fn load_validation_report(
+    workspace_root: &Path,
+    trace_id: Option<&str>,
+) -> Result<ValidationReport> {
+    let reports_dir = workspace_root.join(".order").join("reports");
+    let path = match trace_id {
+        Some(trace_id) => reports_dir.join(trace_id).join("validation.json"),
+        None => latest_report_path(&reports_dir)?
+            .ok_or_else(|| anyhow!("未找到任何验证报告: {}", reports_dir.display()))?,
+    };
+    if !path.exists() {
+        return Err(anyhow!("验证报告不存在: {}", path.display()));
+    }
+
+    let (text, _) = read_utf8_text_with_report(&path)
+        .with_context(|| format!("读取验证报告失败: {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("解析验证报告失败: {}", path.display()))
+}
+
+/// 在报告目录下找出最近修改的 `validation.json`。
+///
+/// 以文件修改时间而不是 trace_id 排序：trace_id 不保证按时间单调。
+fn latest_report_path(reports_dir: &Path) -> Result<Option<PathBuf>> {
+    if !reports_dir.exists() {
+        return Ok(None);
+    }
+
+    let mut latest: Option<(SystemTime, PathBuf)> = None;
+    let entries = fs::read_dir(reports_dir)
+        .with_context(|| format!("读取报告目录失败: {}", reports_dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path().join("validation.json");
+        let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else {
+            continue;
+        };
+        if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            latest = Some((modified, path));
+        }
+    }
+    Ok(latest.map(|(_, path)| path))
 }
 
 fn unix_ms() -> u128 {
@@ -162,21 +415,15 @@ fn unix_ms() -> u128 {
 }
 
 fn load_validation_config(workspace_root: &Path) -> Result<ValidationConfig> {
-    let path = workspace_root.join(".order").join("validation.toml");
-    if !path.exists() {
-        return Ok(ValidationConfig::default());
-    }
-    let (text, report) = read_utf8_text_with_report(&path)
-        .with_context(|| format!("读取验证配置失败: {}", path.display()))?;
-    if report.has_warning() {
-        for warning in report.warnings_for(&path) {
-            eprintln!("validation config encoding warning: {warning}");
-        }
-    }
-    if text.trim().is_empty() {
-        return Ok(ValidationConfig::default());
-    }
-    toml::from_str(&text).with_context(|| format!("解析验证配置失败: {}", path.display()))
+    load_settings(
+        workspace_root,
+        "validation.toml",
+        &[
+            ("ORDER_VALIDATION_MINIMAL", "minimal"),
+            ("ORDER_VALIDATION_EXTENDED", "extended"),
+        ],
+    )
+    .context("加载验证配置失败")
 }
 
 fn default_minimal_commands(changed_files: &[String]) -> Vec<String> {
@@ -334,3 +581,216 @@ fn write_report(workspace_root: &Path, trace_id: &str, report: &ValidationReport
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CommandReport, StagePlan, StageReport, ValidationConfig, ValidationReport, failed_commands,
+        load_validation_report, rerun_failed_commands, skipped_commands, write_report,
+    };
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    fn new_temp_workspace() -> PathBuf {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "order-validation-report-{}-{stamp}",
+            std::process::id()
+        ))
+    }
+
+    fn report(trace_id: &str) -> ValidationReport {
+        ValidationReport {
+            trace_id: trace_id.to_string(),
+            started_at_unix_ms: 0,
+            duration_ms: 10,
+            ok: true,
+            stages: Vec::new(),
+            failed_command: None,
+            suggestion: None,
+            plan: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn load_validation_report_should_read_by_trace_id_and_latest() {
+        let root = new_temp_workspace();
+        write_report(&root, "trace-a", &report("trace-a")).expect("report a should be written");
+        // 保证两份报告的修改时间可区分。
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        write_report(&root, "trace-b", &report("trace-b")).expect("report b should be written");
+
+        let by_id = load_validation_report(&root, Some("trace-a")).expect("report a should load");
+        assert_eq!(by_id.trace_id, "trace-a");
+        let latest = load_validation_report(&root, None).expect("latest report should load");
+        assert_eq!(latest.trace_id, "trace-b");
+        assert!(load_validation_report(&root, Some("missing")).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    fn command(command: &str, ok: bool, duration_ms: u128) -> CommandReport {
+        CommandReport {
+            command: command.to_string(),
+            ok,
+            exit_code: Some(if ok { 0 } else { 101 }),
+            duration_ms,
+            stdout_tail: String::new(),
+            stderr_tail: String::new(),
+        }
+    }
+
+    fn failed_report() -> ValidationReport {
+        ValidationReport {
+            ok: false,
+            duration_ms: 300,
+            stages: vec![StageReport {
+                name: "minimal".to_string(),
+                commands: vec![
+                    command("cargo test -p core", true, 100),
+                    command("cargo test -p rander", false, 200),
+                ],
+            }],
+            failed_command: Some("cargo test -p rander".to_string()),
+            plan: vec![
+                StagePlan {
+                    name: "minimal".to_string(),
+                    commands: vec![
+                        "cargo test -p core".to_string(),
+                        "cargo test -p rander".to_string(),
+                        "cargo test -p lsp".to_string(),
+                    ],
+                },
+                StagePlan {
+                    name: "extended".to_string(),
+                    commands: vec!["cargo check --workspace".to_string()],
+                },
+            ],
+            ..report("trace-retry")
+        }
+    }
+
+    #[test]
+    fn failed_commands_should_select_only_failed_entries() {
+        assert_eq!(
+            failed_commands(&failed_report()),
+            vec!["cargo test -p rander".to_string()]
+        );
+        assert!(failed_commands(&report("trace-ok")).is_empty());
+    }
+
+    #[test]
+    fn rerun_failed_commands_should_update_report_in_place() {
+        assert_eq!(
+            skipped_commands(&failed_report()),
+            vec![
+                "cargo test -p lsp".to_string(),
+                "cargo check --workspace".to_string()
+            ]
+        );
+
+        let mut executed = Vec::new();
+        let updated = rerun_failed_commands(failed_report(), |line| {
+            executed.push(line.to_string());
+            Ok(command(line, true, 50))
+        })
+        .expect("重跑应成功");
+
+        assert_eq!(
+            executed,
+            vec![
+                "cargo test -p rander".to_string(),
+                "cargo test -p lsp".to_string(),
+                "cargo check --workspace".to_string()
+            ],
+            "失败命令通过后应补跑此前跳过的命令"
+        );
+        assert!(updated.ok);
+        assert_eq!(updated.failed_command, None);
+        assert_eq!(updated.duration_ms, 250, "耗时应替换为重跑命令的耗时");
+        assert_eq!(updated.stages.len(), 2);
+        assert!(skipped_commands(&updated).is_empty());
+        assert!(
+            updated
+                .suggestion
+                .as_deref()
+                .is_some_and(|text| text.starts_with("验证通过"))
+        );
+
+        let still_failing =
+            rerun_failed_commands(failed_report(), |line| Ok(command(line, false, 80)))
+                .expect("重跑应成功");
+        assert!(!still_failing.ok);
+        assert_eq!(
+            still_failing.failed_command.as_deref(),
+            Some("cargo test -p rander")
+        );
+        assert_eq!(
+            skipped_commands(&still_failing).len(),
+            2,
+            "失败命令仍失败时不补跑"
+        );
+    }
+
+    #[test]
+    fn rerun_failed_commands_should_stop_at_skipped_failure_and_keep_legacy_reports_failed() {
+        let stopped = rerun_failed_commands(failed_report(), |line| {
+            Ok(command(line, line != "cargo test -p lsp", 10))
+        })
+        .expect("重跑应成功");
+        assert!(!stopped.ok);
+        assert_eq!(stopped.failed_command.as_deref(), Some("cargo test -p lsp"));
+        assert_eq!(
+            skipped_commands(&stopped),
+            vec!["cargo check --workspace".to_string()],
+            "补跑遇到失败后停止"
+        );
+
+        let legacy = ValidationReport {
+            plan: Vec::new(),
+            ..failed_report()
+        };
+        let updated =
+            rerun_failed_commands(legacy, |line| Ok(command(line, true, 50))).expect("重跑应成功");
+        assert!(!updated.ok, "没有命令计划时无法确认完整验证通过");
+        assert_eq!(updated.failed_command, None);
+        assert!(
+            updated
+                .suggestion
+                .as_deref()
+                .is_some_and(|text| text.contains("重新运行完整验证"))
+        );
+    }
+
+    #[test]
+    fn validation_config_should_accept_plain_string_commands() {
+        // 环境变量不是合法 JSON 时，settings 层会把它当作普通字符串传进来。
+        let config: ValidationConfig = serde_json::from_value(serde_json::json!({
+            "minimal": "cargo test -p core",
+            "extended": "cargo clippy --workspace; cargo test --workspace ;",
+        }))
+        .expect("字符串形式的命令列表应能解析");
+        assert_eq!(config.minimal, Some(vec!["cargo test -p core".to_string()]));
+        assert_eq!(
+            config.extended,
+            Some(vec![
+                "cargo clippy --workspace".to_string(),
+                "cargo test --workspace".to_string(),
+            ]),
+            "按分号拆分并忽略空项"
+        );
+
+        let config: ValidationConfig = serde_json::from_value(serde_json::json!({
+            "minimal": ["cargo test -p core"],
+        }))
+        .expect("数组形式的命令列表应能解析");
+        assert_eq!(config.minimal, Some(vec!["cargo test -p core".to_string()]));
+        assert_eq!(config.extended, None);
+    }
+}
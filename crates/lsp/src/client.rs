@@ -5,6 +5,7 @@ use std::{
     process::{Child, ChildStdin, Command, Stdio},
     sync::mpsc::{self, Receiver, Sender, TryRecvError},
     thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, anyhow};
@@ -14,13 +15,22 @@ use crate::{
     language::{LspLanguage, all_languages, detect_language},
     protocol,
     types::{
-        DiagnosticItem, LspCommand, LspEvent, LspServerCapabilities, LspServerCheckItem,
-        LspServerCheckReport,
+        DiagnosticItem, LspCallDirection, LspCallHierarchyItem, LspCommand, LspEvent,
+        LspFormatScope, LspServerCapabilities, LspServerCheckItem, LspServerCheckReport,
     },
+    workspace::{configuration_response, workspace_folders, workspace_folders_change},
 };
 
+/// 格式化请求的最长等待时间，超时后视为丢失，允许再次发起格式化。
+const FORMATTING_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 空闲回收时发送 `shutdown` / `exit` 后等待进程自行退出的时长，超时再强制结束。
+const IDLE_SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
 pub struct LspClient {
     sessions: HashMap<LspLanguage, LspSession>,
+    /// 各语言的服务端设置，用于回应 `workspace/configuration`。
+    settings: HashMap<LspLanguage, Value>,
     status_message: String,
     last_action: String,
 }
@@ -35,6 +45,7 @@ impl LspClient {
     pub fn new() -> Self {
         Self {
             sessions: HashMap::new(),
+            settings: HashMap::new(),
             status_message: "LSP 未启动".to_string(),
             last_action: "idle".to_string(),
         }
@@ -58,6 +69,17 @@ impl LspClient {
             .is_some_and(|session| session.running)
     }
 
+    /// 返回指定语言会话当前生效的服务端能力。
+    ///
+    /// 会话未启动或尚未完成 `initialize` 时返回 `None`，以免把默认值误当作服务端声明。
+    /// 运行中被 method-not-found 降级的能力也会反映在结果里。
+    pub fn server_capabilities(&self, language: LspLanguage) -> Option<LspServerCapabilities> {
+        self.sessions
+            .get(&language)
+            .filter(|session| session.running && session.initialized)
+            .map(|session| session.capabilities.clone())
+    }
+
     pub fn check_server_availability(&self) -> LspServerCheckReport {
         let mut items = Vec::new();
         for language in all_languages() {
@@ -95,7 +117,8 @@ impl LspClient {
             return Ok(());
         }
 
-        let session = match LspSession::spawn(workspace_root, language) {
+        let settings = self.settings.get(&language).cloned().unwrap_or(Value::Null);
+        let session = match LspSession::spawn(workspace_root, language, settings) {
             Ok(session) => session,
             Err(error) => {
                 let (binary, _) = language.server_command();
@@ -149,12 +172,26 @@ impl LspClient {
                 LspEvent::SemanticTokens { tokens, .. } => {
                     self.last_action = format!("semanticTokens({})", tokens.len());
                 }
+                LspEvent::SemanticTokensRange { tokens, .. } => {
+                    self.last_action = format!("semanticTokens/range({})", tokens.len());
+                }
                 LspEvent::FormattingEdits { edits, .. } => {
                     self.last_action = format!("formatting({} edits)", edits.len());
                 }
                 LspEvent::RenameWorkspaceEdit { new_name, .. } => {
                     self.last_action = format!("rename({})", new_name);
                 }
+                LspEvent::DefinitionLocations { locations, .. } => {
+                    self.last_action = format!("definition({})", locations.len());
+                }
+                LspEvent::CallHierarchyPrepared { items, .. } => {
+                    self.last_action = format!("prepareCallHierarchy({})", items.len());
+                }
+                LspEvent::CallHierarchyCalls {
+                    direction, calls, ..
+                } => {
+                    self.last_action = format!("{}({})", direction.method(), calls.len());
+                }
                 LspEvent::CodeActions { actions, .. } => {
                     self.last_action = format!("codeAction({})", actions.len());
                 }
@@ -456,6 +493,45 @@ impl LspClient {
         Ok(())
     }
 
+    /// 请求 `[start_line, end_line)` 范围内的语义 token。
+    ///
+    /// 大文件只关心可见区域的高亮，范围请求能显著减少服务端计算与传输量；
+    /// 服务端未声明 `semanticTokens/range` 时回退为整文件请求。
+    pub fn request_semantic_tokens_range(
+        &mut self,
+        file_path: &Path,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<()> {
+        let Some(language) = detect_language(file_path) else {
+            return Ok(());
+        };
+        let range_supported = self
+            .sessions
+            .get(&language)
+            .is_some_and(|session| session.capabilities.semantic_tokens_range);
+        if !range_supported {
+            return self.request_semantic_tokens(file_path);
+        }
+        let Some(session) = self.sessions.get_mut(&language) else {
+            return Ok(());
+        };
+
+        let file_uri = protocol::path_to_file_uri(file_path).with_context(|| {
+            format!("semanticTokens/range 路径转换失败: {}", file_path.display())
+        })?;
+        let request_id = session.next_request_id();
+        let request =
+            build_semantic_tokens_range_request(request_id, &file_uri, start_line, end_line);
+
+        session
+            .pending_semantic_tokens_range
+            .insert(request_id, (file_path.to_path_buf(), start_line, end_line));
+        session.send_or_queue_message(&request)?;
+        self.last_action = format!("semanticTokens/range request({})", language.language_id());
+        Ok(())
+    }
+
     /// 请求 `textDocument/formatting`。
     pub fn request_formatting(
         &mut self,
@@ -482,6 +558,9 @@ impl LspClient {
                 language.display_name()
             ));
         }
+        if session.formatting_in_flight(file_path, Instant::now()) {
+            return Err(anyhow!("上一次格式化尚未返回"));
+        }
 
         let file_uri = protocol::path_to_file_uri(file_path)
             .with_context(|| format!("formatting 路径转换失败: {}", file_path.display()))?;
@@ -502,11 +581,110 @@ impl LspClient {
         session
             .pending_formatting
             .insert(request_id, file_path.to_path_buf());
+        session.begin_formatting(file_path, request_id, Instant::now());
         session.send_or_queue_message(&request)?;
         self.last_action = format!("formatting request({})", language.language_id());
         Ok(())
     }
 
+    /// 指定文件是否有尚未返回的格式化请求。
+    ///
+    /// 上一次结果返回前再次格式化，两份编辑会基于同一份旧文本先后应用而损坏缓冲区，
+    /// 编辑器据此忽略重复按键；超过 [`FORMATTING_REQUEST_TIMEOUT`] 的请求视为丢失并被清理。
+    pub fn is_formatting_in_flight(&mut self, file_path: &Path) -> bool {
+        let Some(language) = detect_language(file_path) else {
+            return false;
+        };
+        self.sessions
+            .get_mut(&language)
+            .is_some_and(|session| session.formatting_in_flight(file_path, Instant::now()))
+    }
+
+    /// 统一的格式化入口：有选区时请求范围格式化，否则格式化整个文件。
+    ///
+    /// `selection` 为 `((start_line, start_character), (end_line, end_character))`。
+    /// 服务端不支持范围格式化时自动回退整文件；返回值为实际发送的请求范围，
+    /// 便于上层向用户说明是否发生了回退。
+    pub fn request_document_format(
+        &mut self,
+        file_path: &Path,
+        selection: Option<((usize, usize), (usize, usize))>,
+        tab_size: usize,
+        insert_spaces: bool,
+    ) -> Result<LspFormatScope> {
+        let Some(language) = detect_language(file_path) else {
+            return Err(anyhow!("当前文件没有对应的 LSP"));
+        };
+        let Some(session) = self.sessions.get_mut(&language) else {
+            return Err(anyhow!("{} LSP 会话不存在", language.display_name()));
+        };
+
+        if !session.running {
+            return Err(anyhow!("{} LSP 会话未运行", language.display_name()));
+        }
+        if !session.initialized {
+            return Err(anyhow!("{} LSP 正在初始化", language.display_name()));
+        }
+        let Some(scope) = session.capabilities.format_scope(selection.is_some()) else {
+            return Err(anyhow!(
+                "{} LSP 不支持 textDocument/formatting",
+                language.display_name()
+            ));
+        };
+        if session.formatting_in_flight(file_path, Instant::now()) {
+            return Err(anyhow!("上一次格式化尚未返回"));
+        }
+
+        let file_uri = protocol::path_to_file_uri(file_path)
+            .with_context(|| format!("formatting 路径转换失败: {}", file_path.display()))?;
+        let request_id = session.next_request_id();
+        let options = serde_json::json!({
+            "tabSize": tab_size,
+            "insertSpaces": insert_spaces
+        });
+        match (scope, selection) {
+            (LspFormatScope::Range, Some((start, end))) => {
+                let request = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id,
+                    "method": "textDocument/rangeFormatting",
+                    "params": {
+                        "textDocument": { "uri": file_uri },
+                        "range": {
+                            "start": { "line": start.0, "character": start.1 },
+                            "end": { "line": end.0, "character": end.1 }
+                        },
+                        "options": options
+                    }
+                });
+                session
+                    .pending_range_formatting
+                    .insert(request_id, file_path.to_path_buf());
+                session.begin_formatting(file_path, request_id, Instant::now());
+                session.send_or_queue_message(&request)?;
+                self.last_action = format!("rangeFormatting request({})", language.language_id());
+            }
+            _ => {
+                let request = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id,
+                    "method": "textDocument/formatting",
+                    "params": {
+                        "textDocument": { "uri": file_uri },
+                        "options": options
+                    }
+                });
+                session
+                    .pending_formatting
+                    .insert(request_id, file_path.to_path_buf());
+                session.begin_formatting(file_path, request_id, Instant::now());
+                session.send_or_queue_message(&request)?;
+                self.last_action = format!("formatting request({})", language.language_id());
+            }
+        }
+        Ok(scope)
+    }
+
     /// 请求 `textDocument/rename`。
     pub fn request_rename(
         &mut self,
@@ -567,16 +745,15 @@ impl LspClient {
         Ok(())
     }
 
-    /// 请求 `textDocument/codeAction`（仅 quick fix）。
-    pub fn request_code_actions(
+    /// 请求 `textDocument/definition`，返回结果通过 [`LspEvent::DefinitionLocations`] 回传。
+    pub fn request_definition(
         &mut self,
         file_path: &Path,
         line: usize,
         character: usize,
-        diagnostics: &[DiagnosticItem],
     ) -> Result<()> {
         let Some(language) = detect_language(file_path) else {
-            return Ok(());
+            return Err(anyhow!("当前文件没有对应的 LSP"));
         };
         let Some(session) = self.sessions.get_mut(&language) else {
             return Err(anyhow!("{} LSP 会话不存在", language.display_name()));
@@ -588,40 +765,157 @@ impl LspClient {
         if !session.initialized {
             return Err(anyhow!("{} LSP 正在初始化", language.display_name()));
         }
-        if !session.capabilities.code_action {
+        if !session.capabilities.definition {
             return Err(anyhow!(
-                "{} LSP 不支持 textDocument/codeAction",
+                "{} LSP 不支持 textDocument/definition",
                 language.display_name()
             ));
         }
 
         let file_uri = protocol::path_to_file_uri(file_path)
-            .with_context(|| format!("codeAction 路径转换失败: {}", file_path.display()))?;
+            .with_context(|| format!("definition 路径转换失败: {}", file_path.display()))?;
         let request_id = session.next_request_id();
         let request = serde_json::json!({
             "jsonrpc": "2.0",
             "id": request_id,
-            "method": "textDocument/codeAction",
+            "method": "textDocument/definition",
             "params": {
                 "textDocument": { "uri": file_uri },
-                "range": {
-                    "start": {
-                        "line": line,
-                        "character": character
-                    },
-                    "end": {
-                        "line": line,
-                        "character": character
-                    }
-                },
-                "context": {
-                    "diagnostics": serialize_diagnostics_for_code_action(diagnostics),
-                    "only": ["quickfix"],
-                    "triggerKind": 1
+                "position": {
+                    "line": line,
+                    "character": character
                 }
             }
         });
 
+        session
+            .pending_definition
+            .insert(request_id, file_path.to_path_buf());
+        session.send_or_queue_message(&request)?;
+        self.last_action = format!("definition request({})", language.language_id());
+        Ok(())
+    }
+
+    /// 请求 `textDocument/prepareCallHierarchy`，结果通过 [`LspEvent::CallHierarchyPrepared`] 回传。
+    pub fn prepare_call_hierarchy(
+        &mut self,
+        file_path: &Path,
+        line: usize,
+        character: usize,
+    ) -> Result<()> {
+        let file_uri = protocol::path_to_file_uri(file_path)
+            .with_context(|| format!("callHierarchy 路径转换失败: {}", file_path.display()))?;
+        let (language, session) = self.call_hierarchy_session(file_path)?;
+        let request_id = session.next_request_id();
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "textDocument/prepareCallHierarchy",
+            "params": {
+                "textDocument": { "uri": file_uri },
+                "position": {
+                    "line": line,
+                    "character": character
+                }
+            }
+        });
+
+        session
+            .pending_call_hierarchy_prepare
+            .insert(request_id, file_path.to_path_buf());
+        session.send_or_queue_message(&request)?;
+        self.last_action = format!("prepareCallHierarchy request({})", language.language_id());
+        Ok(())
+    }
+
+    /// 展开调用层级节点，结果通过 [`LspEvent::CallHierarchyCalls`] 回传。
+    ///
+    /// 请求发往节点所在文件的语言服务，节点按服务端原始结构回传。
+    pub fn request_call_hierarchy_calls(
+        &mut self,
+        item: &LspCallHierarchyItem,
+        direction: LspCallDirection,
+    ) -> Result<()> {
+        let (language, session) = self.call_hierarchy_session(&item.file_path)?;
+        let request_id = session.next_request_id();
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": direction.method(),
+            "params": { "item": item.raw }
+        });
+
+        session
+            .pending_call_hierarchy_calls
+            .insert(request_id, (item.clone(), direction));
+        session.send_or_queue_message(&request)?;
+        self.last_action = format!("{} request({})", direction.method(), language.language_id());
+        Ok(())
+    }
+
+    // 取出可发送调用层级请求的会话：需已运行、已初始化且声明了 `callHierarchyProvider`。
+    fn call_hierarchy_session(
+        &mut self,
+        file_path: &Path,
+    ) -> Result<(LspLanguage, &mut LspSession)> {
+        let Some(language) = detect_language(file_path) else {
+            return Err(anyhow!("当前文件没有对应的 LSP"));
+        };
+        let Some(session) = self.sessions.get_mut(&language) else {
+            return Err(anyhow!("{} LSP 会话不存在", language.display_name()));
+        };
+
+        if !session.running {
+            return Err(anyhow!("{} LSP 会话未运行", language.display_name()));
+        }
+        if !session.initialized {
+            return Err(anyhow!("{} LSP 正在初始化", language.display_name()));
+        }
+        if !session.capabilities.call_hierarchy {
+            return Err(anyhow!(
+                "{} LSP 不支持 callHierarchy",
+                language.display_name()
+            ));
+        }
+        Ok((language, session))
+    }
+
+    /// 请求 `textDocument/codeAction`（仅 quick fix）。
+    ///
+    /// `start`/`end` 为 `(line, character)` 形式的 LSP 坐标：光标请求传入空范围，
+    /// 针对单条诊断的请求传入诊断范围，服务端据此返回更精确的修复。
+    pub fn request_code_actions(
+        &mut self,
+        file_path: &Path,
+        start: (usize, usize),
+        end: (usize, usize),
+        diagnostics: &[DiagnosticItem],
+    ) -> Result<()> {
+        let Some(language) = detect_language(file_path) else {
+            return Ok(());
+        };
+        let Some(session) = self.sessions.get_mut(&language) else {
+            return Err(anyhow!("{} LSP 会话不存在", language.display_name()));
+        };
+
+        if !session.running {
+            return Err(anyhow!("{} LSP 会话未运行", language.display_name()));
+        }
+        if !session.initialized {
+            return Err(anyhow!("{} LSP 正在初始化", language.display_name()));
+        }
+        if !session.capabilities.code_action {
+            return Err(anyhow!(
+                "{} LSP 不支持 textDocument/codeAction",
+                language.display_name()
+            ));
+        }
+
+        let file_uri = protocol::path_to_file_uri(file_path)
+            .with_context(|| format!("codeAction 路径转换失败: {}", file_path.display()))?;
+        let request_id = session.next_request_id();
+        let request = build_code_action_request(request_id, &file_uri, start, end, diagnostics);
+
         session
             .pending_code_action
             .insert(request_id, file_path.to_path_buf());
@@ -707,6 +1001,90 @@ impl LspClient {
         Ok(())
     }
 
+    /// 设置某个语言的服务端配置。
+    ///
+    /// 只影响之后启动的会话：它们收到 `workspace/configuration` 时按此回包。
+    /// 设置只在 editor 启动时从 `editor.json` 读取一次，不会推送给已在运行的会话。
+    pub fn set_language_settings(&mut self, language: LspLanguage, settings: Value) {
+        self.settings.insert(language, settings);
+    }
+
+    /// 重新计算各会话的工作区目录，并通过 `workspace/didChangeWorkspaceFolders` 发送差异。
+    ///
+    /// 目前只在编辑器内保存 `go.work` 后调用；目录没有变化的会话不发送通知。
+    /// 在编辑器外修改 `go.work` 不会被察觉，需要重启对应会话，新会话启动时会重新读取。
+    pub fn refresh_workspace_folders(&mut self, workspace_root: &Path) -> Result<()> {
+        let mut notified = Vec::new();
+        for session in self.sessions.values_mut() {
+            if !session.running {
+                continue;
+            }
+            let folders = workspace_folders(workspace_root, session.language)?;
+            if folders == session.workspace_folders {
+                continue;
+            }
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "workspace/didChangeWorkspaceFolders",
+                "params": workspace_folders_change(&session.workspace_folders, &folders)
+            });
+            session.send_or_queue_message(&notification)?;
+            session.workspace_folders = folders;
+            notified.push(session.language.display_name());
+        }
+        if !notified.is_empty() {
+            self.last_action = format!("didChangeWorkspaceFolders({})", notified.join(", "));
+        }
+        Ok(())
+    }
+
+    /// 关闭空闲的语言会话，返回被关闭的语言。
+    ///
+    /// 参数：
+    /// - `idle_timeout`：最近一次请求/响应距今超过该时长才视为空闲；
+    /// - `has_open_buffer`：该语言是否仍有打开的缓冲区，由调用方判断，
+    ///   因为客户端并不知道哪些文件仍在编辑器里。
+    ///
+    /// 被关闭的会话在下次打开对应语言文件时按需重新启动。
+    pub fn shutdown_idle_sessions(
+        &mut self,
+        idle_timeout: Duration,
+        has_open_buffer: impl Fn(LspLanguage) -> bool,
+    ) -> Vec<LspLanguage> {
+        let now = Instant::now();
+        let idle_languages = self
+            .sessions
+            .iter()
+            .filter(|(language, session)| {
+                is_session_idle(
+                    session.last_activity,
+                    now,
+                    idle_timeout,
+                    has_open_buffer(**language),
+                )
+            })
+            .map(|(language, _)| *language)
+            .collect::<Vec<_>>();
+
+        for language in &idle_languages {
+            if let Some(mut session) = self.sessions.remove(language) {
+                session.shutdown_gracefully();
+            }
+        }
+        if !idle_languages.is_empty() {
+            self.status_message = format!(
+                "已关闭空闲的 LSP：{}",
+                idle_languages
+                    .iter()
+                    .map(|language| language.display_name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            self.last_action = "idle shutdown".to_string();
+        }
+        idle_languages
+    }
+
     pub fn stop_all(&mut self) {
         for session in self.sessions.values_mut() {
             session.stop();
@@ -726,6 +1104,8 @@ impl Drop for LspClient {
 enum ReaderMessage {
     Event(LspEvent),
     Response(Value),
+    /// 服务端的 `workspace/configuration` 请求：`(原始请求 id, 各项 section)`。
+    ConfigurationRequest(Value, Vec<Option<String>>),
 }
 
 #[derive(Debug, Clone)]
@@ -745,8 +1125,12 @@ enum PendingRequestKind {
     WillSaveWaitUntil,
     Completion,
     SemanticTokens,
+    SemanticTokensRange,
     Formatting,
+    RangeFormatting,
     Rename,
+    Definition,
+    CallHierarchy,
     CodeAction,
     ExecuteCommand,
 }
@@ -773,14 +1157,31 @@ struct LspSession {
     pending_will_save_wait_until: HashMap<u64, PathBuf>,
     pending_completion: HashMap<u64, PathBuf>,
     pending_semantic_tokens: HashMap<u64, PathBuf>,
+    /// 范围语义 token 请求：`(文件, 起始行, 结束行)`，结束行不含。
+    pending_semantic_tokens_range: HashMap<u64, (PathBuf, usize, usize)>,
     pending_formatting: HashMap<u64, PathBuf>,
+    pending_range_formatting: HashMap<u64, PathBuf>,
+    /// 每个文件正在进行的格式化请求：`文件 -> (请求 id, 发送时刻)`，同一文件同时只允许一个。
+    formatting_in_flight: HashMap<PathBuf, (u64, Instant)>,
     pending_rename: HashMap<u64, PendingRename>,
+    pending_definition: HashMap<u64, PathBuf>,
+    pending_call_hierarchy_prepare: HashMap<u64, PathBuf>,
+    /// `incomingCalls` / `outgoingCalls` 请求：被展开的节点与方向，响应回传时原样带回。
+    pending_call_hierarchy_calls: HashMap<u64, (LspCallHierarchyItem, LspCallDirection)>,
     pending_code_action: HashMap<u64, PathBuf>,
     pending_execute_command: HashMap<u64, PendingExecuteCommand>,
+    /// 最近一次向服务端发送消息或收到响应的时刻，用于空闲回收。
+    ///
+    /// 服务端主动推送的通知（诊断、进度）不计入，否则后台索引会让会话永远不空闲。
+    last_activity: Instant,
+    /// `initialize` 时声明的工作区目录，目录变化时据此计算增删差异。
+    workspace_folders: Vec<Value>,
+    /// 回应 `workspace/configuration` 的设置；未配置时为 `null`。
+    settings: Value,
 }
 
 impl LspSession {
-    fn spawn(workspace_root: &Path, language: LspLanguage) -> Result<Self> {
+    fn spawn(workspace_root: &Path, language: LspLanguage, settings: Value) -> Result<Self> {
         let (binary, args) = language.server_command();
         let mut command = Command::new(binary);
         command
@@ -835,10 +1236,19 @@ impl LspSession {
             pending_will_save_wait_until: HashMap::new(),
             pending_completion: HashMap::new(),
             pending_semantic_tokens: HashMap::new(),
+            pending_semantic_tokens_range: HashMap::new(),
             pending_formatting: HashMap::new(),
+            pending_range_formatting: HashMap::new(),
+            formatting_in_flight: HashMap::new(),
             pending_rename: HashMap::new(),
+            pending_definition: HashMap::new(),
+            pending_call_hierarchy_prepare: HashMap::new(),
+            pending_call_hierarchy_calls: HashMap::new(),
             pending_code_action: HashMap::new(),
             pending_execute_command: HashMap::new(),
+            last_activity: Instant::now(),
+            workspace_folders: Vec::new(),
+            settings,
         };
 
         session.send_initialize_sequence(workspace_root)?;
@@ -852,10 +1262,26 @@ impl LspSession {
             match self.reader_rx.try_recv() {
                 Ok(ReaderMessage::Event(event)) => events.push(event),
                 Ok(ReaderMessage::Response(response)) => {
+                    self.last_activity = Instant::now();
                     if let Some(event) = self.map_response(response) {
                         events.push(event);
                     }
                 }
+                Ok(ReaderMessage::ConfigurationRequest(request_id, sections)) => {
+                    // 必须回包，否则部分服务端（如 pyright）会一直等待配置而不开始分析。
+                    let response = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request_id,
+                        "result": configuration_response(&self.settings, &sections)
+                    });
+                    if let Err(error) = self.send_message(&response) {
+                        events.push(LspEvent::Status(format!(
+                            "{} workspace/configuration 回包失败: {}",
+                            self.language.language_id(),
+                            error
+                        )));
+                    }
+                }
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => {
                     self.running = false;
@@ -953,7 +1379,28 @@ impl LspSession {
             });
         }
 
-        if let Some(file_path) = self.pending_formatting.remove(&request_id) {
+        if let Some((file_path, start_line, end_line)) =
+            self.pending_semantic_tokens_range.remove(&request_id)
+        {
+            return Some(LspEvent::SemanticTokensRange {
+                file_path,
+                start_line,
+                end_line,
+                tokens: protocol::parse_semantic_tokens_from_response(
+                    &response,
+                    &self.semantic_token_types,
+                    &self.semantic_token_modifiers,
+                ),
+            });
+        }
+
+        // 范围格式化与整文件格式化的响应结构一致，统一交给同一条应用路径。
+        if let Some(file_path) = self
+            .pending_formatting
+            .remove(&request_id)
+            .or_else(|| self.pending_range_formatting.remove(&request_id))
+        {
+            self.formatting_in_flight.remove(&file_path);
             return Some(LspEvent::FormattingEdits {
                 file_path,
                 edits: protocol::parse_text_edits_from_response(&response),
@@ -968,6 +1415,28 @@ impl LspSession {
             });
         }
 
+        if let Some(file_path) = self.pending_definition.remove(&request_id) {
+            return Some(LspEvent::DefinitionLocations {
+                file_path,
+                locations: protocol::parse_definition_locations_from_response(&response),
+            });
+        }
+
+        if let Some(file_path) = self.pending_call_hierarchy_prepare.remove(&request_id) {
+            return Some(LspEvent::CallHierarchyPrepared {
+                file_path,
+                items: protocol::parse_call_hierarchy_items_from_response(&response),
+            });
+        }
+
+        if let Some((item, direction)) = self.pending_call_hierarchy_calls.remove(&request_id) {
+            return Some(LspEvent::CallHierarchyCalls {
+                calls: protocol::parse_call_hierarchy_calls_from_response(&response, direction),
+                item,
+                direction,
+            });
+        }
+
         if let Some(file_path) = self.pending_code_action.remove(&request_id) {
             return Some(LspEvent::CodeActions {
                 file_path,
@@ -987,6 +1456,27 @@ impl LspSession {
         None
     }
 
+    // 记录文件的格式化请求已发出。
+    fn begin_formatting(&mut self, file_path: &Path, request_id: u64, now: Instant) {
+        self.formatting_in_flight
+            .insert(file_path.to_path_buf(), (request_id, now));
+    }
+
+    /// 判断文件是否有未返回的格式化请求，并顺带清理超时的请求。
+    ///
+    /// 超时请求的 pending 记录一并移除，之后即使响应迟到也不会再被应用，
+    /// 避免与用户重新发起的格式化叠加。
+    fn formatting_in_flight(&mut self, file_path: &Path, now: Instant) -> bool {
+        let Some(&(request_id, sent_at)) = self.formatting_in_flight.get(file_path) else {
+            return false;
+        };
+        if now.saturating_duration_since(sent_at) < FORMATTING_REQUEST_TIMEOUT {
+            return true;
+        }
+        self.clear_pending_request(request_id);
+        false
+    }
+
     /// 判断请求 id 对应的待处理请求类型。
     fn pending_request_kind(&self, request_id: u64) -> Option<PendingRequestKind> {
         if self.pending_will_save_wait_until.contains_key(&request_id) {
@@ -998,12 +1488,28 @@ impl LspSession {
         if self.pending_semantic_tokens.contains_key(&request_id) {
             return Some(PendingRequestKind::SemanticTokens);
         }
+        if self.pending_semantic_tokens_range.contains_key(&request_id) {
+            return Some(PendingRequestKind::SemanticTokensRange);
+        }
         if self.pending_formatting.contains_key(&request_id) {
             return Some(PendingRequestKind::Formatting);
         }
+        if self.pending_range_formatting.contains_key(&request_id) {
+            return Some(PendingRequestKind::RangeFormatting);
+        }
         if self.pending_rename.contains_key(&request_id) {
             return Some(PendingRequestKind::Rename);
         }
+        if self.pending_definition.contains_key(&request_id) {
+            return Some(PendingRequestKind::Definition);
+        }
+        if self
+            .pending_call_hierarchy_prepare
+            .contains_key(&request_id)
+            || self.pending_call_hierarchy_calls.contains_key(&request_id)
+        {
+            return Some(PendingRequestKind::CallHierarchy);
+        }
         if self.pending_code_action.contains_key(&request_id) {
             return Some(PendingRequestKind::CodeAction);
         }
@@ -1018,8 +1524,15 @@ impl LspSession {
         self.pending_will_save_wait_until.remove(&request_id);
         self.pending_completion.remove(&request_id);
         self.pending_semantic_tokens.remove(&request_id);
+        self.pending_semantic_tokens_range.remove(&request_id);
         self.pending_formatting.remove(&request_id);
+        self.pending_range_formatting.remove(&request_id);
+        self.formatting_in_flight
+            .retain(|_, (in_flight_id, _)| *in_flight_id != request_id);
         self.pending_rename.remove(&request_id);
+        self.pending_definition.remove(&request_id);
+        self.pending_call_hierarchy_prepare.remove(&request_id);
+        self.pending_call_hierarchy_calls.remove(&request_id);
         self.pending_code_action.remove(&request_id);
         self.pending_execute_command.remove(&request_id);
     }
@@ -1033,9 +1546,21 @@ impl LspSession {
             PendingRequestKind::Formatting => {
                 self.capabilities.formatting = false;
             }
+            PendingRequestKind::RangeFormatting => {
+                self.capabilities.range_formatting = false;
+            }
+            PendingRequestKind::SemanticTokensRange => {
+                self.capabilities.semantic_tokens_range = false;
+            }
             PendingRequestKind::Rename => {
                 self.capabilities.rename = false;
             }
+            PendingRequestKind::Definition => {
+                self.capabilities.definition = false;
+            }
+            PendingRequestKind::CallHierarchy => {
+                self.capabilities.call_hierarchy = false;
+            }
             PendingRequestKind::CodeAction => {
                 self.capabilities.code_action = false;
             }
@@ -1050,6 +1575,7 @@ impl LspSession {
         let root_uri = protocol::path_to_file_uri(workspace_root)
             .with_context(|| format!("工作区路径无法转换为 URI: {}", workspace_root.display()))?;
 
+        let folders = workspace_folders(workspace_root, self.language)?;
         let initialize_request_id = self.next_request_id();
         self.initialize_request_id = Some(initialize_request_id);
 
@@ -1067,6 +1593,8 @@ impl LspSession {
                 "capabilities": {
                     "workspace": {
                         "applyEdit": true,
+                        "configuration": true,
+                        "workspaceFolders": true,
                         "workspaceEdit": {
                             "documentChanges": true
                         }
@@ -1074,7 +1602,10 @@ impl LspSession {
                     "textDocument": {
                         "completion": {
                             "completionItem": {
-                                "snippetSupport": true
+                                "snippetSupport": true,
+                                "labelDetailsSupport": true,
+                                "deprecatedSupport": true,
+                                "tagSupport": { "valueSet": [1] }
                             }
                         },
                         "codeAction": {
@@ -1092,13 +1623,24 @@ impl LspSession {
                         "rename": {
                             "dynamicRegistration": false
                         },
+                        "definition": {
+                            "dynamicRegistration": false,
+                            "linkSupport": true
+                        },
+                        "callHierarchy": {
+                            "dynamicRegistration": false
+                        },
                         "formatting": {
                             "dynamicRegistration": false
                         },
+                        "rangeFormatting": {
+                            "dynamicRegistration": false
+                        },
                         "semanticTokens": {
                             "dynamicRegistration": false,
                             "requests": {
-                                "full": true
+                                "full": true,
+                                "range": true
                             },
                             "tokenTypes": self.language.semantic_token_types(),
                             "tokenModifiers": self.language.semantic_token_modifiers(),
@@ -1106,9 +1648,10 @@ impl LspSession {
                         }
                     }
                 },
-                "workspaceFolders": []
+                "workspaceFolders": folders
             }
         });
+        self.workspace_folders = folders;
         self.send_message(&initialize)?;
 
         let initialized = serde_json::json!({
@@ -1120,6 +1663,7 @@ impl LspSession {
     }
 
     fn send_message(&mut self, value: &Value) -> Result<()> {
+        self.last_activity = Instant::now();
         let stdin = self
             .stdin
             .as_mut()
@@ -1188,6 +1732,30 @@ impl LspSession {
         self.running = false;
     }
 
+    /// 按协议发送 `shutdown` / `exit` 后等待进程退出，超过宽限时间再强制结束。
+    fn shutdown_gracefully(&mut self) {
+        if self.running && self.initialized {
+            let request_id = self.next_request_id();
+            let _ = self.send_message(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "method": "shutdown"
+            }));
+            let _ = self.send_message(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "exit"
+            }));
+            let deadline = Instant::now() + IDLE_SHUTDOWN_GRACE;
+            while let Some(child) = self.child.as_mut() {
+                if !matches!(child.try_wait(), Ok(None)) || Instant::now() >= deadline {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+        self.stop();
+    }
+
     fn drain_legacy_events(&mut self) -> Option<LspEvent> {
         let rx = self.event_rx.as_ref()?;
         match rx.try_recv() {
@@ -1202,6 +1770,16 @@ impl LspSession {
     }
 }
 
+/// 判断会话是否可以按空闲回收：没有打开的缓冲区，且最近活动已超过 `idle_timeout`。
+fn is_session_idle(
+    last_activity: Instant,
+    now: Instant,
+    idle_timeout: Duration,
+    has_open_buffer: bool,
+) -> bool {
+    !has_open_buffer && now.saturating_duration_since(last_activity) >= idle_timeout
+}
+
 fn spawn_reader_thread(
     stdout: std::process::ChildStdout,
     reader_tx: Sender<ReaderMessage>,
@@ -1265,6 +1843,13 @@ fn spawn_reader_thread(
                 continue;
             }
 
+            if let Some((request_id, sections)) =
+                protocol::parse_workspace_configuration_request(&message)
+            {
+                let _ = reader_tx.send(ReaderMessage::ConfigurationRequest(request_id, sections));
+                continue;
+            }
+
             // 仅把“method 为空的消息”视为 response，避免把服务端请求误判进响应分支。
             if protocol::response_request_id(&message).is_some() && message.get("method").is_none()
             {
@@ -1284,6 +1869,60 @@ impl LspClient {
     }
 }
 
+// 构建 `textDocument/semanticTokens/range` 请求，`end_line` 不含。
+fn build_semantic_tokens_range_request(
+    request_id: u64,
+    file_uri: &str,
+    start_line: usize,
+    end_line: usize,
+) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": request_id,
+        "method": "textDocument/semanticTokens/range",
+        "params": {
+            "textDocument": { "uri": file_uri },
+            "range": {
+                "start": { "line": start_line, "character": 0 },
+                "end": { "line": end_line, "character": 0 }
+            }
+        }
+    })
+}
+
+// 构建 `textDocument/codeAction` 请求（仅 quick fix）。
+fn build_code_action_request(
+    request_id: u64,
+    file_uri: &str,
+    start: (usize, usize),
+    end: (usize, usize),
+    diagnostics: &[DiagnosticItem],
+) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": request_id,
+        "method": "textDocument/codeAction",
+        "params": {
+            "textDocument": { "uri": file_uri },
+            "range": {
+                "start": {
+                    "line": start.0,
+                    "character": start.1
+                },
+                "end": {
+                    "line": end.0,
+                    "character": end.1
+                }
+            },
+            "context": {
+                "diagnostics": serialize_diagnostics_for_code_action(diagnostics),
+                "only": ["quickfix"],
+                "triggerKind": 1
+            }
+        }
+    })
+}
+
 /// 将内部诊断结构转换为 `codeAction` 上下文所需的 LSP 诊断 JSON。
 ///
 /// quick fix 能力高度依赖诊断上下文，若只传空数组，服务端通常只能返回很少的动作。
@@ -1361,11 +2000,22 @@ fn is_command_available(command: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, path::PathBuf, sync::mpsc};
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
 
     use serde_json::json;
 
-    use super::{LspEvent, LspLanguage, LspServerCapabilities, LspSession, ReaderMessage};
+    use crate::{DiagnosticItem, DiagnosticSeverity, LspFormatScope};
+
+    use super::{
+        FORMATTING_REQUEST_TIMEOUT, LspClient, LspEvent, LspLanguage, LspServerCapabilities,
+        LspSession, ReaderMessage, build_code_action_request, build_semantic_tokens_range_request,
+        is_session_idle,
+    };
 
     fn build_minimal_session() -> LspSession {
         let (_reader_tx, reader_rx) = mpsc::channel::<ReaderMessage>();
@@ -1382,7 +2032,10 @@ mod tests {
                 rename: true,
                 code_action: true,
                 formatting: true,
+                range_formatting: true,
+                semantic_tokens_range: true,
                 execute_command: true,
+                ..LspServerCapabilities::default()
             },
             request_id: 3,
             initialize_request_id: Some(1),
@@ -1395,10 +2048,19 @@ mod tests {
             pending_will_save_wait_until: HashMap::new(),
             pending_completion: HashMap::new(),
             pending_semantic_tokens,
+            pending_semantic_tokens_range: HashMap::new(),
             pending_formatting: HashMap::new(),
+            pending_range_formatting: HashMap::new(),
+            formatting_in_flight: HashMap::new(),
             pending_rename: HashMap::new(),
+            pending_definition: HashMap::new(),
+            pending_call_hierarchy_prepare: HashMap::new(),
+            pending_call_hierarchy_calls: HashMap::new(),
             pending_code_action: HashMap::new(),
             pending_execute_command: HashMap::new(),
+            last_activity: Instant::now(),
+            workspace_folders: Vec::new(),
+            settings: serde_json::Value::Null,
         }
     }
 
@@ -1510,6 +2172,48 @@ mod tests {
         assert!(session.capabilities.execute_command);
     }
 
+    #[test]
+    fn capabilities_describe_should_render_all_tracked_fields() {
+        let mut session = build_minimal_session();
+        session.capabilities = LspServerCapabilities::default();
+
+        let initialize_response = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 2 },
+                    "completionProvider": { "triggerCharacters": [".", "::"] },
+                    "renameProvider": { "prepareProvider": true },
+                    "documentRangeFormattingProvider": true,
+                    "semanticTokensProvider": { "range": true, "full": true }
+                }
+            }
+        });
+        assert!(session.map_response(initialize_response).is_none());
+
+        let described = session.capabilities.describe();
+        let value_of = |name: &str| {
+            described
+                .iter()
+                .find(|(label, _)| *label == name)
+                .map(|(_, value)| value.as_str())
+                .unwrap_or_else(|| panic!("能力面板缺少字段 {name}"))
+        };
+        assert_eq!(value_of("textDocumentSync"), "incremental");
+        assert_eq!(value_of("completionProvider"), "yes");
+        assert_eq!(value_of("completion.triggerCharacters"), "`.` `::`");
+        assert_eq!(value_of("renameProvider"), "yes");
+        assert_eq!(value_of("codeActionProvider"), "no");
+        assert_eq!(value_of("executeCommandProvider"), "no");
+        assert_eq!(value_of("documentFormattingProvider"), "no");
+        assert_eq!(value_of("documentRangeFormattingProvider"), "yes");
+        assert_eq!(value_of("semanticTokensProvider.range"), "yes");
+        assert_eq!(value_of("definitionProvider"), "no");
+        assert_eq!(value_of("callHierarchyProvider"), "no");
+        assert_eq!(described.len(), 11, "新增能力字段时需同步加入 describe");
+    }
+
     #[test]
     fn will_save_wait_until_unknown_request_should_disable_request() {
         let mut session = build_minimal_session();
@@ -1532,6 +2236,36 @@ mod tests {
         assert!(!session.pending_will_save_wait_until.contains_key(&9));
     }
 
+    #[test]
+    fn formatting_in_flight_should_block_until_response_or_timeout() {
+        let mut session = build_minimal_session();
+        let path = PathBuf::from("main.rs");
+        let sent_at = Instant::now();
+        session.pending_formatting.insert(7, path.clone());
+        session.begin_formatting(&path, 7, sent_at);
+
+        assert!(
+            session.formatting_in_flight(&path, sent_at),
+            "响应返回前应拒绝再次格式化"
+        );
+        assert!(!session.formatting_in_flight(Path::new("other.rs"), sent_at));
+
+        let event = session.map_response(json!({ "jsonrpc": "2.0", "id": 7, "result": [] }));
+        assert!(matches!(event, Some(LspEvent::FormattingEdits { .. })));
+        assert!(
+            !session.formatting_in_flight(&path, sent_at),
+            "响应返回后应允许再次格式化"
+        );
+
+        session.pending_range_formatting.insert(8, path.clone());
+        session.begin_formatting(&path, 8, sent_at);
+        assert!(!session.formatting_in_flight(&path, sent_at + FORMATTING_REQUEST_TIMEOUT));
+        assert!(
+            !session.pending_range_formatting.contains_key(&8),
+            "超时的请求应被清理，迟到的响应不再应用"
+        );
+    }
+
     #[test]
     fn formatting_unknown_request_should_disable_capability() {
         let mut session = build_minimal_session();
@@ -1552,4 +2286,182 @@ mod tests {
         assert!(!session.capabilities.formatting);
         assert!(!session.pending_formatting.contains_key(&7));
     }
+
+    #[test]
+    fn code_action_request_should_scope_range_to_diagnostic() {
+        let diagnostic = DiagnosticItem {
+            file_path: PathBuf::from("main.rs"),
+            line: 4,
+            column: 9,
+            severity: DiagnosticSeverity::Error,
+            message: "cannot find value `x`".to_string(),
+            lsp_start_line: 3,
+            lsp_start_character: 8,
+            lsp_end_line: 3,
+            lsp_end_character: 9,
+            source: Some("rustc".to_string()),
+            code: Some("E0425".to_string()),
+            related: Vec::new(),
+        };
+
+        let request = build_code_action_request(
+            7,
+            "file:///main.rs",
+            (3, 8),
+            (3, 9),
+            std::slice::from_ref(&diagnostic),
+        );
+
+        assert_eq!(request["id"], json!(7));
+        assert_eq!(request["method"], json!("textDocument/codeAction"));
+        assert_eq!(
+            request["params"]["range"],
+            json!({
+                "start": { "line": 3, "character": 8 },
+                "end": { "line": 3, "character": 9 }
+            }),
+            "请求范围应与诊断范围一致"
+        );
+        let diagnostics = request["params"]["context"]["diagnostics"]
+            .as_array()
+            .expect("context 应携带诊断数组");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], json!("E0425"));
+        assert_eq!(diagnostics[0]["severity"], json!(1));
+        assert_eq!(request["params"]["context"]["only"], json!(["quickfix"]));
+    }
+
+    #[test]
+    fn format_scope_should_prefer_range_and_fall_back_to_full() {
+        let capabilities = |formatting, range_formatting| LspServerCapabilities {
+            formatting,
+            range_formatting,
+            ..LspServerCapabilities::default()
+        };
+
+        assert_eq!(
+            capabilities(true, true).format_scope(true),
+            Some(LspFormatScope::Range)
+        );
+        assert_eq!(
+            capabilities(true, true).format_scope(false),
+            Some(LspFormatScope::Full),
+            "无选区时应格式化整个文件"
+        );
+        assert_eq!(
+            capabilities(true, false).format_scope(true),
+            Some(LspFormatScope::Full),
+            "不支持范围格式化时应回退整文件"
+        );
+        assert_eq!(
+            capabilities(false, true).format_scope(true),
+            Some(LspFormatScope::Range)
+        );
+        assert_eq!(capabilities(false, true).format_scope(false), None);
+    }
+
+    #[test]
+    fn range_formatting_unknown_request_should_only_disable_range_capability() {
+        let mut session = build_minimal_session();
+        session
+            .pending_range_formatting
+            .insert(8, PathBuf::from("main.rs"));
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 8,
+            "error": {
+                "code": -32601,
+                "message": "unknown request: textDocument/rangeFormatting"
+            }
+        });
+
+        let _ = session.map_response(response);
+        assert!(!session.capabilities.range_formatting);
+        assert!(
+            session.capabilities.formatting,
+            "整文件格式化能力不应受影响"
+        );
+        assert!(!session.pending_range_formatting.contains_key(&8));
+    }
+
+    #[test]
+    fn semantic_tokens_range_request_should_cover_whole_lines_and_map_response() {
+        let request = build_semantic_tokens_range_request(9, "file:///main.rs", 40, 120);
+        assert_eq!(
+            request["method"],
+            json!("textDocument/semanticTokens/range")
+        );
+        assert_eq!(request["id"], json!(9));
+        assert_eq!(
+            request["params"]["range"],
+            json!({
+                "start": { "line": 40, "character": 0 },
+                "end": { "line": 120, "character": 0 }
+            }),
+            "范围应按整行覆盖，结束行不含"
+        );
+
+        let mut session = build_minimal_session();
+        session
+            .pending_semantic_tokens_range
+            .insert(9, (PathBuf::from("main.rs"), 40, 120));
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 9,
+            "result": { "data": [41, 4, 3, 1, 0] }
+        });
+        match session.map_response(response) {
+            Some(LspEvent::SemanticTokensRange {
+                start_line,
+                end_line,
+                tokens,
+                ..
+            }) => {
+                assert_eq!((start_line, end_line), (40, 120));
+                assert_eq!(tokens.len(), 1);
+                assert_eq!(tokens[0].line, 41, "范围响应的行号相对文档开头");
+                assert_eq!(tokens[0].token_type, "function");
+            }
+            other => panic!("返回事件类型错误，期望 SemanticTokensRange: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn idle_shutdown_should_require_no_open_buffer_and_elapsed_timeout() {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(300);
+        let last_activity = now - Duration::from_secs(301);
+
+        assert!(is_session_idle(last_activity, now, timeout, false));
+        assert!(
+            !is_session_idle(last_activity, now, timeout, true),
+            "仍有打开的缓冲区时不应回收"
+        );
+        assert!(
+            !is_session_idle(now - Duration::from_secs(10), now, timeout, false),
+            "最近有请求活动时不应回收"
+        );
+    }
+
+    #[test]
+    fn shutdown_idle_sessions_should_remove_only_idle_languages() {
+        let mut idle = build_minimal_session();
+        idle.last_activity = Instant::now() - Duration::from_secs(120);
+        let mut busy = build_minimal_session();
+        busy.language = LspLanguage::Python;
+        busy.last_activity = Instant::now() - Duration::from_secs(120);
+
+        let mut client = LspClient::new();
+        client.sessions.insert(LspLanguage::Rust, idle);
+        client.sessions.insert(LspLanguage::Python, busy);
+
+        let closed = client.shutdown_idle_sessions(Duration::from_secs(60), |language| {
+            language == LspLanguage::Python
+        });
+
+        assert_eq!(closed, vec![LspLanguage::Rust]);
+        assert!(!client.sessions.contains_key(&LspLanguage::Rust));
+        assert!(client.sessions.contains_key(&LspLanguage::Python));
+    }
 }
@@ -210,7 +210,7 @@ impl LspLanguage {
             Self::Html | Self::Css => &[],
             Self::Vue => &["package.json"],
             Self::Java => &["pom.xml", "build.gradle", "build.gradle.kts"],
-            Self::Go => &["go.mod"],
+            Self::Go => &["go.mod", "go.work"],
             Self::C | Self::Cpp => &["compile_commands.json", "CMakeLists.txt", "Makefile"],
         }
     }
@@ -4,19 +4,23 @@
 //! - `types`：对外数据结构与事件定义；
 //! - `language`：语言识别与语言服务器路由策略；
 //! - `protocol`：LSP JSON-RPC 报文编解码工具；
+//! - `workspace`：工作区目录（含 Go `go.work` 多模块）到 `workspaceFolders` 的映射；
 //! - `client`：多语言 LSP 客户端管理实现。
 
 mod client;
 mod language;
 mod protocol;
 mod types;
+mod workspace;
 
 pub use client::LspClient;
 pub use language::{
     LspLanguage, all_languages, detect_language, detect_language_from_path_or_name,
 };
 pub use types::{
-    DiagnosticItem, DiagnosticSeverity, LspCodeAction, LspCommand, LspCompletionItem, LspEvent,
-    LspSemanticToken, LspServerCapabilities, LspServerCheckItem, LspServerCheckReport, LspTextEdit,
-    LspWorkspaceEdit, LspWorkspaceFileEdit,
+    DiagnosticItem, DiagnosticRelated, DiagnosticSeverity, LspCallDirection, LspCallHierarchyItem,
+    LspCodeAction, LspCommand, LspCompletionItem, LspCompletionLabelDetails, LspEvent,
+    LspFormatScope, LspLocation, LspSemanticToken, LspServerCapabilities, LspServerCheckItem,
+    LspServerCheckReport, LspTextDocumentSyncKind, LspTextEdit, LspWorkspaceEdit,
+    LspWorkspaceFileEdit,
 };
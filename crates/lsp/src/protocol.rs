@@ -9,8 +9,10 @@ use anyhow::{Context, Result};
 use serde_json::Value;
 
 use crate::types::{
-    DiagnosticItem, DiagnosticSeverity, LspCodeAction, LspCommand, LspCompletionItem,
-    LspSemanticToken, LspServerCapabilities, LspTextEdit, LspWorkspaceEdit, LspWorkspaceFileEdit,
+    DiagnosticItem, DiagnosticRelated, DiagnosticSeverity, LspCallDirection, LspCallHierarchyItem,
+    LspCodeAction, LspCommand, LspCompletionItem, LspCompletionLabelDetails, LspLocation,
+    LspSemanticToken, LspServerCapabilities, LspTextDocumentSyncKind, LspTextEdit,
+    LspWorkspaceEdit, LspWorkspaceFileEdit,
 };
 
 /// 从 LSP 输出流读取下一条 JSON-RPC 消息。
@@ -143,6 +145,7 @@ pub fn parse_publish_diagnostics(value: &Value) -> (Option<PathBuf>, Vec<Diagnos
             .and_then(Value::as_str)
             .map(ToOwned::to_owned);
         let code = parse_diagnostic_code(diagnostic.get("code"));
+        let related = parse_diagnostic_related(diagnostic.get("relatedInformation"));
 
         let file_path = file_path
             .clone()
@@ -159,12 +162,51 @@ pub fn parse_publish_diagnostics(value: &Value) -> (Option<PathBuf>, Vec<Diagnos
             lsp_end_character,
             source,
             code,
+            related,
         });
     }
 
     (file_path, items)
 }
 
+/// 解析诊断的 `relatedInformation`，URI 无法识别的条目直接跳过。
+fn parse_diagnostic_related(raw: Option<&Value>) -> Vec<DiagnosticRelated> {
+    raw.and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    let location = entry.get("location")?;
+                    let file_path = location
+                        .get("uri")
+                        .and_then(Value::as_str)
+                        .and_then(file_uri_to_path)?;
+                    let start = location.get("range").and_then(|range| range.get("start"));
+                    let position = |key: &str| {
+                        start
+                            .and_then(|start| start.get(key))
+                            .and_then(Value::as_u64)
+                            .and_then(|value| usize::try_from(value).ok())
+                            .unwrap_or(0)
+                    };
+                    Some(DiagnosticRelated {
+                        location: LspLocation {
+                            file_path,
+                            line: position("line"),
+                            character: position("character"),
+                        },
+                        message: entry
+                            .get("message")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// 归一化诊断 code 字段。
 ///
 /// 部分服务端返回数字，部分返回字符串，这里统一转为字符串，
@@ -177,6 +219,9 @@ fn parse_diagnostic_code(raw: Option<&Value>) -> Option<String> {
     raw.as_i64().map(|code| code.to_string())
 }
 
+/// LSP `CompletionItemTag.Deprecated`。
+const COMPLETION_ITEM_TAG_DEPRECATED: u64 = 1;
+
 /// 解析 `textDocument/completion` 响应。
 pub fn parse_completion_items_from_response(value: &Value) -> Vec<LspCompletionItem> {
     let mut items = Vec::new();
@@ -212,18 +257,49 @@ pub fn parse_completion_items_from_response(value: &Value) -> Vec<LspCompletionI
             .get("detail")
             .and_then(Value::as_str)
             .map(ToOwned::to_owned);
+        let label_details = item
+            .get("labelDetails")
+            .map(|details| LspCompletionLabelDetails {
+                detail: non_empty_str(details.get("detail")),
+                description: non_empty_str(details.get("description")),
+            })
+            .filter(|details| details.detail.is_some() || details.description.is_some());
+        // 旧版协议用布尔字段，3.15 起改用 `tags`，两种写法都要识别。
+        let deprecated = item
+            .get("deprecated")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+            || item
+                .get("tags")
+                .and_then(Value::as_array)
+                .is_some_and(|tags| {
+                    tags.iter()
+                        .any(|tag| tag.as_u64() == Some(COMPLETION_ITEM_TAG_DEPRECATED))
+                });
 
         items.push(LspCompletionItem {
             label,
             insert_text,
             detail,
+            label_details,
+            deprecated,
         });
     }
 
     items
 }
 
-/// 解析 `textDocument/semanticTokens/full` 响应。
+// 读取非空字符串字段，空串视为缺失。
+fn non_empty_str(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// 解析 `textDocument/semanticTokens/full` 与 `semanticTokens/range` 响应。
+///
+/// 两者的 `data` 编码一致，范围响应的行号同样相对文档开头计算。
 pub fn parse_semantic_tokens_from_response(
     value: &Value,
     token_types: &[String],
@@ -360,13 +436,51 @@ pub fn parse_server_capabilities_from_initialize_response(
         rename: is_capability_enabled(capabilities.get("renameProvider")),
         code_action: is_capability_enabled(capabilities.get("codeActionProvider")),
         formatting: is_capability_enabled(capabilities.get("documentFormattingProvider")),
+        range_formatting: is_capability_enabled(
+            capabilities.get("documentRangeFormattingProvider"),
+        ),
+        semantic_tokens_range: is_capability_enabled(
+            capabilities
+                .get("semanticTokensProvider")
+                .and_then(|provider| provider.get("range")),
+        ),
         execute_command: capabilities
             .get("executeCommandProvider")
             .and_then(Value::as_object)
             .is_some(),
+        definition: is_capability_enabled(capabilities.get("definitionProvider")),
+        call_hierarchy: is_capability_enabled(capabilities.get("callHierarchyProvider")),
+        completion: is_capability_enabled(capabilities.get("completionProvider")),
+        completion_trigger_characters: capabilities
+            .get("completionProvider")
+            .and_then(|provider| provider.get("triggerCharacters"))
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        text_document_sync: parse_text_document_sync_kind(capabilities.get("textDocumentSync")),
     })
 }
 
+/// 解析 `textDocumentSync`：既可能是数字，也可能是带 `change` 字段的对象。
+fn parse_text_document_sync_kind(value: Option<&Value>) -> LspTextDocumentSyncKind {
+    let kind = match value {
+        Some(Value::Object(options)) => options.get("change").and_then(Value::as_u64),
+        Some(value) => value.as_u64(),
+        None => None,
+    };
+    match kind {
+        Some(1) => LspTextDocumentSyncKind::Full,
+        Some(2) => LspTextDocumentSyncKind::Incremental,
+        _ => LspTextDocumentSyncKind::None,
+    }
+}
+
 /// 统一处理「能力可能是 bool 或 object」的 LSP 字段。
 fn is_capability_enabled(value: Option<&Value>) -> bool {
     let Some(value) = value else {
@@ -451,6 +565,87 @@ pub fn parse_workspace_edit_from_value(value: &Value) -> Option<LspWorkspaceEdit
     Some(LspWorkspaceEdit { document_edits })
 }
 
+/// 解析 `textDocument/definition` 响应。
+///
+/// 规范允许返回单个 `Location`、`Location[]` 或 `LocationLink[]`，这里统一成位置列表；
+/// `LocationLink` 取 `targetSelectionRange`（符号名本身）而非整个定义体的起点。
+pub fn parse_definition_locations_from_response(value: &Value) -> Vec<LspLocation> {
+    let items = match value.get("result") {
+        Some(Value::Array(items)) => items.iter().collect::<Vec<_>>(),
+        Some(item @ Value::Object(_)) => vec![item],
+        _ => return Vec::new(),
+    };
+
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let (uri, range) = match item.get("targetUri") {
+                Some(uri) => (
+                    uri,
+                    item.get("targetSelectionRange")
+                        .or_else(|| item.get("targetRange"))?,
+                ),
+                None => (item.get("uri")?, item.get("range")?),
+            };
+            let start = range.get("start")?;
+            Some(LspLocation {
+                file_path: file_uri_to_path(uri.as_str()?)?,
+                line: start.get("line")?.as_u64()? as usize,
+                character: start.get("character")?.as_u64()? as usize,
+            })
+        })
+        .collect()
+}
+
+/// 解析 `textDocument/prepareCallHierarchy` 响应，`null` 或缺字段的节点直接忽略。
+pub fn parse_call_hierarchy_items_from_response(value: &Value) -> Vec<LspCallHierarchyItem> {
+    value
+        .get("result")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(parse_call_hierarchy_item).collect())
+        .unwrap_or_default()
+}
+
+/// 解析 `callHierarchy/incomingCalls` / `outgoingCalls` 响应。
+///
+/// 两者结构对称：incoming 的节点在 `from`，outgoing 的节点在 `to`，
+/// 这里只取节点本身，调用点范围（`fromRanges`）暂不展示。
+pub fn parse_call_hierarchy_calls_from_response(
+    value: &Value,
+    direction: LspCallDirection,
+) -> Vec<LspCallHierarchyItem> {
+    let key = match direction {
+        LspCallDirection::Incoming => "from",
+        LspCallDirection::Outgoing => "to",
+    };
+    value
+        .get("result")
+        .and_then(Value::as_array)
+        .map(|calls| {
+            calls
+                .iter()
+                .filter_map(|call| parse_call_hierarchy_item(call.get(key)?))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 解析单个 `CallHierarchyItem`，位置取 `selectionRange`，缺失时回退 `range`。
+fn parse_call_hierarchy_item(value: &Value) -> Option<LspCallHierarchyItem> {
+    let start = value
+        .get("selectionRange")
+        .or_else(|| value.get("range"))?
+        .get("start")?;
+    Some(LspCallHierarchyItem {
+        name: value.get("name")?.as_str()?.to_string(),
+        detail: non_empty_str(value.get("detail")),
+        file_path: file_uri_to_path(value.get("uri")?.as_str()?)?,
+        line: start.get("line")?.as_u64()? as usize,
+        character: start.get("character")?.as_u64()? as usize,
+        raw: value.clone(),
+    })
+}
+
 /// 解析 `textDocument/codeAction` 响应。
 pub fn parse_code_actions_from_response(value: &Value) -> Vec<LspCodeAction> {
     let Some(items) = value.get("result").and_then(Value::as_array) else {
@@ -514,6 +709,45 @@ pub fn is_workspace_apply_edit_request(value: &Value) -> bool {
         && response_request_id(value).is_some()
 }
 
+/// 判断消息是否为服务端发起的 `workspace/configuration` 请求。
+pub fn is_workspace_configuration_request(value: &Value) -> bool {
+    value
+        .get("method")
+        .and_then(Value::as_str)
+        .is_some_and(|method| method == "workspace/configuration")
+        && server_request_id(value).is_some()
+}
+
+/// 解析服务端 `workspace/configuration` 请求：`(请求 id, 各项 section)`。
+///
+/// 请求 id 按原值返回（JSON-RPC 允许数字或字符串），回包时原样带回；
+/// `scopeUri` 暂不区分，所有文件共用同一份语言设置。
+pub fn parse_workspace_configuration_request(
+    value: &Value,
+) -> Option<(Value, Vec<Option<String>>)> {
+    if !is_workspace_configuration_request(value) {
+        return None;
+    }
+
+    let request_id = server_request_id(value)?;
+    let sections = value
+        .get("params")
+        .and_then(|params| params.get("items"))
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| {
+                    item.get("section")
+                        .and_then(Value::as_str)
+                        .map(ToOwned::to_owned)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((request_id, sections))
+}
+
 /// 解析服务端 `workspace/applyEdit` 请求。
 pub fn parse_workspace_apply_edit_request(
     value: &Value,
@@ -794,6 +1028,17 @@ pub fn response_request_id(value: &Value) -> Option<u64> {
     value.get("id").and_then(Value::as_u64)
 }
 
+/// 抽取服务端请求的原始 id（数字或字符串）。
+///
+/// 客户端发出的请求 id 都是数字，但服务端发起的请求可能使用字符串 id，
+/// 回包必须原样带回，否则服务端匹配不到响应。
+pub fn server_request_id(value: &Value) -> Option<Value> {
+    value
+        .get("id")
+        .filter(|id| id.is_number() || id.is_string())
+        .cloned()
+}
+
 /// 判断响应是否存在 `result` 字段。
 pub fn has_result(value: &Value) -> bool {
     value.get("result").is_some()
@@ -851,12 +1096,113 @@ pub fn parse_rust_analyzer_progress(value: &Value) -> Option<(String, bool)> {
 mod tests {
     use serde_json::json;
 
+    use crate::types::LspCallDirection;
+
     use super::{
-        is_workspace_apply_edit_request, parse_code_actions_from_response,
-        parse_server_capabilities_from_initialize_response, parse_workspace_apply_edit_request,
+        is_workspace_apply_edit_request, parse_call_hierarchy_calls_from_response,
+        parse_call_hierarchy_items_from_response, parse_code_actions_from_response,
+        parse_completion_items_from_response, parse_definition_locations_from_response,
+        parse_publish_diagnostics, parse_server_capabilities_from_initialize_response,
+        parse_workspace_apply_edit_request, parse_workspace_configuration_request,
         parse_workspace_edit_from_value,
     };
 
+    #[test]
+    fn completion_items_should_parse_label_details() {
+        let value = json!({
+            "result": {
+                "items": [
+                    {
+                        "label": "push",
+                        "detail": "fn(&mut self, T)",
+                        "labelDetails": {"detail": "(value: T)", "description": "Vec<T>"}
+                    },
+                    {
+                        "label": "len",
+                        "detail": "fn(&self) -> usize",
+                        "labelDetails": {"detail": "", "description": ""}
+                    }
+                ]
+            }
+        });
+
+        let items = parse_completion_items_from_response(&value);
+        assert_eq!(items.len(), 2);
+        let details = items[0]
+            .label_details
+            .as_ref()
+            .expect("应解析出 labelDetails");
+        assert_eq!(details.detail.as_deref(), Some("(value: T)"));
+        assert_eq!(details.description.as_deref(), Some("Vec<T>"));
+        assert!(
+            items[1].label_details.is_none(),
+            "全部为空的 labelDetails 应视为缺失，以便回退 detail"
+        );
+        assert_eq!(items[1].detail.as_deref(), Some("fn(&self) -> usize"));
+    }
+
+    #[test]
+    fn publish_diagnostics_should_parse_related_information() {
+        let value = json!({
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": "file:///project/src/main.rs",
+                "diagnostics": [{
+                    "range": {"start": {"line": 9, "character": 4}, "end": {"line": 9, "character": 7}},
+                    "severity": 1,
+                    "message": "the name `foo` is defined multiple times",
+                    "relatedInformation": [
+                        {
+                            "location": {
+                                "uri": "file:///project/src/lib.rs",
+                                "range": {"start": {"line": 2, "character": 7}, "end": {"line": 2, "character": 10}}
+                            },
+                            "message": "previous definition of `foo` here"
+                        },
+                        {"location": {"uri": "not-a-file-uri"}, "message": "ignored"}
+                    ]
+                }]
+            }
+        });
+
+        let (_, items) = parse_publish_diagnostics(&value);
+        assert_eq!(items.len(), 1);
+        let related = &items[0].related;
+        assert_eq!(related.len(), 1, "无法识别的 URI 应被跳过");
+        assert!(related[0].location.file_path.ends_with("src/lib.rs"));
+        assert_eq!(
+            (related[0].location.line, related[0].location.character),
+            (2, 7)
+        );
+        assert_eq!(related[0].message, "previous definition of `foo` here");
+    }
+
+    #[test]
+    fn completion_items_should_parse_deprecated_flag_and_tags() {
+        let value = json!({
+            "result": [
+                {"label": "old_api", "deprecated": true},
+                {"label": "legacy", "tags": [1]},
+                {"label": "current", "tags": [], "deprecated": false},
+                {"label": "plain"}
+            ]
+        });
+
+        let deprecated = parse_completion_items_from_response(&value)
+            .into_iter()
+            .map(|item| (item.label, item.deprecated))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deprecated,
+            vec![
+                ("old_api".to_string(), true),
+                ("legacy".to_string(), true),
+                ("current".to_string(), false),
+                ("plain".to_string(), false),
+            ]
+        );
+    }
+
     #[test]
     fn workspace_edit_should_parse_changes() {
         let value = json!({
@@ -948,6 +1294,39 @@ mod tests {
         assert_eq!(edit.document_edits.len(), 1);
     }
 
+    #[test]
+    fn workspace_configuration_request_should_parse_sections() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 9,
+            "method": "workspace/configuration",
+            "params": {
+                "items": [
+                    {"scopeUri": "file:///tmp/main.py", "section": "python.analysis"},
+                    {"scopeUri": "file:///tmp/main.py"}
+                ]
+            }
+        });
+
+        let (request_id, sections) = parse_workspace_configuration_request(&request)
+            .expect("workspace/configuration 请求应可解析");
+        assert_eq!(request_id, json!(9));
+        assert_eq!(sections, vec![Some("python.analysis".to_string()), None]);
+
+        let string_id = json!({
+            "jsonrpc": "2.0",
+            "id": "cfg-1",
+            "method": "workspace/configuration",
+            "params": {"items": [{"section": "python"}]}
+        });
+        let (request_id, _) = parse_workspace_configuration_request(&string_id)
+            .expect("字符串 id 的请求同样需要回包");
+        assert_eq!(request_id, json!("cfg-1"));
+
+        let notification = json!({"jsonrpc": "2.0", "method": "workspace/configuration"});
+        assert!(parse_workspace_configuration_request(&notification).is_none());
+    }
+
     #[test]
     fn initialize_capabilities_should_parse_bool_and_object() {
         let response = json!({
@@ -972,4 +1351,121 @@ mod tests {
         assert!(!capabilities.formatting);
         assert!(capabilities.execute_command);
     }
+
+    #[test]
+    fn definition_response_should_accept_location_and_location_link() {
+        let single = json!({
+            "result": {
+                "uri": "file:///tmp/lib.rs",
+                "range": {
+                    "start": { "line": 3, "character": 4 },
+                    "end": { "line": 3, "character": 8 }
+                }
+            }
+        });
+        let locations = parse_definition_locations_from_response(&single);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(
+            locations[0].file_path,
+            std::path::PathBuf::from("/tmp/lib.rs")
+        );
+        assert_eq!((locations[0].line, locations[0].character), (3, 4));
+
+        let links = json!({
+            "result": [{
+                "targetUri": "file:///tmp/main.rs",
+                "targetRange": {
+                    "start": { "line": 10, "character": 0 },
+                    "end": { "line": 20, "character": 1 }
+                },
+                "targetSelectionRange": {
+                    "start": { "line": 10, "character": 3 },
+                    "end": { "line": 10, "character": 7 }
+                }
+            }]
+        });
+        let locations = parse_definition_locations_from_response(&links);
+        assert_eq!(
+            (locations[0].line, locations[0].character),
+            (10, 3),
+            "LocationLink 应优先取符号名所在位置"
+        );
+
+        assert!(parse_definition_locations_from_response(&json!({ "result": null })).is_empty());
+    }
+
+    #[test]
+    fn call_hierarchy_responses_should_parse_items_and_calls() {
+        let item = |name: &str, uri: &str, line: u64| {
+            json!({
+                "name": name,
+                "kind": 12,
+                "detail": "fn()",
+                "uri": uri,
+                "range": {
+                    "start": { "line": line, "character": 0 },
+                    "end": { "line": line + 5, "character": 1 }
+                },
+                "selectionRange": {
+                    "start": { "line": line, "character": 3 },
+                    "end": { "line": line, "character": 7 }
+                },
+                "data": { "opaque": 42 }
+            })
+        };
+
+        let prepared =
+            json!({ "result": [item("main", "file:///tmp/main.rs", 4), { "name": "broken" }] });
+        let items = parse_call_hierarchy_items_from_response(&prepared);
+        assert_eq!(items.len(), 1, "缺少位置的节点应忽略");
+        assert_eq!(items[0].name, "main");
+        assert_eq!(items[0].detail.as_deref(), Some("fn()"));
+        assert_eq!(items[0].file_path, std::path::PathBuf::from("/tmp/main.rs"));
+        assert_eq!(
+            (items[0].line, items[0].character),
+            (4, 3),
+            "应取 selectionRange 的起点"
+        );
+        assert_eq!(
+            items[0].raw["data"]["opaque"], 42,
+            "原始 item 需保留 data 以便回传"
+        );
+        assert!(parse_call_hierarchy_items_from_response(&json!({ "result": null })).is_empty());
+
+        let incoming = json!({
+            "result": [{
+                "from": item("caller", "file:///tmp/a.rs", 10),
+                "fromRanges": [{
+                    "start": { "line": 12, "character": 4 },
+                    "end": { "line": 12, "character": 8 }
+                }]
+            }]
+        });
+        let callers =
+            parse_call_hierarchy_calls_from_response(&incoming, LspCallDirection::Incoming);
+        assert_eq!(callers.len(), 1);
+        assert_eq!(callers[0].name, "caller");
+        assert_eq!(callers[0].line, 10);
+        assert!(
+            parse_call_hierarchy_calls_from_response(&incoming, LspCallDirection::Outgoing)
+                .is_empty(),
+            "outgoing 只读取 to 字段"
+        );
+
+        let outgoing = json!({
+            "result": [
+                { "to": item("helper", "file:///tmp/b.rs", 1), "fromRanges": [] },
+                { "to": item("other", "file:///tmp/b.rs", 20), "fromRanges": [] }
+            ]
+        });
+        let callees =
+            parse_call_hierarchy_calls_from_response(&outgoing, LspCallDirection::Outgoing);
+        assert_eq!(
+            callees
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["helper", "other"]
+        );
+    }
 }
@@ -70,6 +70,15 @@ pub struct DiagnosticItem {
     pub lsp_end_character: usize,
     pub source: Option<String>,
     pub code: Option<String>,
+    /// `relatedInformation` 中的关联位置，可能位于其他文件（如“此前的定义在这里”）。
+    pub related: Vec<DiagnosticRelated>,
+}
+
+/// 诊断的一条关联信息：位置与说明。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticRelated {
+    pub location: LspLocation,
+    pub message: String,
 }
 
 /// LSP `TextEdit` 的简化结构。
@@ -82,12 +91,69 @@ pub struct LspTextEdit {
     pub new_text: String,
 }
 
+/// `textDocument/definition` 返回的目标位置（取 `Location.range` 或 `LocationLink.targetSelectionRange` 的起点）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspLocation {
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub character: usize,
+}
+
+/// 调用层级中的一个节点（`CallHierarchyItem` 的简化结构）。
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspCallHierarchyItem {
+    pub name: String,
+    /// 附加说明，通常是所在模块或函数签名。
+    pub detail: Option<String>,
+    pub file_path: PathBuf,
+    /// `selectionRange` 起点（0-based），跳转后光标落在符号名上。
+    pub line: usize,
+    pub character: usize,
+    /// 服务端返回的原始 item。
+    ///
+    /// `incomingCalls` / `outgoingCalls` 要求把 item 原样回传（含服务端私有的 `data`），
+    /// 只保留解析后的字段会导致部分服务端找不到对应符号。
+    pub raw: Value,
+}
+
+/// 调用层级的展开方向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LspCallDirection {
+    /// 谁调用了它（`callHierarchy/incomingCalls`）。
+    Incoming,
+    /// 它调用了谁（`callHierarchy/outgoingCalls`）。
+    Outgoing,
+}
+
+impl LspCallDirection {
+    // 对应的 LSP 方法名。
+    pub fn method(self) -> &'static str {
+        match self {
+            Self::Incoming => "callHierarchy/incomingCalls",
+            Self::Outgoing => "callHierarchy/outgoingCalls",
+        }
+    }
+}
+
 /// LSP 补全项的简化结构。
 #[derive(Debug, Clone)]
 pub struct LspCompletionItem {
     pub label: String,
     pub insert_text: Option<String>,
     pub detail: Option<String>,
+    /// LSP 3.17 的 `labelDetails`；服务端提供时应优先于 `detail` 展示。
+    pub label_details: Option<LspCompletionLabelDetails>,
+    /// 是否已弃用：`deprecated: true` 或 `tags` 含 `Deprecated`（1）任一成立即为真。
+    pub deprecated: bool,
+}
+
+/// LSP `CompletionItemLabelDetails` 的简化结构。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LspCompletionLabelDetails {
+    /// 紧跟在 label 后展示的补充（通常是函数签名，如 `(a: i32)`）。
+    pub detail: Option<String>,
+    /// 弱化展示的说明（通常是类型或来源模块）。
+    pub description: Option<String>,
 }
 
 /// LSP 语义高亮 Token。
@@ -138,12 +204,105 @@ pub struct LspCodeAction {
 }
 
 /// 由服务端 `initialize` 响应归一化出的能力标记。
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct LspServerCapabilities {
     pub rename: bool,
     pub code_action: bool,
     pub formatting: bool,
+    pub range_formatting: bool,
+    /// 服务端是否支持 `textDocument/semanticTokens/range`。
+    pub semantic_tokens_range: bool,
     pub execute_command: bool,
+    pub definition: bool,
+    pub call_hierarchy: bool,
+    pub completion: bool,
+    /// 服务端声明的补全触发字符（`completionProvider.triggerCharacters`）。
+    pub completion_trigger_characters: Vec<String>,
+    pub text_document_sync: LspTextDocumentSyncKind,
+}
+
+/// 服务端声明的文档同步方式（`textDocumentSync`）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LspTextDocumentSyncKind {
+    /// 未声明或为 `0`：服务端不接收文档变更。
+    #[default]
+    None,
+    /// `1`：每次发送整份文本。
+    Full,
+    /// `2`：发送增量变更。
+    Incremental,
+}
+
+impl LspTextDocumentSyncKind {
+    // 能力面板中的展示文本。
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Full => "full",
+            Self::Incremental => "incremental",
+        }
+    }
+}
+
+impl LspServerCapabilities {
+    /// 根据是否存在选区与服务端能力，选择格式化请求的范围。
+    ///
+    /// 有选区时优先范围格式化；服务端不支持时回退整文件，
+    /// 让同一个入口在任意服务端上都有可预期的结果。两者都不支持时返回 `None`。
+    pub fn format_scope(&self, has_selection: bool) -> Option<LspFormatScope> {
+        if has_selection && self.range_formatting {
+            return Some(LspFormatScope::Range);
+        }
+        self.formatting.then_some(LspFormatScope::Full)
+    }
+
+    /// 按 `(能力名, 取值)` 列出全部已跟踪的能力，供编辑器能力面板展示。
+    ///
+    /// 能力名使用 LSP 规范中的字段名，便于与服务端日志或文档直接对照，
+    /// 排查某个功能为什么被禁用。
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let flag = |enabled: bool| if enabled { "yes" } else { "no" }.to_string();
+        let trigger_characters = if self.completion_trigger_characters.is_empty() {
+            "-".to_string()
+        } else {
+            self.completion_trigger_characters
+                .iter()
+                .map(|item| format!("`{item}`"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        vec![
+            (
+                "textDocumentSync",
+                self.text_document_sync.label().to_string(),
+            ),
+            ("completionProvider", flag(self.completion)),
+            ("completion.triggerCharacters", trigger_characters),
+            ("renameProvider", flag(self.rename)),
+            ("codeActionProvider", flag(self.code_action)),
+            ("executeCommandProvider", flag(self.execute_command)),
+            ("definitionProvider", flag(self.definition)),
+            ("callHierarchyProvider", flag(self.call_hierarchy)),
+            ("documentFormattingProvider", flag(self.formatting)),
+            (
+                "documentRangeFormattingProvider",
+                flag(self.range_formatting),
+            ),
+            (
+                "semanticTokensProvider.range",
+                flag(self.semantic_tokens_range),
+            ),
+        ]
+    }
+}
+
+/// 格式化请求实际使用的范围。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LspFormatScope {
+    /// `textDocument/rangeFormatting`。
+    Range,
+    /// `textDocument/formatting`。
+    Full,
 }
 
 /// 由 LSP 客户端发给上层 UI 的事件。
@@ -169,6 +328,13 @@ pub enum LspEvent {
         file_path: PathBuf,
         tokens: Vec<LspSemanticToken>,
     },
+    /// `semanticTokens/range` 返回，只覆盖 `[start_line, end_line)` 内的行。
+    SemanticTokensRange {
+        file_path: PathBuf,
+        start_line: usize,
+        end_line: usize,
+        tokens: Vec<LspSemanticToken>,
+    },
     /// `textDocument/formatting` 返回。
     FormattingEdits {
         file_path: PathBuf,
@@ -180,6 +346,22 @@ pub enum LspEvent {
         new_name: String,
         edit: Option<LspWorkspaceEdit>,
     },
+    /// `textDocument/definition` 返回。
+    DefinitionLocations {
+        file_path: PathBuf,
+        locations: Vec<LspLocation>,
+    },
+    /// `textDocument/prepareCallHierarchy` 返回：光标处可展开的根节点。
+    CallHierarchyPrepared {
+        file_path: PathBuf,
+        items: Vec<LspCallHierarchyItem>,
+    },
+    /// `callHierarchy/incomingCalls` / `outgoingCalls` 返回：`item` 的调用者或被调用者。
+    CallHierarchyCalls {
+        item: LspCallHierarchyItem,
+        direction: LspCallDirection,
+        calls: Vec<LspCallHierarchyItem>,
+    },
     /// `textDocument/codeAction` 返回。
     CodeActions {
         file_path: PathBuf,
@@ -0,0 +1,236 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::{language::LspLanguage, protocol};
+
+/// 计算发送给语言服务器的工作区目录列表（`initialize.params.workspaceFolders`）。
+///
+/// 默认只有工作区根目录；Go 项目根目录存在 `go.work` 时，
+/// 额外把其中 `use` 的每个模块目录加入列表，让 gopls 为多模块仓库建立正确的视图，
+/// 否则子模块内的诊断会按“不在任何模块中”报告。
+pub(crate) fn workspace_folders(
+    workspace_root: &Path,
+    language: LspLanguage,
+) -> Result<Vec<Value>> {
+    let mut directories = vec![workspace_root.to_path_buf()];
+    if language == LspLanguage::Go
+        && let Ok(text) = fs::read_to_string(workspace_root.join("go.work"))
+    {
+        for module in parse_go_work_uses(&text) {
+            // 去掉 `./` 等冗余组件，保证同一目录只出现一次。
+            let directory = workspace_root
+                .join(module)
+                .components()
+                .collect::<PathBuf>();
+            if !directories.contains(&directory) {
+                directories.push(directory);
+            }
+        }
+    }
+
+    directories
+        .iter()
+        .map(|directory| workspace_folder(directory))
+        .collect()
+}
+
+/// 构建 `workspace/didChangeWorkspaceFolders` 的参数：旧目录中不再需要的移除，新增的加入。
+pub(crate) fn workspace_folders_change(old_folders: &[Value], new_folders: &[Value]) -> Value {
+    let removed = old_folders
+        .iter()
+        .filter(|folder| !new_folders.contains(folder))
+        .cloned()
+        .collect::<Vec<_>>();
+    let added = new_folders
+        .iter()
+        .filter(|folder| !old_folders.contains(folder))
+        .cloned()
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "event": {
+            "added": added,
+            "removed": removed
+        }
+    })
+}
+
+/// 按 `workspace/configuration` 请求的 `items` 逐项取出配置，结果与请求一一对应。
+///
+/// `section` 为空时返回整份设置；带点号的 section（如 `python.analysis`）逐级查找，
+/// 找不到的项返回 `null`，让服务端使用自身默认值而不是一直等待响应。
+pub(crate) fn configuration_response(settings: &Value, sections: &[Option<String>]) -> Vec<Value> {
+    sections
+        .iter()
+        .map(|section| match section.as_deref() {
+            None | Some("") => settings.clone(),
+            Some(section) => section
+                .split('.')
+                .try_fold(settings, |value, key| value.get(key))
+                .cloned()
+                .unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+/// 解析 `go.work` 中 `use` 指令列出的模块目录（保持原始相对路径）。
+///
+/// 同时支持单行 `use ./a` 与块形式 `use ( ... )`，忽略 `//` 注释与其他指令。
+fn parse_go_work_uses(text: &str) -> Vec<String> {
+    let mut modules = Vec::new();
+    let mut in_use_block = false;
+    for raw_line in text.lines() {
+        let line = raw_line
+            .split_once("//")
+            .map_or(raw_line, |(code, _)| code)
+            .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if in_use_block {
+            if line == ")" {
+                in_use_block = false;
+            } else {
+                modules.push(unquote(line));
+            }
+            continue;
+        }
+
+        // `use` 后必须是空白或 `(`，避免把 `user ...` 之类误当作指令。
+        let Some(rest) = line
+            .strip_prefix("use")
+            .filter(|rest| rest.starts_with(char::is_whitespace) || rest.starts_with('('))
+        else {
+            continue;
+        };
+        let rest = rest.trim();
+        if rest == "(" {
+            in_use_block = true;
+        } else {
+            modules.push(unquote(rest));
+        }
+    }
+    modules
+}
+
+// 去掉 `go.work` 路径两侧可选的引号。
+fn unquote(value: &str) -> String {
+    value.trim_matches(|ch| ch == '"' || ch == '`').to_string()
+}
+
+// 单个工作区目录：`uri` 加上用于展示的目录名。
+fn workspace_folder(directory: &Path) -> Result<Value> {
+    let uri = protocol::path_to_file_uri(directory)
+        .with_context(|| format!("工作区目录无法转换为 URI: {}", directory.display()))?;
+    let name = directory
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| directory.display().to_string());
+    Ok(serde_json::json!({ "uri": uri, "name": name }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use crate::language::LspLanguage;
+
+    use super::{
+        configuration_response, parse_go_work_uses, workspace_folders, workspace_folders_change,
+    };
+
+    #[test]
+    fn parse_go_work_uses_should_read_block_and_single_line_forms() {
+        let text = "go 1.22\n\nuse (\n\t./svc/api // 接口服务\n\t\"./svc/worker\"\n)\n\nuse ./tools\nreplace example.com/x => ./x\n";
+        assert_eq!(
+            parse_go_work_uses(text),
+            vec!["./svc/api", "./svc/worker", "./tools"]
+        );
+        assert!(parse_go_work_uses("go 1.22\nuser ./not-a-directive\n").is_empty());
+    }
+
+    #[test]
+    fn workspace_folders_should_include_go_work_modules() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let root =
+            std::env::temp_dir().join(format!("order-go-work-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(&root).expect("创建临时工作区失败");
+        fs::write(
+            root.join("go.work"),
+            "go 1.22\n\nuse (\n\t.\n\t./svc/api\n\t./svc/worker\n)\n",
+        )
+        .expect("写入 go.work 失败");
+
+        let folders = workspace_folders(&root, LspLanguage::Go).expect("应生成工作区目录");
+        let names = folders
+            .iter()
+            .map(|folder| folder["name"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        let root_name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // `.` 与根目录相同，只保留一次。
+        assert_eq!(
+            names,
+            vec![root_name, "api".to_string(), "worker".to_string()]
+        );
+        assert!(
+            folders[1]["uri"]
+                .as_str()
+                .is_some_and(|uri| uri.starts_with("file://") && uri.ends_with("/svc/api"))
+        );
+
+        // 非 Go 语言不读取 go.work。
+        let rust_folders = workspace_folders(&root, LspLanguage::Rust).expect("应生成工作区目录");
+        assert_eq!(rust_folders.len(), 1);
+
+        let change = workspace_folders_change(&folders, &rust_folders);
+        assert_eq!(change["event"]["added"].as_array().map(Vec::len), Some(0));
+        assert_eq!(change["event"]["removed"].as_array().map(Vec::len), Some(2));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn configuration_response_should_match_requested_sections() {
+        let settings = serde_json::json!({
+            "python": {
+                "analysis": { "typeCheckingMode": "strict" },
+                "pythonPath": "/usr/bin/python3"
+            }
+        });
+        let sections = vec![
+            Some("python.analysis".to_string()),
+            Some("python".to_string()),
+            Some("python.venvPath".to_string()),
+            Some("pyright".to_string()),
+            None,
+        ];
+
+        let response = configuration_response(&settings, &sections);
+        assert_eq!(response.len(), sections.len(), "响应项数应与请求一致");
+        assert_eq!(
+            response[0],
+            serde_json::json!({ "typeCheckingMode": "strict" })
+        );
+        assert_eq!(response[1], settings["python"]);
+        assert!(response[2].is_null(), "未配置的子项应返回 null");
+        assert!(response[3].is_null(), "未知 section 应返回 null");
+        assert_eq!(response[4], settings, "未指定 section 时返回整份设置");
+
+        let empty = configuration_response(&serde_json::Value::Null, &sections);
+        assert!(empty.iter().all(serde_json::Value::is_null));
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use lsp::detect_language_from_path_or_name;
+
+use super::utils::is_word_char;
+
+/// INSERT 模式输入一个字符时的配对处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PairAction {
+    /// 按普通字符插入。
+    Insert,
+    /// 插入字符及其闭合字符，光标停在两者之间。
+    InsertPair(char),
+    /// 光标后恰好是同一个闭合字符，直接越过而不重复插入。
+    SkipOver,
+}
+
+// 返回开字符对应的闭合字符。
+fn closing_char(ch: char) -> Option<char> {
+    match ch {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' | '\'' | '`' => Some(ch),
+        _ => None,
+    }
+}
+
+/// 计算在 `line` 的第 `col` 个字符处输入 `ch` 时的配对动作。
+///
+/// 引号只在前后都不是单词字符时才配对，避免 `don't`、`a"b` 这类输入被多补一个引号；
+/// 光标后紧跟单词字符时括号也不配对，给已有内容加括号时不会凭空多出闭合符。
+pub(super) fn pair_action(line: &str, col: usize, ch: char) -> PairAction {
+    let mut chars = line.chars().skip(col.saturating_sub(1));
+    let prev = if col == 0 { None } else { chars.next() };
+    let next = chars.next();
+
+    if next == Some(ch) && matches!(ch, ')' | ']' | '}' | '"' | '\'' | '`') {
+        return PairAction::SkipOver;
+    }
+    let Some(close) = closing_char(ch) else {
+        return PairAction::Insert;
+    };
+    if next.is_some_and(is_word_char) {
+        return PairAction::Insert;
+    }
+    if close == ch && prev.is_some_and(is_word_char) {
+        return PairAction::Insert;
+    }
+    PairAction::InsertPair(close)
+}
+
+/// 计算缓冲区用于查询自动配对设置的语言键。
+///
+/// 能识别为 LSP 语言时使用 `languageId`，与 `lsp_auto_activate` 的键保持一致；
+/// 否则 Markdown 归为 `markdown`，无扩展名或 `.txt` 归为 `plaintext`，
+/// 其余使用小写扩展名（如 `lisp`、`clj`），便于为没有 LSP 的语言单独关闭。
+pub(super) fn pairing_language_key(path: Option<&Path>, name: &str) -> String {
+    if let Some(language) = detect_language_from_path_or_name(path, name) {
+        return language.language_id().to_string();
+    }
+    let extension = path
+        .unwrap_or_else(|| Path::new(name))
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "md" | "markdown" => "markdown".to_string(),
+        "" | "txt" | "text" => "plaintext".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{PairAction, pair_action, pairing_language_key};
+
+    #[test]
+    fn test_pair_action_brackets_and_quotes() {
+        assert_eq!(pair_action("", 0, '('), PairAction::InsertPair(')'));
+        assert_eq!(pair_action("foo ", 4, '{'), PairAction::InsertPair('}'));
+        assert_eq!(
+            pair_action("()", 1, ')'),
+            PairAction::SkipOver,
+            "应越过已有闭合符"
+        );
+        assert_eq!(
+            pair_action("x", 0, '('),
+            PairAction::Insert,
+            "后面紧跟单词时不配对"
+        );
+        assert_eq!(pair_action("let s = ", 8, '"'), PairAction::InsertPair('"'));
+        assert_eq!(
+            pair_action("don", 3, '\''),
+            PairAction::Insert,
+            "单词后的撇号不配对"
+        );
+        assert_eq!(pair_action("\"\"", 1, '"'), PairAction::SkipOver);
+        assert_eq!(pair_action("abc", 3, 'd'), PairAction::Insert);
+    }
+
+    #[test]
+    fn test_pairing_language_key() {
+        assert_eq!(
+            pairing_language_key(Some(Path::new("src/main.rs")), "main.rs"),
+            "rust"
+        );
+        assert_eq!(
+            pairing_language_key(Some(Path::new("README.md")), "README.md"),
+            "markdown"
+        );
+        assert_eq!(pairing_language_key(None, "untitled-1"), "plaintext");
+        assert_eq!(
+            pairing_language_key(Some(Path::new("init.el")), "init.el"),
+            "el"
+        );
+    }
+}
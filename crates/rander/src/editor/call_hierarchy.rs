@@ -0,0 +1,228 @@
+use lsp::{LspCallDirection, LspCallHierarchyItem};
+
+/// 调用层级浮层中的一行。
+#[derive(Debug, Clone)]
+pub(super) struct CallHierarchyEntry {
+    pub(super) item: LspCallHierarchyItem,
+    pub(super) depth: usize,
+    /// 已展开：子节点紧跟在其后，深度加一。
+    pub(super) expanded: bool,
+    /// 展开请求已发出、尚未返回。
+    pub(super) loading: bool,
+}
+
+impl CallHierarchyEntry {
+    // 新建一个未展开的节点。
+    fn new(item: LspCallHierarchyItem, depth: usize) -> Self {
+        Self {
+            item,
+            depth,
+            expanded: false,
+            loading: false,
+        }
+    }
+}
+
+/// 调用层级浮层：以扁平列表保存可展开的树，按深度缩进展示。
+///
+/// 与目录树一样用“列表 + 深度”表示树，展开时把子节点插到父节点之后、
+/// 折叠时删掉其后更深的连续行，选中与滚动都只需处理一个下标。
+#[derive(Debug, Clone)]
+pub(super) struct CallHierarchyView {
+    pub(super) direction: LspCallDirection,
+    pub(super) entries: Vec<CallHierarchyEntry>,
+    pub(super) selected: usize,
+}
+
+impl CallHierarchyView {
+    // 以 prepare 返回的节点作为根创建浮层。
+    pub(super) fn new(roots: Vec<LspCallHierarchyItem>, direction: LspCallDirection) -> Self {
+        Self {
+            direction,
+            entries: roots
+                .into_iter()
+                .map(|item| CallHierarchyEntry::new(item, 0))
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    // 当前选中的节点。
+    pub(super) fn selected_entry(&self) -> Option<&CallHierarchyEntry> {
+        self.entries.get(self.selected)
+    }
+
+    // 上下移动选中行，到两端时停住。
+    pub(super) fn move_selection(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// 标记选中节点开始展开，返回需要向服务端请求的节点。
+    ///
+    /// 已展开或正在加载时返回 `None`，避免重复请求把同一批子节点插入两次。
+    pub(super) fn begin_expand(&mut self) -> Option<LspCallHierarchyItem> {
+        let entry = self.entries.get_mut(self.selected)?;
+        if entry.expanded || entry.loading {
+            return None;
+        }
+        entry.loading = true;
+        Some(entry.item.clone())
+    }
+
+    // 展开请求发送失败时撤销加载标记，允许再次尝试。
+    pub(super) fn abort_expand(&mut self, item: &LspCallHierarchyItem) {
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.loading && entry.item == *item)
+        {
+            entry.loading = false;
+        }
+    }
+
+    /// 把展开结果插到对应节点之后，返回是否找到了等待结果的节点。
+    ///
+    /// 方向不一致说明请求发出后用户已切换方向，结果已过期，直接丢弃。
+    pub(super) fn apply_calls(
+        &mut self,
+        item: &LspCallHierarchyItem,
+        direction: LspCallDirection,
+        calls: Vec<LspCallHierarchyItem>,
+    ) -> bool {
+        if direction != self.direction {
+            return false;
+        }
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.loading && entry.item == *item)
+        else {
+            return false;
+        };
+        let depth = self.entries[index].depth + 1;
+        self.entries[index].loading = false;
+        self.entries[index].expanded = true;
+        let children = calls
+            .into_iter()
+            .map(|call| CallHierarchyEntry::new(call, depth));
+        self.entries.splice(index + 1..index + 1, children);
+        true
+    }
+
+    /// 折叠选中节点；已折叠时选中其父节点，与目录树的 `h` 行为一致。
+    pub(super) fn collapse_selected(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        let depth = entry.depth;
+        if entry.expanded {
+            let end = self.subtree_end(self.selected);
+            self.entries.drain(self.selected + 1..end);
+            self.entries[self.selected].expanded = false;
+            return;
+        }
+        if let Some(parent) = self.entries[..self.selected]
+            .iter()
+            .rposition(|entry| entry.depth < depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    /// 切换展开方向：只保留根节点并全部折叠，之后的展开按新方向请求。
+    pub(super) fn set_direction(&mut self, direction: LspCallDirection) {
+        self.direction = direction;
+        self.entries.retain(|entry| entry.depth == 0);
+        for entry in &mut self.entries {
+            entry.expanded = false;
+            entry.loading = false;
+        }
+        self.selected = 0;
+    }
+
+    // `index` 节点子树之后第一行的下标。
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.entries[index].depth;
+        self.entries[index + 1..]
+            .iter()
+            .position(|entry| entry.depth <= depth)
+            .map_or(self.entries.len(), |offset| index + 1 + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lsp::{LspCallDirection, LspCallHierarchyItem};
+
+    use super::CallHierarchyView;
+
+    fn item(name: &str, line: usize) -> LspCallHierarchyItem {
+        LspCallHierarchyItem {
+            name: name.to_string(),
+            detail: None,
+            file_path: PathBuf::from(format!("/tmp/{name}.rs")),
+            line,
+            character: 0,
+            raw: serde_json::json!({ "name": name }),
+        }
+    }
+
+    fn names(view: &CallHierarchyView) -> Vec<(usize, &str)> {
+        view.entries
+            .iter()
+            .map(|entry| (entry.depth, entry.item.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_call_hierarchy_expand_and_collapse() {
+        let mut view = CallHierarchyView::new(vec![item("root", 1)], LspCallDirection::Incoming);
+
+        let requested = view.begin_expand().expect("根节点应可展开");
+        assert!(view.begin_expand().is_none(), "加载中不应重复请求");
+        assert!(view.apply_calls(
+            &requested,
+            LspCallDirection::Incoming,
+            vec![item("a", 2), item("b", 3)]
+        ));
+        assert_eq!(names(&view), vec![(0, "root"), (1, "a"), (1, "b")]);
+
+        view.move_selection(1);
+        let child = view.begin_expand().expect("子节点应可展开");
+        view.apply_calls(&child, LspCallDirection::Incoming, vec![item("c", 4)]);
+        assert_eq!(
+            names(&view),
+            vec![(0, "root"), (1, "a"), (2, "c"), (1, "b")]
+        );
+
+        view.move_selection(1);
+        view.collapse_selected();
+        assert_eq!(view.selected, 1, "未展开节点按 h 时回到父节点");
+        view.collapse_selected();
+        assert_eq!(names(&view), vec![(0, "root"), (1, "a"), (1, "b")]);
+
+        view.move_selection(-5);
+        view.collapse_selected();
+        assert_eq!(names(&view), vec![(0, "root")], "折叠根节点应移除整棵子树");
+    }
+
+    #[test]
+    fn test_call_hierarchy_ignores_stale_direction() {
+        let mut view = CallHierarchyView::new(vec![item("root", 1)], LspCallDirection::Incoming);
+        let requested = view.begin_expand().expect("根节点应可展开");
+
+        view.set_direction(LspCallDirection::Outgoing);
+        assert!(
+            !view.apply_calls(&requested, LspCallDirection::Incoming, vec![item("a", 2)]),
+            "切换方向后旧结果应丢弃"
+        );
+        assert_eq!(names(&view), vec![(0, "root")]);
+
+        let requested = view.begin_expand().expect("切换方向后可重新展开");
+        view.abort_expand(&requested);
+        assert!(view.begin_expand().is_some(), "发送失败后允许再次展开");
+    }
+}
@@ -0,0 +1,363 @@
+use std::path::Path;
+
+use lsp::DiagnosticItem;
+
+/// 每条诊断向上/向下附带的代码行数。
+const DIAGNOSTIC_CONTEXT_LINES: usize = 3;
+
+/// 自动附带活动文件时的字符预算。
+///
+/// 按字符而非 token 计算，足够粗略地限制单条消息不被整文件撑爆。
+pub const ACTIVE_FILE_CONTEXT_BUDGET_CHARS: usize = 6000;
+
+/// editor 交给主对话界面的预填充内容。
+///
+/// 拆成“指令 + 上下文”两部分的原因是：主界面输入框是单行的，
+/// 只适合放一句可编辑的指令；多行的诊断与代码片段作为附件在发送时拼接。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatHandoff {
+    /// 预填充到输入框的指令，用户可在发送前修改。
+    pub instruction: String,
+    /// 发送时追加在指令后的多行上下文。
+    pub context: String,
+    /// 带入内容的简短说明，如“2 条诊断及代码上下文”，用于主界面提示。
+    pub summary: String,
+}
+
+/// editor 退出时的活动文件快照，主界面据此在下一条消息中自动附带代码。
+///
+/// 保存缓冲区内容而不是路径，是为了让尚未保存的修改也能带给模型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveFileContext {
+    /// 展示给模型的文件路径（相对工作区根目录）。
+    pub label: String,
+    /// 缓冲区全部行。
+    pub lines: Vec<String>,
+    /// 退出时光标所在行（0 基），预算不足时以此为中心截取。
+    pub focus_row: usize,
+}
+
+impl ActiveFileContext {
+    /// 在字符预算内生成附带的上下文文本。
+    ///
+    /// 整个文件放得下时全量附带；否则从光标行出发交替向上、向下扩展，
+    /// 保证用户正在看的区域优先进入预算。文件为空或光标行本身就超出预算
+    /// （如压缩过的单行文件）时返回 `None`，不附带半行代码。
+    pub fn render_within_budget(&self, budget_chars: usize) -> Option<String> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        // 每行额外计一个换行符。
+        let cost = |row: usize| self.lines[row].chars().count() + 1;
+        let last_index = self.lines.len() - 1;
+        let total = (0..=last_index).map(cost).sum::<usize>();
+
+        let (start, end) = if total <= budget_chars {
+            (0, last_index)
+        } else {
+            let focus = self.focus_row.min(last_index);
+            let mut used = cost(focus);
+            if used > budget_chars {
+                return None;
+            }
+            let (mut start, mut end) = (focus, focus);
+            loop {
+                let mut grew = false;
+                if start > 0 && used + cost(start - 1) <= budget_chars {
+                    start -= 1;
+                    used += cost(start);
+                    grew = true;
+                }
+                if end < last_index && used + cost(end + 1) <= budget_chars {
+                    end += 1;
+                    used += cost(end);
+                    grew = true;
+                }
+                if !grew {
+                    break;
+                }
+            }
+            (start, end)
+        };
+
+        let fence = Path::new(&self.label)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        let width = (end + 1).to_string().len();
+        let body = (start..=end)
+            .map(|row| format!("{:>width$} | {}", row + 1, self.lines[row]))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let scope = if start == 0 && end == last_index {
+            format!("全文，共 {} 行", self.lines.len())
+        } else {
+            format!(
+                "第 {}-{} 行，共 {} 行",
+                start + 1,
+                end + 1,
+                self.lines.len()
+            )
+        };
+        Some(format!(
+            "当前活动文件 `{}`（{scope}）：\n```{fence}\n{body}\n```",
+            self.label
+        ))
+    }
+}
+
+/// 根据当前文件的诊断与缓冲区内容构建“修复诊断”提示。
+///
+/// 参数：
+/// - `file_label`：展示给模型的文件路径（通常是相对工作区根目录的路径）；
+/// - `diagnostics`：当前文件的诊断列表；
+/// - `lines`：缓冲区全部行，用于截取诊断附近的代码片段。
+///
+/// 返回值：诊断为空时返回 `None`，避免向对话发送空提示。
+pub(super) fn build_diagnostics_fix_handoff(
+    file_label: &str,
+    diagnostics: &[DiagnosticItem],
+    lines: &[String],
+) -> Option<ChatHandoff> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let mut sorted = diagnostics.to_vec();
+    sorted.sort_by(|left, right| {
+        left.lsp_start_line
+            .cmp(&right.lsp_start_line)
+            .then(left.lsp_start_character.cmp(&right.lsp_start_character))
+    });
+
+    let fence = Path::new(file_label)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let mut sections = Vec::with_capacity(sorted.len());
+    for (index, item) in sorted.iter().enumerate() {
+        let mut header = format!(
+            "{}. [{}] 第 {} 行第 {} 列",
+            index + 1,
+            item.severity.as_str(),
+            item.line,
+            item.column
+        );
+        if let Some(code) = item.code.as_deref() {
+            header.push_str(&format!("（{code}）"));
+        }
+        header.push_str(&format!("：{}", item.message));
+        if let Some(source) = item.source.as_deref() {
+            header.push_str(&format!("（来源：{source}）"));
+        }
+
+        let slice = format_code_slice(lines, item.lsp_start_line, item.lsp_end_line);
+        sections.push(format!("{header}\n```{fence}\n{slice}\n```"));
+    }
+
+    Some(ChatHandoff {
+        instruction: format!("请修复 `{file_label}` 中的 {} 条诊断问题", sorted.len()),
+        context: format!("诊断详情（行号从 1 开始）：\n\n{}", sections.join("\n\n")),
+        summary: format!("{} 条诊断及代码上下文", sorted.len()),
+    })
+}
+
+/// 把 VISUAL 选区（含两端的行号范围）包装成代码块交给对话。
+///
+/// `line_numbers` 为 `true` 时每行加上与诊断片段相同的 `N | ` 行号前缀，
+/// 方便模型引用具体位置；默认不加，保证代码块可以原样复制回文件。
+pub(super) fn build_selection_handoff(
+    file_label: &str,
+    lines: &[String],
+    start_row: usize,
+    end_row: usize,
+    line_numbers: bool,
+) -> Option<ChatHandoff> {
+    if start_row > end_row || end_row >= lines.len() {
+        return None;
+    }
+
+    let fence = Path::new(file_label)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let width = (end_row + 1).to_string().len();
+    let body = (start_row..=end_row)
+        .map(|row| {
+            if line_numbers {
+                format!("{:>width$} | {}", row + 1, lines[row])
+            } else {
+                lines[row].clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let line_count = end_row - start_row + 1;
+
+    Some(ChatHandoff {
+        instruction: format!(
+            "关于 `{file_label}` 第 {}-{} 行的代码：",
+            start_row + 1,
+            end_row + 1
+        ),
+        context: format!("`{file_label}` 选中的代码：\n```{fence}\n{body}\n```"),
+        summary: format!("{line_count} 行选中代码"),
+    })
+}
+
+/// 截取诊断范围上下各 `DIAGNOSTIC_CONTEXT_LINES` 行，并加上行号前缀。
+///
+/// 带行号的原因是模型回复时可以直接引用具体位置，减少来回确认。
+fn format_code_slice(lines: &[String], start_line: usize, end_line: usize) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    let last_index = lines.len() - 1;
+    let start = start_line
+        .min(last_index)
+        .saturating_sub(DIAGNOSTIC_CONTEXT_LINES);
+    let end = (end_line.max(start_line) + DIAGNOSTIC_CONTEXT_LINES).min(last_index);
+    let width = (end + 1).to_string().len();
+
+    (start..=end)
+        .map(|row| format!("{:>width$} | {}", row + 1, lines[row]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+    use super::{ActiveFileContext, build_diagnostics_fix_handoff, build_selection_handoff};
+
+    fn diagnostic(line: usize, message: &str, code: Option<&str>) -> DiagnosticItem {
+        DiagnosticItem {
+            file_path: PathBuf::from("src/main.rs"),
+            line: line as u64 + 1,
+            column: 5,
+            severity: DiagnosticSeverity::Error,
+            message: message.to_string(),
+            lsp_start_line: line,
+            lsp_start_character: 4,
+            lsp_end_line: line,
+            lsp_end_character: 8,
+            source: Some("rustc".to_string()),
+            code: code.map(ToString::to_string),
+            related: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_build_handoff_returns_none_without_diagnostics() {
+        let lines = vec!["fn main() {}".to_string()];
+        assert!(build_diagnostics_fix_handoff("src/main.rs", &[], &lines).is_none());
+    }
+
+    #[test]
+    fn test_build_handoff_formats_diagnostics_with_code_slices() {
+        let lines = (1..=10)
+            .map(|index| format!("line{index}"))
+            .collect::<Vec<_>>();
+        let diagnostics = vec![
+            diagnostic(8, "unused variable", None),
+            diagnostic(1, "mismatched types", Some("E0308")),
+        ];
+
+        let handoff = build_diagnostics_fix_handoff("src/main.rs", &diagnostics, &lines)
+            .expect("存在诊断时应生成提示");
+
+        assert_eq!(handoff.summary, "2 条诊断及代码上下文");
+        assert_eq!(
+            handoff.instruction,
+            "请修复 `src/main.rs` 中的 2 条诊断问题"
+        );
+        // 诊断应按行号排序，且包含 code/message/来源。
+        let first = handoff
+            .context
+            .find("1. [error] 第 2 行第 5 列（E0308）：mismatched types（来源：rustc）")
+            .expect("应包含第一条诊断");
+        let second = handoff
+            .context
+            .find("2. [error] 第 9 行第 5 列：unused variable")
+            .expect("应包含第二条诊断");
+        assert!(first < second);
+        // 代码片段带行号，且在文件边界处截断。
+        assert!(handoff.context.contains("```rs\n1 | line1\n2 | line2"));
+        assert!(handoff.context.contains("5 | line5\n```"));
+        assert!(handoff.context.contains(" 6 | line6\n"));
+        assert!(handoff.context.contains("10 | line10\n```"));
+    }
+
+    #[test]
+    fn test_build_selection_handoff_formats_numbered_and_plain_blocks() {
+        let lines = (1..=12)
+            .map(|index| format!("let v{index} = {index};"))
+            .collect::<Vec<_>>();
+
+        let plain = build_selection_handoff("src/lib.rs", &lines, 8, 10, false)
+            .expect("合法选区应生成提示");
+        assert_eq!(plain.instruction, "关于 `src/lib.rs` 第 9-11 行的代码：");
+        assert_eq!(plain.summary, "3 行选中代码");
+        assert!(
+            plain
+                .context
+                .ends_with("```rs\nlet v9 = 9;\nlet v10 = 10;\nlet v11 = 11;\n```")
+        );
+
+        // 行号按选区末行宽度右对齐，便于对齐阅读。
+        let numbered =
+            build_selection_handoff("src/lib.rs", &lines, 8, 10, true).expect("合法选区应生成提示");
+        assert!(
+            numbered
+                .context
+                .ends_with("```rs\n 9 | let v9 = 9;\n10 | let v10 = 10;\n11 | let v11 = 11;\n```")
+        );
+
+        assert!(build_selection_handoff("src/lib.rs", &lines, 3, 12, false).is_none());
+    }
+
+    #[test]
+    fn test_active_file_context_includes_whole_file_within_budget() {
+        let context = ActiveFileContext {
+            label: "src/lib.rs".to_string(),
+            lines: vec!["fn a() {}".to_string(), "fn b() {}".to_string()],
+            focus_row: 1,
+        };
+
+        let text = context.render_within_budget(100).expect("预算充足时应附带");
+        assert!(text.starts_with("当前活动文件 `src/lib.rs`（全文，共 2 行）"));
+        assert!(text.contains("```rs\n1 | fn a() {}\n2 | fn b() {}\n```"));
+    }
+
+    #[test]
+    fn test_active_file_context_centers_on_focus_row_when_over_budget() {
+        // 每行 6 个字符（含换行），预算 18 只能容纳 3 行。
+        let context = ActiveFileContext {
+            label: "notes".to_string(),
+            lines: (1..=9).map(|index| format!("line{index}")).collect(),
+            focus_row: 4,
+        };
+
+        let text = context
+            .render_within_budget(18)
+            .expect("光标行放得下时应附带");
+        assert!(
+            text.contains("（第 4-6 行，共 9 行）"),
+            "应以光标行为中心截取"
+        );
+        assert!(text.contains("4 | line4\n5 | line5\n6 | line6\n```"));
+        assert!(!text.contains("line3"));
+
+        // 光标行本身超出预算时不附带。
+        assert!(context.render_within_budget(3).is_none());
+        let empty = ActiveFileContext {
+            lines: Vec::new(),
+            ..context
+        };
+        assert!(empty.render_within_budget(100).is_none());
+    }
+}
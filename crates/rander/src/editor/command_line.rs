@@ -0,0 +1,104 @@
+/// `:` 命令行解析出的命令。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum EditorCommand {
+    /// `:lsplog <path>` / `:lsplog off` / `:lsplog`。
+    LspLog(LspLogCommand),
+    /// `:r <path>`：把文件内容插入到光标所在行之后。
+    ReadFile(String),
+    /// `:lsp on` / `:lsp off`：在本次会话中整体开启或关闭语言服务。
+    Lsp(bool),
+    /// `:bn` / `:bp`：在当前标签页中切换到下一个（`true`）或上一个缓冲区。
+    CycleBuffer(bool),
+    /// `:diagexport <path>`：把当前诊断导出为 JSON（`.sarif` 结尾时为 SARIF）。
+    DiagExport(String),
+}
+
+/// LSP 事件日志镜像的开关操作。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum LspLogCommand {
+    /// 开始（或切换）镜像到指定路径，相对路径按工作区根目录解析。
+    Start(String),
+    Stop,
+    /// 不带参数时只报告当前状态。
+    Status,
+}
+
+/// 解析命令行输入（不含开头的 `:`）。
+///
+/// 允许命令名前多写一个 `/`，与主界面斜杠命令的习惯保持一致，
+/// 这样 `:/lsplog off` 与 `:lsplog off` 等价。
+pub(super) fn parse_command_line(input: &str) -> Result<EditorCommand, String> {
+    let input = input.trim();
+    let input = input.strip_prefix('/').unwrap_or(input);
+    let (name, args) = match input.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (input, ""),
+    };
+    match name {
+        "lsplog" => Ok(EditorCommand::LspLog(match args {
+            "" => LspLogCommand::Status,
+            "off" => LspLogCommand::Stop,
+            path => LspLogCommand::Start(path.to_string()),
+        })),
+        "lsp" => match args {
+            "on" => Ok(EditorCommand::Lsp(true)),
+            "off" => Ok(EditorCommand::Lsp(false)),
+            _ => Err("用法: :lsp on|off".to_string()),
+        },
+        "bn" | "bnext" => Ok(EditorCommand::CycleBuffer(true)),
+        "bp" | "bprev" | "bprevious" => Ok(EditorCommand::CycleBuffer(false)),
+        "diagexport" if args.is_empty() => Err("用法: :diagexport <path>".to_string()),
+        "diagexport" => Ok(EditorCommand::DiagExport(args.to_string())),
+        "r" | "read" if args.is_empty() => Err("用法: :r <path>".to_string()),
+        "r" | "read" => Ok(EditorCommand::ReadFile(args.to_string())),
+        "" => Err("命令为空".to_string()),
+        other => Err(format!("未知命令: {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditorCommand, LspLogCommand, parse_command_line};
+
+    #[test]
+    fn test_parse_command_line_lsplog() {
+        assert_eq!(
+            parse_command_line("lsplog /tmp/lsp log.jsonl"),
+            Ok(EditorCommand::LspLog(LspLogCommand::Start(
+                "/tmp/lsp log.jsonl".to_string()
+            ))),
+            "路径中的空格应保留"
+        );
+        assert_eq!(
+            parse_command_line(" /lsplog off "),
+            Ok(EditorCommand::LspLog(LspLogCommand::Stop))
+        );
+        assert_eq!(
+            parse_command_line("lsplog"),
+            Ok(EditorCommand::LspLog(LspLogCommand::Status))
+        );
+        assert_eq!(
+            parse_command_line("r src/lib.rs"),
+            Ok(EditorCommand::ReadFile("src/lib.rs".to_string()))
+        );
+        assert!(parse_command_line("r").is_err(), "缺少路径应报错");
+        assert_eq!(parse_command_line("lsp off"), Ok(EditorCommand::Lsp(false)));
+        assert_eq!(parse_command_line("lsp on"), Ok(EditorCommand::Lsp(true)));
+        assert!(parse_command_line("lsp").is_err(), "缺少 on/off 应报错");
+        assert_eq!(
+            parse_command_line("bn"),
+            Ok(EditorCommand::CycleBuffer(true))
+        );
+        assert_eq!(
+            parse_command_line("bprevious"),
+            Ok(EditorCommand::CycleBuffer(false))
+        );
+        assert_eq!(
+            parse_command_line("diagexport out/diag.sarif"),
+            Ok(EditorCommand::DiagExport("out/diag.sarif".to_string()))
+        );
+        assert!(parse_command_line("diagexport").is_err(), "缺少路径应报错");
+        assert!(parse_command_line("").is_err());
+        assert!(parse_command_line("nope arg").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+/// 补全请求的防抖状态：记录最近一次触发补全的输入时刻。
+///
+/// 连续快速输入时每次都刷新时刻，只有停顿超过延迟后才真正发出一次请求，
+/// 既减少弹窗闪烁，也避免快速打字时把请求堆到语言服务上。
+#[derive(Debug, Default)]
+pub(super) struct CompletionDebounce {
+    last_input: Option<Instant>,
+}
+
+impl CompletionDebounce {
+    /// 记录一次会触发补全的输入，覆盖尚未发出的请求。
+    pub(super) fn note_input(&mut self, now: Instant) {
+        self.last_input = Some(now);
+    }
+
+    /// 取消尚未发出的请求。
+    pub(super) fn cancel(&mut self) {
+        self.last_input = None;
+    }
+
+    /// 停顿已超过延迟时返回 `true` 并清空状态，保证一次停顿只发出一次请求。
+    pub(super) fn take_ready(&mut self, now: Instant, delay: Duration) -> bool {
+        match self.last_input {
+            Some(last) if now.saturating_duration_since(last) >= delay => {
+                self.last_input = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 距离请求发出还需等待的时间，供主循环缩短事件轮询的超时。
+    pub(super) fn remaining(&self, now: Instant, delay: Duration) -> Option<Duration> {
+        self.last_input
+            .map(|last| delay.saturating_sub(now.saturating_duration_since(last)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::CompletionDebounce;
+
+    #[test]
+    fn test_rapid_input_suppresses_intermediate_requests() {
+        let delay = Duration::from_millis(120);
+        let start = Instant::now();
+        let mut debounce = CompletionDebounce::default();
+        let mut requests = 0;
+
+        // 每 50ms 输入一个字符，间隔都短于延迟，期间不应发出请求。
+        for step in 0..5u64 {
+            let now = start + Duration::from_millis(step * 50);
+            if debounce.take_ready(now, delay) {
+                requests += 1;
+            }
+            debounce.note_input(now);
+        }
+        assert_eq!(requests, 0, "快速输入期间不应请求补全");
+
+        let last_input = start + Duration::from_millis(200);
+        assert_eq!(
+            debounce.remaining(last_input + Duration::from_millis(20), delay),
+            Some(Duration::from_millis(100))
+        );
+        assert!(!debounce.take_ready(last_input + Duration::from_millis(119), delay));
+        assert!(
+            debounce.take_ready(last_input + Duration::from_millis(120), delay),
+            "停顿达到延迟后发出请求"
+        );
+        assert!(
+            !debounce.take_ready(last_input + Duration::from_millis(500), delay),
+            "一次停顿只请求一次"
+        );
+        assert_eq!(debounce.remaining(last_input, delay), None);
+    }
+
+    #[test]
+    fn test_cancel_drops_pending_request() {
+        let now = Instant::now();
+        let mut debounce = CompletionDebounce::default();
+        debounce.note_input(now);
+        debounce.cancel();
+        assert!(!debounce.take_ready(now + Duration::from_secs(1), Duration::ZERO));
+    }
+}
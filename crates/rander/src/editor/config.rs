@@ -0,0 +1,359 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use core::settings::load_settings;
+use serde::Deserialize;
+
+use super::tree::TreeSort;
+
+/// editor 配置文件名（项目配置位于 `.order/`，用户全局配置位于 `~/.config/order/`）。
+const EDITOR_CONFIG_FILE: &str = "editor.json";
+
+/// 默认关闭自动配对的语言：写散文时引号与括号很少成对出现，自动补全反而需要频繁删除。
+const AUTO_PAIRS_DEFAULT_OFF: &[&str] = &["markdown", "plaintext"];
+
+/// 未配置 `completion_debounce_ms` 时的补全防抖延迟（毫秒）。
+const DEFAULT_COMPLETION_DEBOUNCE_MS: u64 = 120;
+
+/// 未配置 `large_file_bytes` 时的大文件阈值（5 MiB）。
+const DEFAULT_LARGE_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// 可通过环境变量覆盖的编辑器配置项：`(环境变量名, 配置键)`。
+const EDITOR_ENV_KEYS: &[(&str, &str)] = &[
+    ("ORDER_EDITOR_WILL_SAVE_PREVIEW", "will_save_preview"),
+    ("ORDER_EDITOR_COMPLETION_PLACEMENT", "completion_placement"),
+    ("ORDER_EDITOR_LARGE_FILE_BYTES", "large_file_bytes"),
+    (
+        "ORDER_EDITOR_SEMANTIC_TOKENS_VISIBLE_RANGE",
+        "semantic_tokens_visible_range",
+    ),
+];
+
+/// `.order/editor.json` 中的编辑器配置。
+///
+/// 所有字段都带默认值，这样用户只需写出想覆盖的部分，
+/// 缺省字段不会导致整个文件解析失败。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(super) struct EditorConfig {
+    /// 显式格式化覆盖项，优先级高于 `.editorconfig`。
+    pub(super) format: FormatOverrides,
+    /// 保存时是否先预览 `willSaveWaitUntil` 返回的编辑并等待确认。
+    ///
+    /// 默认关闭，保持“保存即应用服务端编辑”的既有行为。
+    pub(super) will_save_preview: bool,
+    /// 补全弹窗相对光标的位置。
+    pub(super) completion_placement: CompletionPlacement,
+    /// 超过该字节数的文件以大文件模式打开；`0` 表示关闭大文件模式。
+    ///
+    /// 使用 `Option` 区分“未配置”和“显式关闭”，未配置时回退到内置阈值。
+    pub(super) large_file_bytes: Option<u64>,
+    /// 是否只按可见区域请求语义 token（`semanticTokens/range`）。
+    ///
+    /// 默认关闭，保持整文件请求；服务端不支持范围请求时自动回退整文件。
+    pub(super) semantic_tokens_visible_range: bool,
+    /// 按语言开关 LSP 自动激活，键为 LSP `languageId`（如 `rust`、`cpp`）。
+    ///
+    /// 未列出的语言默认允许；被关闭的语言仍可用 `la` 手动启动。
+    pub(super) lsp_auto_activate: HashMap<String, bool>,
+    /// 按语言提供给语言服务的设置，键为 LSP `languageId`，值原样回应 `workspace/configuration`。
+    ///
+    /// 例如 `{"python": {"python": {"analysis": {"typeCheckingMode": "strict"}}}}`；
+    /// 服务端请求未配置的 section 时回应 `null`，由服务端使用自身默认值。
+    pub(super) lsp_settings: HashMap<String, serde_json::Value>,
+    /// 语言会话空闲多少秒后自动关闭；未配置或为 `0` 时不回收。
+    ///
+    /// 空闲指该语言没有打开的缓冲区，且这段时间内没有任何请求或响应。
+    pub(super) lsp_idle_shutdown_secs: Option<u64>,
+    /// INSERT 模式下括号与引号的自动配对设置。
+    pub(super) auto_pairs: AutoPairsConfig,
+    /// Home 与 `^` 的行首行为。
+    pub(super) home_key: HomeKeyBehavior,
+    /// 退出 editor 后，是否在下一条对话消息中自动附带活动文件。
+    ///
+    /// 默认关闭：整文件上下文会明显增加请求体积，需要用户显式开启。
+    pub(super) auto_context_active_file: bool,
+    /// VISUAL `y` 把选区带到对话时，是否给每行加上行号前缀。
+    ///
+    /// 默认关闭，保证代码块可以直接复制回文件。
+    pub(super) chat_line_numbers: bool,
+    /// 标签页数量上限；超出时关闭最久未使用且没有未保存修改的标签页。
+    ///
+    /// 未配置或为 `0` 时不限制。
+    pub(super) max_tabs: Option<usize>,
+    /// 是否默认开启打字机滚动：移动时让光标行尽量停在窗格垂直中央。
+    ///
+    /// 默认关闭；运行中可用 `tw` 切换。
+    pub(super) typewriter_scrolling: bool,
+    /// 是否把已弃用的补全候选排到列表末尾。
+    ///
+    /// 默认关闭，保持按插入文本排序；无论是否开启，弃用候选都会以删除线显示。
+    pub(super) completion_deprecated_last: bool,
+    /// 是否把诊断缓存到 `.order/diagnostics_cache.json`，下次打开 editor 时先行展示。
+    ///
+    /// 默认关闭；恢复的诊断标记为“缓存”，对应文件收到新的发布后替换。
+    pub(super) persist_diagnostics: bool,
+    /// 输入停顿多少毫秒后才发出补全请求；`0` 表示每次输入立即请求。
+    ///
+    /// 使用 `Option` 区分“未配置”和“显式关闭”，未配置时使用内置延迟。
+    pub(super) completion_debounce_ms: Option<u64>,
+    /// 目录树子项的初始排序方式：`dirs_first`、`name` 或 `modified`。
+    ///
+    /// 运行中可用 `to` 循环切换。
+    pub(super) tree_sort: TreeSort,
+    /// 是否在编辑窗格最右侧显示小地图滚动条（可见区域、光标与错误 / 警告位置）。
+    ///
+    /// 默认关闭，避免占用正文宽度。
+    pub(super) minimap: bool,
+}
+
+/// 括号 / 引号自动配对设置。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(super) struct AutoPairsConfig {
+    /// 全局开关，关闭后所有语言都不再自动配对。
+    pub(super) enabled: bool,
+    /// 按语言覆盖，键为 LSP `languageId`；非 LSP 语言使用 `markdown`、`plaintext` 或文件扩展名。
+    pub(super) languages: HashMap<String, bool>,
+}
+
+impl Default for AutoPairsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            languages: HashMap::new(),
+        }
+    }
+}
+
+impl AutoPairsConfig {
+    /// 判断该语言是否自动配对：全局关闭优先，其次是按语言配置，最后是内置默认值。
+    pub(super) fn is_enabled_for(&self, language_key: &str) -> bool {
+        self.enabled
+            && self
+                .languages
+                .get(language_key)
+                .copied()
+                .unwrap_or(!AUTO_PAIRS_DEFAULT_OFF.contains(&language_key))
+    }
+}
+
+/// 补全弹窗的放置策略。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum CompletionPlacement {
+    /// 光标下方空间不足时自动翻到上方（默认）。
+    #[default]
+    Auto,
+    /// 总是放在光标上方。
+    Above,
+    /// 总是放在光标下方。
+    Below,
+}
+
+/// Home 键的行首行为。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum HomeKeyBehavior {
+    /// 在首个非空白字符与第 0 列之间来回切换（默认）。
+    #[default]
+    Smart,
+    /// Home 总是回到第 0 列，`^` 总是跳到首个非空白字符。
+    Classic,
+}
+
+/// 用户在 `.order/editor.json` 中显式指定的格式化设置。
+///
+/// 字段名与 `.editorconfig` 属性保持一致，降低两套配置之间的迁移成本；
+/// 未填写的字段保持 `None`，表示沿用 `.editorconfig` 或内置默认值。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(super) struct FormatOverrides {
+    pub(super) indent_style: Option<String>,
+    pub(super) indent_size: Option<usize>,
+    pub(super) tab_width: Option<usize>,
+    pub(super) end_of_line: Option<String>,
+    pub(super) charset: Option<String>,
+    pub(super) trim_trailing_whitespace: Option<bool>,
+    pub(super) insert_final_newline: Option<bool>,
+}
+
+impl FormatOverrides {
+    /// 转换为与 `.editorconfig` 一致的 `key=value` 属性序列。
+    ///
+    /// 统一成字符串属性后即可复用同一套合并逻辑，
+    /// 保证两种来源的取值解释完全一致。
+    pub(super) fn to_properties(&self) -> Vec<(String, String)> {
+        let mut properties = Vec::new();
+        if let Some(value) = &self.indent_style {
+            properties.push(("indent_style".to_string(), value.to_ascii_lowercase()));
+        }
+        if let Some(value) = self.indent_size {
+            properties.push(("indent_size".to_string(), value.to_string()));
+        }
+        if let Some(value) = self.tab_width {
+            properties.push(("tab_width".to_string(), value.to_string()));
+        }
+        if let Some(value) = &self.end_of_line {
+            properties.push(("end_of_line".to_string(), value.to_ascii_lowercase()));
+        }
+        if let Some(value) = &self.charset {
+            properties.push(("charset".to_string(), value.to_ascii_lowercase()));
+        }
+        if let Some(value) = self.trim_trailing_whitespace {
+            properties.push(("trim_trailing_whitespace".to_string(), value.to_string()));
+        }
+        if let Some(value) = self.insert_final_newline {
+            properties.push(("insert_final_newline".to_string(), value.to_string()));
+        }
+        properties
+    }
+}
+
+impl EditorConfig {
+    /// 生效的标签页上限，未配置或为 `0` 时返回 `None`。
+    pub(super) fn tab_limit(&self) -> Option<usize> {
+        self.max_tabs.filter(|limit| *limit > 0)
+    }
+
+    /// 当前生效的补全防抖延迟。
+    pub(super) fn completion_debounce(&self) -> Duration {
+        Duration::from_millis(
+            self.completion_debounce_ms
+                .unwrap_or(DEFAULT_COMPLETION_DEBOUNCE_MS),
+        )
+    }
+
+    /// 当前生效的大文件阈值（字节），`0` 表示关闭。
+    pub(super) fn large_file_threshold(&self) -> u64 {
+        self.large_file_bytes.unwrap_or(DEFAULT_LARGE_FILE_BYTES)
+    }
+
+    /// 判断是否应自动为该语言启动 LSP：需要存在项目标识文件，且未被配置关闭。
+    ///
+    /// 混合语言仓库里常有用户并不需要的重量级服务（例如 clangd），
+    /// 允许逐语言关闭，避免仅因存在 `Makefile` 之类的标识就被拉起。
+    pub(super) fn should_auto_activate_lsp(
+        &self,
+        language: lsp::LspLanguage,
+        has_project_marker: bool,
+    ) -> bool {
+        has_project_marker
+            && self
+                .lsp_auto_activate
+                .get(language.language_id())
+                .copied()
+                .unwrap_or(true)
+    }
+
+    /// 按“默认值 < 用户全局 < 项目 < 环境变量”的顺序加载编辑器配置。
+    ///
+    /// 返回值：
+    /// - `Ok(config)`：各层都不存在时返回默认配置；
+    /// - `Err(message)`：某层存在但读取或解析失败，由调用方决定如何提示。
+    pub(super) fn load(root: &Path) -> Result<Self, String> {
+        load_settings(root, EDITOR_CONFIG_FILE, EDITOR_ENV_KEYS)
+            .map_err(|error| format!("加载编辑器配置失败: {error:#}"))
+    }
+}
+
+/// 根据文件大小判断是否以大文件模式打开。
+///
+/// 大文件模式下关闭高亮与 LSP 同步：这两者的开销都随文件体积线性增长，
+/// 对日志、生成代码这类大文件收益很低，却会明显拖慢打开与每帧渲染。
+pub(super) fn is_large_file(file_bytes: u64, threshold: u64) -> bool {
+    threshold > 0 && file_bytes > threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp::LspLanguage;
+
+    use super::{
+        AutoPairsConfig, DEFAULT_LARGE_FILE_BYTES, EditorConfig, HomeKeyBehavior, is_large_file,
+    };
+
+    #[test]
+    fn test_is_large_file_uses_strictly_greater_threshold() {
+        assert!(!is_large_file(1024, 1024), "恰好等于阈值时仍按普通文件打开");
+        assert!(is_large_file(1025, 1024));
+        assert!(!is_large_file(0, 1024));
+        assert!(!is_large_file(u64::MAX, 0), "阈值为 0 表示关闭大文件模式");
+    }
+
+    #[test]
+    fn test_large_file_threshold_falls_back_to_default() {
+        let mut config = EditorConfig::default();
+        assert_eq!(config.large_file_threshold(), DEFAULT_LARGE_FILE_BYTES);
+        assert!(is_large_file(
+            DEFAULT_LARGE_FILE_BYTES + 1,
+            config.large_file_threshold()
+        ));
+
+        config.large_file_bytes = Some(0);
+        assert!(!is_large_file(
+            DEFAULT_LARGE_FILE_BYTES + 1,
+            config.large_file_threshold()
+        ));
+    }
+
+    #[test]
+    fn test_should_auto_activate_lsp_respects_allow_and_deny() {
+        let config: EditorConfig =
+            serde_json::from_str(r#"{"lsp_auto_activate": {"rust": true, "cpp": false}}"#)
+                .expect("解析编辑器配置失败");
+
+        assert!(config.should_auto_activate_lsp(LspLanguage::Rust, true));
+        assert!(
+            !config.should_auto_activate_lsp(LspLanguage::Cpp, true),
+            "被关闭的语言即使存在项目标识也不应自动启动"
+        );
+        assert!(
+            config.should_auto_activate_lsp(LspLanguage::C, true),
+            "未列出的语言默认允许"
+        );
+        assert!(
+            !config.should_auto_activate_lsp(LspLanguage::Rust, false),
+            "缺少项目标识时不自动启动"
+        );
+        assert!(EditorConfig::default().should_auto_activate_lsp(LspLanguage::Cpp, true));
+    }
+
+    #[test]
+    fn test_home_key_behavior_parses_and_defaults_to_smart() {
+        assert_eq!(EditorConfig::default().home_key, HomeKeyBehavior::Smart);
+        let config: EditorConfig =
+            serde_json::from_str(r#"{"home_key": "classic"}"#).expect("解析编辑器配置失败");
+        assert_eq!(config.home_key, HomeKeyBehavior::Classic);
+    }
+
+    #[test]
+    fn test_auto_pairs_per_language_and_global_switch() {
+        let defaults = AutoPairsConfig::default();
+        assert!(defaults.is_enabled_for("rust"), "代码语言默认开启");
+        assert!(!defaults.is_enabled_for("markdown"), "markdown 默认关闭");
+        assert!(!defaults.is_enabled_for("plaintext"));
+
+        let config: EditorConfig = serde_json::from_str(
+            r#"{"auto_pairs": {"languages": {"lisp": false, "markdown": true}}}"#,
+        )
+        .expect("解析编辑器配置失败");
+        assert!(
+            !config.auto_pairs.is_enabled_for("lisp"),
+            "显式关闭的语言不配对"
+        );
+        assert!(
+            config.auto_pairs.is_enabled_for("markdown"),
+            "可以为默认关闭的语言开启"
+        );
+        assert!(config.auto_pairs.is_enabled_for("python"));
+
+        let config: EditorConfig = serde_json::from_str(
+            r#"{"auto_pairs": {"enabled": false, "languages": {"rust": true}}}"#,
+        )
+        .expect("解析编辑器配置失败");
+        assert!(
+            !config.auto_pairs.is_enabled_for("rust"),
+            "全局关闭优先于按语言开启"
+        );
+    }
+}
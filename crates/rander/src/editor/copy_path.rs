@@ -0,0 +1,154 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::{clipboard::CopyToClipboard, execute};
+
+/// 可复制的路径形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CopyPathKind {
+    /// 绝对路径。
+    Absolute,
+    /// 绝对路径加 `:行:列`（均从 1 开始），可直接粘贴给编译器风格的跳转工具。
+    WithPosition,
+    /// 相对项目根目录的路径。
+    Relative,
+}
+
+impl CopyPathKind {
+    // 状态栏中展示的形式名称。
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Absolute => "绝对路径",
+            Self::WithPosition => "路径与位置",
+            Self::Relative => "仓库相对路径",
+        }
+    }
+}
+
+/// 查找文件所属的项目根目录。
+///
+/// 从文件所在目录向上找第一个包含 `.git` 的目录（`.git` 为文件时同样算作 worktree 根）；
+/// 找不到时回退到编辑器根目录，保证在未初始化仓库的目录里也能得到稳定结果。
+pub(super) fn detect_project_root(file: &Path, fallback: &Path) -> PathBuf {
+    file.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| fallback.to_path_buf())
+}
+
+/// 计算文件相对项目根目录的路径，统一使用 `/` 分隔。
+///
+/// 文件不在根目录之下时返回 `None`，由调用方决定回退方式。
+pub(super) fn relative_path(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?;
+    if relative.as_os_str().is_empty() {
+        return None;
+    }
+    Some(
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// 按指定形式生成要复制的文本；`row`、`col` 为从 0 开始的光标位置。
+pub(super) fn format_copy_text(
+    kind: CopyPathKind,
+    file: &Path,
+    project_root: &Path,
+    row: usize,
+    col: usize,
+) -> String {
+    match kind {
+        CopyPathKind::Absolute => file.display().to_string(),
+        CopyPathKind::WithPosition => format!("{}:{}:{}", file.display(), row + 1, col + 1),
+        CopyPathKind::Relative => {
+            relative_path(project_root, file).unwrap_or_else(|| file.display().to_string())
+        }
+    }
+}
+
+/// 通过 OSC 52 写入终端剪贴板。
+///
+/// 使用终端转义序列而不是调用系统剪贴板程序，SSH 远程会话中同样可用；
+/// 终端是否真正支持无法得知，因此调用方总会同时写入内部寄存器。
+pub(super) fn copy_to_terminal_clipboard(text: &str) -> io::Result<()> {
+    execute!(io::stdout(), CopyToClipboard::to_clipboard_from(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{CopyPathKind, detect_project_root, format_copy_text, relative_path};
+
+    #[test]
+    fn test_relative_path_for_file_under_root() {
+        let root = PathBuf::from("/work/order");
+        let file = root.join("crates").join("core").join("src").join("lib.rs");
+
+        assert_eq!(
+            relative_path(&root, &file).as_deref(),
+            Some("crates/core/src/lib.rs")
+        );
+        assert_eq!(relative_path(&root, &PathBuf::from("/tmp/other.rs")), None);
+        assert_eq!(relative_path(&root, &root), None, "根目录本身没有相对路径");
+
+        assert_eq!(
+            format_copy_text(CopyPathKind::Relative, &file, &root, 9, 4),
+            "crates/core/src/lib.rs"
+        );
+        assert_eq!(
+            format_copy_text(CopyPathKind::WithPosition, &file, &root, 9, 4),
+            format!("{}:10:5", file.display())
+        );
+        assert_eq!(
+            format_copy_text(
+                CopyPathKind::Relative,
+                &PathBuf::from("/tmp/other.rs"),
+                &root,
+                0,
+                0
+            ),
+            "/tmp/other.rs",
+            "不在根目录下时回退为绝对路径"
+        );
+    }
+
+    #[test]
+    fn test_detect_project_root_prefers_nearest_git_dir() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let base =
+            std::env::temp_dir().join(format!("order-copy-path-{}-{nanos}", std::process::id()));
+        let repo = base.join("repo");
+        fs::create_dir_all(repo.join(".git")).expect("创建 .git 目录失败");
+        fs::create_dir_all(repo.join("src")).expect("创建 src 目录失败");
+        let file = repo.join("src").join("main.rs");
+
+        assert_eq!(detect_project_root(&file, &base), repo);
+        assert_eq!(
+            relative_path(&detect_project_root(&file, &base), &file).as_deref(),
+            Some("src/main.rs")
+        );
+        let outside = base.join("loose").join("a.rs");
+        assert_eq!(
+            detect_project_root(&outside, &base),
+            base,
+            "找不到 .git 时回退到编辑器根目录"
+        );
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{cmp::Ordering, collections::HashMap, path::PathBuf};
+
+use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+use super::quick_fix::severity_rank;
+
+/// 诊断列表的排序规则：先按级别，再按文件与位置。
+///
+/// 这样 error 总是排在最前面，`[g`/`]g` 逐条浏览与 `[G`/`]G` 按组跳转
+/// 共用同一份顺序，用户可以先集中处理错误再看警告。
+pub(super) fn compare_diagnostics(left: &DiagnosticItem, right: &DiagnosticItem) -> Ordering {
+    severity_rank(left.severity)
+        .cmp(&severity_rank(right.severity))
+        .then(left.file_path.cmp(&right.file_path))
+        .then(left.line.cmp(&right.line))
+        .then(left.column.cmp(&right.column))
+}
+
+/// 去重后的一条诊断，附带报告它的不同来源数量。
+#[derive(Debug, Clone)]
+pub(super) struct DedupedDiagnostic {
+    pub(super) item: DiagnosticItem,
+    pub(super) source_count: usize,
+}
+
+// 去重键：文件、位置、消息与错误码都相同才视为同一条诊断，级别不参与。
+type DiagnosticKey = (PathBuf, u64, u64, String, Option<String>);
+
+/// 合并多个来源报告的重复诊断。
+///
+/// rust-analyzer 会把自身诊断与 `cargo check` 的结果一起发布，同一问题常出现两次。
+/// 键相同的诊断合并为一条并记录来源数；仅级别不同的近似重复保留最严重的级别，
+/// 避免同一个 error 因另一来源报成 warning 而被排到后面。结果保持首次出现的顺序。
+pub(super) fn dedup_diagnostics(items: Vec<DiagnosticItem>) -> Vec<DedupedDiagnostic> {
+    let mut merged: Vec<(DiagnosticItem, Vec<Option<String>>)> = Vec::new();
+    let mut positions: HashMap<DiagnosticKey, usize> = HashMap::new();
+    for item in items {
+        let key = (
+            item.file_path.clone(),
+            item.line,
+            item.column,
+            item.message.clone(),
+            item.code.clone(),
+        );
+        match positions.get(&key) {
+            Some(&position) => {
+                let (kept, sources) = &mut merged[position];
+                if !sources.contains(&item.source) {
+                    sources.push(item.source.clone());
+                }
+                if severity_rank(item.severity) < severity_rank(kept.severity) {
+                    kept.severity = item.severity;
+                }
+            }
+            None => {
+                positions.insert(key, merged.len());
+                let sources = vec![item.source.clone()];
+                merged.push((item, sources));
+            }
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(item, sources)| DedupedDiagnostic {
+            item,
+            source_count: sources.len(),
+        })
+        .collect()
+}
+
+/// 计算按级别分组跳转后的诊断下标。
+///
+/// `severities` 必须已按 [`compare_diagnostics`] 排序，即同级别的诊断连续排列。
+/// 向后跳到下一组的第一条；向前跳时若不在本组开头，先回到本组开头，
+/// 否则跳到上一组的第一条。到达两端时循环，便于反复巡检。
+pub(super) fn severity_group_jump(
+    severities: &[DiagnosticSeverity],
+    current: usize,
+    forward: bool,
+) -> Option<usize> {
+    if severities.is_empty() {
+        return None;
+    }
+    let starts = group_starts(severities);
+    let current = current.min(severities.len() - 1);
+    let group = starts
+        .iter()
+        .rposition(|start| *start <= current)
+        .unwrap_or(0);
+
+    let target_group = if forward {
+        (group + 1) % starts.len()
+    } else if current > starts[group] {
+        group
+    } else {
+        (group + starts.len() - 1) % starts.len()
+    };
+    Some(starts[target_group])
+}
+
+/// 返回下标所在组内的位置与组大小（位置从 1 开始），用于状态栏展示。
+pub(super) fn severity_group_position(
+    severities: &[DiagnosticSeverity],
+    index: usize,
+) -> Option<(usize, usize)> {
+    let severity = *severities.get(index)?;
+    let start = severities[..index]
+        .iter()
+        .rposition(|item| *item != severity)
+        .map_or(0, |position| position + 1);
+    let len = severities[start..]
+        .iter()
+        .take_while(|item| **item == severity)
+        .count();
+    Some((index - start + 1, len))
+}
+
+// 每个级别分组的起始下标。
+fn group_starts(severities: &[DiagnosticSeverity]) -> Vec<usize> {
+    (0..severities.len())
+        .filter(|index| *index == 0 || severities[*index] != severities[*index - 1])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+    use super::{
+        compare_diagnostics, dedup_diagnostics, severity_group_jump, severity_group_position,
+    };
+
+    fn diagnostic(file: &str, line: u64, severity: DiagnosticSeverity) -> DiagnosticItem {
+        DiagnosticItem {
+            file_path: PathBuf::from(file),
+            line,
+            column: 1,
+            severity,
+            message: format!("{file}:{line}"),
+            lsp_start_line: line as usize - 1,
+            lsp_start_character: 0,
+            lsp_end_line: line as usize - 1,
+            lsp_end_character: 1,
+            source: None,
+            code: None,
+            related: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_severity_group_jump_moves_between_groups_in_mixed_set() {
+        let mut items = [
+            diagnostic("b.rs", 3, DiagnosticSeverity::Warning),
+            diagnostic("a.rs", 9, DiagnosticSeverity::Error),
+            diagnostic("a.rs", 1, DiagnosticSeverity::Information),
+            diagnostic("a.rs", 2, DiagnosticSeverity::Warning),
+            diagnostic("c.rs", 1, DiagnosticSeverity::Error),
+        ];
+        items.sort_by(compare_diagnostics);
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a.rs:9", "c.rs:1", "a.rs:2", "b.rs:3", "a.rs:1"],
+            "应先按级别、再按位置排序"
+        );
+
+        let severities = items.iter().map(|item| item.severity).collect::<Vec<_>>();
+        // 向后：error 组 -> warning 组 -> info 组 -> 循环回 error 组。
+        assert_eq!(severity_group_jump(&severities, 0, true), Some(2));
+        assert_eq!(severity_group_jump(&severities, 1, true), Some(2));
+        assert_eq!(severity_group_jump(&severities, 3, true), Some(4));
+        assert_eq!(severity_group_jump(&severities, 4, true), Some(0));
+        // 向前：组中间先回到本组开头，组开头再跳到上一组。
+        assert_eq!(severity_group_jump(&severities, 3, false), Some(2));
+        assert_eq!(severity_group_jump(&severities, 2, false), Some(0));
+        assert_eq!(severity_group_jump(&severities, 0, false), Some(4));
+        assert_eq!(severity_group_jump(&[], 0, true), None);
+
+        assert_eq!(severity_group_position(&severities, 3), Some((2, 2)));
+        assert_eq!(severity_group_position(&severities, 4), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_dedup_diagnostics_collapses_identical_and_keeps_distinct() {
+        let mut native = diagnostic("a.rs", 3, DiagnosticSeverity::Warning);
+        native.source = Some("rust-analyzer".to_string());
+        native.code = Some("E0308".to_string());
+        let mut cargo = native.clone();
+        cargo.source = Some("rustc".to_string());
+        cargo.severity = DiagnosticSeverity::Error;
+        let repeated = cargo.clone();
+        let mut other_code = native.clone();
+        other_code.code = Some("E0599".to_string());
+        let other_line = diagnostic("a.rs", 4, DiagnosticSeverity::Warning);
+
+        let deduped = dedup_diagnostics(vec![native, cargo, repeated, other_code, other_line]);
+        assert_eq!(deduped.len(), 3, "只有键完全相同的诊断才合并");
+
+        assert_eq!(deduped[0].source_count, 2, "同一来源重复报告只计一次");
+        assert_eq!(
+            deduped[0].item.severity,
+            DiagnosticSeverity::Error,
+            "仅级别不同时保留最严重的级别"
+        );
+        assert_eq!(deduped[1].item.code.as_deref(), Some("E0599"));
+        assert_eq!(deduped[1].source_count, 1);
+        assert_eq!(deduped[2].item.line, 4);
+    }
+
+    #[test]
+    fn test_severity_group_jump_with_single_group_stays_on_group_start() {
+        let severities = [DiagnosticSeverity::Warning; 3];
+        assert_eq!(severity_group_jump(&severities, 2, true), Some(0));
+        assert_eq!(severity_group_jump(&severities, 2, false), Some(0));
+        assert_eq!(severity_group_jump(&severities, 0, false), Some(0));
+    }
+}
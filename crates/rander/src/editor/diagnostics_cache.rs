@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use lsp::{DiagnosticItem, DiagnosticRelated, DiagnosticSeverity, LspLocation};
+use serde::{Deserialize, Serialize};
+
+/// 诊断缓存文件（相对工作区根目录）。
+pub(super) const DIAGNOSTICS_CACHE_FILE: &str = ".order/diagnostics_cache.json";
+
+/// 缓存文件结构：键为文件路径，值为该文件最近一次发布的诊断。
+///
+/// 使用 `BTreeMap` 让写出的 JSON 按路径稳定排序，便于对比与排查。
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DiagnosticsCacheFile {
+    files: BTreeMap<String, Vec<CachedDiagnostic>>,
+}
+
+/// 单条诊断的缓存形式；级别按 LSP 数字保存。
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDiagnostic {
+    line: u64,
+    column: u64,
+    severity: u8,
+    message: String,
+    start_line: usize,
+    start_character: usize,
+    end_line: usize,
+    end_character: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    related: Vec<CachedRelated>,
+}
+
+/// 关联位置的缓存形式。
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRelated {
+    file: String,
+    line: usize,
+    character: usize,
+    message: String,
+}
+
+impl CachedDiagnostic {
+    // 从诊断构建缓存条目。
+    fn from_item(item: &DiagnosticItem) -> Self {
+        Self {
+            line: item.line,
+            column: item.column,
+            severity: item.severity.to_lsp_number(),
+            message: item.message.clone(),
+            start_line: item.lsp_start_line,
+            start_character: item.lsp_start_character,
+            end_line: item.lsp_end_line,
+            end_character: item.lsp_end_character,
+            source: item.source.clone(),
+            code: item.code.clone(),
+            related: item
+                .related
+                .iter()
+                .map(|related| CachedRelated {
+                    file: related.location.file_path.to_string_lossy().to_string(),
+                    line: related.location.line,
+                    character: related.location.character,
+                    message: related.message.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    // 还原为诊断。
+    fn into_item(self, file_path: PathBuf) -> DiagnosticItem {
+        DiagnosticItem {
+            file_path,
+            line: self.line,
+            column: self.column,
+            severity: DiagnosticSeverity::from_lsp_number(u64::from(self.severity)),
+            message: self.message,
+            lsp_start_line: self.start_line,
+            lsp_start_character: self.start_character,
+            lsp_end_line: self.end_line,
+            lsp_end_character: self.end_character,
+            source: self.source,
+            code: self.code,
+            related: self
+                .related
+                .into_iter()
+                .map(|related| DiagnosticRelated {
+                    location: LspLocation {
+                        file_path: PathBuf::from(related.file),
+                        line: related.line,
+                        character: related.character,
+                    },
+                    message: related.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// 把按文件分组的诊断写入缓存文件，整份覆盖。
+pub(super) fn save_diagnostics_cache(
+    root: &Path,
+    diagnostics: &HashMap<PathBuf, Vec<DiagnosticItem>>,
+) -> io::Result<()> {
+    let cache = DiagnosticsCacheFile {
+        files: diagnostics
+            .iter()
+            .filter(|(_, items)| !items.is_empty())
+            .map(|(path, items)| {
+                (
+                    path.to_string_lossy().to_string(),
+                    items.iter().map(CachedDiagnostic::from_item).collect(),
+                )
+            })
+            .collect(),
+    };
+    let path = root.join(DIAGNOSTICS_CACHE_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let text = serde_json::to_string_pretty(&cache).map_err(io::Error::other)?;
+    fs::write(path, text)
+}
+
+/// 读取缓存文件；文件不存在时返回空表，内容损坏时返回错误。
+pub(super) fn load_diagnostics_cache(
+    root: &Path,
+) -> io::Result<HashMap<PathBuf, Vec<DiagnosticItem>>> {
+    let text = match fs::read_to_string(root.join(DIAGNOSTICS_CACHE_FILE)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(error) => return Err(error),
+    };
+    let cache: DiagnosticsCacheFile = serde_json::from_str(&text)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Ok(cache
+        .files
+        .into_iter()
+        .map(|(path, items)| {
+            let path = PathBuf::from(path);
+            let items = items
+                .into_iter()
+                .map(|item| item.into_item(path.clone()))
+                .collect();
+            (path, items)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use lsp::{DiagnosticItem, DiagnosticRelated, DiagnosticSeverity, LspLocation};
+
+    use super::{DIAGNOSTICS_CACHE_FILE, load_diagnostics_cache, save_diagnostics_cache};
+
+    fn temp_root() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!(
+            "order-diagnostics-cache-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_diagnostics_cache_round_trip() {
+        let root = temp_root();
+        let file = root.join("src/main.rs");
+        let mut diagnostics = HashMap::new();
+        diagnostics.insert(
+            file.clone(),
+            vec![DiagnosticItem {
+                file_path: file.clone(),
+                line: 3,
+                column: 5,
+                severity: DiagnosticSeverity::Error,
+                message: "mismatched types".to_string(),
+                lsp_start_line: 2,
+                lsp_start_character: 4,
+                lsp_end_line: 2,
+                lsp_end_character: 9,
+                source: Some("rustc".to_string()),
+                code: Some("E0308".to_string()),
+                related: vec![DiagnosticRelated {
+                    location: LspLocation {
+                        file_path: root.join("src/lib.rs"),
+                        line: 1,
+                        character: 0,
+                    },
+                    message: "expected due to this".to_string(),
+                }],
+            }],
+        );
+
+        assert!(
+            load_diagnostics_cache(&root)
+                .expect("缓存不存在时应返回空表")
+                .is_empty()
+        );
+        save_diagnostics_cache(&root, &diagnostics).expect("写入缓存失败");
+        assert!(root.join(DIAGNOSTICS_CACHE_FILE).exists());
+
+        let restored = load_diagnostics_cache(&root).expect("读取缓存失败");
+        let items = restored.get(&file).expect("应恢复该文件的诊断");
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.file_path, file);
+        assert_eq!((item.line, item.column), (3, 5));
+        assert_eq!(item.severity, DiagnosticSeverity::Error);
+        assert_eq!(item.message, "mismatched types");
+        assert_eq!(
+            (
+                item.lsp_start_line,
+                item.lsp_start_character,
+                item.lsp_end_line,
+                item.lsp_end_character
+            ),
+            (2, 4, 2, 9)
+        );
+        assert_eq!(item.code.as_deref(), Some("E0308"));
+        assert_eq!(item.related, diagnostics[&file][0].related);
+
+        fs::write(root.join(DIAGNOSTICS_CACHE_FILE), "{ not json").expect("写入失败");
+        assert!(load_diagnostics_cache(&root).is_err(), "损坏的缓存应报错");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
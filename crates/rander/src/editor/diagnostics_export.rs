@@ -0,0 +1,283 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use core::encoding::write_utf8_text_with_report;
+use lsp::{DiagnosticItem, DiagnosticSeverity};
+use serde_json::{Value, json};
+
+/// 导出文件的格式，由目标路径的扩展名决定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum DiagnosticsExportFormat {
+    /// 扁平 JSON：`{"diagnostics": [{file, line, column, severity, message, code, source}]}`。
+    Json,
+    /// SARIF 2.1.0 子集，便于直接上传到代码扫描类平台。
+    Sarif,
+}
+
+impl DiagnosticsExportFormat {
+    // `.sarif` 结尾时导出 SARIF，其余一律导出普通 JSON。
+    pub(super) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("sarif") => Self::Sarif,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// 把诊断写入 `path`，返回导出的条目数。
+///
+/// 写入走统一的 UTF-8 校验，父目录不存在时自动创建。
+pub(super) fn export_diagnostics(
+    root: &Path,
+    path: &Path,
+    diagnostics: &HashMap<PathBuf, Vec<DiagnosticItem>>,
+) -> io::Result<usize> {
+    let items = sorted_items(diagnostics);
+    let document = match DiagnosticsExportFormat::from_path(path) {
+        DiagnosticsExportFormat::Json => diagnostics_to_json(root, &items),
+        DiagnosticsExportFormat::Sarif => diagnostics_to_sarif(root, &items),
+    };
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let text = serde_json::to_string_pretty(&document).map_err(io::Error::other)?;
+    write_utf8_text_with_report(path, &text)?;
+    Ok(items.len())
+}
+
+/// 按文件、行、列排序，保证同一份诊断每次导出的内容一致，便于在 CI 中比对。
+fn sorted_items(diagnostics: &HashMap<PathBuf, Vec<DiagnosticItem>>) -> Vec<&DiagnosticItem> {
+    let mut items = diagnostics.values().flatten().collect::<Vec<_>>();
+    items.sort_by(|left, right| {
+        left.file_path
+            .cmp(&right.file_path)
+            .then(left.line.cmp(&right.line))
+            .then(left.column.cmp(&right.column))
+    });
+    items
+}
+
+// 工作区内的文件输出相对路径（统一使用 `/`），工作区外的保留原路径。
+fn display_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// 构建扁平 JSON 导出内容；行列均为 1 起始。
+fn diagnostics_to_json(root: &Path, items: &[&DiagnosticItem]) -> Value {
+    let diagnostics = items
+        .iter()
+        .map(|item| {
+            json!({
+                "file": display_path(root, &item.file_path),
+                "line": item.line,
+                "column": item.column,
+                "severity": item.severity.as_str(),
+                "message": item.message,
+                "code": item.code,
+                "source": item.source,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "version": 1, "diagnostics": diagnostics })
+}
+
+/// 构建 SARIF 2.1.0 子集：单个 run，每条诊断一个 result。
+///
+/// SARIF 只有 `error` / `warning` / `note` 三个级别，信息与提示都归为 `note`。
+fn diagnostics_to_sarif(root: &Path, items: &[&DiagnosticItem]) -> Value {
+    let results = items
+        .iter()
+        .map(|item| {
+            let level = match item.severity {
+                DiagnosticSeverity::Error => "error",
+                DiagnosticSeverity::Warning => "warning",
+                DiagnosticSeverity::Information | DiagnosticSeverity::Hint => "note",
+            };
+            let mut result = json!({
+                "level": level,
+                "message": { "text": item.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": display_path(root, &item.file_path) },
+                        "region": {
+                            "startLine": item.line,
+                            "startColumn": item.column
+                        }
+                    }
+                }]
+            });
+            if let Some(code) = item.code.as_deref() {
+                result["ruleId"] = json!(code);
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": { "driver": { "name": "order-editor" } },
+            "results": results
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+    use super::{
+        DiagnosticsExportFormat, diagnostics_to_json, diagnostics_to_sarif, export_diagnostics,
+        sorted_items,
+    };
+
+    fn item(
+        file: &Path,
+        line: u64,
+        severity: DiagnosticSeverity,
+        code: Option<&str>,
+    ) -> DiagnosticItem {
+        DiagnosticItem {
+            file_path: file.to_path_buf(),
+            line,
+            column: 5,
+            severity,
+            message: format!("problem at {line}"),
+            lsp_start_line: 0,
+            lsp_start_character: 0,
+            lsp_end_line: 0,
+            lsp_end_character: 0,
+            source: Some("rustc".to_string()),
+            code: code.map(ToString::to_string),
+            related: Vec::new(),
+        }
+    }
+
+    fn sample(root: &Path) -> HashMap<PathBuf, Vec<DiagnosticItem>> {
+        let main = root.join("src/main.rs");
+        let lib = root.join("src/lib.rs");
+        HashMap::from([
+            (
+                main.clone(),
+                vec![
+                    item(&main, 9, DiagnosticSeverity::Hint, None),
+                    item(&main, 2, DiagnosticSeverity::Error, Some("E0308")),
+                ],
+            ),
+            (
+                lib.clone(),
+                vec![item(&lib, 4, DiagnosticSeverity::Warning, None)],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_diagnostics_to_json_schema() {
+        let root = PathBuf::from("/workspace");
+        let diagnostics = sample(&root);
+        let value = diagnostics_to_json(&root, &sorted_items(&diagnostics));
+
+        assert_eq!(value["version"], 1);
+        let entries = value["diagnostics"].as_array().expect("应输出诊断数组");
+        let positions = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry["file"].as_str().unwrap_or_default(),
+                    entry["line"].as_u64(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                ("src/lib.rs", Some(4)),
+                ("src/main.rs", Some(2)),
+                ("src/main.rs", Some(9))
+            ],
+            "应按文件与行号排序，路径相对工作区"
+        );
+        let first_error = &entries[1];
+        assert_eq!(first_error["column"], 5);
+        assert_eq!(first_error["severity"], "error");
+        assert_eq!(first_error["message"], "problem at 2");
+        assert_eq!(first_error["code"], "E0308");
+        assert_eq!(first_error["source"], "rustc");
+        assert!(entries[0]["code"].is_null(), "没有错误码时输出 null");
+    }
+
+    #[test]
+    fn test_diagnostics_to_sarif_subset() {
+        let root = PathBuf::from("/workspace");
+        let diagnostics = sample(&root);
+        let value = diagnostics_to_sarif(&root, &sorted_items(&diagnostics));
+
+        assert_eq!(value["version"], "2.1.0");
+        let results = value["runs"][0]["results"]
+            .as_array()
+            .expect("应输出 results 数组");
+        let levels = results
+            .iter()
+            .map(|result| result["level"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(levels, vec!["warning", "error", "note"]);
+        let error = &results[1];
+        assert_eq!(error["ruleId"], "E0308");
+        assert_eq!(error["message"]["text"], "problem at 2");
+        let location = &error["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+        assert_eq!(location["region"]["startLine"], 2);
+        assert_eq!(location["region"]["startColumn"], 5);
+        assert!(results[0].get("ruleId").is_none());
+    }
+
+    #[test]
+    fn test_export_diagnostics_writes_format_by_extension() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root = std::env::temp_dir().join(format!(
+            "order-diagnostics-export-{}-{nanos}",
+            std::process::id()
+        ));
+        let diagnostics = sample(&root);
+
+        assert_eq!(
+            DiagnosticsExportFormat::from_path(Path::new("out/report.SARIF")),
+            DiagnosticsExportFormat::Sarif
+        );
+        assert_eq!(
+            DiagnosticsExportFormat::from_path(Path::new("report.json")),
+            DiagnosticsExportFormat::Json
+        );
+
+        let sarif_path = root.join("out/report.sarif");
+        let count = export_diagnostics(&root, &sarif_path, &diagnostics).expect("导出失败");
+        assert_eq!(count, 3);
+        let text = fs::read_to_string(&sarif_path).expect("读取导出文件失败");
+        let value: serde_json::Value = serde_json::from_str(&text).expect("导出内容应为 JSON");
+        assert_eq!(value["version"], "2.1.0");
+
+        let json_path = root.join("report.json");
+        export_diagnostics(&root, &json_path, &HashMap::new()).expect("导出失败");
+        let text = fs::read_to_string(&json_path).expect("读取导出文件失败");
+        let value: serde_json::Value = serde_json::from_str(&text).expect("导出内容应为 JSON");
+        assert_eq!(value["diagnostics"].as_array().map(Vec::len), Some(0));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
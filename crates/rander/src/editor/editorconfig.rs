@@ -0,0 +1,624 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::config::FormatOverrides;
+
+/// `.editorconfig` 文件名。
+const EDITORCONFIG_FILE: &str = ".editorconfig";
+
+/// 缩进风格。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// 保存时使用的行尾风格。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl EndOfLine {
+    // 返回行尾对应的字符串。
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+}
+
+/// 针对单个文件解析后的最终格式化设置。
+///
+/// 默认值与引入 `.editorconfig` 之前的行为保持一致（4 空格、LF、不追加行尾换行），
+/// 这样没有任何配置的项目在保存时不会出现意料之外的文件变化。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct FormatSettings {
+    pub(super) indent_style: IndentStyle,
+    pub(super) indent_size: usize,
+    pub(super) tab_width: usize,
+    pub(super) end_of_line: EndOfLine,
+    pub(super) charset: String,
+    pub(super) trim_trailing_whitespace: bool,
+    pub(super) insert_final_newline: bool,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self {
+            indent_style: IndentStyle::Space,
+            indent_size: 4,
+            tab_width: 4,
+            end_of_line: EndOfLine::Lf,
+            charset: "utf-8".to_string(),
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+        }
+    }
+}
+
+impl FormatSettings {
+    /// 返回 INSERT 模式下按 Tab 时应插入的缩进文本。
+    pub(super) fn indent_unit(&self) -> String {
+        match self.indent_style {
+            IndentStyle::Tab => "\t".to_string(),
+            IndentStyle::Space => " ".repeat(self.indent_size.max(1)),
+        }
+    }
+
+    /// 是否使用空格缩进，对应 LSP formatting 的 `insertSpaces`。
+    pub(super) fn insert_spaces(&self) -> bool {
+        self.indent_style == IndentStyle::Space
+    }
+
+    /// 返回 LSP formatting 使用的 `tabSize`。
+    ///
+    /// 空格缩进时服务端按 `tabSize` 生成缩进宽度，因此取 `indent_size`；
+    /// Tab 缩进时则取 `tab_width`，与制表符的显示宽度一致。
+    pub(super) fn lsp_tab_size(&self) -> usize {
+        let size = match self.indent_style {
+            IndentStyle::Space => self.indent_size,
+            IndentStyle::Tab => self.tab_width,
+        };
+        size.max(1)
+    }
+
+    /// 对待保存的行执行保存前变换（去除行尾空白）。
+    ///
+    /// 返回是否有行被修改，调用方据此决定是否需要同步 LSP。
+    pub(super) fn apply_save_transforms(&self, lines: &mut [String]) -> bool {
+        if !self.trim_trailing_whitespace {
+            return false;
+        }
+
+        let mut changed = false;
+        for line in lines.iter_mut() {
+            let trimmed_len = line.trim_end_matches([' ', '\t']).len();
+            if trimmed_len != line.len() {
+                line.truncate(trimmed_len);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// 将缓冲区行按行尾、末尾换行与字符集设置编码为待写入字节。
+    ///
+    /// 目前只支持 UTF-8 家族：`utf-8-bom` 会写入 BOM，
+    /// 其余字符集按 UTF-8 写入，避免因不支持的编码导致保存失败。
+    pub(super) fn encode_lines(&self, lines: &[String]) -> Vec<u8> {
+        let eol = self.end_of_line.as_str();
+        let mut text = lines.join(eol);
+        if self.insert_final_newline && !text.is_empty() {
+            text.push_str(eol);
+        }
+
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        if self.charset == "utf-8-bom" {
+            bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+        }
+        bytes.extend_from_slice(text.as_bytes());
+        bytes
+    }
+
+    /// 应用单个属性；无法识别的值直接忽略，保持已有设置。
+    fn apply_property(&mut self, key: &str, value: &str) {
+        match key {
+            "indent_style" => match value {
+                "space" => self.indent_style = IndentStyle::Space,
+                "tab" => self.indent_style = IndentStyle::Tab,
+                _ => {}
+            },
+            "indent_size" => {
+                if let Ok(size) = value.parse::<usize>() {
+                    self.indent_size = size;
+                }
+            }
+            "tab_width" => {
+                if let Ok(width) = value.parse::<usize>() {
+                    self.tab_width = width;
+                }
+            }
+            "end_of_line" => match value {
+                "lf" => self.end_of_line = EndOfLine::Lf,
+                "crlf" => self.end_of_line = EndOfLine::Crlf,
+                "cr" => self.end_of_line = EndOfLine::Cr,
+                _ => {}
+            },
+            "charset" => self.charset = value.to_string(),
+            "trim_trailing_whitespace" => {
+                if let Some(flag) = parse_bool(value) {
+                    self.trim_trailing_whitespace = flag;
+                }
+            }
+            "insert_final_newline" => {
+                if let Some(flag) = parse_bool(value) {
+                    self.insert_final_newline = flag;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 由合并后的属性表构建最终设置。
+    ///
+    /// 按 editorconfig 规范处理 `indent_size` 与 `tab_width` 的相互默认：
+    /// - `indent_size = tab` 时取 `tab_width`；
+    /// - 未设置 `tab_width` 时沿用 `indent_size`；
+    /// - Tab 缩进且未设置 `indent_size` 时沿用 `tab_width`。
+    fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let mut settings = Self::default();
+        for key in [
+            "indent_style",
+            "tab_width",
+            "end_of_line",
+            "charset",
+            "trim_trailing_whitespace",
+            "insert_final_newline",
+        ] {
+            if let Some(value) = properties.get(key) {
+                settings.apply_property(key, value);
+            }
+        }
+
+        match properties.get("indent_size").map(String::as_str) {
+            Some("tab") => settings.indent_size = settings.tab_width,
+            Some(value) => {
+                settings.apply_property("indent_size", value);
+                if !properties.contains_key("tab_width") {
+                    settings.tab_width = settings.indent_size;
+                }
+            }
+            None if settings.indent_style == IndentStyle::Tab => {
+                settings.indent_size = settings.tab_width;
+            }
+            None => {}
+        }
+        settings
+    }
+}
+
+/// 解析后的单个 `.editorconfig` 文件。
+#[derive(Debug, Clone, Default)]
+struct EditorConfigFile {
+    /// 是否声明了 `root = true`，为真时停止向上查找。
+    root: bool,
+    /// 按文件中出现顺序保存的 `[glob]` 段及其属性。
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+/// 解析 `.editorconfig` 文本。
+///
+/// 规则：
+/// - `#` / `;` 开头为注释，空行忽略；
+/// - 首个段之前只识别 `root`；
+/// - 键名与取值统一转小写（`charset` 等属性值大小写不敏感）。
+fn parse_editorconfig(content: &str) -> EditorConfigFile {
+    let mut file = EditorConfigFile::default();
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            file.sections.push((glob.to_string(), Vec::new()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_ascii_lowercase();
+        match file.sections.last_mut() {
+            Some((_, properties)) => properties.push((key, value)),
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+    file
+}
+
+/// 为指定文件解析最终格式化设置。
+///
+/// 合并顺序（后者覆盖前者）：
+/// 1. 内置默认值；
+/// 2. 从最外层到最内层目录的 `.editorconfig`（遇到 `root = true` 停止向上）；
+/// 3. `.order/editor.json` 中的显式覆盖项。
+///
+/// `file_path` 为 `None`（尚未保存的缓冲区）时跳过 `.editorconfig`，只应用覆盖项。
+pub(super) fn resolve_format_settings(
+    file_path: Option<&Path>,
+    overrides: &FormatOverrides,
+) -> FormatSettings {
+    let mut config_files = Vec::new();
+    for dir in file_path
+        .into_iter()
+        .flat_map(|path| path.ancestors().skip(1))
+    {
+        let config_path = dir.join(EDITORCONFIG_FILE);
+        let Ok(content) = fs::read_to_string(&config_path) else {
+            continue;
+        };
+        let parsed = parse_editorconfig(&content);
+        let is_root = parsed.root;
+        config_files.push((dir.to_path_buf(), parsed));
+        if is_root {
+            break;
+        }
+    }
+
+    let mut properties = HashMap::new();
+    if let Some(file_path) = file_path {
+        for (dir, config) in config_files.iter().rev() {
+            merge_matching_sections(&mut properties, dir, config, file_path);
+        }
+    }
+    for (key, value) in overrides.to_properties() {
+        properties.insert(key, value);
+    }
+
+    FormatSettings::from_properties(&properties)
+}
+
+/// 把匹配目标文件的段属性合并到属性表。
+///
+/// `unset` 按规范表示移除该属性，使其回落到更低优先级的默认值。
+fn merge_matching_sections(
+    properties: &mut HashMap<String, String>,
+    config_dir: &Path,
+    config: &EditorConfigFile,
+    file_path: &Path,
+) {
+    let Some(relative) = relative_slash_path(config_dir, file_path) else {
+        return;
+    };
+
+    for (glob, section_properties) in &config.sections {
+        if !section_matches(glob, &relative) {
+            continue;
+        }
+        for (key, value) in section_properties {
+            if value == "unset" {
+                properties.remove(key);
+            } else {
+                properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// 计算文件相对 `.editorconfig` 所在目录的 `/` 分隔路径。
+fn relative_slash_path(config_dir: &Path, file_path: &Path) -> Option<String> {
+    let relative: PathBuf = file_path.strip_prefix(config_dir).ok()?.to_path_buf();
+    let parts = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    Some(parts.join("/"))
+}
+
+/// 判断段 glob 是否匹配相对路径。
+///
+/// 不含 `/` 的 glob 可匹配任意层级的文件名（等价于前缀 `**/`），
+/// 含 `/` 的 glob 则相对 `.editorconfig` 所在目录匹配。
+fn section_matches(glob: &str, relative_path: &str) -> bool {
+    let pattern = if glob.contains('/') {
+        glob.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{glob}")
+    };
+
+    let path_chars = relative_path.chars().collect::<Vec<_>>();
+    expand_braces(&pattern).iter().any(|candidate| {
+        let pattern_chars = candidate.chars().collect::<Vec<_>>();
+        glob_match(&pattern_chars, &path_chars)
+    })
+}
+
+/// 展开 `{a,b}` 形式的候选分支。
+///
+/// 不含逗号的 `{...}` 按字面量处理，避免误把普通花括号当作分支。
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let Some(open) = chars.iter().position(|ch| *ch == '{') else {
+        return vec![pattern.to_string()];
+    };
+
+    let mut depth = 0usize;
+    let mut close = None;
+    let mut splits = Vec::new();
+    for (idx, ch) in chars.iter().enumerate().skip(open) {
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(idx);
+                    break;
+                }
+            }
+            ',' if depth == 1 => splits.push(idx),
+            _ => {}
+        }
+    }
+
+    let Some(close) = close else {
+        return vec![pattern.to_string()];
+    };
+    if splits.is_empty() {
+        return vec![pattern.to_string()];
+    }
+
+    let prefix = chars[..open].iter().collect::<String>();
+    let suffix = chars[close + 1..].iter().collect::<String>();
+    let mut bounds = vec![open];
+    bounds.extend(splits);
+    bounds.push(close);
+
+    bounds
+        .windows(2)
+        .flat_map(|window| {
+            let option = chars[window[0] + 1..window[1]].iter().collect::<String>();
+            expand_braces(&format!("{prefix}{option}{suffix}"))
+        })
+        .collect()
+}
+
+/// 基于回溯的 glob 匹配。
+///
+/// 支持 `*`（不跨目录）、`**`（可跨目录，`**/` 可匹配零层目录）、
+/// `?`、`[...]`/`[!...]` 字符集与 `\` 转义。
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let Some((&first, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+
+    match first {
+        '*' if rest.first() == Some(&'*') => {
+            let after = &rest[1..];
+            if after.first() == Some(&'/') && glob_match(&after[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|skip| glob_match(after, &text[skip..]))
+        }
+        '*' => {
+            for skip in 0..=text.len() {
+                if glob_match(rest, &text[skip..]) {
+                    return true;
+                }
+                if text.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        '?' => text
+            .split_first()
+            .is_some_and(|(ch, remain)| *ch != '/' && glob_match(rest, remain)),
+        '[' => match parse_char_class(rest) {
+            Some((class, remain_pattern)) => text.split_first().is_some_and(|(ch, remain)| {
+                *ch != '/' && class.matches(*ch) && glob_match(remain_pattern, remain)
+            }),
+            None => text
+                .split_first()
+                .is_some_and(|(ch, remain)| *ch == '[' && glob_match(rest, remain)),
+        },
+        '\\' if !rest.is_empty() => text
+            .split_first()
+            .is_some_and(|(ch, remain)| *ch == rest[0] && glob_match(&rest[1..], remain)),
+        literal => text
+            .split_first()
+            .is_some_and(|(ch, remain)| *ch == literal && glob_match(rest, remain)),
+    }
+}
+
+/// glob 中 `[...]` 字符集。
+struct CharClass {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    // 判断字符是否命中字符集。
+    fn matches(&self, ch: char) -> bool {
+        let hit = self
+            .ranges
+            .iter()
+            .any(|(start, end)| *start <= ch && ch <= *end);
+        hit != self.negated
+    }
+}
+
+/// 解析 `[...]` 字符集，返回字符集与剩余模式。
+///
+/// 找不到闭合 `]` 时返回 `None`，调用方把 `[` 当作字面量处理。
+fn parse_char_class(pattern: &[char]) -> Option<(CharClass, &[char])> {
+    let close = pattern.iter().skip(1).position(|ch| *ch == ']')? + 1;
+    let (negated, body) = match pattern.first() {
+        Some('!') | Some('^') => (true, &pattern[1..close]),
+        _ => (false, &pattern[..close]),
+    };
+
+    let mut ranges = Vec::new();
+    let mut idx = 0;
+    while idx < body.len() {
+        if idx + 2 < body.len() && body[idx + 1] == '-' {
+            ranges.push((body[idx], body[idx + 2]));
+            idx += 3;
+        } else {
+            ranges.push((body[idx], body[idx]));
+            idx += 1;
+        }
+    }
+    Some((CharClass { negated, ranges }, &pattern[close + 1..]))
+}
+
+// 解析 editorconfig 布尔值。
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::super::config::FormatOverrides;
+    use super::{EndOfLine, IndentStyle, resolve_format_settings, section_matches};
+
+    fn temp_workspace(case_name: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "order-editorconfig-{case_name}-{}-{timestamp}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&path).expect("临时目录应创建成功");
+        path
+    }
+
+    #[test]
+    fn test_section_glob_matching() {
+        assert!(section_matches("*", "src/main.rs"));
+        assert!(section_matches("*.rs", "src/main.rs"));
+        assert!(section_matches("*.{js,ts}", "web/app.ts"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+        assert!(section_matches("src/**.rs", "src/a/b.rs"));
+        assert!(section_matches("/docs/*.md", "docs/readme.md"));
+        assert!(section_matches("[Mm]akefile", "makefile"));
+
+        assert!(!section_matches("*.rs", "src/main.py"));
+        assert!(!section_matches("/docs/*.md", "docs/nested/readme.md"));
+        assert!(!section_matches("[!M]akefile", "Makefile"));
+    }
+
+    #[test]
+    fn test_resolve_settings_merges_sections_and_nested_configs() {
+        let root = temp_workspace("nested");
+        fs::create_dir_all(root.join("web")).expect("子目录应创建成功");
+        fs::write(
+            root.join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = lf\n\n[*.{ts,js}]\nindent_size = 2\ntrim_trailing_whitespace = true\n\n[Makefile]\nindent_style = tab\n",
+        )
+        .expect("写入根 .editorconfig 应成功");
+        fs::write(
+            root.join("web").join(".editorconfig"),
+            "[*.ts]\nend_of_line = crlf\ninsert_final_newline = true\n",
+        )
+        .expect("写入子目录 .editorconfig 应成功");
+
+        let ts =
+            resolve_format_settings(Some(&root.join("web/app.ts")), &FormatOverrides::default());
+        assert_eq!(ts.indent_style, IndentStyle::Space);
+        assert_eq!(ts.indent_size, 2, "更具体的段应覆盖 [*] 的缩进");
+        assert_eq!(ts.tab_width, 2, "未设置 tab_width 时应沿用 indent_size");
+        assert_eq!(ts.end_of_line, EndOfLine::Crlf, "内层配置应覆盖外层配置");
+        assert!(ts.trim_trailing_whitespace);
+        assert!(ts.insert_final_newline);
+        assert_eq!(ts.lsp_tab_size(), 2);
+
+        let makefile =
+            resolve_format_settings(Some(&root.join("Makefile")), &FormatOverrides::default());
+        assert_eq!(makefile.indent_style, IndentStyle::Tab);
+        assert_eq!(makefile.indent_unit(), "\t");
+        assert!(!makefile.insert_spaces());
+
+        let rust =
+            resolve_format_settings(Some(&root.join("src/main.rs")), &FormatOverrides::default());
+        assert_eq!(rust.indent_size, 4);
+        assert_eq!(rust.end_of_line, EndOfLine::Lf);
+        assert!(!rust.trim_trailing_whitespace);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_resolve_settings_applies_editor_json_overrides_last() {
+        let root = temp_workspace("overrides");
+        fs::write(
+            root.join(".editorconfig"),
+            "root = true\n[*]\nindent_style = tab\ntab_width = 8\n",
+        )
+        .expect("写入 .editorconfig 应成功");
+
+        let overrides = FormatOverrides {
+            indent_style: Some("space".to_string()),
+            indent_size: Some(3),
+            ..FormatOverrides::default()
+        };
+        let settings = resolve_format_settings(Some(&root.join("lib.rs")), &overrides);
+        assert_eq!(settings.indent_style, IndentStyle::Space);
+        assert_eq!(settings.indent_unit(), "   ");
+        assert_eq!(
+            settings.tab_width, 8,
+            "显式 tab_width 不应被 indent_size 覆盖"
+        );
+
+        let unsaved = resolve_format_settings(None, &overrides);
+        assert_eq!(unsaved.indent_unit(), "   ");
+        assert_eq!(
+            unsaved.tab_width, 3,
+            "未保存的缓冲区不读取 .editorconfig，tab_width 跟随覆盖的 indent_size"
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_encode_lines_respects_eol_final_newline_and_bom() {
+        let settings = super::FormatSettings {
+            end_of_line: EndOfLine::Crlf,
+            insert_final_newline: true,
+            charset: "utf-8-bom".to_string(),
+            trim_trailing_whitespace: true,
+            ..super::FormatSettings::default()
+        };
+        let mut lines = vec!["a  ".to_string(), "b\t".to_string()];
+        assert!(settings.apply_save_transforms(&mut lines));
+        assert_eq!(
+            settings.encode_lines(&lines),
+            b"\xEF\xBB\xBFa\r\nb\r\n".to_vec()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use core::settings::load_settings;
+use serde::Deserialize;
+
+/// 外部格式化器配置文件名（与 `editor.json` 同样按用户全局 / 项目两层叠加）。
+const FORMATTERS_CONFIG_FILE: &str = "formatters.json";
+
+/// 未配置 `timeout_ms` 时外部格式化器的最长运行时间。
+const DEFAULT_FORMATTER_TIMEOUT_MS: u64 = 10_000;
+
+/// 单个语言的外部格式化命令：从 stdin 读入整份文本，把格式化结果写到 stdout。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub(super) struct ExternalFormatter {
+    pub(super) command: String,
+    pub(super) args: Vec<String>,
+    /// 超时时间（毫秒），超时后结束进程并保持缓冲区不变；未配置时为 10 秒。
+    pub(super) timeout_ms: Option<u64>,
+}
+
+impl ExternalFormatter {
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_FORMATTER_TIMEOUT_MS))
+    }
+}
+
+/// `.order/formatters.json`：键为 LSP `languageId`（如 `rust`、`python`）。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub(super) struct FormattersConfig {
+    languages: HashMap<String, ExternalFormatter>,
+}
+
+impl FormattersConfig {
+    /// 加载外部格式化器配置；文件不存在时返回空配置。
+    pub(super) fn load(root: &Path) -> Result<Self, String> {
+        load_settings(root, FORMATTERS_CONFIG_FILE, &[])
+            .map_err(|error| format!("加载格式化器配置失败: {error:#}"))
+    }
+
+    // 查找语言对应的格式化器，未配置命令时视为没有。
+    pub(super) fn get(&self, language_id: &str) -> Option<&ExternalFormatter> {
+        self.languages
+            .get(language_id)
+            .filter(|formatter| !formatter.command.trim().is_empty())
+    }
+}
+
+/// 一次格式化请求实际采用的方式。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum FormatStrategy {
+    /// 走 LSP `formatting` / `rangeFormatting`。
+    Lsp,
+    /// 调用外部格式化命令。
+    External(ExternalFormatter),
+    /// 两者都不可用，沿用 LSP 路径以便给出原有的错误提示。
+    Unavailable,
+}
+
+/// 选择格式化方式：LSP 能格式化时优先 LSP，否则回退到外部格式化器。
+///
+/// LSP 格式化会遵循项目里的语言服务配置（例如 rust-analyzer 的 rustfmt 参数），
+/// 所以只有服务未运行或未声明格式化能力时才使用外部命令。
+pub(super) fn choose_format_strategy(
+    lsp_can_format: bool,
+    external: Option<&ExternalFormatter>,
+) -> FormatStrategy {
+    match (lsp_can_format, external) {
+        (true, _) => FormatStrategy::Lsp,
+        (false, Some(formatter)) => FormatStrategy::External(formatter.clone()),
+        (false, None) => FormatStrategy::Unavailable,
+    }
+}
+
+/// 以 stdin/stdout 方式运行外部格式化器，返回格式化后的文本。
+///
+/// 写 stdin 与读 stdout/stderr 都放在独立线程：格式化器可能边读边写，
+/// 避免大文件时双方都卡在管道缓冲区上。主线程轮询进程状态，
+/// 超过 `timeout_ms` 仍未退出时结束进程，防止卡住或等待终端输入的命令冻结界面。
+pub(super) fn run_external_formatter(
+    formatter: &ExternalFormatter,
+    input: &str,
+    cwd: &Path,
+) -> Result<String, String> {
+    let mut child = Command::new(&formatter.command)
+        .args(&formatter.args)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("启动 {} 失败: {error}", formatter.command))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| format!("无法获取 {} 标准输入", formatter.command))?;
+    let input = input.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let stdout = child.stdout.take().map(spawn_pipe_reader);
+    let stderr = child.stderr.take().map(spawn_pipe_reader);
+
+    let deadline = Instant::now() + formatter.timeout();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                // 不等待读写线程：格式化器派生的子进程可能仍占用管道。
+                return Err(format!(
+                    "{} 超过 {} ms 未结束，已终止",
+                    formatter.command,
+                    formatter.timeout().as_millis()
+                ));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(error) => {
+                let _ = child.kill();
+                return Err(format!("等待 {} 结束失败: {error}", formatter.command));
+            }
+        }
+    };
+    let _ = writer.join();
+    let stdout = stdout
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let reason = stderr
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("无错误输出");
+        return Err(format!(
+            "{} 退出码 {}: {reason}",
+            formatter.command,
+            status.code().unwrap_or(-1)
+        ));
+    }
+    String::from_utf8(stdout).map_err(|_| format!("{} 输出不是 UTF-8", formatter.command))
+}
+
+// 在后台线程读完整个管道，读取失败时返回已读到的部分。
+fn spawn_pipe_reader(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/// 把缓冲区的行拼成交给格式化器的文本：格式化器普遍期望以换行结尾。
+pub(super) fn formatter_input(lines: &[String]) -> String {
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// 把格式化器输出拆回缓冲区的行：去掉末尾换行并统一为 LF。
+pub(super) fn formatter_output_lines(output: &str) -> Vec<String> {
+    let normalized = output.replace("\r\n", "\n");
+    let trimmed = normalized.strip_suffix('\n').unwrap_or(&normalized);
+    trimmed.split('\n').map(ToOwned::to_owned).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ExternalFormatter, FormatStrategy, FormattersConfig, choose_format_strategy,
+        formatter_input, formatter_output_lines, run_external_formatter,
+    };
+
+    #[test]
+    fn test_choose_format_strategy_prefers_lsp() {
+        let config: FormattersConfig = serde_json::from_str(
+            r#"{"rust": {"command": "rustfmt", "args": ["--emit", "stdout"]}, "python": {"command": " "}}"#,
+        )
+        .expect("解析格式化器配置失败");
+        let rustfmt = config.get("rust").cloned();
+
+        assert_eq!(
+            choose_format_strategy(true, rustfmt.as_ref()),
+            FormatStrategy::Lsp,
+            "LSP 支持格式化时优先使用 LSP"
+        );
+        assert_eq!(
+            choose_format_strategy(false, rustfmt.as_ref()),
+            FormatStrategy::External(ExternalFormatter {
+                command: "rustfmt".to_string(),
+                args: vec!["--emit".to_string(), "stdout".to_string()],
+                timeout_ms: None,
+            })
+        );
+        assert_eq!(
+            choose_format_strategy(false, config.get("python")),
+            FormatStrategy::Unavailable,
+            "空命令视为未配置"
+        );
+        assert_eq!(
+            choose_format_strategy(false, config.get("go")),
+            FormatStrategy::Unavailable
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_external_formatter_round_trips_stdin_stdout() {
+        let lines = vec!["fn main() {".to_string(), "}".to_string()];
+        let input = formatter_input(&lines);
+        assert_eq!(input, "fn main() {\n}\n");
+
+        // 用 `tr` 模拟格式化器：把输入转换后写回 stdout。
+        let upper = ExternalFormatter {
+            command: "tr".to_string(),
+            args: vec!["a-z".to_string(), "A-Z".to_string()],
+            timeout_ms: None,
+        };
+        let output =
+            run_external_formatter(&upper, &input, &std::env::temp_dir()).expect("运行 tr 失败");
+        assert_eq!(
+            formatter_output_lines(&output),
+            vec!["FN MAIN() {".to_string(), "}".to_string()]
+        );
+
+        assert_eq!(
+            formatter_output_lines("a\r\nb\r\n"),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(formatter_output_lines(""), vec![String::new()]);
+
+        let missing = ExternalFormatter {
+            command: "order-formatter-that-does-not-exist".to_string(),
+            args: Vec::new(),
+            timeout_ms: None,
+        };
+        assert!(run_external_formatter(&missing, &input, &std::env::temp_dir()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_external_formatter_kills_on_timeout() {
+        let config: FormattersConfig = serde_json::from_str(
+            r#"{"rust": {"command": "sleep", "args": ["5"], "timeout_ms": 100}}"#,
+        )
+        .expect("解析格式化器配置失败");
+        let sleeper = config.get("rust").expect("应读取到格式化器");
+
+        let started = std::time::Instant::now();
+        let error = run_external_formatter(sleeper, "", &std::env::temp_dir())
+            .expect_err("超时的格式化器应返回错误");
+        assert!(error.contains("超过 100 ms"), "错误信息应说明超时: {error}");
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(3),
+            "超时后应立即结束进程，而不是等待其自行退出"
+        );
+    }
+}
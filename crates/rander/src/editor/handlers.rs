@@ -2,19 +2,42 @@ use std::{
     cmp::min,
     collections::BTreeMap,
     path::{Path, PathBuf},
+    time::Instant,
 };
 
 use core::commands::get_exit;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use lsp::{LspCallDirection, LspCallHierarchyItem, LspFormatScope};
 use ratatui::layout::{Constraint, Direction, Layout};
 
 use super::{
     Editor, MAX_TREE_RATIO, MIN_TREE_RATIO,
+    auto_pairs::{PairAction, pair_action, pairing_language_key},
+    call_hierarchy::CallHierarchyView,
+    chat_bridge::{build_diagnostics_fix_handoff, build_selection_handoff},
+    command_line::{EditorCommand, LspLogCommand, parse_command_line},
+    config::is_large_file,
+    copy_path::{CopyPathKind, copy_to_terminal_clipboard, detect_project_root, format_copy_text},
+    diagnostic_groups::{severity_group_jump, severity_group_position},
+    diagnostics_export::export_diagnostics,
+    formatter::{
+        FormatStrategy, choose_format_strategy, formatter_input, formatter_output_lines,
+        run_external_formatter,
+    },
+    lsp_log::LspEventLog,
+    peek::{PEEK_CONTEXT_LINES, PEEK_VISIBLE_ROWS, PeekView, peek_window, read_peek_window},
+    quick_fix::select_line_diagnostic,
+    read_file::read_insert_lines,
+    related_info::{next_related_cursor, related_jump_target, related_status},
+    tabs::{ClosedBuffer, cycle_buffer_index, lru_eviction_candidate, move_tab, toggle_pin},
     types::{
         CompletionDisplayItem, EditorBuffer, EditorMode, MainFocus, PaneFocus, SplitDirection,
         TabState,
     },
-    utils::{contains_point, file_name_or, is_completion_trigger_char, is_normal_command_prefix},
+    utils::{
+        char_count, contains_point, file_name_or, is_completion_trigger_char,
+        is_normal_command_prefix,
+    },
 };
 
 const COMPLETION_VISIBLE_COUNT: usize = 7;
@@ -24,6 +47,7 @@ impl Editor {
     ///
     /// 将“候选列表 + 选中索引 + 滚动偏移”一并重置，避免后续按键复用到旧状态。
     fn clear_completion_state(&mut self) {
+        self.completion_debounce.cancel();
         self.completion_items.clear();
         self.completion_selected = 0;
         self.completion_scroll_offset = 0;
@@ -60,6 +84,11 @@ impl Editor {
             EditorMode::Terminal => self.handle_terminal_key_event(key),
             EditorMode::BufferPicker => self.handle_buffer_picker_key_event(key),
             EditorMode::RenameInput => self.handle_rename_input_key_event(key),
+            EditorMode::WillSaveConfirm => self.handle_will_save_confirm_key_event(key),
+            EditorMode::CapabilitiesView => self.handle_capabilities_view_key_event(key),
+            EditorMode::PeekView => self.handle_peek_view_key_event(key),
+            EditorMode::CommandLine => self.handle_command_line_key_event(key),
+            EditorMode::CallHierarchy => self.handle_call_hierarchy_key_event(key),
         }
     }
 
@@ -85,10 +114,15 @@ impl Editor {
                 self.status_message = "INSERT".to_string();
             }
             KeyCode::Char('v') if self.normal_pending.is_empty() => {
-                // 与 Vim 习惯对齐：NORMAL 下按 v 进入 VISUAL，先只切换模式，后续可扩展选区。
+                // 与 Vim 习惯对齐：NORMAL 下按 v 进入 VISUAL，并以当前行作为选区锚点。
                 self.mode = EditorMode::Visual;
+                self.visual_anchor_row = Some(self.active_buffer().cursor_row);
                 self.status_message = "VISUAL".to_string();
             }
+            KeyCode::Char(':') if self.normal_pending.is_empty() => {
+                self.command_input.clear();
+                self.mode = EditorMode::CommandLine;
+            }
             KeyCode::Char('h') if self.normal_pending.is_empty() => {
                 if self.main_focus == MainFocus::Tree {
                     return;
@@ -107,14 +141,16 @@ impl Editor {
                     self.tree_select_next();
                     return;
                 }
-                self.active_buffer_mut().move_down();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_down_display(width);
             }
             KeyCode::Char('k') if self.normal_pending.is_empty() => {
                 if self.main_focus == MainFocus::Tree {
                     self.tree_select_prev();
                     return;
                 }
-                self.active_buffer_mut().move_up();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_up_display(width);
             }
             KeyCode::Left if self.normal_pending.is_empty() => {
                 if self.main_focus == MainFocus::Tree {
@@ -122,6 +158,11 @@ impl Editor {
                 }
                 self.active_buffer_mut().move_left();
             }
+            KeyCode::Home | KeyCode::Char('^')
+                if self.normal_pending.is_empty() && self.main_focus == MainFocus::Editor =>
+            {
+                self.move_to_line_home(key.code == KeyCode::Char('^'));
+            }
             KeyCode::Right if self.normal_pending.is_empty() => {
                 if self.main_focus == MainFocus::Tree {
                     self.main_focus = MainFocus::Editor;
@@ -135,14 +176,16 @@ impl Editor {
                     self.tree_select_next();
                     return;
                 }
-                self.active_buffer_mut().move_down();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_down_display(width);
             }
             KeyCode::Up if self.normal_pending.is_empty() => {
                 if self.main_focus == MainFocus::Tree {
                     self.tree_select_prev();
                     return;
                 }
-                self.active_buffer_mut().move_up();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_up_display(width);
             }
             KeyCode::Esc => {
                 self.normal_pending.clear();
@@ -202,10 +245,21 @@ impl Editor {
 
         match key.code {
             KeyCode::Esc | KeyCode::Char('v') => {
-                self.mode = EditorMode::Normal;
-                self.normal_pending.clear();
+                self.leave_visual_mode();
                 self.status_message = "NORMAL".to_string();
             }
+            KeyCode::Char('=') if self.main_focus == MainFocus::Editor => {
+                let selection = self.visual_selection_range();
+                self.leave_visual_mode();
+                self.request_lsp_document_format(selection);
+            }
+            KeyCode::Char('y') if self.main_focus == MainFocus::Editor => {
+                let selection = self.visual_selection_rows();
+                self.leave_visual_mode();
+                if let Some((start_row, end_row)) = selection {
+                    self.send_selection_to_chat(start_row, end_row);
+                }
+            }
             KeyCode::Char('h') => {
                 if self.main_focus == MainFocus::Tree {
                     return;
@@ -224,14 +278,16 @@ impl Editor {
                     self.tree_select_next();
                     return;
                 }
-                self.active_buffer_mut().move_down();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_down_display(width);
             }
             KeyCode::Char('k') => {
                 if self.main_focus == MainFocus::Tree {
                     self.tree_select_prev();
                     return;
                 }
-                self.active_buffer_mut().move_up();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_up_display(width);
             }
             KeyCode::Left => {
                 if self.main_focus == MainFocus::Tree {
@@ -239,6 +295,9 @@ impl Editor {
                 }
                 self.active_buffer_mut().move_left();
             }
+            KeyCode::Home | KeyCode::Char('^') if self.main_focus == MainFocus::Editor => {
+                self.move_to_line_home(key.code == KeyCode::Char('^'));
+            }
             KeyCode::Right => {
                 if self.main_focus == MainFocus::Tree {
                     self.main_focus = MainFocus::Editor;
@@ -252,14 +311,16 @@ impl Editor {
                     self.tree_select_next();
                     return;
                 }
-                self.active_buffer_mut().move_down();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_down_display(width);
             }
             KeyCode::Up => {
                 if self.main_focus == MainFocus::Tree {
                     self.tree_select_prev();
                     return;
                 }
-                self.active_buffer_mut().move_up();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_up_display(width);
             }
             KeyCode::Enter => {
                 if self.main_focus == MainFocus::Tree {
@@ -283,25 +344,102 @@ impl Editor {
         }
     }
 
+    // 按配置的 Home 行为移动到行首，`caret` 表示由 `^` 触发。
+    fn move_to_line_home(&mut self, caret: bool) {
+        let behavior = self.config.home_key;
+        let buffer = self.active_buffer_mut();
+        if caret {
+            buffer.move_caret(behavior);
+        } else {
+            buffer.move_home(behavior);
+        }
+    }
+
+    // 退出 VISUAL 模式并清空选区。
+    fn leave_visual_mode(&mut self) {
+        self.mode = EditorMode::Normal;
+        self.normal_pending.clear();
+        self.visual_anchor_row = None;
+    }
+
+    /// 当前 VISUAL 选区覆盖的行范围（含两端），非 VISUAL 模式返回 `None`。
+    pub(super) fn visual_selection_rows(&self) -> Option<(usize, usize)> {
+        if self.mode != EditorMode::Visual {
+            return None;
+        }
+        let buffer = self.active_buffer();
+        let last_row = buffer.lines.len().saturating_sub(1);
+        let anchor = self.visual_anchor_row?.min(last_row);
+        Some((anchor.min(buffer.cursor_row), anchor.max(buffer.cursor_row)))
+    }
+
+    /// 将 VISUAL 整行选区转换为 LSP 范围：首行行首到末行行尾。
+    fn visual_selection_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (start_row, end_row) = self.visual_selection_rows()?;
+        let end_character = char_count(&self.active_buffer().lines[end_row]);
+        Some(((start_row, 0), (end_row, end_character)))
+    }
+
+    // `Ctrl+V` 之后的按键：原样插入字符、制表符或换行，`Esc` 放弃字面输入。
+    fn insert_literal_key(&mut self, key: KeyEvent) {
+        self.literal_next = false;
+        self.status_message = "INSERT".to_string();
+        let ch = match key.code {
+            KeyCode::Char(ch) => ch,
+            KeyCode::Tab => '\t',
+            KeyCode::Enter => '\n',
+            _ => return,
+        };
+        self.take_snippet_placeholder();
+        if ch == '\n' {
+            self.active_buffer_mut().insert_newline();
+        } else {
+            self.active_buffer_mut().insert_char(ch);
+        }
+    }
+
     // 处理 INSERT 模式按键。
     pub(super) fn handle_insert_key_event(&mut self, key: KeyEvent) {
+        if self.literal_next {
+            self.insert_literal_key(key);
+            return;
+        }
         match key.code {
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.insert_j_pending = false;
+                self.literal_next = true;
+                self.clear_completion_state();
+                self.status_message = "INSERT ^V".to_string();
+            }
             KeyCode::Esc => {
                 self.insert_j_pending = false;
                 if !self.completion_items.is_empty() {
                     // 补全弹窗可见时，优先关闭弹窗，避免误退出 INSERT 模式。
                     self.clear_completion_state();
                 } else {
+                    self.snippet_session = None;
                     self.mode = EditorMode::Normal;
                     self.status_message = "NORMAL".to_string();
                     self.clear_completion_state();
                 }
             }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // `Ctrl+R` 粘贴内部寄存器，作为终端剪贴板不可用时的兜底。
+                self.insert_j_pending = false;
+                match self.copy_register.clone() {
+                    Some(text) => {
+                        self.active_buffer_mut().insert_str(&text);
+                        self.clear_completion_state();
+                    }
+                    None => self.status_message = "内部寄存器为空".to_string(),
+                }
+            }
             KeyCode::Char('k') if self.insert_j_pending => {
                 // `jk` 作为 INSERT 模式退出快捷键：
                 // - 首个 `j` 已在上一拍被插入；
                 // - 当前拍输入 `k` 时回退该 `j`，避免将 `jk` 残留到文本里。
                 self.active_buffer_mut().backspace();
+                self.snippet_session = None;
                 self.mode = EditorMode::Normal;
                 self.insert_j_pending = false;
                 self.status_message = "NORMAL".to_string();
@@ -310,7 +448,8 @@ impl Editor {
             KeyCode::Char(ch) => {
                 self.resume_completion_after_input();
                 self.insert_j_pending = ch == 'j';
-                self.active_buffer_mut().insert_char(ch);
+                self.take_snippet_placeholder();
+                self.insert_char_with_auto_pairs(ch);
                 if is_completion_trigger_char(ch) {
                     self.refresh_completion_with_request();
                 } else {
@@ -320,7 +459,10 @@ impl Editor {
             KeyCode::Backspace => {
                 self.resume_completion_after_input();
                 self.insert_j_pending = false;
-                self.active_buffer_mut().backspace();
+                // 刚跳到的占位整体删除，与直接输入替换占位保持一致。
+                if !self.take_snippet_placeholder() {
+                    self.active_buffer_mut().backspace();
+                }
                 self.refresh_completion();
             }
             KeyCode::Enter => {
@@ -334,13 +476,15 @@ impl Editor {
                 }
             }
             KeyCode::Tab => {
-                if !self.completion_items.is_empty() {
+                // 片段触发词优先于补全弹窗：触发词本身常常也会弹出补全。
+                if self.try_expand_snippet() || self.jump_to_next_snippet_stop() {
+                    self.clear_completion_state();
+                } else if !self.completion_items.is_empty() {
                     self.select_next_completion();
                 } else {
                     self.resume_completion_after_input();
-                    for _ in 0..4 {
-                        self.active_buffer_mut().insert_char(' ');
-                    }
+                    let indent = self.active_format_settings().indent_unit();
+                    self.active_buffer_mut().insert_str(&indent);
                     self.refresh_completion();
                 }
             }
@@ -353,6 +497,10 @@ impl Editor {
                 self.active_buffer_mut().move_left();
                 self.refresh_completion();
             }
+            KeyCode::Home => {
+                self.move_to_line_home(false);
+                self.refresh_completion();
+            }
             KeyCode::Right => {
                 self.active_buffer_mut().move_right();
                 self.refresh_completion();
@@ -361,7 +509,8 @@ impl Editor {
                 if !self.completion_items.is_empty() {
                     self.select_prev_completion();
                 } else {
-                    self.active_buffer_mut().move_up();
+                    let width = self.soft_wrap_width();
+                    self.active_buffer_mut().move_up_display(width);
                     self.refresh_completion();
                 }
             }
@@ -369,7 +518,8 @@ impl Editor {
                 if !self.completion_items.is_empty() {
                     self.select_next_completion();
                 } else {
-                    self.active_buffer_mut().move_down();
+                    let width = self.soft_wrap_width();
+                    self.active_buffer_mut().move_down_display(width);
                     self.refresh_completion();
                 }
             }
@@ -415,22 +565,38 @@ impl Editor {
             KeyCode::Char(ch) => {
                 let idx = (ch as u8).wrapping_sub(b'a') as usize;
                 if idx < self.buffers.len() {
-                    // 切换前对当前 Rust 文件发送 didClose，避免语言服务端保留陈旧打开状态。
-                    let current_idx = self.tabs[self.active_tab].buffer_index;
-                    self.try_send_did_close_for_buffer_idx(current_idx);
-
-                    self.tabs[self.active_tab].buffer_index = idx;
                     self.mode = EditorMode::Normal;
-                    self.status_message = format!("已切换到缓冲区：{}", self.buffers[idx].name);
-
-                    // 切换后对目标 Rust 缓冲区补发 didOpen，恢复语义上下文。
-                    self.try_send_did_open_for_buffer_idx(idx);
+                    self.switch_active_buffer(idx);
                 }
             }
             _ => {}
         }
     }
 
+    /// 让当前标签页显示指定缓冲区；光标与滚动位置随缓冲区保存，切回时原样恢复。
+    fn switch_active_buffer(&mut self, idx: usize) {
+        // 切换前对当前文件发送 didClose，避免语言服务端保留陈旧打开状态。
+        // 缓冲区本身保留在列表中，未保存的修改不受影响。
+        let current_idx = self.tabs[self.active_tab].buffer_index;
+        self.try_send_did_close_for_buffer_idx(current_idx);
+
+        self.tabs[self.active_tab].buffer_index = idx;
+        self.status_message = format!("已切换到缓冲区：{}", self.buffers[idx].name);
+
+        // 切换后对目标缓冲区补发 didOpen，恢复语义上下文。
+        self.try_send_did_open_for_buffer_idx(idx);
+    }
+
+    /// `:bn` / `:bp` / `]b` / `[b`：不经选择器，循环切换到下一个或上一个缓冲区。
+    fn cycle_buffer(&mut self, forward: bool) {
+        if self.buffers.len() <= 1 {
+            self.status_message = "只有一个缓冲区".to_string();
+            return;
+        }
+        let current_idx = self.tabs[self.active_tab].buffer_index;
+        self.switch_active_buffer(cycle_buffer_index(current_idx, self.buffers.len(), forward));
+    }
+
     /// 处理 LSP rename 输入模式按键。
     ///
     /// 采用轻量输入模型（字符/退格/确认/取消）即可覆盖 MVP，
@@ -455,6 +621,415 @@ impl Editor {
         }
     }
 
+    /// 插入一个字符，并按当前语言的设置处理括号 / 引号自动配对。
+    fn insert_char_with_auto_pairs(&mut self, ch: char) {
+        let buffer = self.active_buffer();
+        let language_key = pairing_language_key(buffer.path.as_deref(), &buffer.name);
+        let action = if self.config.auto_pairs.is_enabled_for(&language_key) {
+            pair_action(&buffer.lines[buffer.cursor_row], buffer.cursor_col, ch)
+        } else {
+            PairAction::Insert
+        };
+
+        let buffer = self.active_buffer_mut();
+        match action {
+            PairAction::Insert => buffer.insert_char(ch),
+            PairAction::InsertPair(close) => {
+                buffer.insert_char(ch);
+                buffer.insert_char(close);
+                buffer.move_left();
+            }
+            PairAction::SkipOver => buffer.move_right(),
+        }
+    }
+
+    /// 处理 `:` 命令行输入模式按键。
+    ///
+    /// 输入为空时退格直接退出，与 Vim 命令行的手感一致。
+    pub(super) fn handle_command_line_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.command_input.clear();
+                self.mode = EditorMode::Normal;
+            }
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.command_input);
+                self.mode = EditorMode::Normal;
+                match parse_command_line(&input) {
+                    Ok(command) => self.execute_editor_command(command),
+                    Err(error) => self.status_message = error,
+                }
+            }
+            KeyCode::Backspace if self.command_input.is_empty() => {
+                self.mode = EditorMode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.command_input.pop();
+            }
+            KeyCode::Char(ch) => {
+                self.command_input.push(ch);
+            }
+            _ => {}
+        }
+    }
+
+    // 执行命令行解析出的命令；大文件插入确认只对紧接着的同一条 `:r` 有效。
+    fn execute_editor_command(&mut self, command: EditorCommand) {
+        let pending_read_confirm = self.pending_read_confirm.take();
+        match command {
+            EditorCommand::LspLog(command) => self.execute_lsp_log_command(command),
+            EditorCommand::ReadFile(path) => {
+                self.execute_read_file_command(&path, pending_read_confirm)
+            }
+            EditorCommand::Lsp(enabled) => self.set_lsp_enabled(enabled),
+            EditorCommand::CycleBuffer(forward) => self.cycle_buffer(forward),
+            EditorCommand::DiagExport(path) => self.execute_diag_export_command(&path),
+        }
+    }
+
+    // 把当前工作区诊断导出到文件，相对路径按工作区根目录解析。
+    fn execute_diag_export_command(&mut self, path: &str) {
+        let path = self.root.join(path);
+        self.status_message =
+            match export_diagnostics(&self.root, &path, &self.lsp_diagnostics_by_file) {
+                Ok(count) => format!("已导出 {count} 条诊断：{}", path.display()),
+                Err(error) => format!(":diagexport 失败：{} ({error})", path.display()),
+            };
+    }
+
+    /// 在本次会话中整体开启或关闭语言服务。
+    ///
+    /// 关闭时停止全部会话，并清掉语义 token、补全与诊断这些已过期的结果，
+    /// 渲染随之回退到 syntect；同步快照也一并清空，重新开启后按 didOpen 全量同步。
+    /// 开启后只为当前缓冲区补发 didOpen，其余语言交给自动激活按需启动。
+    fn set_lsp_enabled(&mut self, enabled: bool) {
+        let currently_enabled = !self.lsp_disabled;
+        if enabled == currently_enabled {
+            self.status_message =
+                format!("LSP 已经是{}状态", if enabled { "开启" } else { "关闭" });
+            return;
+        }
+
+        if enabled {
+            self.lsp_disabled = false;
+            self.rust_analyzer_status = "rust-analyzer: 未激活".to_string();
+            let active_idx = self.tabs[self.active_tab].buffer_index;
+            self.try_send_did_open_for_buffer_idx(active_idx);
+            self.status_message = "LSP 已开启".to_string();
+            return;
+        }
+
+        self.lsp_disabled = true;
+        self.lsp_client.stop_all();
+        for buffer in &mut self.buffers {
+            buffer.lsp_semantic_tokens.clear();
+            buffer.lsp_tokens_by_line.clear();
+            buffer.lsp_completion_items.clear();
+            buffer.lsp_last_synced_text = None;
+            buffer.lsp_dirty = false;
+        }
+        self.clear_completion_state();
+        self.semantic_range_requested = None;
+        // 经由面板刷新统一清空诊断列表及其并行的级别、关联位置与 `]r` 游标。
+        self.lsp_diagnostics_by_file.clear();
+        self.stale_diagnostic_files.clear();
+        self.diagnostic_index = 0;
+        self.refresh_diagnostics_panel();
+        self.lsp_loading_status.clear();
+        self.rust_analyzer_status = "rust-analyzer: 已关闭".to_string();
+        self.status_message = "LSP 已关闭（:lsp on 重新开启）".to_string();
+    }
+
+    /// 确保文件对应的语言服务已启动，失败时写入状态栏并返回 `false`。
+    ///
+    /// 所有按需触发的 LSP 请求都经过这里，`:lsp off` 后统一拒绝，
+    /// 避免一次悬停或跳转把刚关掉的语言服务又拉起来。
+    fn ensure_lsp_started_for_file(&mut self, path: &Path) -> bool {
+        if self.lsp_disabled {
+            self.status_message = "LSP 已关闭（:lsp on 重新开启）".to_string();
+            return false;
+        }
+        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, path) {
+            self.status_message = format!("LSP 启动失败: {error}");
+            return false;
+        }
+        true
+    }
+
+    /// 把文件内容插入到当前缓冲区光标所在行之后。
+    ///
+    /// 超过大文件阈值时先提示大小，需再次执行同一条 `:r` 才真正插入：
+    /// 误读入几 MB 的日志会让缓冲区与 LSP 同步都变得很慢，且没有撤销可用。
+    fn execute_read_file_command(&mut self, path: &str, pending_confirm: Option<PathBuf>) {
+        let path = self.root.join(path);
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => {
+                self.status_message = format!(":r 失败：{} 不是文件", path.display());
+                return;
+            }
+            Err(error) => {
+                self.status_message = format!(":r 失败：{} ({error})", path.display());
+                return;
+            }
+        };
+
+        if is_large_file(metadata.len(), self.config.large_file_threshold())
+            && pending_confirm.as_deref() != Some(path.as_path())
+        {
+            self.status_message = format!(
+                "{} 较大（{} KB），再次执行同一条 :r 确认插入",
+                path.display(),
+                metadata.len() / 1024
+            );
+            self.pending_read_confirm = Some(path);
+            return;
+        }
+
+        let lines = match read_insert_lines(&path) {
+            Ok(lines) => lines,
+            Err(error) => {
+                self.status_message = format!(":r 读取失败：{} ({error})", path.display());
+                return;
+            }
+        };
+        let inserted = self.active_buffer_mut().insert_lines_below_cursor(lines);
+        self.status_message = format!("已插入 {inserted} 行：{}", path.display());
+    }
+
+    /// 开启、切换或关闭 LSP 事件日志镜像。
+    ///
+    /// 切换到新文件时先停掉旧日志，保证同一时刻只有一个后台写线程。
+    fn execute_lsp_log_command(&mut self, command: LspLogCommand) {
+        match command {
+            LspLogCommand::Status => {
+                self.status_message = match &self.lsp_event_log {
+                    Some(log) => format!("LSP 日志写入中: {}", log.path().display()),
+                    None => "LSP 日志未开启（:lsplog <path> 开启）".to_string(),
+                };
+            }
+            LspLogCommand::Stop => {
+                self.status_message = match self.lsp_event_log.take() {
+                    Some(log) => {
+                        let message = format!("已停止 LSP 日志: {}", log.path().display());
+                        // 不等待后台线程：剩余行由它自行写完，避免关闭日志时卡住界面。
+                        drop(log.stop());
+                        message
+                    }
+                    None => "LSP 日志未开启".to_string(),
+                };
+            }
+            LspLogCommand::Start(path) => {
+                if let Some(log) = self.lsp_event_log.take() {
+                    drop(log.stop());
+                }
+                let path = self.root.join(path);
+                match LspEventLog::start(path.clone()) {
+                    Ok(log) => {
+                        self.lsp_event_log = Some(log);
+                        self.status_message = format!("LSP 日志写入: {}", path.display());
+                    }
+                    Err(error) => {
+                        self.status_message =
+                            format!("LSP 日志开启失败 {}: {error}", path.display());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 处理 `willSaveWaitUntil` 确认弹窗按键。
+    ///
+    /// 大写/小写均可，`a`/`s` 额外记住该语言的选择；其余按键忽略，保证必须显式决定。
+    pub(super) fn handle_will_save_confirm_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                self.resolve_pending_will_save(true, false);
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.resolve_pending_will_save(false, false);
+            }
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                self.resolve_pending_will_save(true, true);
+            }
+            KeyCode::Char('s') | KeyCode::Char('S') => {
+                self.resolve_pending_will_save(false, true);
+            }
+            _ => {}
+        }
+    }
+
+    // 能力面板只读，按 Esc / q / Enter 关闭。
+    fn handle_capabilities_view_key_event(&mut self, key: KeyEvent) {
+        if matches!(
+            key.code,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('Q')
+        ) {
+            self.capabilities_view = None;
+            self.mode = EditorMode::Normal;
+        }
+    }
+
+    // peek 浮层只读：j/k、方向键与翻页键滚动，Esc / q 关闭并回到原光标位置。
+    fn handle_peek_view_key_event(&mut self, key: KeyEvent) {
+        let Some(peek) = self.peek_view.as_mut() else {
+            self.mode = EditorMode::Normal;
+            return;
+        };
+        let page = PEEK_VISIBLE_ROWS as isize;
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                self.peek_view = None;
+                self.mode = EditorMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => peek.scroll_by(1, PEEK_VISIBLE_ROWS),
+            KeyCode::Char('k') | KeyCode::Up => peek.scroll_by(-1, PEEK_VISIBLE_ROWS),
+            KeyCode::PageDown => peek.scroll_by(page, PEEK_VISIBLE_ROWS),
+            KeyCode::PageUp => peek.scroll_by(-page, PEEK_VISIBLE_ROWS),
+            _ => {}
+        }
+    }
+
+    // 调用层级浮层：j/k 移动，l 展开，h 折叠，t 切换方向，Enter 跳转，Esc / q 关闭。
+    fn handle_call_hierarchy_key_event(&mut self, key: KeyEvent) {
+        let Some(view) = self.call_hierarchy_view.as_mut() else {
+            self.mode = EditorMode::Normal;
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                self.call_hierarchy_view = None;
+                self.mode = EditorMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => view.move_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => view.move_selection(-1),
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => {
+                self.expand_selected_call_hierarchy_entry();
+            }
+            KeyCode::Char('h') | KeyCode::Left => view.collapse_selected(),
+            KeyCode::Char('t') => {
+                let direction = match view.direction {
+                    LspCallDirection::Incoming => LspCallDirection::Outgoing,
+                    LspCallDirection::Outgoing => LspCallDirection::Incoming,
+                };
+                view.set_direction(direction);
+                self.expand_selected_call_hierarchy_entry();
+            }
+            KeyCode::Enter => self.jump_to_selected_call_hierarchy_entry(),
+            _ => {}
+        }
+    }
+
+    /// 对光标处符号请求调用层级，结果返回后以浮层展示调用者。
+    fn request_call_hierarchy(&mut self) {
+        let buffer = self.active_buffer();
+        let Some(path) = buffer.path.clone() else {
+            self.status_message = "调用层级仅支持已保存文件".to_string();
+            return;
+        };
+        let (cursor_row, cursor_col) = (buffer.cursor_row, buffer.cursor_col);
+
+        if !self.ensure_lsp_started_for_file(&path) {
+            return;
+        }
+        match self
+            .lsp_client
+            .prepare_call_hierarchy(&path, cursor_row, cursor_col)
+        {
+            Ok(()) => self.status_message = "LSP callHierarchy 请求已发送".to_string(),
+            Err(error) => self.status_message = format!("LSP callHierarchy 请求失败: {error}"),
+        }
+    }
+
+    /// 以 prepare 结果打开调用层级浮层，并自动展开第一个根节点。
+    pub(super) fn show_call_hierarchy(
+        &mut self,
+        source_path: &Path,
+        items: Vec<LspCallHierarchyItem>,
+    ) {
+        if self.active_buffer().path.as_deref() != Some(source_path) {
+            // 请求发出后已切换到其他文件，结果与当前上下文无关。
+            return;
+        }
+        if items.is_empty() {
+            self.status_message = "光标处没有可展开的调用层级".to_string();
+            return;
+        }
+        self.call_hierarchy_view = Some(CallHierarchyView::new(items, LspCallDirection::Incoming));
+        self.mode = EditorMode::CallHierarchy;
+        self.expand_selected_call_hierarchy_entry();
+    }
+
+    // 把展开结果插入浮层；浮层已关闭或结果过期时忽略。
+    pub(super) fn apply_call_hierarchy_calls(
+        &mut self,
+        item: &LspCallHierarchyItem,
+        direction: LspCallDirection,
+        calls: Vec<LspCallHierarchyItem>,
+    ) {
+        let Some(view) = self.call_hierarchy_view.as_mut() else {
+            return;
+        };
+        let count = calls.len();
+        if !view.apply_calls(item, direction, calls) {
+            return;
+        }
+        let label = match direction {
+            LspCallDirection::Incoming => "调用者",
+            LspCallDirection::Outgoing => "被调用者",
+        };
+        self.status_message = if count == 0 {
+            format!("{} 没有{label}", item.name)
+        } else {
+            format!("{}：{count} 个{label}", item.name)
+        };
+    }
+
+    // 按浮层当前方向展开选中节点。
+    fn expand_selected_call_hierarchy_entry(&mut self) {
+        let Some(view) = self.call_hierarchy_view.as_mut() else {
+            return;
+        };
+        let direction = view.direction;
+        let Some(item) = view.begin_expand() else {
+            return;
+        };
+        if let Err(error) = self
+            .lsp_client
+            .request_call_hierarchy_calls(&item, direction)
+        {
+            if let Some(view) = self.call_hierarchy_view.as_mut() {
+                view.abort_expand(&item);
+            }
+            self.status_message = format!("LSP {} 请求失败: {error}", direction.method());
+        }
+    }
+
+    // 打开选中节点所在文件并把光标放到符号名上，随后关闭浮层。
+    fn jump_to_selected_call_hierarchy_entry(&mut self) {
+        let Some(item) = self
+            .call_hierarchy_view
+            .as_ref()
+            .and_then(|view| view.selected_entry())
+            .map(|entry| entry.item.clone())
+        else {
+            return;
+        };
+        self.call_hierarchy_view = None;
+        self.mode = EditorMode::Normal;
+
+        self.open_file_in_current_tab(item.file_path.clone());
+        if self.active_buffer().path.as_deref() != Some(item.file_path.as_path()) {
+            // 打开失败时保留 open_file_in_current_tab 写入的错误提示。
+            return;
+        }
+        let buffer = self.active_buffer_mut();
+        buffer.cursor_row = item.line;
+        buffer.cursor_col = item.character;
+        buffer.ensure_cursor_in_bounds();
+        self.status_message = format!("已跳转到 {}", item.name);
+    }
+
     pub(super) fn handle_mouse_event(&mut self, mouse: MouseEvent) {
         let Some(area) = self.last_area else {
             return;
@@ -517,13 +1092,15 @@ impl Editor {
             if self.main_focus == MainFocus::Tree {
                 self.tree_select_next();
             } else {
-                self.active_buffer_mut().move_down();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_down_display(width);
             }
         } else if matches!(mouse.kind, MouseEventKind::ScrollUp) {
             if self.main_focus == MainFocus::Tree {
                 self.tree_select_prev();
             } else {
-                self.active_buffer_mut().move_up();
+                let width = self.soft_wrap_width();
+                self.active_buffer_mut().move_up_display(width);
             }
         }
     }
@@ -568,6 +1145,13 @@ impl Editor {
             return;
         }
         let idx = self.tree_selected;
+        if let Some(hidden) = self.tree_entries[idx].more_count {
+            self.status_message = format!(
+                "该目录还有 {hidden} 项未展示（单目录上限 {}），可在终端中查看",
+                super::MAX_DIR_CHILDREN
+            );
+            return;
+        }
         if self.tree_entries[idx].is_dir {
             let path = self.tree_entries[idx].path.clone();
             self.toggle_expand_dir(path);
@@ -579,6 +1163,8 @@ impl Editor {
     // 切换目录展开/折叠状态。
     pub(super) fn toggle_expand_dir(&mut self, dir: PathBuf) {
         if self.expanded_dirs.contains(&dir) {
+            // 折叠时丢弃缓存，再次展开即重新读取，相当于手动刷新该目录。
+            self.tree_cache.invalidate(&dir);
             self.expanded_dirs.remove(&dir);
         } else {
             self.expanded_dirs.insert(dir);
@@ -623,6 +1209,7 @@ impl Editor {
             }
             "fl" => {
                 self.load_session();
+                self.tree_cache.clear();
                 self.refresh_tree_entries();
                 true
             }
@@ -695,6 +1282,27 @@ impl Editor {
                 self.close_tab();
                 true
             }
+            "tu" => {
+                self.reopen_closed_tab();
+                true
+            }
+            "tp" => {
+                self.active_tab = toggle_pin(&mut self.tabs, self.active_tab);
+                self.status_message = if self.tabs[self.active_tab].pinned {
+                    "已固定 TAB".to_string()
+                } else {
+                    "已取消固定 TAB".to_string()
+                };
+                true
+            }
+            "tH" => {
+                self.move_active_tab(false);
+                true
+            }
+            "tL" => {
+                self.move_active_tab(true);
+                true
+            }
             "tt" => {
                 self.show_tagbar = !self.show_tagbar;
                 self.status_message =
@@ -762,6 +1370,22 @@ impl Editor {
                 self.run_lsp_server_check();
                 true
             }
+            "la" => {
+                self.activate_lsp_for_active_buffer();
+                true
+            }
+            "li" => {
+                self.show_lsp_capabilities();
+                true
+            }
+            "gp" => {
+                self.request_peek_definition();
+                true
+            }
+            "ch" => {
+                self.request_call_hierarchy();
+                true
+            }
             "lr" => {
                 self.start_lsp_rename_input();
                 true
@@ -770,16 +1394,77 @@ impl Editor {
                 self.request_lsp_format_for_active_buffer();
                 true
             }
+            "fmt" => {
+                self.request_lsp_document_format(None);
+                true
+            }
             "lq" => {
                 self.request_lsp_quick_fix_for_active_buffer();
                 true
             }
+            "fx" => {
+                self.request_line_quick_fix_for_active_buffer();
+                true
+            }
+            "dc" => {
+                self.send_diagnostics_to_chat();
+                true
+            }
+            "yp" => {
+                self.copy_active_path(CopyPathKind::Absolute);
+                true
+            }
+            "yl" => {
+                self.copy_active_path(CopyPathKind::WithPosition);
+                true
+            }
+            "yr" => {
+                self.copy_active_path(CopyPathKind::Relative);
+                true
+            }
             "fb" => {
                 self.theme = self.theme.next();
                 self.status_message = format!("theme => {}", self.theme.as_str());
                 true
             }
-            "[g" => {
+            "gs" => {
+                // 仅切换当前缓冲区，便于在同一会话里对比不同文件的高亮表现。
+                let buffer = self.active_buffer_mut();
+                buffer.semantic_highlight_disabled = !buffer.semantic_highlight_disabled;
+                self.status_message = if self.active_buffer().semantic_highlight_disabled {
+                    "高亮：已切换为 syntect（当前缓冲区）".to_string()
+                } else {
+                    "高亮：已恢复 LSP 语义高亮（有 token 时生效）".to_string()
+                };
+                true
+            }
+            "to" => {
+                let sort = self.tree_cache.sort().next();
+                self.tree_cache.set_sort(sort);
+                self.refresh_tree_entries();
+                self.status_message = format!("目录树排序：{}", sort.label());
+                true
+            }
+            "tw" => {
+                self.typewriter_scrolling = !self.typewriter_scrolling;
+                self.status_message = if self.typewriter_scrolling {
+                    "打字机滚动：已开启".to_string()
+                } else {
+                    "打字机滚动：已关闭".to_string()
+                };
+                true
+            }
+            "gw" => {
+                let buffer = self.active_buffer_mut();
+                buffer.soft_wrap = !buffer.soft_wrap;
+                self.status_message = if self.active_buffer().soft_wrap {
+                    "软换行：已开启（当前缓冲区）".to_string()
+                } else {
+                    "软换行：已关闭（当前缓冲区）".to_string()
+                };
+                true
+            }
+            "[g" => {
                 if !self.diagnostics.is_empty() {
                     self.diagnostic_index = self.diagnostic_index.saturating_sub(1);
                     self.status_message = self.diagnostics[self.diagnostic_index].clone();
@@ -794,6 +1479,30 @@ impl Editor {
                 }
                 true
             }
+            "[G" => {
+                self.jump_diagnostic_severity_group(false);
+                true
+            }
+            "]G" => {
+                self.jump_diagnostic_severity_group(true);
+                true
+            }
+            "]r" => {
+                self.jump_related_location(true);
+                true
+            }
+            "]b" => {
+                self.cycle_buffer(true);
+                true
+            }
+            "[b" => {
+                self.cycle_buffer(false);
+                true
+            }
+            "[r" => {
+                self.jump_related_location(false);
+                true
+            }
             "K" => {
                 if !self.diagnostics.is_empty() {
                     self.status_message = self.diagnostics[self.diagnostic_index].clone();
@@ -804,6 +1513,83 @@ impl Editor {
         }
     }
 
+    /// 复制当前缓冲区的路径到剪贴板，并同步写入内部寄存器。
+    fn copy_active_path(&mut self, kind: CopyPathKind) {
+        let buffer = self.active_buffer();
+        let Some(path) = buffer.path.clone() else {
+            self.status_message = "当前缓冲区尚未保存，没有可复制的路径".to_string();
+            return;
+        };
+        let (row, col) = (buffer.cursor_row, buffer.cursor_col);
+        let project_root = detect_project_root(&path, &self.root);
+        let text = format_copy_text(kind, &path, &project_root, row, col);
+
+        self.status_message = match copy_to_terminal_clipboard(&text) {
+            Ok(()) => format!("已复制{}：{text}", kind.label()),
+            Err(error) => format!(
+                "终端剪贴板不可用（{error}），{}已存入内部寄存器：{text}",
+                kind.label()
+            ),
+        };
+        self.copy_register = Some(text);
+    }
+
+    /// 在诊断列表中按级别分组跳转（error → warning → info → hint）。
+    ///
+    /// 状态栏带上组内位置，便于判断当前级别还剩多少条未处理。
+    fn jump_diagnostic_severity_group(&mut self, forward: bool) {
+        let Some(index) =
+            severity_group_jump(&self.diagnostic_severities, self.diagnostic_index, forward)
+        else {
+            return;
+        };
+        self.diagnostic_index = index;
+        let Some(text) = self.diagnostics.get(index) else {
+            return;
+        };
+        self.status_message = match severity_group_position(&self.diagnostic_severities, index) {
+            Some((position, len)) => format!(
+                "[{} {position}/{len}] {text}",
+                self.diagnostic_severities[index].as_str()
+            ),
+            None => text.clone(),
+        };
+    }
+
+    /// 在当前诊断的关联位置（`relatedInformation`）之间循环跳转，必要时打开其他文件。
+    ///
+    /// 当前诊断即 `[g` / `]g` 选中的那一条，状态栏标出停在第几个关联位置。
+    fn jump_related_location(&mut self, forward: bool) {
+        let diagnostic = self.diagnostic_index;
+        let related = self
+            .diagnostic_related
+            .get(diagnostic)
+            .cloned()
+            .unwrap_or_default();
+        let Some(cursor) =
+            next_related_cursor(self.related_cursor, diagnostic, related.len(), forward)
+        else {
+            self.status_message = "当前诊断没有关联位置".to_string();
+            return;
+        };
+        let item = &related[cursor.index];
+        let target = related_jump_target(self.active_buffer().path.as_deref(), item);
+        if let Some(path) = target.open {
+            self.open_file_in_current_tab(path.clone());
+            if self.active_buffer().path.as_deref() != Some(path.as_path()) {
+                // 打开失败时保留 open_file_in_current_tab 写入的错误提示。
+                return;
+            }
+        }
+        self.main_focus = MainFocus::Editor;
+        let buffer = self.active_buffer_mut();
+        buffer.cursor_row = target.row;
+        buffer.cursor_col = target.col;
+        buffer.ensure_cursor_in_bounds();
+        self.related_cursor = Some(cursor);
+        self.status_message = related_status(cursor, related.len(), item);
+    }
+
     // 功能说明：见下方实现。
     pub(super) fn save_current_file(&mut self) {
         // 在本地落盘前先发送 willSave 系列通知/请求，
@@ -811,10 +1597,17 @@ impl Editor {
         self.try_send_will_save_for_active_buffer();
 
         let root = self.root.clone();
-        match self.active_buffer_mut().save(&root) {
+        let settings = self.active_format_settings();
+        match self.active_buffer_mut().save(&root, &settings) {
             Ok(path) => {
                 self.status_message = format!("保存成功：{}", path.display());
 
+                // 保存可能创建新文件，失效所在目录缓存让目录树及时显示。
+                if let Some(parent) = path.parent() {
+                    self.tree_cache.invalidate(parent);
+                    self.refresh_tree_entries();
+                }
+
                 // 保存后发送 didSave，让 rust-analyzer 尽快更新语义/诊断。
                 self.try_send_did_save_for_path(&path);
             }
@@ -866,11 +1659,29 @@ impl Editor {
 
     /// 刷新补全并请求新的补全候选。
     ///
-    /// 仅在光标前是补全触发字符（`a-z`/`A-Z`/`_`）时发送请求。
+    /// 仅在光标前是补全触发字符（`a-z`/`A-Z`/`_`）时发送请求；
+    /// 配置了防抖延迟时只记录输入时刻，由主循环在输入停顿后发出。
     pub(super) fn refresh_completion_with_request(&mut self) {
         self.refresh_completion_from_lsp_cache();
 
-        if self.should_request_completion() {
+        if !self.should_request_completion() {
+            self.completion_debounce.cancel();
+        } else if self.config.completion_debounce().is_zero() {
+            self.request_completion_for_active_buffer();
+        } else {
+            self.completion_debounce.note_input(Instant::now());
+        }
+    }
+
+    /// 输入停顿超过防抖延迟后发出补全请求；期间离开 INSERT 或光标不再处于触发位置则放弃。
+    pub(super) fn flush_debounced_completion(&mut self) {
+        if !self
+            .completion_debounce
+            .take_ready(Instant::now(), self.config.completion_debounce())
+        {
+            return;
+        }
+        if self.mode == EditorMode::Insert && self.should_request_completion() {
             self.request_completion_for_active_buffer();
         }
     }
@@ -985,10 +1796,14 @@ impl Editor {
                 continue;
             }
 
+            let label_details = item.label_details.clone().unwrap_or_default();
             let display = CompletionDisplayItem {
                 label,
                 insert_text: insert_text.clone(),
                 detail: item.detail.clone(),
+                label_detail: label_details.detail,
+                label_description: label_details.description,
+                deprecated: item.deprecated,
             };
             candidates
                 .entry(insert_text)
@@ -996,11 +1811,22 @@ impl Editor {
                     if existing.detail.is_none() && display.detail.is_some() {
                         existing.detail = display.detail.clone();
                     }
+                    if existing.label_detail.is_none() && existing.label_description.is_none() {
+                        existing.label_detail = display.label_detail.clone();
+                        existing.label_description = display.label_description.clone();
+                    }
+                    // 同一插入文本只要有一个未弃用的来源，就不视为弃用。
+                    existing.deprecated &= display.deprecated;
                 })
                 .or_insert(display);
         }
 
-        self.completion_items = candidates.into_values().take(20).collect();
+        let mut candidates = candidates.into_values().collect::<Vec<_>>();
+        if self.config.completion_deprecated_last {
+            // 稳定排序：弃用候选整体后移，各组内部仍保持原有顺序。
+            candidates.sort_by_key(|item| item.deprecated);
+        }
+        self.completion_items = candidates.into_iter().take(20).collect();
         if self.completion_selected >= self.completion_items.len() {
             self.completion_selected = 0;
             self.completion_scroll_offset = 0;
@@ -1012,6 +1838,7 @@ impl Editor {
         let Some(path) = self
             .buffers
             .get(buffer_idx)
+            .filter(|buffer| !buffer.large_file)
             .and_then(|buffer| buffer.path.clone())
         else {
             return;
@@ -1020,8 +1847,7 @@ impl Editor {
         let cursor_row = self.buffers[buffer_idx].cursor_row;
         let cursor_col = self.buffers[buffer_idx].cursor_col;
 
-        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, &path) {
-            self.status_message = format!("LSP 启动失败: {error}");
+        if !self.ensure_lsp_started_for_file(&path) {
             return;
         }
 
@@ -1051,8 +1877,7 @@ impl Editor {
             return;
         };
 
-        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, &path) {
-            self.status_message = format!("LSP 启动失败: {error}");
+        if !self.ensure_lsp_started_for_file(&path) {
             return;
         }
 
@@ -1088,8 +1913,7 @@ impl Editor {
         let cursor_row = self.buffers[buffer_idx].cursor_row;
         let cursor_col = self.buffers[buffer_idx].cursor_col;
 
-        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, &path) {
-            self.status_message = format!("LSP 启动失败: {error}");
+        if !self.ensure_lsp_started_for_file(&path) {
             return;
         }
 
@@ -1108,6 +1932,9 @@ impl Editor {
 
     /// 对当前文件请求 LSP 格式化。
     fn request_lsp_format_for_active_buffer(&mut self) {
+        if self.try_external_format(false) {
+            return;
+        }
         let buffer_idx = self.tabs[self.active_tab].buffer_index;
         let Some(path) = self
             .buffers
@@ -1118,14 +1945,127 @@ impl Editor {
             return;
         };
 
-        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, &path) {
-            self.status_message = format!("LSP 启动失败: {error}");
+        if !self.ensure_lsp_started_for_file(&path) {
+            return;
+        }
+        if self.lsp_client.is_formatting_in_flight(&path) {
+            self.status_message = "formatting… 等待上一次格式化结果".to_string();
             return;
         }
 
-        match self.lsp_client.request_formatting(&path, 4, true) {
+        let settings = self.active_format_settings();
+        match self.lsp_client.request_formatting(
+            &path,
+            settings.lsp_tab_size(),
+            settings.insert_spaces(),
+        ) {
             Ok(()) => {
-                self.status_message = "LSP format 请求已发送".to_string();
+                self.status_message = "formatting…".to_string();
+            }
+            Err(error) => {
+                self.status_message = format!("LSP format 请求失败: {error}");
+            }
+        }
+    }
+
+    /// LSP 无法格式化时，尝试用 `.order/formatters.json` 中配置的外部命令格式化当前缓冲区。
+    ///
+    /// 返回 `true` 表示已由外部格式化器处理（无论成功与否），调用方不再发送 LSP 请求；
+    /// LSP 会话在运行且声明了格式化能力时始终优先 LSP，保持与服务端配置一致。
+    /// 外部命令只能处理整份文本，因此有选区时也会格式化整个文件。
+    fn try_external_format(&mut self, has_selection: bool) -> bool {
+        let buffer = self.active_buffer();
+        let Some(language) =
+            lsp::detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name)
+        else {
+            return false;
+        };
+        let lsp_can_format = self
+            .lsp_client
+            .server_capabilities(language)
+            .is_some_and(|capabilities| capabilities.format_scope(has_selection).is_some());
+        let formatter = match choose_format_strategy(
+            lsp_can_format,
+            self.formatters.get(language.language_id()),
+        ) {
+            FormatStrategy::External(formatter) => formatter,
+            FormatStrategy::Lsp | FormatStrategy::Unavailable => return false,
+        };
+
+        if buffer.large_file {
+            self.status_message = "大文件模式下不运行外部格式化器".to_string();
+            return true;
+        }
+        let input = formatter_input(&buffer.lines);
+        let output = match run_external_formatter(&formatter, &input, &self.root) {
+            Ok(output) => output,
+            Err(error) => {
+                self.status_message = format!("外部格式化失败: {error}");
+                return true;
+            }
+        };
+
+        let lines = formatter_output_lines(&output);
+        let buffer = self.active_buffer_mut();
+        if buffer.lines == lines {
+            self.status_message = format!("{}: 内容无需格式化", formatter.command);
+            return true;
+        }
+        buffer.lines = lines;
+        buffer.modified = true;
+        buffer.lsp_dirty = true;
+        buffer.ensure_cursor_in_bounds();
+        self.status_message = if has_selection {
+            format!(
+                "{}: 外部格式化器不支持范围，已格式化整个文件",
+                formatter.command
+            )
+        } else {
+            format!("{}: 已格式化整个文件", formatter.command)
+        };
+        true
+    }
+
+    /// 统一格式化入口：有选区时格式化选区，否则格式化整个文件。
+    ///
+    /// 请求类型由 LSP 客户端按服务端能力决定，范围格式化不可用时回退整文件，
+    /// 返回的编辑与 `lf` 走同一条应用路径（标记 modified 与 LSP dirty）。
+    fn request_lsp_document_format(&mut self, selection: Option<((usize, usize), (usize, usize))>) {
+        if self.try_external_format(selection.is_some()) {
+            return;
+        }
+        let Some(path) = self.active_buffer().path.clone() else {
+            self.status_message = "LSP format 仅支持已保存文件".to_string();
+            return;
+        };
+
+        if !self.ensure_lsp_started_for_file(&path) {
+            return;
+        }
+        if self.lsp_client.is_formatting_in_flight(&path) {
+            self.status_message = "formatting… 等待上一次格式化结果".to_string();
+            return;
+        }
+
+        let settings = self.active_format_settings();
+        match self.lsp_client.request_document_format(
+            &path,
+            selection,
+            settings.lsp_tab_size(),
+            settings.insert_spaces(),
+        ) {
+            Ok(LspFormatScope::Range) => {
+                if let Some(((start_row, _), (end_row, _))) = selection {
+                    self.status_message =
+                        format!("formatting…（第 {}-{} 行）", start_row + 1, end_row + 1);
+                }
+            }
+            Ok(LspFormatScope::Full) => {
+                self.status_message = if selection.is_some() {
+                    "LSP 不支持范围格式化，已改为格式化整个文件".to_string()
+                } else {
+                    "formatting…（整个文件）".to_string()
+                };
             }
             Err(error) => {
                 self.status_message = format!("LSP format 请求失败: {error}");
@@ -1133,6 +2073,57 @@ impl Editor {
         }
     }
 
+    /// 把当前文件的诊断与代码上下文交给主对话界面，并退出 editor。
+    ///
+    /// 这里只退出 editor 而不设置全局退出标记，
+    /// 这样主界面可以接管预填充的提示，让用户确认后再发送给模型。
+    fn send_diagnostics_to_chat(&mut self) {
+        let Some(path) = self.active_buffer().path.clone() else {
+            self.status_message = "诊断发送到对话仅支持已保存文件".to_string();
+            return;
+        };
+
+        let file_label = path
+            .strip_prefix(&self.root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let diagnostics = self.diagnostics_for_file(&path);
+        match build_diagnostics_fix_handoff(&file_label, &diagnostics, &self.active_buffer().lines)
+        {
+            Some(handoff) => {
+                self.chat_handoff = Some(handoff);
+                self.should_exit = true;
+            }
+            None => {
+                self.status_message = format!("{file_label}：当前没有诊断可发送到对话");
+            }
+        }
+    }
+
+    /// 把选中的行作为代码块交给主对话界面，并退出 editor。
+    fn send_selection_to_chat(&mut self, start_row: usize, end_row: usize) {
+        let buffer = self.active_buffer();
+        let file_label = match buffer.path.as_deref() {
+            Some(path) => path
+                .strip_prefix(&self.root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/"),
+            None => buffer.name.clone(),
+        };
+        if let Some(handoff) = build_selection_handoff(
+            &file_label,
+            &buffer.lines,
+            start_row,
+            end_row,
+            self.config.chat_line_numbers,
+        ) {
+            self.chat_handoff = Some(handoff);
+            self.should_exit = true;
+        }
+    }
+
     /// 对当前光标请求 quick fix。
     fn request_lsp_quick_fix_for_active_buffer(&mut self) {
         let buffer_idx = self.tabs[self.active_tab].buffer_index;
@@ -1147,8 +2138,7 @@ impl Editor {
         let cursor_row = self.buffers[buffer_idx].cursor_row;
         let cursor_col = self.buffers[buffer_idx].cursor_col;
 
-        if let Err(error) = self.lsp_client.ensure_started_for_file(&self.root, &path) {
-            self.status_message = format!("LSP 启动失败: {error}");
+        if !self.ensure_lsp_started_for_file(&path) {
             return;
         }
 
@@ -1167,8 +2157,8 @@ impl Editor {
 
         match self.lsp_client.request_code_actions(
             &path,
-            cursor_row,
-            cursor_col,
+            (cursor_row, cursor_col),
+            (cursor_row, cursor_col),
             &request_diagnostics,
         ) {
             Ok(()) => {
@@ -1180,6 +2170,45 @@ impl Editor {
         }
     }
 
+    /// 针对光标所在行的诊断请求 quick fix，并自动应用首选修复。
+    ///
+    /// 与 `lq` 的区别是请求范围取诊断本身的范围，且只传入这一条诊断，
+    /// 服务端返回的动作更聚焦，首选修复也更可能正是用户想要的那一个。
+    fn request_line_quick_fix_for_active_buffer(&mut self) {
+        let Some(path) = self.active_buffer().path.clone() else {
+            self.status_message = "LSP quick fix 仅支持已保存文件".to_string();
+            return;
+        };
+        let cursor_row = self.active_buffer().cursor_row;
+        let diagnostics = self.diagnostics_for_file(&path);
+        let Some(diagnostic) = select_line_diagnostic(&diagnostics, cursor_row) else {
+            self.status_message = format!("第 {} 行没有诊断可修复", cursor_row + 1);
+            return;
+        };
+
+        if !self.ensure_lsp_started_for_file(&path) {
+            return;
+        }
+
+        match self.lsp_client.request_code_actions(
+            &path,
+            (diagnostic.lsp_start_line, diagnostic.lsp_start_character),
+            (diagnostic.lsp_end_line, diagnostic.lsp_end_character),
+            std::slice::from_ref(&diagnostic),
+        ) {
+            Ok(()) => {
+                self.status_message = format!(
+                    "LSP quick fix 请求已发送（第 {} 行：{}）",
+                    cursor_row + 1,
+                    diagnostic.message
+                );
+            }
+            Err(error) => {
+                self.status_message = format!("LSP quick fix 请求失败: {error}");
+            }
+        }
+    }
+
     // 应用当前选中的补全项。
     pub(super) fn accept_completion(&mut self) {
         if self.completion_items.is_empty() {
@@ -1216,9 +2245,70 @@ impl Editor {
             buffer_index: idx,
             split: SplitDirection::None,
             focus: PaneFocus::Primary,
+            pinned: false,
+            last_focused: Instant::now(),
         });
         self.active_tab = self.tabs.len().saturating_sub(1);
         self.status_message = "已新建 TAB".to_string();
+        self.enforce_tab_limit();
+    }
+
+    /// 标签页数量超过 `max_tabs` 时，逐个淘汰最久未使用的标签页。
+    ///
+    /// 有未保存修改或已固定的标签页不会被自动关闭；剩余候选都不可淘汰时只提示用户，
+    /// 宁可暂时超出上限也不丢弃修改。
+    pub(super) fn enforce_tab_limit(&mut self) {
+        let Some(limit) = self.config.tab_limit() else {
+            return;
+        };
+        let mut evicted = Vec::new();
+        while self.tabs.len() > limit {
+            let Some(index) = lru_eviction_candidate(&self.tabs, self.active_tab, |buffer_idx| {
+                self.buffers
+                    .get(buffer_idx)
+                    .is_some_and(|buffer| buffer.modified)
+            }) else {
+                self.status_message = format!(
+                    "TAB 数已超过上限 {limit}，其余 TAB 都有未保存修改或已固定，请先保存或手动关闭"
+                );
+                return;
+            };
+            evicted.push(self.tabs[index].title.clone());
+            self.evict_tab(index);
+        }
+        if !evicted.is_empty() {
+            self.status_message = format!(
+                "TAB 数超过上限 {limit}，已关闭最久未使用的：{}",
+                evicted.join("、")
+            );
+        }
+    }
+
+    // 关闭一个非激活标签页；缓冲区不再被任何标签页引用时发送 didClose 并释放。
+    fn evict_tab(&mut self, index: usize) {
+        let tab = self.tabs.remove(index);
+        if index < self.active_tab {
+            self.active_tab -= 1;
+        }
+        let buffer_idx = tab.buffer_index;
+        if self.tabs.iter().any(|tab| tab.buffer_index == buffer_idx) {
+            return;
+        }
+        if let Some(closed) = self
+            .buffers
+            .get(buffer_idx)
+            .and_then(ClosedBuffer::from_buffer)
+        {
+            self.closed_buffers.push(closed);
+        }
+        self.try_send_did_close_for_buffer_idx(buffer_idx);
+        // 只有已保存的缓冲区会走到这里，释放后 `tu` 会重新从磁盘读取。
+        self.buffers.remove(buffer_idx);
+        for tab in &mut self.tabs {
+            if tab.buffer_index > buffer_idx {
+                tab.buffer_index -= 1;
+            }
+        }
     }
 
     // 关闭当前标签页。
@@ -1227,8 +2317,19 @@ impl Editor {
             self.status_message = "至少保留一个 TAB".to_string();
             return;
         }
+        if self.tabs[self.active_tab].pinned {
+            self.status_message = "已固定的 TAB 不能关闭，先用 tp 取消固定".to_string();
+            return;
+        }
 
         let closing_idx = self.tabs[self.active_tab].buffer_index;
+        if let Some(closed) = self
+            .buffers
+            .get(closing_idx)
+            .and_then(ClosedBuffer::from_buffer)
+        {
+            self.closed_buffers.push(closed);
+        }
         self.try_send_did_close_for_buffer_idx(closing_idx);
 
         self.tabs.remove(self.active_tab);
@@ -1245,6 +2346,65 @@ impl Editor {
         }
     }
 
+    /// 在新标签页中重新打开最近关闭的文件，并恢复关闭时的光标位置。
+    ///
+    /// 缓冲区仍在缓存中时直接复用（保留未保存的修改），否则重新从磁盘读取；
+    /// 两种情况都会补发 didOpen，因为关闭时已经向 LSP 发过 didClose。
+    pub(super) fn reopen_closed_tab(&mut self) {
+        let Some(closed) = self.closed_buffers.pop() else {
+            self.status_message = "没有可重新打开的 TAB".to_string();
+            return;
+        };
+
+        let cached = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.path.as_ref() == Some(&closed.path));
+        let buffer_idx = match cached {
+            Some(idx) => idx,
+            None => match EditorBuffer::from_file(&closed.path, self.config.large_file_threshold())
+            {
+                Ok(buffer) => {
+                    self.buffers.push(buffer);
+                    self.buffers.len().saturating_sub(1)
+                }
+                Err(err) => {
+                    self.status_message =
+                        format!("重新打开失败：{}：{}", closed.path.display(), err);
+                    return;
+                }
+            },
+        };
+
+        closed.restore_cursor(&mut self.buffers[buffer_idx]);
+        self.tabs.push(TabState {
+            title: file_name_or(closed.path.as_path(), "Tab").to_string(),
+            buffer_index: buffer_idx,
+            split: SplitDirection::None,
+            focus: PaneFocus::Primary,
+            pinned: false,
+            last_focused: Instant::now(),
+        });
+        self.active_tab = self.tabs.len().saturating_sub(1);
+        self.main_focus = MainFocus::Editor;
+        self.normalize_active_tab_focus();
+        self.status_message = format!("已重新打开：{}", closed.path.display());
+
+        self.try_send_did_open_for_buffer_idx(buffer_idx);
+        self.enforce_tab_limit();
+    }
+
+    // 将当前标签页在所属分组（固定 / 普通）内左右移动一格。
+    fn move_active_tab(&mut self, forward: bool) {
+        let moved = move_tab(&mut self.tabs, self.active_tab, forward);
+        self.status_message = if moved == self.active_tab {
+            "已到达分组边界，无法继续移动".to_string()
+        } else {
+            format!("TAB 已移动到第 {} 位", moved + 1)
+        };
+        self.active_tab = moved;
+    }
+
     // 切换到下一个标签页。
     pub(super) fn next_tab(&mut self) {
         if self.tabs.is_empty() {
@@ -1288,13 +2448,18 @@ impl Editor {
             return;
         }
 
-        match EditorBuffer::from_file(&path) {
+        match EditorBuffer::from_file(&path, self.config.large_file_threshold()) {
             Ok(buffer) => {
+                let large_file = buffer.large_file;
                 self.buffers.push(buffer);
                 let idx = self.buffers.len().saturating_sub(1);
                 self.tabs[self.active_tab].buffer_index = idx;
                 self.tabs[self.active_tab].title = file_name_or(path.as_path(), "Tab").to_string();
-                self.status_message = format!("已打开：{}", path.display());
+                self.status_message = if large_file {
+                    format!("已打开：{}（大文件模式：已关闭高亮与 LSP）", path.display())
+                } else {
+                    format!("已打开：{}", path.display())
+                };
 
                 self.try_send_did_open_for_buffer_idx(idx);
             }
@@ -1309,7 +2474,14 @@ impl Editor {
     /// 该方法会在 `editor::mod` 的缓冲区切换逻辑中被复用，
     /// 因此需要对父模块可见，避免重复实现同一套 didOpen 触发流程。
     pub(super) fn try_send_did_open_for_buffer_idx(&mut self, buffer_idx: usize) {
+        if self.lsp_disabled {
+            return;
+        }
+        // 大文件模式不发送 didOpen：整份文本同步与后续语义分析的开销都过高。
         let Some((path, text, version)) = self.buffers.get(buffer_idx).and_then(|buffer| {
+            if buffer.large_file {
+                return None;
+            }
             let path = buffer.path.as_ref()?.clone();
             Some((path, buffer.lines.join("\n"), buffer.lsp_version))
         }) else {
@@ -1320,6 +2492,13 @@ impl Editor {
         let language = lsp::detect_language_from_path_or_name(Some(&path), "");
         let started_from_cold =
             language.is_some_and(|detected| !self.lsp_client.is_language_running(detected));
+        // didOpen 会顺带冷启动语言服务；被关闭自动激活的语言在手动 `la` 之前不发送。
+        if started_from_cold
+            && language
+                .is_some_and(|detected| !self.config.should_auto_activate_lsp(detected, true))
+        {
+            return;
+        }
 
         match self
             .lsp_client
@@ -1340,7 +2519,7 @@ impl Editor {
                 }
 
                 // `didOpen` 后主动拉取语义 token，确保首次渲染就有语义高亮。
-                if let Err(error) = self.lsp_client.request_semantic_tokens(&path) {
+                if let Err(error) = self.request_semantic_tokens_for_path(&path) {
                     self.status_message = format!(
                         "已打开：{}（LSP semanticTokens 失败: {}）",
                         path.display(),
@@ -1377,13 +2556,22 @@ impl Editor {
 
     /// 若路径是受支持语言文件，则发送 `textDocument/didSave`。
     fn try_send_did_save_for_path(&mut self, path: &Path) {
+        if self.active_buffer().large_file {
+            return;
+        }
         let text = self.active_buffer().lines.join("\n");
+        // go.work 增删模块后，让 gopls 按新的模块列表更新工作区目录。
+        if path.file_name().is_some_and(|name| name == "go.work")
+            && let Err(error) = self.lsp_client.refresh_workspace_folders(&self.root)
+        {
+            self.status_message = format!("LSP 工作区目录更新失败: {error}");
+        }
         match self.lsp_client.send_did_save(path, &text) {
             Ok(_) => {
                 self.status_message = format!("保存成功：{}（LSP didSave 已发送）", path.display());
 
                 // 保存后触发语义 token 刷新，确保格式化/导入变化能及时反映。
-                if let Err(error) = self.lsp_client.request_semantic_tokens(path) {
+                if let Err(error) = self.request_semantic_tokens_for_path(path) {
                     self.status_message = format!(
                         "保存成功：{}（LSP semanticTokens 失败: {}）",
                         path.display(),
@@ -1407,6 +2595,7 @@ impl Editor {
         let Some(path) = self
             .buffers
             .get(buffer_idx)
+            .filter(|buffer| !buffer.large_file)
             .and_then(|buffer| buffer.path.as_ref().cloned())
         else {
             return;
@@ -1428,7 +2617,8 @@ impl Editor {
             .get(self.tree_selected)
             .map(|entry| entry.path.clone());
 
-        self.tree_entries = super::collect_tree_entries(&self.root, &self.expanded_dirs);
+        self.tree_entries =
+            super::collect_tree_entries(&self.root, &self.expanded_dirs, &mut self.tree_cache);
 
         if self.tree_entries.is_empty() {
             self.tree_selected = 0;
@@ -1449,6 +2639,144 @@ impl Editor {
         self.tree_selected = min(self.tree_selected, self.tree_entries.len() - 1);
     }
 
+    /// 手动为当前文件的语言启动 LSP，并补发 didOpen。
+    ///
+    /// 不受 `lsp_auto_activate` 限制，供关闭了自动激活的语言按需使用。
+    fn activate_lsp_for_active_buffer(&mut self) {
+        if self.lsp_disabled {
+            self.status_message = "LSP 已关闭（:lsp on 重新开启）".to_string();
+            return;
+        }
+        let buffer_idx = self.tabs[self.active_tab].buffer_index;
+        let Some(buffer) = self.buffers.get(buffer_idx) else {
+            return;
+        };
+        let Some(language) = lsp::detect_language_from_path_or_name(buffer.path.as_deref(), "")
+        else {
+            self.status_message = "当前文件没有对应的 LSP 语言".to_string();
+            return;
+        };
+        if buffer.large_file {
+            self.status_message = "大文件模式下不启动 LSP".to_string();
+            return;
+        }
+        if self.lsp_client.is_language_running(language) {
+            self.status_message = format!("{} LSP 已在运行", language.display_name());
+            return;
+        }
+        if let Err(error) = self
+            .lsp_client
+            .ensure_started_for_language(&self.root, language)
+        {
+            self.status_message = format!("{} LSP 启动失败: {error}", language.display_name());
+            return;
+        }
+        self.try_send_did_open_for_buffer_idx(buffer_idx);
+        self.mark_lsp_project_loading(language);
+    }
+
+    /// 对光标处符号请求定义位置，结果返回后以 peek 浮层展示而不跳转。
+    fn request_peek_definition(&mut self) {
+        let buffer = self.active_buffer();
+        let Some(path) = buffer.path.clone() else {
+            self.status_message = "peek 定义仅支持已保存文件".to_string();
+            return;
+        };
+        let (cursor_row, cursor_col) = (buffer.cursor_row, buffer.cursor_col);
+
+        if !self.ensure_lsp_started_for_file(&path) {
+            return;
+        }
+        match self
+            .lsp_client
+            .request_definition(&path, cursor_row, cursor_col)
+        {
+            Ok(()) => self.status_message = "LSP definition 请求已发送".to_string(),
+            Err(error) => self.status_message = format!("LSP definition 请求失败: {error}"),
+        }
+    }
+
+    /// 用 `textDocument/definition` 的结果打开 peek 浮层。
+    ///
+    /// 目标文件已打开时直接取缓冲区内容（包含未保存的修改），
+    /// 否则只从磁盘读取定义附近的几行；返回多个位置时展示第一个。
+    pub(super) fn show_peek_definition(
+        &mut self,
+        source_path: &Path,
+        locations: Vec<lsp::LspLocation>,
+    ) {
+        if self.active_buffer().path.as_deref() != Some(source_path) {
+            // 请求发出后已切换到其他文件，浮层与当前上下文无关，不再弹出。
+            return;
+        }
+        let Some(location) = locations.first() else {
+            self.status_message = "LSP definition：未找到定义".to_string();
+            return;
+        };
+
+        let window = match self
+            .buffers
+            .iter()
+            .find(|buffer| buffer.path.as_deref() == Some(location.file_path.as_path()))
+        {
+            Some(buffer) => Ok(peek_window(
+                &buffer.lines,
+                location.line,
+                PEEK_CONTEXT_LINES,
+            )),
+            None => read_peek_window(&location.file_path, location.line, PEEK_CONTEXT_LINES),
+        };
+        let (start_line, lines) = match window {
+            Ok(window) => window,
+            Err(error) => {
+                self.status_message = format!(
+                    "peek 定义读取失败 {}: {error}",
+                    location.file_path.display()
+                );
+                return;
+            }
+        };
+
+        let file_label = location
+            .file_path
+            .strip_prefix(&self.root)
+            .unwrap_or(&location.file_path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut title = format!("{file_label}:{}", location.line + 1);
+        if locations.len() > 1 {
+            title.push_str(&format!("（共 {} 处）", locations.len()));
+        }
+        self.peek_view = Some(PeekView::new(title, start_line, location.line, lines));
+        self.mode = EditorMode::PeekView;
+        self.status_message = "peek 定义：j/k 滚动，Esc 关闭".to_string();
+    }
+
+    /// 打开当前文件语言的 LSP 能力面板。
+    ///
+    /// 展示的是客户端实际用于功能判断的能力（含运行中被降级的部分），
+    /// 用来解释“为什么某个功能不可用”，而不是服务端原始响应。
+    fn show_lsp_capabilities(&mut self) {
+        let buffer_idx = self.tabs[self.active_tab].buffer_index;
+        let path = self
+            .buffers
+            .get(buffer_idx)
+            .and_then(|buffer| buffer.path.clone());
+        let Some(language) = lsp::detect_language_from_path_or_name(path.as_deref(), "") else {
+            self.status_message = "当前文件没有对应的 LSP 语言".to_string();
+            return;
+        };
+        let Some(capabilities) = self.lsp_client.server_capabilities(language) else {
+            self.status_message = format!(
+                "{} LSP 未运行或尚未完成初始化，可先用 la 启动",
+                language.display_name()
+            );
+            return;
+        };
+        self.capabilities_view = Some((language, capabilities.describe()));
+        self.mode = EditorMode::CapabilitiesView;
+    }
+
     /// 执行 LSP 服务器可用性检查，并将结果汇总到状态栏。
     ///
     /// 结果展示策略：
@@ -1507,6 +2835,36 @@ mod tests {
     use super::super::types::{CompletionDisplayItem, EditorMode};
     use super::Editor;
 
+    #[test]
+    fn test_ctrl_v_inserts_next_key_literally() {
+        let mut editor = Editor::new(PathBuf::from("."));
+        editor.mode = EditorMode::Insert;
+        let ctrl_v = KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL);
+
+        editor.handle_insert_key_event(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        editor.handle_insert_key_event(ctrl_v);
+        editor.handle_insert_key_event(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        assert_eq!(
+            editor.mode,
+            EditorMode::Insert,
+            "字面输入的 k 不应触发 jk 退出"
+        );
+        assert_eq!(editor.active_buffer().lines[0], "jk");
+
+        editor.handle_insert_key_event(ctrl_v);
+        editor.handle_insert_key_event(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(
+            editor.active_buffer().lines[0],
+            "jk\t",
+            "字面输入的 Tab 不按缩进展开"
+        );
+
+        editor.handle_insert_key_event(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        editor.handle_insert_key_event(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        assert_eq!(editor.mode, EditorMode::Normal, "之后的按键恢复常规行为");
+        assert_eq!(editor.active_buffer().lines[0], "jk\t");
+    }
+
     #[test]
     fn test_insert_esc_closes_completion_before_leave_insert() {
         let mut editor = Editor::new(PathBuf::from("."));
@@ -1515,6 +2873,9 @@ mod tests {
             label: "alpha".to_string(),
             insert_text: "alpha".to_string(),
             detail: None,
+            label_detail: None,
+            label_description: None,
+            deprecated: false,
         }];
         editor.completion_selected = 3;
         editor.completion_scroll_offset = 2;
@@ -1536,11 +2897,16 @@ mod tests {
             label: "foo".to_string(),
             insert_text: Some("foo".to_string()),
             detail: None,
+            label_details: None,
+            deprecated: false,
         }];
         editor.completion_items = vec![CompletionDisplayItem {
             label: "foo".to_string(),
             insert_text: "foo".to_string(),
             detail: None,
+            label_detail: None,
+            label_description: None,
+            deprecated: false,
         }];
         editor.completion_selected = 0;
 
@@ -1556,4 +2922,270 @@ mod tests {
         editor.handle_insert_key_event(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
         assert!(!editor.suppress_completion_until_input);
     }
+
+    #[test]
+    fn test_visual_selection_range_covers_whole_lines_between_anchor_and_cursor() {
+        let mut editor = Editor::new(PathBuf::from("."));
+        editor.active_buffer_mut().lines = vec![
+            "fn main() {".to_string(),
+            "let  x=1;".to_string(),
+            "println!(\"{x}\");".to_string(),
+            "}".to_string(),
+        ];
+        editor.active_buffer_mut().cursor_row = 2;
+
+        assert_eq!(
+            editor.visual_selection_range(),
+            None,
+            "非 VISUAL 模式不应有选区"
+        );
+
+        editor.handle_normal_key_event(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::NONE));
+        assert_eq!(editor.mode, EditorMode::Visual);
+        editor.handle_visual_key_event(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        assert_eq!(editor.visual_selection_range(), Some(((1, 0), (2, 16))));
+
+        // 向锚点另一侧移动时，选区应以锚点为界翻转。
+        editor.active_buffer_mut().cursor_row = 3;
+        assert_eq!(editor.visual_selection_range(), Some(((2, 0), (3, 1))));
+
+        editor.handle_visual_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(editor.mode, EditorMode::Normal);
+        assert_eq!(editor.visual_anchor_row, None);
+    }
+
+    #[test]
+    fn test_reopen_closed_tab_restores_cursor() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let path =
+            std::env::temp_dir().join(format!("order-reopen-{}-{nanos}.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\nthree\n").expect("写入测试文件失败");
+
+        let mut editor = Editor::new(PathBuf::from("."));
+        editor.new_tab();
+        editor.open_file_in_current_tab(path.clone());
+        editor.active_buffer_mut().cursor_row = 2;
+        editor.active_buffer_mut().cursor_col = 3;
+        editor.close_tab();
+        assert_eq!(editor.tabs.len(), 1);
+
+        editor.reopen_closed_tab();
+        assert_eq!(editor.tabs.len(), 2, "应在新 TAB 中重新打开");
+        assert_eq!(editor.active_buffer().path.as_ref(), Some(&path));
+        assert_eq!(
+            (
+                editor.active_buffer().cursor_row,
+                editor.active_buffer().cursor_col
+            ),
+            (2, 3),
+            "应恢复关闭时的光标位置"
+        );
+
+        editor.reopen_closed_tab();
+        assert_eq!(editor.tabs.len(), 2, "栈已空时不应新建 TAB");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_max_tabs_evicts_least_recently_focused_unmodified_tab() {
+        let mut editor = Editor::new(PathBuf::from("."));
+        editor.config.max_tabs = Some(2);
+        editor.active_buffer_mut().modified = true;
+        editor.new_tab();
+        editor.new_tab();
+        assert_eq!(editor.tabs.len(), 2, "超过上限时应淘汰一个 TAB");
+        assert!(
+            editor.buffers[editor.tabs[0].buffer_index].modified,
+            "有未保存修改的 TAB 不应被淘汰"
+        );
+        assert_eq!(editor.active_tab, 1, "新建的 TAB 保持激活");
+
+        editor.active_buffer_mut().modified = true;
+        editor.new_tab();
+        assert_eq!(editor.tabs.len(), 3, "其余 TAB 都有修改时暂时超出上限");
+        assert!(editor.status_message.contains("未保存修改"));
+        assert!(
+            editor
+                .tabs
+                .iter()
+                .all(|tab| tab.buffer_index < editor.buffers.len())
+        );
+    }
+
+    #[test]
+    fn test_cycle_buffer_switches_active_tab_buffer() {
+        let mut editor = Editor::new(PathBuf::from("."));
+        editor.new_tab();
+        editor.new_tab();
+        assert_eq!(editor.buffers.len(), 3);
+        editor.active_buffer_mut().modified = true;
+        let modified_idx = editor.tabs[editor.active_tab].buffer_index;
+
+        editor.execute_editor_command(super::super::command_line::EditorCommand::CycleBuffer(true));
+        let next_idx = editor.tabs[editor.active_tab].buffer_index;
+        assert_eq!(next_idx, (modified_idx + 1) % 3, "末尾之后回到第一个缓冲区");
+        assert_eq!(editor.buffers.len(), 3, "切换不关闭缓冲区");
+        assert!(editor.buffers[modified_idx].modified, "未保存修改保持不变");
+
+        editor.handle_normal_key_event(KeyEvent::new(KeyCode::Char('['), KeyModifiers::NONE));
+        editor.handle_normal_key_event(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
+        assert_eq!(editor.tabs[editor.active_tab].buffer_index, modified_idx);
+
+        editor.buffers.truncate(1);
+        editor.tabs.truncate(1);
+        editor.active_tab = 0;
+        editor.tabs[0].buffer_index = 0;
+        editor.execute_editor_command(super::super::command_line::EditorCommand::CycleBuffer(
+            false,
+        ));
+        assert_eq!(editor.status_message, "只有一个缓冲区");
+    }
+
+    #[test]
+    fn test_cached_diagnostics_are_stale_until_next_publish() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root = std::env::temp_dir().join(format!(
+            "order-diagnostics-stale-{}-{nanos}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(root.join(".order")).expect("创建测试目录失败");
+        std::fs::write(
+            root.join(".order/editor.json"),
+            r#"{ "persist_diagnostics": true }"#,
+        )
+        .expect("写入测试配置失败");
+        let file = root.join("main.rs");
+        let diagnostic = |message: &str| lsp::DiagnosticItem {
+            file_path: file.clone(),
+            line: 1,
+            column: 1,
+            severity: lsp::DiagnosticSeverity::Error,
+            message: message.to_string(),
+            lsp_start_line: 0,
+            lsp_start_character: 0,
+            lsp_end_line: 0,
+            lsp_end_character: 1,
+            source: None,
+            code: None,
+            related: Vec::new(),
+        };
+        let mut cached = std::collections::HashMap::new();
+        cached.insert(file.clone(), vec![diagnostic("old error")]);
+        let removed = root.join("removed.rs");
+        cached.insert(
+            removed.clone(),
+            vec![lsp::DiagnosticItem {
+                file_path: removed.clone(),
+                ..diagnostic("gone")
+            }],
+        );
+        super::super::diagnostics_cache::save_diagnostics_cache(&root, &cached)
+            .expect("写入诊断缓存失败");
+
+        let mut editor = Editor::new(root.clone());
+        assert!(editor.stale_diagnostic_files.contains(&file));
+        assert!(
+            editor
+                .diagnostics
+                .contains(&"[缓存] main.rs:1:1 [error] old error".to_string())
+        );
+
+        editor.apply_lsp_diagnostics(file.clone(), vec![diagnostic("new error")]);
+        assert!(
+            !editor.stale_diagnostic_files.contains(&file),
+            "新的发布后不再标记缓存"
+        );
+        assert!(
+            editor
+                .diagnostics
+                .contains(&"main.rs:1:1 [error] new error".to_string())
+        );
+
+        let persisted = editor.diagnostics_to_persist();
+        assert_eq!(
+            persisted.keys().collect::<Vec<_>>(),
+            vec![&file],
+            "一直没有重新发布的缓存条目不应写回"
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_lsp_off_suppresses_auto_activation() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root =
+            std::env::temp_dir().join(format!("order-lsp-off-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("创建测试目录失败");
+        // 项目标识文件存在时，未关闭的 LSP 会尝试自动启动 rust-analyzer。
+        std::fs::write(root.join("Cargo.toml"), "[package]\n").expect("写入测试文件失败");
+        std::fs::write(root.join("main.rs"), "fn main() {}\n").expect("写入测试文件失败");
+
+        let mut editor = Editor::new(root.clone());
+        let main = root.join("main.rs");
+        editor.apply_lsp_diagnostics(
+            main.clone(),
+            vec![lsp::DiagnosticItem {
+                file_path: main.clone(),
+                line: 1,
+                column: 1,
+                severity: lsp::DiagnosticSeverity::Error,
+                message: "mismatched types".to_string(),
+                lsp_start_line: 0,
+                lsp_start_character: 0,
+                lsp_end_line: 0,
+                lsp_end_character: 1,
+                source: None,
+                code: None,
+                related: vec![lsp::DiagnosticRelated {
+                    location: lsp::LspLocation {
+                        file_path: root.join("lib.rs"),
+                        line: 3,
+                        character: 0,
+                    },
+                    message: "expected due to this".to_string(),
+                }],
+            }],
+        );
+        assert_eq!(editor.diagnostic_related.len(), 1);
+        editor.set_lsp_enabled(false);
+        assert!(editor.diagnostics.is_empty());
+        assert!(
+            editor.diagnostic_related.is_empty() && editor.related_cursor.is_none(),
+            "关闭后不应保留已移除诊断的关联位置"
+        );
+        editor.jump_related_location(true);
+        assert_eq!(editor.status_message, "当前诊断没有关联位置");
+        editor.open_file_in_current_tab(main);
+        editor.auto_activate_lsp();
+
+        assert!(editor.lsp_disabled);
+        assert!(
+            !editor.lsp_client.is_running(),
+            "关闭期间不应启动任何语言服务"
+        );
+        assert_eq!(
+            editor.rust_analyzer_status, "rust-analyzer: 已关闭",
+            "关闭期间自动激活不应改写状态"
+        );
+
+        editor.request_completion_for_active_buffer();
+        assert!(
+            !editor.lsp_client.is_running(),
+            "按需请求同样不应拉起语言服务"
+        );
+        assert!(editor.status_message.contains(":lsp on"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
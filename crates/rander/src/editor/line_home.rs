@@ -0,0 +1,93 @@
+use super::{config::HomeKeyBehavior, types::EditorBuffer, utils::char_count};
+
+impl EditorBuffer {
+    /// 当前行第一个非空白字符的列（按字符计），整行都是空白时返回行尾。
+    pub(super) fn first_non_whitespace_col(&self) -> usize {
+        let line = &self.lines[self.cursor_row];
+        line.chars()
+            .position(|ch| !ch.is_whitespace())
+            .unwrap_or_else(|| char_count(line))
+    }
+
+    /// 按 Home 键行为移动到行首。
+    ///
+    /// `smart` 先跳到首个非空白字符，已经在那里时再回到第 0 列，
+    /// 缩进较深的代码里一次按键就能落在代码开头，再按一次仍可回到真正的行首；
+    /// `classic` 始终回到第 0 列。
+    pub(super) fn move_home(&mut self, behavior: HomeKeyBehavior) {
+        self.ensure_cursor_in_bounds();
+        let first = self.first_non_whitespace_col();
+        self.cursor_col = match behavior {
+            HomeKeyBehavior::Smart if self.cursor_col != first => first,
+            _ => 0,
+        };
+    }
+
+    /// 按 `^` 的行为移动：`smart` 下与 Home 相同地来回切换，`classic` 下与 Vim 一致固定跳到首个非空白字符。
+    pub(super) fn move_caret(&mut self, behavior: HomeKeyBehavior) {
+        match behavior {
+            HomeKeyBehavior::Smart => self.move_home(behavior),
+            HomeKeyBehavior::Classic => {
+                self.ensure_cursor_in_bounds();
+                self.cursor_col = self.first_non_whitespace_col();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditorBuffer, HomeKeyBehavior};
+
+    fn buffer_with(lines: &[&str]) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new_empty("test".to_string());
+        buffer.lines = lines.iter().map(ToString::to_string).collect();
+        buffer
+    }
+
+    #[test]
+    fn test_smart_home_toggles_between_indent_and_column_zero() {
+        let mut buffer = buffer_with(&["    let x = 1;", "\t\t全角", "no_indent", "   "]);
+
+        buffer.cursor_col = 10;
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 4, "第一次应跳到首个非空白字符");
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 0, "已在首个非空白字符时回到第 0 列");
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 4, "再次按下继续来回切换");
+
+        buffer.cursor_row = 1;
+        buffer.cursor_col = 3;
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 2, "按字符而非字节计算缩进");
+
+        buffer.cursor_row = 2;
+        buffer.cursor_col = 5;
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 0, "无缩进的行直接到行首");
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 0);
+
+        buffer.cursor_row = 3;
+        buffer.cursor_col = 0;
+        buffer.move_home(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 3, "全空白行的首个非空白位置视为行尾");
+    }
+
+    #[test]
+    fn test_classic_home_and_caret() {
+        let mut buffer = buffer_with(&["    let x = 1;"]);
+        buffer.cursor_col = 10;
+        buffer.move_home(HomeKeyBehavior::Classic);
+        assert_eq!(buffer.cursor_col, 0, "classic 的 Home 总是回到第 0 列");
+
+        buffer.move_caret(HomeKeyBehavior::Classic);
+        assert_eq!(buffer.cursor_col, 4);
+        buffer.move_caret(HomeKeyBehavior::Classic);
+        assert_eq!(buffer.cursor_col, 4, "classic 的 ^ 不切换");
+
+        buffer.move_caret(HomeKeyBehavior::Smart);
+        assert_eq!(buffer.cursor_col, 0, "smart 的 ^ 与 Home 一样切换");
+    }
+}
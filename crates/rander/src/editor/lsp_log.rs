@@ -0,0 +1,185 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+
+use core::encoding::append_utf8_json_line;
+use lsp::LspEvent;
+use serde_json::json;
+
+/// LSP 事件镜像日志：把每个事件格式化为一行 JSON，交给后台线程追加到文件。
+///
+/// 事件在主循环里产生，写盘放到独立线程并通过通道排队，
+/// 磁盘慢或文件在网络盘上时也不会拖慢按键响应。
+#[derive(Debug)]
+pub(super) struct LspEventLog {
+    path: PathBuf,
+    sender: Sender<String>,
+    worker: JoinHandle<()>,
+}
+
+impl LspEventLog {
+    /// 开始镜像到 `path`（追加写入）；文件无法创建时立即报错，而不是等到后台静默失败。
+    pub(super) fn start(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+
+        let (sender, receiver) = mpsc::channel::<String>();
+        let worker_path = path.clone();
+        let worker = thread::spawn(move || {
+            // 单行写入失败（例如内容含 U+FFFD 被拒绝）只跳过该行，不终止整个日志。
+            for line in receiver {
+                let _ = append_utf8_json_line(&worker_path, &line);
+            }
+        });
+        Ok(Self {
+            path,
+            sender,
+            worker,
+        })
+    }
+
+    // 当前日志文件路径。
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 记录一个事件；只做格式化与入队，不在调用线程上写盘。
+    pub(super) fn record(&self, event: &LspEvent) {
+        let line = lsp_event_log_line(event, &chrono::Local::now().to_rfc3339());
+        let _ = self.sender.send(line);
+    }
+
+    /// 停止镜像：关闭通道后后台线程写完剩余行自行退出。
+    ///
+    /// 返回后台线程句柄，编辑器直接丢弃即可，测试可以等待它写完再检查文件。
+    pub(super) fn stop(self) -> JoinHandle<()> {
+        drop(self.sender);
+        self.worker
+    }
+}
+
+/// 把事件格式化为一行 JSON：`ts`、`kind`、`file` 便于 grep 过滤，`detail` 保留完整内容。
+pub(super) fn lsp_event_log_line(event: &LspEvent, ts: &str) -> String {
+    let (kind, file) = lsp_event_kind(event);
+    json!({
+        "ts": ts,
+        "kind": kind,
+        "file": file.map(|path| path.display().to_string()),
+        "detail": format!("{event:?}"),
+    })
+    .to_string()
+}
+
+// 事件类型名（与 LSP 方法名对齐）及关联文件。
+fn lsp_event_kind(event: &LspEvent) -> (&'static str, Option<&Path>) {
+    match event {
+        LspEvent::Status(_) => ("status", None),
+        LspEvent::PublishDiagnostics { file_path, .. } => ("publishDiagnostics", Some(file_path)),
+        LspEvent::WillSaveWaitUntilEdits { file_path, .. } => {
+            ("willSaveWaitUntil", Some(file_path))
+        }
+        LspEvent::CompletionItems { file_path, .. } => ("completion", Some(file_path)),
+        LspEvent::SemanticTokens { file_path, .. } => ("semanticTokens", Some(file_path)),
+        LspEvent::SemanticTokensRange { file_path, .. } => {
+            ("semanticTokens/range", Some(file_path))
+        }
+        LspEvent::FormattingEdits { file_path, .. } => ("formatting", Some(file_path)),
+        LspEvent::RenameWorkspaceEdit { file_path, .. } => ("rename", Some(file_path)),
+        LspEvent::DefinitionLocations { file_path, .. } => ("definition", Some(file_path)),
+        LspEvent::CallHierarchyPrepared { file_path, .. } => {
+            ("prepareCallHierarchy", Some(file_path))
+        }
+        LspEvent::CallHierarchyCalls {
+            direction, item, ..
+        } => (direction.method(), Some(item.file_path.as_path())),
+        LspEvent::CodeActions { file_path, .. } => ("codeAction", Some(file_path)),
+        LspEvent::WorkspaceApplyEditRequest { .. } => ("workspace/applyEdit", None),
+        LspEvent::RustAnalyzerStatus { .. } => ("rustAnalyzerStatus", None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use lsp::LspEvent;
+
+    use super::{LspEventLog, lsp_event_log_line};
+
+    #[test]
+    fn test_lsp_event_log_line_is_single_json_line() {
+        let line = lsp_event_log_line(
+            &LspEvent::FormattingEdits {
+                file_path: PathBuf::from("src/main.rs"),
+                edits: Vec::new(),
+            },
+            "2026-01-01T00:00:00+08:00",
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).expect("日志行应为合法 JSON");
+        assert_eq!(value["kind"], "formatting");
+        assert_eq!(value["file"], "src/main.rs");
+        assert_eq!(value["ts"], "2026-01-01T00:00:00+08:00");
+
+        let line = lsp_event_log_line(
+            &LspEvent::Status("第一行\n第二行".to_string()),
+            "2026-01-01T00:00:00+08:00",
+        );
+        assert!(!line.contains('\n'), "多行内容应被转义为单行");
+        let value: serde_json::Value = serde_json::from_str(&line).expect("日志行应为合法 JSON");
+        assert_eq!(value["kind"], "status");
+        assert!(value["file"].is_null());
+    }
+
+    #[test]
+    fn test_lsp_event_log_start_record_stop() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!("order-lsplog-{}-{nanos}", std::process::id()));
+        let path = dir.join("nested").join("lsp.log");
+
+        let log = LspEventLog::start(path.clone()).expect("启动日志失败");
+        assert_eq!(log.path(), path.as_path());
+        log.record(&LspEvent::Status("启动".to_string()));
+        log.record(&LspEvent::PublishDiagnostics {
+            file_path: PathBuf::from("lib.rs"),
+            items: Vec::new(),
+        });
+        log.stop().join().expect("后台线程异常退出");
+
+        let content = fs::read_to_string(&path).expect("读取日志失败");
+        let kinds = content
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("非法日志行"))
+            .map(|value| value["kind"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec!["status", "publishDiagnostics"],
+            "停止前的事件应全部落盘"
+        );
+
+        assert!(
+            LspEventLog::start(dir.clone()).is_err(),
+            "目标是目录时应立即报错"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
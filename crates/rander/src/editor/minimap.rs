@@ -0,0 +1,143 @@
+use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+/// 小地图上的诊断标记，错误优先于警告。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum MinimapMarker {
+    Warning,
+    Error,
+}
+
+/// 小地图单行的展示内容。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct MinimapCell {
+    /// 该行对应的文件区段与当前可见区域重叠。
+    pub(super) in_viewport: bool,
+    /// 光标所在行落在该行。
+    pub(super) cursor: bool,
+    /// 该区段内最严重的诊断。
+    pub(super) marker: Option<MinimapMarker>,
+}
+
+/// 把 0 起始的文件行号按比例缩放到小地图的行。
+///
+/// 文件短于小地图高度时不拉伸，逐行对应，避免短文件的标记分散在整列上。
+pub(super) fn minimap_row(line: usize, total_lines: usize, height: usize) -> usize {
+    if height == 0 {
+        return 0;
+    }
+    let total_lines = total_lines.max(1);
+    if total_lines <= height {
+        return line.min(height - 1);
+    }
+    (line.saturating_mul(height) / total_lines).min(height - 1)
+}
+
+/// 计算小地图每一行的内容。
+///
+/// `viewport` 为 `(首个可见行, 可见行数)`；`markers` 中的行号为 0 起始。
+pub(super) fn minimap_cells(
+    total_lines: usize,
+    height: usize,
+    viewport: (usize, usize),
+    cursor_row: usize,
+    markers: &[(usize, MinimapMarker)],
+) -> Vec<MinimapCell> {
+    let mut cells = vec![MinimapCell::default(); height];
+    if height == 0 {
+        return cells;
+    }
+
+    let (scroll_row, visible) = viewport;
+    let last_visible = scroll_row
+        .saturating_add(visible.max(1) - 1)
+        .min(total_lines.saturating_sub(1));
+    let first = minimap_row(scroll_row, total_lines, height);
+    let last = minimap_row(last_visible, total_lines, height).max(first);
+    for cell in &mut cells[first..=last] {
+        cell.in_viewport = true;
+    }
+
+    cells[minimap_row(cursor_row, total_lines, height)].cursor = true;
+
+    for &(line, marker) in markers {
+        let cell = &mut cells[minimap_row(line, total_lines, height)];
+        cell.marker = cell.marker.max(Some(marker));
+    }
+    cells
+}
+
+/// 从诊断中提取小地图标记；信息与提示级别不占用小地图。
+pub(super) fn diagnostic_markers(items: &[DiagnosticItem]) -> Vec<(usize, MinimapMarker)> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let marker = match item.severity {
+                DiagnosticSeverity::Error => MinimapMarker::Error,
+                DiagnosticSeverity::Warning => MinimapMarker::Warning,
+                DiagnosticSeverity::Information | DiagnosticSeverity::Hint => return None,
+            };
+            // 诊断行号从 1 开始。
+            Some((item.line.saturating_sub(1) as usize, marker))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MinimapCell, MinimapMarker, minimap_cells, minimap_row};
+
+    #[test]
+    fn test_minimap_row_scales_to_height() {
+        assert_eq!(minimap_row(0, 1000, 10), 0);
+        assert_eq!(minimap_row(99, 1000, 10), 0);
+        assert_eq!(minimap_row(100, 1000, 10), 1);
+        assert_eq!(minimap_row(999, 1000, 10), 9);
+        assert_eq!(minimap_row(5000, 1000, 10), 9, "越界行号应落在最后一行");
+        assert_eq!(minimap_row(3, 5, 10), 3, "短文件逐行对应，不拉伸");
+        assert_eq!(minimap_row(0, 0, 10), 0);
+        assert_eq!(minimap_row(7, 100, 0), 0);
+    }
+
+    #[test]
+    fn test_minimap_cells_place_viewport_cursor_and_markers() {
+        let markers = [
+            (10, MinimapMarker::Warning),
+            (15, MinimapMarker::Error),
+            (950, MinimapMarker::Warning),
+        ];
+        let cells = minimap_cells(1000, 10, (500, 200), 620, &markers);
+
+        let viewport = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.in_viewport)
+            .map(|(row, _)| row)
+            .collect::<Vec<_>>();
+        assert_eq!(viewport, vec![5, 6]);
+        assert!(cells[6].cursor);
+        assert_eq!(
+            cells.iter().filter(|cell| cell.cursor).count(),
+            1,
+            "光标只占一行"
+        );
+        assert_eq!(
+            cells[0].marker,
+            Some(MinimapMarker::Error),
+            "同一行同时有警告与错误时显示错误"
+        );
+        assert_eq!(cells[9].marker, Some(MinimapMarker::Warning));
+        assert_eq!(cells[3], MinimapCell::default());
+    }
+
+    #[test]
+    fn test_minimap_cells_short_file_keeps_viewport_visible() {
+        let cells = minimap_cells(3, 10, (0, 20), 2, &[(1, MinimapMarker::Error)]);
+        let viewport = cells.iter().filter(|cell| cell.in_viewport).count();
+        assert_eq!(viewport, 3, "可见区域只覆盖文件实际占用的行");
+        assert!(cells[2].cursor);
+        assert_eq!(cells[1].marker, Some(MinimapMarker::Error));
+
+        let empty = minimap_cells(0, 4, (0, 4), 0, &[]);
+        assert!(empty[0].in_viewport && empty[0].cursor);
+    }
+}
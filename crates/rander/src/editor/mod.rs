@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     fs,
     path::Path,
     path::PathBuf,
@@ -8,42 +8,123 @@ use std::{
 
 use crossterm::event::{self, Event, KeyEventKind};
 use lsp::{
-    DiagnosticItem, LspClient, LspCodeAction, LspEvent, LspSemanticToken, LspTextEdit,
-    LspWorkspaceEdit, detect_language_from_path_or_name,
+    DiagnosticItem, DiagnosticRelated, DiagnosticSeverity, LspClient, LspCodeAction, LspEvent,
+    LspLanguage, LspSemanticToken, LspTextEdit, LspWorkspaceEdit,
+    detect_language_from_path_or_name,
 };
 use ratatui::DefaultTerminal;
 
+// INSERT 模式括号与引号的自动配对。
+mod auto_pairs;
+// LSP 调用层级浮层的树状态。
+mod call_hierarchy;
+// editor 到主对话界面的内容桥接。
+mod chat_bridge;
+// `.order/editor.json` 配置读取。
+mod config;
+// `:` 命令行的命令解析。
+mod command_line;
+// 自动补全请求的防抖。
+mod completion_debounce;
+// 复制当前文件路径与位置。
+mod copy_path;
+// 诊断列表的级别排序与按组跳转。
+mod diagnostic_groups;
+// `.order/diagnostics_cache.json` 诊断缓存的保存与恢复。
+mod diagnostics_cache;
+// `:diagexport` 把诊断导出为 JSON / SARIF。
+mod diagnostics_export;
+// `.editorconfig` 解析与按文件格式化设置。
+mod editorconfig;
+// 外部格式化命令回退。
+mod formatter;
 // 输入事件与按键命令处理。
 mod handlers;
+// `:lsplog` 的 LSP 事件日志镜像。
+mod lsp_log;
+// Home / `^` 的行首移动。
+mod line_home;
+// 编辑窗格右侧的小地图滚动条。
+mod minimap;
+// 定义 peek 浮层的内容截取。
+mod peek;
+// 当前行 quick fix 的诊断选择。
+mod quick_fix;
+// `:r` 读入文件并插入到光标之后。
+mod read_file;
+// 诊断 `relatedInformation` 关联位置的循环跳转。
+mod related_info;
 // 编辑器界面渲染。
 mod render;
+// 按可见区域请求语义 token 的范围计算与缓存合并。
+mod semantic_range;
 // 会话保存与恢复。
 mod session;
+// `.order/snippets` 用户片段的读取、解析与 tab stop 跳转。
+mod snippets;
+// 按窗格宽度的软换行显示与显示行移动。
+mod soft_wrap;
+// 标签页的固定、移动与已关闭文件栈。
+mod tabs;
+// `.order/theme.json` 语义高亮样式覆盖。
+mod theme;
 // 目录树数据构建。
 mod tree;
+// 打字机滚动：让光标行停在窗格中央。
+mod typewriter;
 // 编辑器核心类型定义。
 mod types;
 // 公共工具函数。
 mod utils;
+// `willSaveWaitUntil` 编辑的预览与确认决策。
+mod will_save;
+// `WorkspaceEdit` 的逐文件应用结果。
+mod workspace_edit;
+
+pub use self::chat_bridge::{ACTIVE_FILE_CONTEXT_BUDGET_CHARS, ActiveFileContext, ChatHandoff};
 
 use self::{
-    tree::collect_tree_entries,
+    call_hierarchy::CallHierarchyView,
+    completion_debounce::CompletionDebounce,
+    config::EditorConfig,
+    diagnostic_groups::{DedupedDiagnostic, compare_diagnostics, dedup_diagnostics},
+    diagnostics_cache::{load_diagnostics_cache, save_diagnostics_cache},
+    editorconfig::{FormatSettings, resolve_format_settings},
+    formatter::FormattersConfig,
+    lsp_log::LspEventLog,
+    peek::PeekView,
+    related_info::RelatedCursor,
+    semantic_range::{
+        SEMANTIC_RANGE_MARGIN, merge_ranged_tokens, range_covers, visible_token_range,
+    },
+    snippets::SnippetSession,
+    tabs::ClosedBufferStack,
+    theme::SemanticTokenStyles,
+    tree::{TreeCache, collect_tree_entries},
     types::{
         CompletionDisplayItem, EditorBuffer, EditorMode, MainFocus, PaneFocus, SplitDirection,
         TabState, ThemeName, TreeEntry,
     },
+    will_save::{
+        PendingWillSaveEdits, WillSaveChoice, WillSaveDecision, decide_will_save_action,
+        summarize_text_edits,
+    },
+    workspace_edit::WorkspaceEditApplySummary,
 };
 
 const SESSION_FILE: &str = ".order_editor.session";
 const MIN_TREE_RATIO: u16 = 15;
 const MAX_TREE_RATIO: u16 = 70;
-const MAX_TREE_ENTRIES: usize = 1500;
+/// 单个目录最多展示的子项数量，超出部分折叠为“+N more”节点。
+const MAX_DIR_CHILDREN: usize = 500;
 
 // 编辑器主状态对象。
 pub struct Editor {
     root: PathBuf,
     tree_entries: Vec<TreeEntry>,
     expanded_dirs: BTreeSet<PathBuf>,
+    /// 已展开目录的子项缓存，避免每次刷新都重新遍历磁盘。
+    tree_cache: TreeCache,
     tree_selected: usize,
     tree_scroll: usize,
     tree_ratio: u16,
@@ -52,19 +133,35 @@ pub struct Editor {
     dragging_divider: bool,
     last_area: Option<ratatui::layout::Rect>,
     last_editor_inner_area: Option<ratatui::layout::Rect>,
+    /// 最近一次按可见区域请求语义 token 的 `(文件, 起始行, 结束行)`。
+    ///
+    /// 视口仍在该范围内时不重复请求；文本变化后清空，以便按新内容重新请求。
+    semantic_range_requested: Option<(PathBuf, usize, usize)>,
     mode: EditorMode,
     normal_pending: String,
+    /// VISUAL 模式的选区锚点行，进入 VISUAL 时记录、退出时清空。
+    ///
+    /// 选区按整行计算：当前仅用于范围格式化，整行选区与格式化的粒度一致。
+    visual_anchor_row: Option<usize>,
     /// `RenameInput` 模式下的临时输入缓冲。
     ///
     /// 独立存储输入内容可以避免污染 NORMAL 命令串，
     /// 同时为后续扩展“更多带参数的 LSP 命令”预留统一入口。
     rename_input: String,
+    /// `CommandLine` 模式下 `:` 之后输入的内容。
+    command_input: String,
     insert_j_pending: bool,
+    /// INSERT 模式下按过 `Ctrl+V`：下一个按键按字面插入，不触发自动配对、补全与 `jk` 退出。
+    literal_next: bool,
+    /// INSERT 模式下正在展开的片段；Tab 依次跳转其 tab stop，离开 INSERT 时结束。
+    snippet_session: Option<SnippetSession>,
     terminal_escape_pending: bool,
     buffers: Vec<EditorBuffer>,
     tabs: Vec<TabState>,
     active_tab: usize,
     show_tagbar: bool,
+    /// 是否启用打字机滚动（光标行保持在窗格中央），初始值来自配置，`tw` 切换。
+    typewriter_scrolling: bool,
     completion_items: Vec<CompletionDisplayItem>,
     completion_selected: usize,
     completion_scroll_offset: usize,
@@ -73,16 +170,30 @@ pub struct Editor {
     /// 当用户确认补全后，异步 LSP 响应可能会在短时间内返回旧候选。
     /// 该开关用于在“下一次真实输入”前屏蔽这类回流，避免弹窗立即二次打开。
     suppress_completion_until_input: bool,
+    /// 等待输入停顿后再发出的补全请求（`completion_debounce_ms`）。
+    completion_debounce: CompletionDebounce,
     theme: ThemeName,
     diagnostics: Vec<String>,
+    /// 与 `diagnostics` 一一对应的级别，用于按级别分组跳转。
+    diagnostic_severities: Vec<DiagnosticSeverity>,
+    /// 与 `diagnostics` 一一对应的关联位置，供 `]r` / `[r` 跳转。
+    diagnostic_related: Vec<Vec<DiagnosticRelated>>,
+    /// `]r` / `[r` 当前停留的关联位置；诊断刷新后重置。
+    related_cursor: Option<RelatedCursor>,
     diagnostic_index: usize,
     /// 最近一次由 LSP 发布的诊断，按文件路径分组缓存。
     ///
     /// quick fix 请求需要把诊断上下文回传给服务端，
     /// 因此不能只保留渲染后的字符串列表。
     lsp_diagnostics_by_file: HashMap<PathBuf, Vec<DiagnosticItem>>,
+    /// 诊断仍来自上次会话缓存的文件；收到该文件新的发布后移除。
+    stale_diagnostic_files: HashSet<PathBuf>,
     status_message: String,
     command_history: Vec<String>,
+    /// 内部寄存器：最近一次复制的文本。
+    ///
+    /// 终端不支持 OSC 52 时仍可在 INSERT 模式用 `Ctrl+R` 粘贴回来。
+    copy_register: Option<String>,
     /// 多语言 LSP 客户端。
     ///
     /// 负责 Rust/Python/TS/JS/HTML/CSS/Vue/Java/Go/C/C++ 的语义高亮、补全与诊断。
@@ -99,6 +210,34 @@ pub struct Editor {
     lsp_loading_status: String,
     should_exit: bool,
     last_tick: Instant,
+    /// 启动时从 `.order/editor.json` 读取的编辑器配置。
+    config: EditorConfig,
+    /// 启动时从 `.order/formatters.json` 读取的按语言外部格式化命令。
+    formatters: FormattersConfig,
+    /// 启动时从 `.order/theme.json` 解析的语义 token 样式覆盖。
+    semantic_token_styles: SemanticTokenStyles,
+    /// 退出 editor 时需要交给主对话界面的预填充内容。
+    chat_handoff: Option<ChatHandoff>,
+    /// 等待用户确认的 `willSaveWaitUntil` 编辑。
+    pending_will_save: Option<PendingWillSaveEdits>,
+    /// 本次会话中按语言记住的 `willSaveWaitUntil` 处理方式。
+    will_save_choices: HashMap<lsp::LspLanguage, WillSaveChoice>,
+    /// `li` 打开的能力面板：`(语言, [(能力名, 取值)])`。
+    capabilities_view: Option<(lsp::LspLanguage, Vec<(&'static str, String)>)>,
+    /// `gp` 打开的定义 peek 浮层。
+    peek_view: Option<PeekView>,
+    /// `ch` 打开的调用层级浮层。
+    call_hierarchy_view: Option<CallHierarchyView>,
+    /// `:lsplog <path>` 开启的事件镜像，`None` 表示未开启。
+    lsp_event_log: Option<LspEventLog>,
+    /// 等待再次执行 `:r` 确认插入的大文件路径。
+    pending_read_confirm: Option<PathBuf>,
+    /// `tc` 关闭的文件记录，供 `tu` 重新打开。
+    closed_buffers: ClosedBufferStack,
+    /// `:lsp off` 后为 `true`：本次会话不再启动任何语言服务，高亮回退到 syntect。
+    lsp_disabled: bool,
+    /// 因空闲被回收的语言：自动激活跳过它们，直到再次打开该语言的文件。
+    lsp_idle_stopped: HashSet<LspLanguage>,
 }
 
 impl Default for Editor {
@@ -113,13 +252,34 @@ impl Editor {
     pub fn new(root: PathBuf) -> Self {
         let buffer = EditorBuffer::new_empty("untitled-1".to_string());
         let expanded_dirs = BTreeSet::new();
-        let lsp_client = LspClient::new();
-        let lsp_start_message = "LSP: 按需启动语言服务".to_string();
+        let mut tree_cache = TreeCache::default();
+        let mut lsp_client = LspClient::new();
+        // 配置文件损坏时仍以默认配置启动，并把错误展示到状态栏，避免编辑器无法进入。
+        let (config, lsp_start_message) = match EditorConfig::load(&root) {
+            Ok(config) => (config, "LSP: 按需启动语言服务".to_string()),
+            Err(error) => (EditorConfig::default(), error),
+        };
+        let (formatters, lsp_start_message) = match FormattersConfig::load(&root) {
+            Ok(formatters) => (formatters, lsp_start_message),
+            Err(error) => (FormattersConfig::default(), error),
+        };
+        let (semantic_token_styles, lsp_start_message) = match SemanticTokenStyles::load(&root) {
+            Ok(styles) => (styles, lsp_start_message),
+            Err(error) => (SemanticTokenStyles::default(), error),
+        };
 
-        Self {
+        tree_cache.set_sort(config.tree_sort);
+        for &language in lsp::all_languages() {
+            if let Some(settings) = config.lsp_settings.get(language.language_id()) {
+                lsp_client.set_language_settings(language, settings.clone());
+            }
+        }
+
+        let mut editor = Self {
             root: root.clone(),
-            tree_entries: collect_tree_entries(&root, &expanded_dirs),
+            tree_entries: collect_tree_entries(&root, &expanded_dirs, &mut tree_cache),
             expanded_dirs,
+            tree_cache,
             tree_selected: 0,
             tree_scroll: 0,
             tree_ratio: 30,
@@ -128,10 +288,15 @@ impl Editor {
             dragging_divider: false,
             last_area: None,
             last_editor_inner_area: None,
+            semantic_range_requested: None,
             mode: EditorMode::Normal,
             normal_pending: String::new(),
+            visual_anchor_row: None,
             rename_input: String::new(),
+            command_input: String::new(),
             insert_j_pending: false,
+            literal_next: false,
+            snippet_session: None,
             terminal_escape_pending: false,
             buffers: vec![buffer],
             tabs: vec![TabState {
@@ -139,29 +304,92 @@ impl Editor {
                 buffer_index: 0,
                 split: SplitDirection::None,
                 focus: PaneFocus::Primary,
+                pinned: false,
+                last_focused: Instant::now(),
             }],
             active_tab: 0,
             show_tagbar: false,
+            typewriter_scrolling: config.typewriter_scrolling,
             completion_items: Vec::new(),
             completion_selected: 0,
             completion_scroll_offset: 0,
             suppress_completion_until_input: false,
+            completion_debounce: CompletionDebounce::default(),
             theme: ThemeName::MaterialOcean,
             diagnostics: vec![
                 "warning: unused variable".to_string(),
                 "error: mismatched types".to_string(),
             ],
+            diagnostic_severities: vec![DiagnosticSeverity::Warning, DiagnosticSeverity::Error],
+            diagnostic_related: vec![Vec::new(), Vec::new()],
+            related_cursor: None,
             diagnostic_index: 0,
             lsp_diagnostics_by_file: HashMap::new(),
+            stale_diagnostic_files: HashSet::new(),
             status_message: lsp_start_message,
             command_history: Vec::new(),
+            copy_register: None,
             lsp_client,
             lsp_last_action: "idle".to_string(),
             rust_analyzer_status: "rust-analyzer: 未激活".to_string(),
             lsp_loading_status: String::new(),
             should_exit: false,
             last_tick: Instant::now(),
+            config,
+            formatters,
+            semantic_token_styles,
+            chat_handoff: None,
+            pending_will_save: None,
+            will_save_choices: HashMap::new(),
+            capabilities_view: None,
+            peek_view: None,
+            call_hierarchy_view: None,
+            lsp_event_log: None,
+            pending_read_confirm: None,
+            closed_buffers: ClosedBufferStack::default(),
+            lsp_disabled: false,
+            lsp_idle_stopped: HashSet::new(),
+        };
+        if editor.config.persist_diagnostics {
+            editor.restore_cached_diagnostics();
+        }
+        editor
+    }
+
+    /// 取出 editor 退出时留给主对话界面的预填充内容。
+    ///
+    /// 使用 `take` 语义，保证同一份内容只会被主界面消费一次。
+    pub fn take_chat_handoff(&mut self) -> Option<ChatHandoff> {
+        self.chat_handoff.take()
+    }
+
+    /// 生成退出时的活动文件快照；未开启 `auto_context_active_file` 时返回 `None`。
+    pub fn active_file_context(&self) -> Option<ActiveFileContext> {
+        if !self.config.auto_context_active_file {
+            return None;
         }
+        let buffer = self.active_buffer();
+        let label = match buffer.path.as_deref() {
+            Some(path) => path
+                .strip_prefix(&self.root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/"),
+            None => buffer.name.clone(),
+        };
+        Some(ActiveFileContext {
+            label,
+            lines: buffer.lines.clone(),
+            focus_row: buffer.cursor_row,
+        })
+    }
+
+    /// 解析当前活动缓冲区的格式化设置。
+    ///
+    /// 每次按需解析而不做缓存，是为了让用户修改 `.editorconfig` 后无需重启即可生效；
+    /// 未保存的缓冲区没有路径，只应用 `.order/editor.json` 覆盖项。
+    fn active_format_settings(&self) -> FormatSettings {
+        resolve_format_settings(self.active_buffer().path.as_deref(), &self.config.format)
     }
 
     // 编辑器主循环。
@@ -174,14 +402,30 @@ impl Editor {
             }
 
             self.auto_activate_lsp();
+            self.shutdown_idle_lsp_sessions();
             self.handle_lsp_events();
             self.lsp_last_action = self.lsp_client.last_action().to_string();
             self.sync_lsp_did_change();
+            // 每轮刷新激活标签页的使用时间，`max_tabs` 淘汰时按它判断最久未使用。
+            if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+                tab.last_focused = Instant::now();
+            }
+
+            self.flush_debounced_completion();
 
             terminal.draw(|frame| self.draw(frame))?;
-            let timeout = tick_rate
+            // 放在绘制之后：此时滚动位置与编辑区高度都已按本帧更新。
+            self.request_visible_semantic_tokens();
+            let mut timeout = tick_rate
                 .checked_sub(self.last_tick.elapsed())
                 .unwrap_or(Duration::ZERO);
+            // 有待发出的补全请求时按剩余防抖时间提前醒来，停顿后不必等满一个 tick。
+            if let Some(remaining) = self
+                .completion_debounce
+                .remaining(Instant::now(), self.config.completion_debounce())
+            {
+                timeout = timeout.min(remaining);
+            }
             if event::poll(timeout)? {
                 match event::read()? {
                     Event::Key(key) if key.kind == KeyEventKind::Press => {
@@ -195,6 +439,10 @@ impl Editor {
                 self.last_tick = Instant::now();
             }
         }
+        if self.config.persist_diagnostics {
+            // 尽力写入：失败不影响返回主界面，下次打开只是没有缓存可用。
+            let _ = save_diagnostics_cache(&self.root, &self.diagnostics_to_persist());
+        }
         Ok(())
     }
 
@@ -221,8 +469,9 @@ impl Editor {
             return;
         }
 
+        let visible_range_only = self.config.semantic_tokens_visible_range;
         for buffer in &mut self.buffers {
-            if !buffer.lsp_dirty {
+            if !buffer.lsp_dirty || buffer.large_file {
                 continue;
             }
 
@@ -252,7 +501,10 @@ impl Editor {
 
             // `didChange` 成功后立刻请求语义高亮，
             // 可以确保高亮结果与当前文本尽量同步。
-            if let Err(error) = self.lsp_client.request_semantic_tokens(path) {
+            // 按可见区域请求时只清空记录，由下一轮主循环按视口重新请求。
+            if visible_range_only {
+                self.semantic_range_requested = None;
+            } else if let Err(error) = self.lsp_client.request_semantic_tokens(path) {
                 self.status_message = format!("LSP semanticTokens 请求失败: {error}");
             }
         }
@@ -261,6 +513,9 @@ impl Editor {
     /// 处理 LSP 事件并同步到 editor 状态。
     fn handle_lsp_events(&mut self) {
         for event in self.lsp_client.poll_events() {
+            if let Some(log) = &self.lsp_event_log {
+                log.record(&event);
+            }
             match event {
                 LspEvent::Status(text) => {
                     self.status_message = text;
@@ -281,6 +536,18 @@ impl Editor {
                         self.lsp_loading_status = "项目加载完成".to_string();
                     }
                 }
+                LspEvent::SemanticTokensRange {
+                    file_path,
+                    start_line,
+                    end_line,
+                    tokens,
+                } => {
+                    let token_count = tokens.len();
+                    self.apply_lsp_semantic_tokens_range(&file_path, start_line, end_line, tokens);
+                    if token_count > 0 {
+                        self.lsp_loading_status = "项目加载完成".to_string();
+                    }
+                }
                 LspEvent::FormattingEdits { file_path, edits } => {
                     self.apply_formatting_edits(&file_path, edits);
                 }
@@ -291,6 +558,22 @@ impl Editor {
                 } => {
                     self.apply_rename_workspace_edit(&file_path, &new_name, edit);
                 }
+                LspEvent::DefinitionLocations {
+                    file_path,
+                    locations,
+                } => {
+                    self.show_peek_definition(&file_path, locations);
+                }
+                LspEvent::CallHierarchyPrepared { file_path, items } => {
+                    self.show_call_hierarchy(&file_path, items);
+                }
+                LspEvent::CallHierarchyCalls {
+                    item,
+                    direction,
+                    calls,
+                } => {
+                    self.apply_call_hierarchy_calls(&item, direction, calls);
+                }
                 LspEvent::CodeActions { file_path, actions } => {
                     self.apply_quick_fix_code_actions(&file_path, actions);
                 }
@@ -301,9 +584,11 @@ impl Editor {
                     edit,
                 } => {
                     let summary = self.apply_workspace_edit(edit);
-                    let applied = summary.failed_files == 0;
-                    let failure_reason =
-                        (!applied).then(|| format!("{} 个文件应用失败", summary.failed_files));
+                    let failure_details = summary.failure_details();
+                    let applied = failure_details.is_none();
+                    let failure_reason = failure_details.as_ref().map(|details| {
+                        format!("{} 个文件应用失败：{details}", summary.failed_files())
+                    });
 
                     if let Err(error) = self.lsp_client.respond_workspace_apply_edit(
                         language,
@@ -312,16 +597,19 @@ impl Editor {
                         failure_reason.as_deref(),
                     ) {
                         self.status_message = format!("workspace/applyEdit 回包失败: {error}");
-                    } else if let Some(label) = label {
-                        self.status_message = format!(
-                            "workspace/applyEdit：{}（{} 文件，{} 编辑）",
-                            label, summary.touched_files, summary.applied_edits
-                        );
                     } else {
-                        self.status_message = format!(
-                            "workspace/applyEdit：{} 文件，{} 编辑",
-                            summary.touched_files, summary.applied_edits
-                        );
+                        let label = label.map(|label| format!("{label}，")).unwrap_or_default();
+                        self.status_message = match failure_reason {
+                            Some(reason) => format!(
+                                "workspace/applyEdit：{label}成功 {} 文件，{reason}",
+                                summary.touched_files()
+                            ),
+                            None => format!(
+                                "workspace/applyEdit：{label}{} 文件，{} 编辑",
+                                summary.touched_files(),
+                                summary.applied_edits()
+                            ),
+                        };
                     }
                 }
                 LspEvent::RustAnalyzerStatus { message, done } => {
@@ -340,10 +628,11 @@ impl Editor {
                     if done {
                         let tab_idx = self.active_tab;
                         let buffer_idx = self.tabs[tab_idx].buffer_index;
-                        if let Some(path) = self.buffers[buffer_idx].path.clone()
+                        if !self.buffers[buffer_idx].large_file
+                            && let Some(path) = self.buffers[buffer_idx].path.clone()
                             && detect_language_from_path_or_name(Some(&path), "")
                                 .is_some_and(|language| language == lsp::LspLanguage::Rust)
-                            && let Err(error) = self.lsp_client.request_semantic_tokens(&path)
+                            && let Err(error) = self.request_semantic_tokens_for_path(&path)
                         {
                             self.status_message =
                                 format!("rust-analyzer 已就绪，但语义高亮请求失败: {}", error);
@@ -358,10 +647,12 @@ impl Editor {
     ///
     /// 每轮主循环检查：
     /// - 如果当前活跃 buffer 是某语言文件且会话未运行，触发 didOpen；
-    /// - 如果项目根目录存在该语言的项目标识文件且会话未运行，直接启动 LSP。
+    /// - 如果项目根目录存在该语言的项目标识文件且会话未运行，直接启动 LSP；
+    /// - 在 `lsp_auto_activate` 中被关闭的语言跳过，只能通过 `la` 手动启动；
+    /// - `:lsp off` 之后整体跳过，直到 `:lsp on`。
     /// 这样既能实现开箱即用自动激活，也避免重复请求造成噪音。
     fn auto_activate_lsp(&mut self) {
-        if self.tabs.is_empty() {
+        if self.tabs.is_empty() || self.lsp_disabled {
             return;
         }
 
@@ -369,10 +660,19 @@ impl Editor {
         let buffer_path = self
             .buffers
             .get(buffer_idx)
+            .filter(|buffer| !buffer.large_file)
             .and_then(|buffer| buffer.path.as_ref().cloned());
 
+        let buffer_language = buffer_path
+            .as_deref()
+            .and_then(|path| detect_language_from_path_or_name(Some(path), ""));
         for language in lsp::all_languages() {
             if self.lsp_client.is_language_running(*language) {
+                self.lsp_idle_stopped.remove(language);
+                continue;
+            }
+            // 空闲回收的语言只在重新打开该语言文件时再启动，避免回收后下一轮又被项目标记拉起。
+            if self.lsp_idle_stopped.contains(language) && buffer_language != Some(*language) {
                 continue;
             }
 
@@ -381,12 +681,14 @@ impl Editor {
                 .iter()
                 .any(|marker| self.root.join(marker).exists());
 
-            if !has_project_marker {
+            if !self
+                .config
+                .should_auto_activate_lsp(*language, has_project_marker)
+            {
                 continue;
             }
 
-            if let Some(ref path) = buffer_path {
-                let buffer_language = detect_language_from_path_or_name(Some(path), "");
+            if buffer_path.is_some() {
                 if buffer_language == Some(*language) {
                     self.try_send_did_open_for_buffer_idx(buffer_idx);
                     if self.lsp_client.is_language_running(*language) {
@@ -418,6 +720,32 @@ impl Editor {
         }
     }
 
+    /// 按 `lsp_idle_shutdown_secs` 回收没有打开缓冲区、且长时间没有请求的语言会话。
+    fn shutdown_idle_lsp_sessions(&mut self) {
+        let Some(idle_secs) = self.config.lsp_idle_shutdown_secs.filter(|secs| *secs > 0) else {
+            return;
+        };
+        let open_languages = self
+            .tabs
+            .iter()
+            .filter_map(|tab| self.buffers.get(tab.buffer_index))
+            .filter_map(|buffer| {
+                detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name)
+            })
+            .collect::<HashSet<_>>();
+
+        let closed = self
+            .lsp_client
+            .shutdown_idle_sessions(Duration::from_secs(idle_secs), |language| {
+                open_languages.contains(&language)
+            });
+        if closed.is_empty() {
+            return;
+        }
+        self.lsp_idle_stopped.extend(closed);
+        self.status_message = self.lsp_client.status_message().to_string();
+    }
+
     /// 将 LSP 补全候选写回目标缓冲区。
     ///
     /// 通过“路径定位 -> 全量替换”策略，避免跨 buffer 残留旧补全数据。
@@ -468,52 +796,203 @@ impl Editor {
         }
     }
 
+    /// 将范围语义 token 合并进目标缓冲区，范围外的缓存保持不变。
+    fn apply_lsp_semantic_tokens_range(
+        &mut self,
+        file_path: &Path,
+        start_line: usize,
+        end_line: usize,
+        tokens: Vec<LspSemanticToken>,
+    ) {
+        let Some(buffer) = self
+            .buffers
+            .iter_mut()
+            .find(|buffer| buffer.path.as_deref() == Some(file_path))
+        else {
+            return;
+        };
+        merge_ranged_tokens(
+            &mut buffer.lsp_semantic_tokens,
+            &mut buffer.lsp_tokens_by_line,
+            start_line,
+            end_line,
+            tokens,
+        );
+    }
+
+    /// 请求指定文件的语义 token。
+    ///
+    /// 开启 `semantic_tokens_visible_range` 时不在这里发整文件请求，
+    /// 只清空已请求范围，交给 [`Self::request_visible_semantic_tokens`] 按视口请求。
+    fn request_semantic_tokens_for_path(&mut self, path: &Path) -> anyhow::Result<()> {
+        if self.config.semantic_tokens_visible_range {
+            self.semantic_range_requested = None;
+            return Ok(());
+        }
+        self.lsp_client.request_semantic_tokens(path)
+    }
+
+    /// 视口滚出已请求范围时，为当前缓冲区请求可见区域（含边距）的语义 token。
+    fn request_visible_semantic_tokens(&mut self) {
+        if !self.config.semantic_tokens_visible_range || self.tabs.is_empty() {
+            return;
+        }
+        let Some(inner) = self.last_editor_inner_area else {
+            return;
+        };
+        let buffer = self.active_buffer();
+        if buffer.large_file {
+            return;
+        }
+        let Some(path) = buffer.path.clone() else {
+            return;
+        };
+        let Some(language) = detect_language_from_path_or_name(Some(&path), &buffer.name) else {
+            return;
+        };
+        if !self.lsp_client.is_language_running(language) {
+            return;
+        }
+
+        let needed = visible_token_range(
+            buffer.scroll_row,
+            inner.height as usize,
+            buffer.lines.len(),
+            0,
+        );
+        if let Some((requested_path, start, end)) = self.semantic_range_requested.as_ref()
+            && *requested_path == path
+            && range_covers((*start, *end), needed)
+        {
+            return;
+        }
+
+        let (start, end) = visible_token_range(
+            buffer.scroll_row,
+            inner.height as usize,
+            buffer.lines.len(),
+            SEMANTIC_RANGE_MARGIN,
+        );
+        match self
+            .lsp_client
+            .request_semantic_tokens_range(&path, start, end)
+        {
+            Ok(()) => self.semantic_range_requested = Some((path, start, end)),
+            Err(error) => {
+                self.status_message = format!("LSP semanticTokens/range 请求失败: {error}");
+            }
+        }
+    }
+
     /// 将 LSP 诊断按文件缓存，并同步到 diagnostics 面板。
     fn apply_lsp_diagnostics(&mut self, file_path: PathBuf, items: Vec<DiagnosticItem>) {
+        // 新的发布总是替换该文件的缓存诊断，不论结果是否为空。
+        self.stale_diagnostic_files.remove(&file_path);
         if items.is_empty() {
             self.lsp_diagnostics_by_file.remove(&file_path);
         } else {
             self.lsp_diagnostics_by_file.insert(file_path, items);
         }
 
-        let mut flattened = self
-            .lsp_diagnostics_by_file
-            .values()
-            .flat_map(|items| items.iter().cloned())
-            .collect::<Vec<_>>();
-        flattened.sort_by(|left, right| {
-            left.file_path
-                .cmp(&right.file_path)
-                .then(left.line.cmp(&right.line))
-                .then(left.column.cmp(&right.column))
-        });
+        self.refresh_diagnostics_panel();
+        if self.diagnostics.is_empty() {
+            self.status_message = "LSP: 无诊断问题".to_string();
+        } else {
+            self.status_message = format!("LSP: 收到 {} 条诊断", self.diagnostics.len());
+        }
+    }
+
+    /// 从 `.order/diagnostics_cache.json` 恢复上次会话的诊断，并标记为缓存。
+    ///
+    /// 语言服务重新索引期间面板不至于空白；这些文件收到新的发布后自动替换。
+    fn restore_cached_diagnostics(&mut self) {
+        let cached = match load_diagnostics_cache(&self.root) {
+            Ok(cached) => cached,
+            Err(error) => {
+                self.status_message = format!("诊断缓存读取失败: {error}");
+                return;
+            }
+        };
+        if cached.is_empty() {
+            return;
+        }
+        self.stale_diagnostic_files = cached.keys().cloned().collect();
+        self.lsp_diagnostics_by_file = cached;
+        self.refresh_diagnostics_panel();
+        self.status_message = format!(
+            "已恢复 {} 条缓存诊断，等待语言服务刷新",
+            self.diagnostics.len()
+        );
+    }
+
+    /// 需要写回缓存的诊断：只保留本次会话中语言服务重新发布过的文件。
+    ///
+    /// 从缓存恢复、之后一直没有收到新发布的文件（例如已被删除或不再被分析）
+    /// 不再写回，避免过期诊断在每次会话间无限延续。
+    fn diagnostics_to_persist(&self) -> HashMap<PathBuf, Vec<DiagnosticItem>> {
+        self.lsp_diagnostics_by_file
+            .iter()
+            .filter(|(path, _)| !self.stale_diagnostic_files.contains(*path))
+            .map(|(path, items)| (path.clone(), items.clone()))
+            .collect()
+    }
+
+    /// 根据按文件缓存的诊断重建 diagnostics 面板。
+    fn refresh_diagnostics_panel(&mut self) {
+        // 面板只展示去重后的结果；按文件缓存的原始诊断保持不变，供 quick fix 回传。
+        let mut flattened = dedup_diagnostics(
+            self.lsp_diagnostics_by_file
+                .values()
+                .flat_map(|items| items.iter().cloned())
+                .collect(),
+        );
+        flattened.sort_by(|left, right| compare_diagnostics(&left.item, &right.item));
+        self.diagnostic_severities = flattened.iter().map(|entry| entry.item.severity).collect();
+        self.diagnostic_related = flattened
+            .iter()
+            .map(|entry| entry.item.related.clone())
+            .collect();
+        self.related_cursor = None;
 
         self.diagnostics = flattened
             .iter()
-            .map(|item| {
+            .map(|DedupedDiagnostic { item, source_count }| {
                 let file = item
                     .file_path
                     .file_name()
                     .and_then(|name| name.to_str())
                     .unwrap_or("<unknown>");
+                let sources = if *source_count > 1 {
+                    format!("（{source_count} 个来源）")
+                } else {
+                    String::new()
+                };
+                let related = if item.related.is_empty() {
+                    String::new()
+                } else {
+                    format!("（{} 处关联位置，]r 跳转）", item.related.len())
+                };
+                let stale = if self.stale_diagnostic_files.contains(&item.file_path) {
+                    "[缓存] "
+                } else {
+                    ""
+                };
                 format!(
-                    "{}:{}:{} [{}] {}",
+                    "{}{}:{}:{} [{}] {}{}{}",
+                    stale,
                     file,
                     item.line,
                     item.column,
                     item.severity.as_str(),
-                    item.message
+                    item.message,
+                    sources,
+                    related
                 )
             })
             .collect();
         self.diagnostic_index = self
             .diagnostic_index
             .min(self.diagnostics.len().saturating_sub(1));
-        if self.diagnostics.is_empty() {
-            self.status_message = "LSP: 无诊断问题".to_string();
-        } else {
-            self.status_message = format!("LSP: 收到 {} 条诊断", self.diagnostics.len());
-        }
     }
 
     /// 应用 `textDocument/formatting` 返回的编辑。
@@ -551,16 +1030,21 @@ impl Editor {
         }
 
         let summary = self.apply_workspace_edit(edit);
-        if summary.failed_files == 0 {
-            self.status_message = format!(
-                "LSP rename：`{new_name}` 已完成（{} 文件，{} 编辑）",
-                summary.touched_files, summary.applied_edits
-            );
-        } else {
-            self.status_message = format!(
-                "LSP rename：部分失败（成功 {} 文件，失败 {} 文件）",
-                summary.touched_files, summary.failed_files
-            );
+        match summary.failure_details() {
+            None => {
+                self.status_message = format!(
+                    "LSP rename：`{new_name}` 已完成（{} 文件，{} 编辑）",
+                    summary.touched_files(),
+                    summary.applied_edits()
+                );
+            }
+            Some(details) => {
+                self.status_message = format!(
+                    "LSP rename：部分失败（成功 {} 文件，失败 {} 文件：{details}）",
+                    summary.touched_files(),
+                    summary.failed_files()
+                );
+            }
         }
     }
 
@@ -574,16 +1058,17 @@ impl Editor {
         let mut status_parts = Vec::new();
         if let Some(edit) = selected.edit.clone() {
             let summary = self.apply_workspace_edit(edit);
-            if summary.failed_files == 0 {
-                status_parts.push(format!(
+            match summary.failure_details() {
+                None => status_parts.push(format!(
                     "已应用 {} 文件 / {} 编辑",
-                    summary.touched_files, summary.applied_edits
-                ));
-            } else {
-                status_parts.push(format!(
-                    "应用部分失败（成功 {}，失败 {}）",
-                    summary.touched_files, summary.failed_files
-                ));
+                    summary.touched_files(),
+                    summary.applied_edits()
+                )),
+                Some(details) => status_parts.push(format!(
+                    "应用部分失败（成功 {}，失败 {}：{details}）",
+                    summary.touched_files(),
+                    summary.failed_files()
+                )),
             }
         }
 
@@ -609,7 +1094,10 @@ impl Editor {
         }
     }
 
-    /// 将 `willSaveWaitUntil` 返回的 TextEdit 应用到目标缓冲区。
+    /// 处理 `willSaveWaitUntil` 返回的 TextEdit。
+    ///
+    /// 开启 `will_save_preview` 后，未记住选择的语言会进入确认模式，
+    /// 由用户决定是否应用；否则直接应用到目标缓冲区。
     fn apply_will_save_wait_until_edits(
         &mut self,
         file_path: &std::path::Path,
@@ -619,6 +1107,54 @@ impl Editor {
             return;
         }
 
+        let language = detect_language_from_path_or_name(Some(file_path), "");
+        let remembered =
+            language.and_then(|language| self.will_save_choices.get(&language).copied());
+        match decide_will_save_action(self.config.will_save_preview, remembered) {
+            WillSaveDecision::Apply => self.commit_will_save_edits(file_path, edits),
+            WillSaveDecision::Skip => {
+                self.status_message =
+                    format!("LSP: 已按记住的选择跳过 {}", summarize_text_edits(&edits));
+            }
+            WillSaveDecision::Prompt => {
+                // 新的编辑到达时直接替换旧的待确认项：旧编辑基于更早的文本，已不可靠。
+                self.pending_will_save = Some(PendingWillSaveEdits {
+                    file_path: file_path.to_path_buf(),
+                    language,
+                    edits,
+                });
+                self.mode = EditorMode::WillSaveConfirm;
+            }
+        }
+    }
+
+    /// 根据用户在确认弹窗中的选择处理待确认编辑。
+    ///
+    /// `remember` 为真时把选择记到该语言上，本次会话后续保存不再弹窗。
+    pub(super) fn resolve_pending_will_save(&mut self, apply: bool, remember: bool) {
+        self.mode = EditorMode::Normal;
+        let Some(pending) = self.pending_will_save.take() else {
+            return;
+        };
+
+        if remember && let Some(language) = pending.language {
+            let choice = if apply {
+                WillSaveChoice::Apply
+            } else {
+                WillSaveChoice::Skip
+            };
+            self.will_save_choices.insert(language, choice);
+        }
+
+        if apply {
+            self.commit_will_save_edits(&pending.file_path, pending.edits);
+        } else {
+            self.status_message = format!("LSP: 已放弃 {}", summarize_text_edits(&pending.edits));
+        }
+    }
+
+    // 将 `willSaveWaitUntil` 编辑真正写入目标缓冲区。
+    fn commit_will_save_edits(&mut self, file_path: &Path, edits: Vec<LspTextEdit>) {
         match self.apply_text_edits_to_file(file_path, edits) {
             Ok(applied_count) => {
                 self.lsp_last_action = format!("willSaveWaitUntil({} edits)", applied_count);
@@ -641,18 +1177,8 @@ impl Editor {
                 continue;
             }
 
-            match self.apply_text_edits_to_file(&file_edit.file_path, file_edit.edits) {
-                Ok(applied) if applied > 0 => {
-                    summary.touched_files += 1;
-                    summary.applied_edits += applied;
-                }
-                Ok(_) => {
-                    summary.failed_files += 1;
-                }
-                Err(_) => {
-                    summary.failed_files += 1;
-                }
-            }
+            let result = self.apply_text_edits_to_file(&file_edit.file_path, file_edit.edits);
+            summary.record(file_edit.file_path, result);
         }
         summary
     }
@@ -712,14 +1238,6 @@ impl Editor {
     }
 }
 
-/// 工作区编辑应用结果统计。
-#[derive(Default)]
-struct WorkspaceEditApplySummary {
-    touched_files: usize,
-    applied_edits: usize,
-    failed_files: usize,
-}
-
 /// 从 code action 列表中挑选最合适的 quick fix。
 ///
 /// 优先级策略：
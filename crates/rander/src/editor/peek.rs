@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+/// 定义位置上下各展示的行数。
+pub(super) const PEEK_CONTEXT_LINES: usize = 8;
+/// 浮层内一屏展示的行数，超出部分通过滚动查看。
+pub(super) const PEEK_VISIBLE_ROWS: usize = 8;
+
+/// peek 浮层内容：定义附近的一段只读文本。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PeekView {
+    /// 浮层标题，形如 `src/lib.rs:42`。
+    pub(super) title: String,
+    /// `lines[0]` 对应的文件行号（从 0 开始）。
+    pub(super) start_line: usize,
+    /// 定义所在的文件行号（从 0 开始），渲染时高亮。
+    pub(super) target_line: usize,
+    pub(super) lines: Vec<String>,
+    /// 浮层内的滚动偏移（行）。
+    pub(super) scroll: usize,
+}
+
+impl PeekView {
+    // 由一段行窗口构造浮层；窗口通常比一屏高，初始滚动让定义行落在可视区域中部。
+    pub(super) fn new(
+        title: String,
+        start_line: usize,
+        target_line: usize,
+        lines: Vec<String>,
+    ) -> Self {
+        let max_scroll = lines.len().saturating_sub(PEEK_VISIBLE_ROWS);
+        let scroll = target_line
+            .saturating_sub(start_line)
+            .saturating_sub(PEEK_VISIBLE_ROWS / 2)
+            .min(max_scroll);
+        Self {
+            title,
+            start_line,
+            target_line,
+            lines,
+            scroll,
+        }
+    }
+
+    // 在可视高度内滚动，`delta` 为负表示向上。
+    pub(super) fn scroll_by(&mut self, delta: isize, visible_rows: usize) {
+        let max_scroll = self.lines.len().saturating_sub(visible_rows.max(1));
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+}
+
+/// 截取 `target_line` 上下各 `context` 行，返回 `(起始行号, 行内容)`。
+///
+/// 目标行超出文件末尾时（例如文件在请求后被截短）退化为文件末尾的窗口，
+/// 保证浮层总有内容可看。
+pub(super) fn peek_window(
+    lines: &[String],
+    target_line: usize,
+    context: usize,
+) -> (usize, Vec<String>) {
+    let target_line = target_line.min(lines.len().saturating_sub(1));
+    let start = target_line.saturating_sub(context);
+    let end = target_line
+        .saturating_add(context)
+        .saturating_add(1)
+        .min(lines.len());
+    (start, lines[start..end].to_vec())
+}
+
+/// 从磁盘按需读取未打开文件中定义附近的行。
+///
+/// 只读到窗口末尾为止，不把整个文件载入内存：定义经常落在依赖库的大文件里，
+/// 而 peek 只需要其中十几行。
+pub(super) fn read_peek_window(
+    path: &Path,
+    target_line: usize,
+    context: usize,
+) -> io::Result<(usize, Vec<String>)> {
+    let start = target_line.saturating_sub(context);
+    let end = target_line.saturating_add(context).saturating_add(1);
+    let reader = BufReader::new(File::open(path)?);
+    let lines = reader
+        .lines()
+        .skip(start)
+        .take(end - start)
+        .map(|line| line.map(|text| text.trim_end_matches('\r').to_string()))
+        .collect::<io::Result<Vec<_>>>()?;
+    if lines.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("定义行 {} 超出文件范围", target_line + 1),
+        ));
+    }
+    Ok((start, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{PEEK_CONTEXT_LINES, PEEK_VISIBLE_ROWS, PeekView, peek_window, read_peek_window};
+
+    fn numbered_lines(count: usize) -> Vec<String> {
+        (0..count).map(|index| format!("line {index}")).collect()
+    }
+
+    #[test]
+    fn test_peek_window_clamps_around_target() {
+        let lines = numbered_lines(30);
+
+        let (start, window) = peek_window(&lines, 15, 3);
+        assert_eq!(start, 12);
+        assert_eq!(window.first().map(String::as_str), Some("line 12"));
+        assert_eq!(window.last().map(String::as_str), Some("line 18"));
+
+        let (start, window) = peek_window(&lines, 1, 3);
+        assert_eq!(start, 0, "靠近文件开头时不应越界");
+        assert_eq!(window.len(), 5);
+
+        let (start, window) = peek_window(&lines, 29, 3);
+        assert_eq!(start, 26);
+        assert_eq!(window.len(), 4, "靠近文件末尾时截断到最后一行");
+
+        let (start, window) = peek_window(&lines, 100, 3);
+        assert_eq!(start, 26, "目标行越界时退化为文件末尾");
+        assert_eq!(window.last().map(String::as_str), Some("line 29"));
+
+        let mut view = PeekView::new("lib.rs:16".to_string(), start, 29, window);
+        view.scroll_by(10, 2);
+        assert_eq!(view.scroll, 2, "滚动不应超过最后一屏");
+        view.scroll_by(-5, 2);
+        assert_eq!(view.scroll, 0);
+    }
+
+    #[test]
+    fn test_peek_view_starts_with_target_visible() {
+        let lines = numbered_lines(40);
+
+        for target in [0, 3, 20, 39] {
+            let (start, window) = peek_window(&lines, target, PEEK_CONTEXT_LINES);
+            let view = PeekView::new(format!("lib.rs:{}", target + 1), start, target, window);
+            let offset = target - start;
+            assert!(
+                (view.scroll..view.scroll + PEEK_VISIBLE_ROWS).contains(&offset),
+                "打开浮层时定义行 {target} 应在可视区域内，scroll={}",
+                view.scroll
+            );
+            assert!(view.scroll + PEEK_VISIBLE_ROWS <= view.lines.len().max(PEEK_VISIBLE_ROWS));
+        }
+    }
+
+    #[test]
+    fn test_read_peek_window_reads_only_target_region() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let path =
+            std::env::temp_dir().join(format!("order-peek-{}-{nanos}.rs", std::process::id()));
+        fs::write(&path, numbered_lines(20).join("\r\n")).expect("写入测试文件失败");
+
+        let (start, window) = read_peek_window(&path, 10, 2).expect("读取窗口失败");
+        assert_eq!(start, 8);
+        assert_eq!(
+            window,
+            vec!["line 8", "line 9", "line 10", "line 11", "line 12"],
+            "应去掉 CRLF 行尾"
+        );
+        assert!(read_peek_window(&path, 50, 2).is_err(), "越界定义应报错");
+
+        let _ = fs::remove_file(&path);
+    }
+}
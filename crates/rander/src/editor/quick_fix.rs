@@ -0,0 +1,88 @@
+use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+/// 从诊断列表中挑选覆盖指定行的那一条诊断。
+///
+/// 同一行可能同时存在多条诊断（例如 error 与随之产生的 warning），
+/// 这里优先取级别最高的一条，级别相同时取起始列更靠前的，
+/// 因为修掉根因往往会连带消除同一行的其余诊断。
+pub(super) fn select_line_diagnostic(
+    diagnostics: &[DiagnosticItem],
+    row: usize,
+) -> Option<DiagnosticItem> {
+    diagnostics
+        .iter()
+        .filter(|item| item.lsp_start_line <= row && item.lsp_end_line >= row)
+        .min_by_key(|item| (severity_rank(item.severity), item.lsp_start_character))
+        .cloned()
+}
+
+// 诊断级别排序权重，数值越小越优先。
+pub(super) fn severity_rank(severity: DiagnosticSeverity) -> u8 {
+    match severity {
+        DiagnosticSeverity::Error => 0,
+        DiagnosticSeverity::Warning => 1,
+        DiagnosticSeverity::Information => 2,
+        DiagnosticSeverity::Hint => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use lsp::{DiagnosticItem, DiagnosticSeverity};
+
+    use super::select_line_diagnostic;
+
+    fn diagnostic(
+        start_line: usize,
+        end_line: usize,
+        start_character: usize,
+        severity: DiagnosticSeverity,
+        message: &str,
+    ) -> DiagnosticItem {
+        DiagnosticItem {
+            file_path: PathBuf::from("src/main.rs"),
+            line: start_line as u64 + 1,
+            column: start_character as u64 + 1,
+            severity,
+            message: message.to_string(),
+            lsp_start_line: start_line,
+            lsp_start_character: start_character,
+            lsp_end_line: end_line,
+            lsp_end_character: start_character + 1,
+            source: None,
+            code: None,
+            related: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_select_line_diagnostic_prefers_highest_severity_on_line() {
+        let diagnostics = vec![
+            diagnostic(2, 2, 0, DiagnosticSeverity::Warning, "unused import"),
+            diagnostic(2, 2, 10, DiagnosticSeverity::Error, "mismatched types"),
+            diagnostic(5, 5, 0, DiagnosticSeverity::Error, "other line"),
+        ];
+
+        let selected = select_line_diagnostic(&diagnostics, 2).expect("当前行存在诊断");
+        assert_eq!(selected.message, "mismatched types");
+        assert!(
+            select_line_diagnostic(&diagnostics, 3).is_none(),
+            "没有诊断覆盖的行不应返回结果"
+        );
+    }
+
+    #[test]
+    fn test_select_line_diagnostic_matches_multiline_range() {
+        let diagnostics = vec![
+            diagnostic(1, 4, 6, DiagnosticSeverity::Error, "unclosed delimiter"),
+            diagnostic(3, 3, 2, DiagnosticSeverity::Error, "expected `;`"),
+        ];
+
+        let selected = select_line_diagnostic(&diagnostics, 3).expect("多行诊断应覆盖中间行");
+        assert_eq!(selected.message, "expected `;`", "同级别时取起始列更靠前的");
+        let selected = select_line_diagnostic(&diagnostics, 2).expect("多行诊断应覆盖中间行");
+        assert_eq!(selected.message, "unclosed delimiter");
+    }
+}
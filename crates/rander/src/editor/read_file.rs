@@ -0,0 +1,99 @@
+use std::{io, path::Path};
+
+use core::encoding::read_utf8_text_with_report;
+
+use super::types::EditorBuffer;
+
+/// 读取 `:r` 要插入的文件，按行拆分。
+///
+/// 走与历史、配置相同的编码检查：兼容 BOM 与 CRLF，非 UTF-8 直接报错，
+/// 避免把乱码插进正在编辑的文件。空文件返回空列表。
+pub(super) fn read_insert_lines(path: &Path) -> io::Result<Vec<String>> {
+    let (content, _) = read_utf8_text_with_report(path)?;
+    Ok(content.lines().map(ToString::to_string).collect())
+}
+
+impl EditorBuffer {
+    /// 在光标所在行之后插入若干行（与 Vim `:r` 一致），光标停在第一行插入内容的行首。
+    ///
+    /// 插入内容为空时不改动缓冲区，也不标记为已修改。
+    pub(super) fn insert_lines_below_cursor(&mut self, inserted: Vec<String>) -> usize {
+        let count = inserted.len();
+        if count == 0 {
+            return 0;
+        }
+        self.ensure_cursor_in_bounds();
+        let first_row = self.cursor_row + 1;
+        self.lines.splice(first_row..first_row, inserted);
+        self.cursor_row = first_row;
+        self.cursor_col = 0;
+        self.modified = true;
+        self.lsp_dirty = true;
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{EditorBuffer, read_insert_lines};
+
+    fn buffer_with(lines: &[&str]) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new_empty("test".to_string());
+        buffer.lines = lines.iter().map(ToString::to_string).collect();
+        buffer
+    }
+
+    #[test]
+    fn test_insert_lines_below_cursor_position_and_count() {
+        let mut buffer = buffer_with(&["a", "b", "c"]);
+        buffer.cursor_row = 1;
+        buffer.cursor_col = 1;
+
+        let inserted = buffer.insert_lines_below_cursor(vec!["x".to_string(), "y".to_string()]);
+        assert_eq!(inserted, 2);
+        assert_eq!(buffer.lines, vec!["a", "b", "x", "y", "c"]);
+        assert_eq!(
+            (buffer.cursor_row, buffer.cursor_col),
+            (2, 0),
+            "光标应停在第一行插入内容"
+        );
+        assert!(buffer.modified && buffer.lsp_dirty);
+
+        let mut last_line = buffer_with(&["only"]);
+        last_line.insert_lines_below_cursor(vec!["tail".to_string()]);
+        assert_eq!(last_line.lines, vec!["only", "tail"], "末行之后也能插入");
+
+        let mut unchanged = buffer_with(&["a"]);
+        assert_eq!(unchanged.insert_lines_below_cursor(Vec::new()), 0);
+        assert_eq!(unchanged.lines.len(), 1);
+        assert!(!unchanged.modified, "插入空文件不应标记为已修改");
+    }
+
+    #[test]
+    fn test_read_insert_lines_normalizes_encoding() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let path =
+            std::env::temp_dir().join(format!("order-read-{}-{nanos}.txt", std::process::id()));
+
+        fs::write(&path, b"\xEF\xBB\xBFfirst\r\nsecond\r\n").expect("写入测试文件失败");
+        assert_eq!(
+            read_insert_lines(&path).expect("读取失败"),
+            vec!["first", "second"],
+            "应去掉 BOM 并按 LF 拆行"
+        );
+
+        fs::write(&path, b"bad \xFF bytes").expect("写入测试文件失败");
+        assert!(read_insert_lines(&path).is_err(), "非 UTF-8 内容应报错");
+
+        let _ = fs::remove_file(&path);
+        assert!(read_insert_lines(&path).is_err(), "不存在的文件应报错");
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use lsp::DiagnosticRelated;
+
+use super::utils::file_name_or;
+
+/// `]r` / `[r` 的循环位置：属于哪条诊断，以及当前停在第几个关联位置。
+///
+/// 切换到其他诊断后重新从第一个（或最后一个）开始，
+/// 避免沿用上一条诊断的序号跳到不相干的位置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct RelatedCursor {
+    pub(super) diagnostic: usize,
+    pub(super) index: usize,
+}
+
+/// 计算下一个关联位置的序号，到达两端时循环。
+pub(super) fn next_related_cursor(
+    current: Option<RelatedCursor>,
+    diagnostic: usize,
+    len: usize,
+    forward: bool,
+) -> Option<RelatedCursor> {
+    if len == 0 {
+        return None;
+    }
+    let index = match current.filter(|cursor| cursor.diagnostic == diagnostic) {
+        Some(cursor) if forward => (cursor.index + 1) % len,
+        Some(cursor) => (cursor.index + len - 1) % len,
+        None if forward => 0,
+        None => len - 1,
+    };
+    Some(RelatedCursor { diagnostic, index })
+}
+
+/// 跳转到关联位置所需的操作：需要时先打开文件，再把光标放到目标位置。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RelatedJumpTarget {
+    /// 目标不在当前缓冲区时需要打开的文件。
+    pub(super) open: Option<PathBuf>,
+    pub(super) row: usize,
+    pub(super) col: usize,
+}
+
+/// 根据当前缓冲区路径计算跳转目标。
+pub(super) fn related_jump_target(
+    active_path: Option<&Path>,
+    related: &DiagnosticRelated,
+) -> RelatedJumpTarget {
+    let location = &related.location;
+    RelatedJumpTarget {
+        open: (active_path != Some(location.file_path.as_path()))
+            .then(|| location.file_path.clone()),
+        row: location.line,
+        col: location.character,
+    }
+}
+
+/// 状态栏文本：标出当前是第几个关联位置，以及文件、行列与说明。
+pub(super) fn related_status(
+    cursor: RelatedCursor,
+    len: usize,
+    related: &DiagnosticRelated,
+) -> String {
+    let location = &related.location;
+    format!(
+        "[关联 {}/{len}] {}:{}:{} {}",
+        cursor.index + 1,
+        file_name_or(&location.file_path, "<unknown>"),
+        location.line + 1,
+        location.character + 1,
+        related.message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use lsp::{DiagnosticRelated, LspLocation};
+
+    use super::{RelatedCursor, next_related_cursor, related_jump_target, related_status};
+
+    fn related(file: &str, line: usize) -> DiagnosticRelated {
+        DiagnosticRelated {
+            location: LspLocation {
+                file_path: PathBuf::from(file),
+                line,
+                character: 4,
+            },
+            message: "previous definition here".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_next_related_cursor_cycles_within_diagnostic() {
+        let first = next_related_cursor(None, 2, 3, true);
+        assert_eq!(
+            first,
+            Some(RelatedCursor {
+                diagnostic: 2,
+                index: 0
+            })
+        );
+        let second = next_related_cursor(first, 2, 3, true);
+        let third = next_related_cursor(second, 2, 3, true);
+        assert_eq!(third.map(|cursor| cursor.index), Some(2));
+        assert_eq!(
+            next_related_cursor(third, 2, 3, true).map(|cursor| cursor.index),
+            Some(0),
+            "到末尾后回到第一个"
+        );
+        assert_eq!(
+            next_related_cursor(first, 2, 3, false).map(|cursor| cursor.index),
+            Some(2),
+            "向前越过开头时回到最后一个"
+        );
+        assert_eq!(
+            next_related_cursor(third, 5, 3, true),
+            Some(RelatedCursor {
+                diagnostic: 5,
+                index: 0
+            }),
+            "切换诊断后重新开始"
+        );
+        assert_eq!(
+            next_related_cursor(None, 5, 3, false).map(|cursor| cursor.index),
+            Some(2)
+        );
+        assert_eq!(
+            next_related_cursor(None, 0, 0, true),
+            None,
+            "没有关联位置时不跳转"
+        );
+    }
+
+    #[test]
+    fn test_related_jump_target_opens_other_files_only() {
+        let other = related("src/lib.rs", 11);
+        assert_eq!(
+            related_jump_target(Some(Path::new("src/main.rs")), &other).open,
+            Some(PathBuf::from("src/lib.rs"))
+        );
+        let same = related_jump_target(Some(Path::new("src/lib.rs")), &other);
+        assert_eq!((same.open, same.row, same.col), (None, 11, 4));
+        assert!(related_jump_target(None, &other).open.is_some());
+
+        assert_eq!(
+            related_status(
+                RelatedCursor {
+                    diagnostic: 0,
+                    index: 1
+                },
+                3,
+                &other
+            ),
+            "[关联 2/3] lib.rs:12:5 previous definition here"
+        );
+    }
+}
@@ -2,7 +2,7 @@ use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
 use std::cmp::min;
 use std::sync::OnceLock;
 
-use lsp::LspSemanticToken;
+use lsp::{LspCallDirection, LspSemanticToken};
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
@@ -18,7 +18,21 @@ use syntect::{
 
 use super::{
     Editor,
-    types::{EditorBuffer, EditorMode, MainFocus, PaneFocus, SplitDirection, ThemePalette},
+    config::CompletionPlacement,
+    minimap::{MinimapMarker, diagnostic_markers, minimap_cells},
+    peek::PEEK_VISIBLE_ROWS,
+    soft_wrap::{GUTTER_WIDTH, logical_to_display, split_spans, wrap_starts, wrapped_scroll_row},
+    theme::SemanticTokenStyles,
+    types::{
+        CompletionDisplayItem, EditorBuffer, EditorMode, MainFocus, PaneFocus, SplitDirection,
+        ThemePalette,
+    },
+    typewriter::centered_scroll_row,
+    will_save::summarize_text_edits,
+};
+use crate::size_guard::{
+    MIN_PANE_HEIGHT, MIN_PANE_WIDTH, MIN_POPOVER_HEIGHT, MIN_POPOVER_WIDTH, MIN_SCREEN_HEIGHT,
+    MIN_SCREEN_WIDTH, SizeCheck, check_size, render_too_small,
 };
 
 static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
@@ -29,6 +43,10 @@ impl Editor {
         self.last_area = Some(frame.area());
         let area = frame.area();
         let palette = ThemePalette::from_theme(self.theme);
+        if check_size(area, MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT) == SizeCheck::TooSmall {
+            render_too_small(area, frame.buffer_mut());
+            return;
+        }
 
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -61,6 +79,18 @@ impl Editor {
         if self.mode == EditorMode::RenameInput {
             self.render_rename_input_popup(frame, area, palette);
         }
+        if self.mode == EditorMode::WillSaveConfirm {
+            self.render_will_save_confirm_popup(frame, area, palette);
+        }
+        if self.mode == EditorMode::CapabilitiesView {
+            self.render_capabilities_popup(frame, area, palette);
+        }
+        if self.mode == EditorMode::PeekView {
+            self.render_peek_popup(frame, area, palette);
+        }
+        if self.mode == EditorMode::CallHierarchy {
+            self.render_call_hierarchy_popup(frame, area, palette);
+        }
         if self.mode == EditorMode::Insert && !self.completion_items.is_empty() {
             self.render_completion_popover(frame, area, palette);
         }
@@ -76,8 +106,10 @@ impl Editor {
             } else {
                 Style::default().fg(palette.dim)
             };
+            // 固定标签页由数据层保证排在最前，这里只负责加上标记。
+            let marker = if tab.pinned { "◆" } else { "" };
             spans.push(Span::styled(
-                format!(" [{}:{}] ", idx + 1, tab.title),
+                format!(" [{}:{}{}] ", idx + 1, marker, tab.title),
                 style,
             ));
         }
@@ -113,7 +145,13 @@ impl Editor {
         for idx in self.tree_scroll..end {
             let item = &self.tree_entries[idx];
             let indent = "  ".repeat(item.depth);
-            let icon = if item.is_dir { "[D]" } else { "[F]" };
+            let icon = if item.more_count.is_some() {
+                "..."
+            } else if item.is_dir {
+                "[D]"
+            } else {
+                "[F]"
+            };
             let mut style = Style::default().fg(if item.is_dir {
                 palette.warn
             } else {
@@ -237,8 +275,25 @@ impl Editor {
         active_focus: PaneFocus,
         palette: ThemePalette,
     ) {
+        // 分屏或文件树占去大部分宽度时，单个窗格可能窄到放不下行号与边框。
+        if check_size(area, MIN_PANE_WIDTH, MIN_PANE_HEIGHT) == SizeCheck::TooSmall {
+            render_too_small(area, frame.buffer_mut());
+            return;
+        }
         let buffer_idx = self.tabs[self.active_tab].buffer_index;
         let focused = self.main_focus == MainFocus::Editor && pane == active_focus;
+        let visual_rows = self.visual_selection_rows();
+        let typewriter = self.typewriter_scrolling;
+        // 渲染每帧都会执行，这里只按路径直接查表，不做 canonicalize 兜底。
+        let minimap_markers = if self.config.minimap {
+            self.buffers[buffer_idx]
+                .path
+                .as_ref()
+                .and_then(|path| self.lsp_diagnostics_by_file.get(path))
+                .map(|items| diagnostic_markers(items))
+        } else {
+            None
+        };
 
         let buffer = &mut self.buffers[buffer_idx];
         buffer.ensure_cursor_in_bounds();
@@ -250,8 +305,16 @@ impl Editor {
             EditorMode::Terminal => "TERMINAL",
             EditorMode::BufferPicker => "BUFFER",
             EditorMode::RenameInput => "RENAME",
+            EditorMode::WillSaveConfirm => "CONFIRM",
+            EditorMode::CapabilitiesView => "CAPS",
+            EditorMode::PeekView => "PEEK",
+            EditorMode::CommandLine => "COMMAND",
+            EditorMode::CallHierarchy => "CALLS",
         };
         let mut title = format!(" {} [{}] ", buffer.name, mode_text);
+        if buffer.large_file {
+            title.push_str("[LARGE] ");
+        }
         if buffer.modified {
             title.push('*');
         }
@@ -264,20 +327,50 @@ impl Editor {
         if inner.width == 0 || inner.height == 0 {
             return;
         }
+        // 小地图占用最右侧一列；窗格窄到只剩行号栏时不显示。
+        let minimap_area = (self.config.minimap && inner.width as usize > GUTTER_WIDTH + 1)
+            .then(|| Rect::new(inner.x + inner.width - 1, inner.y, 1, inner.height));
+        let inner = if minimap_area.is_some() {
+            Rect::new(inner.x, inner.y, inner.width - 1, inner.height)
+        } else {
+            inner
+        };
 
         let visible = inner.height as usize;
-        if buffer.cursor_row < buffer.scroll_row {
-            buffer.scroll_row = buffer.cursor_row;
-        }
-        if buffer.cursor_row >= buffer.scroll_row + visible {
-            buffer.scroll_row = buffer.cursor_row.saturating_sub(visible.saturating_sub(1));
+        if typewriter {
+            buffer.scroll_row = centered_scroll_row(buffer.cursor_row, visible, buffer.lines.len());
+        }
+        // 软换行的正文宽度；窗格窄到放不下正文时退回到不换行显示。
+        let wrap_width = Some((inner.width as usize).saturating_sub(GUTTER_WIDTH))
+            .filter(|width| buffer.soft_wrap && *width > 0);
+        if let Some(width) = wrap_width {
+            buffer.scroll_row = wrapped_scroll_row(
+                &buffer.lines,
+                buffer.scroll_row,
+                buffer.cursor_row,
+                buffer.cursor_col,
+                width,
+                visible,
+            );
+        } else {
+            if buffer.cursor_row < buffer.scroll_row {
+                buffer.scroll_row = buffer.cursor_row;
+            }
+            if buffer.cursor_row >= buffer.scroll_row + visible {
+                buffer.scroll_row = buffer.cursor_row.saturating_sub(visible.saturating_sub(1));
+            }
         }
 
         let mut lines = Vec::new();
+        // 软换行时一个逻辑行可能占多行显示，最多也只需要 `visible` 个逻辑行。
         let end = min(buffer.lines.len(), buffer.scroll_row + visible);
-        let is_markdown = Self::is_markdown_buffer(buffer);
-        let lsp_language =
-            lsp::detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name);
+        // 大文件模式直接按纯文本渲染，跳过 Markdown 围栏回溯与逐行高亮。
+        let is_markdown = !buffer.large_file && Self::is_markdown_buffer(buffer);
+        let lsp_language = if buffer.large_file {
+            None
+        } else {
+            lsp::detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name)
+        };
         let use_semantic_highlight = Self::can_use_lsp_semantic_highlight(buffer);
         let mut markdown_fence_language = if is_markdown {
             Self::markdown_fence_language_before(buffer, buffer.scroll_row)
@@ -286,10 +379,15 @@ impl Editor {
         };
 
         for row in buffer.scroll_row..end {
-            let mut spans = vec![Span::styled(
-                format!("{:>4} ", row + 1),
-                Style::default().fg(palette.dim),
-            )];
+            // VISUAL 选区只高亮行号栏，保留正文的语法高亮，便于确认格式化范围。
+            let selected = visual_rows.is_some_and(|(start, end)| (start..=end).contains(&row));
+            let gutter_style = if selected {
+                Style::default().bg(palette.accent).fg(Color::Black)
+            } else {
+                Style::default().fg(palette.dim)
+            };
+            let gutter = Span::styled(format!("{:>4} ", row + 1), gutter_style);
+            let mut spans = Vec::new();
 
             let line = &buffer.lines[row];
 
@@ -307,8 +405,12 @@ impl Editor {
                     .get(&row)
                     .cloned()
                     .unwrap_or_default();
-                let mut highlighted =
-                    Self::highlight_line_with_lsp_tokens(line, &semantic_tokens, palette);
+                let mut highlighted = Self::highlight_line_with_lsp_tokens(
+                    line,
+                    &semantic_tokens,
+                    palette,
+                    &self.semantic_token_styles,
+                );
                 spans.append(&mut highlighted);
             } else if let Some(language) = lsp_language {
                 let mut highlighted = Self::highlight_line_with_syntect(line, language, palette);
@@ -317,21 +419,77 @@ impl Editor {
                 spans.push(Span::styled(line.clone(), Style::default().fg(palette.fg)));
             }
 
-            lines.push(Line::from(spans));
+            let Some(width) = wrap_width else {
+                spans.insert(0, gutter);
+                lines.push(Line::from(spans));
+                continue;
+            };
+            // 续行的行号栏留空，便于区分逻辑行与显示行。
+            for (segment, mut segment_spans) in split_spans(spans, &wrap_starts(line, width))
+                .into_iter()
+                .enumerate()
+            {
+                let gutter = if segment == 0 {
+                    gutter.clone()
+                } else {
+                    Span::styled(" ".repeat(GUTTER_WIDTH), gutter_style)
+                };
+                segment_spans.insert(0, gutter);
+                lines.push(Line::from(segment_spans));
+            }
+            if lines.len() >= visible {
+                break;
+            }
         }
 
         Paragraph::new(lines).render(inner, frame.buffer_mut());
 
+        if let Some(minimap_area) = minimap_area {
+            let cells = minimap_cells(
+                buffer.lines.len(),
+                minimap_area.height as usize,
+                (buffer.scroll_row, visible),
+                buffer.cursor_row,
+                minimap_markers.as_deref().unwrap_or_default(),
+            );
+            for (offset, cell) in cells.iter().enumerate() {
+                let (symbol, color) = if cell.cursor {
+                    ('◆', palette.accent)
+                } else {
+                    match cell.marker {
+                        Some(MinimapMarker::Error) => ('▬', Color::Red),
+                        Some(MinimapMarker::Warning) => ('▬', palette.warn),
+                        None if cell.in_viewport => ('┃', palette.fg),
+                        None => ('│', palette.dim),
+                    }
+                };
+                frame.buffer_mut()[(minimap_area.x, minimap_area.y + offset as u16)]
+                    .set_char(symbol)
+                    .set_fg(color);
+            }
+        }
+
         if focused {
             self.last_editor_inner_area = Some(inner);
 
-            let cursor_visible_row = buffer.cursor_row.saturating_sub(buffer.scroll_row);
+            let (cursor_visible_row, cursor_display_col) = match wrap_width {
+                Some(width) => logical_to_display(
+                    &buffer.lines,
+                    buffer.scroll_row,
+                    buffer.cursor_row,
+                    buffer.cursor_col,
+                    width,
+                ),
+                None => (
+                    buffer.cursor_row.saturating_sub(buffer.scroll_row),
+                    buffer.cursor_col,
+                ),
+            };
             if cursor_visible_row < visible {
-                // 5 列偏移：4 位行号 + 1 个空格。
                 let cursor_x = inner
                     .x
-                    .saturating_add(5)
-                    .saturating_add(buffer.cursor_col as u16);
+                    .saturating_add(GUTTER_WIDTH as u16)
+                    .saturating_add(cursor_display_col as u16);
                 let cursor_y = inner.y.saturating_add(cursor_visible_row as u16);
 
                 if cursor_x < inner.x.saturating_add(inner.width)
@@ -373,11 +531,14 @@ impl Editor {
     ///
     /// 条件：
     /// - 文件类型属于 LSP 支持的语言（Rust/Python/TypeScript/JavaScript等）；
-    /// - 已从 LSP 服务端获取到语义 token 数据。
+    /// - 已从 LSP 服务端获取到语义 token 数据；
+    /// - 用户未通过 `gs` 在该缓冲区关闭语义高亮。
     fn can_use_lsp_semantic_highlight(buffer: &EditorBuffer) -> bool {
         let is_supported_language =
             lsp::detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name).is_some();
-        is_supported_language && !buffer.lsp_tokens_by_line.is_empty()
+        is_supported_language
+            && !buffer.lsp_tokens_by_line.is_empty()
+            && !buffer.semantic_highlight_disabled
     }
 
     /// 将单行文本按 LSP 语义 token 进行着色。
@@ -390,6 +551,7 @@ impl Editor {
         line: &str,
         tokens: &[LspSemanticToken],
         palette: ThemePalette,
+        styles: &SemanticTokenStyles,
     ) -> Vec<Span<'static>> {
         if tokens.is_empty() {
             return vec![Span::styled(
@@ -424,7 +586,7 @@ impl Editor {
                 if token_start_byte <= token_end_byte && token_end_byte <= line.len() {
                     spans.push(Span::styled(
                         line[token_start_byte..token_end_byte].to_string(),
-                        Self::semantic_token_style(token, palette),
+                        Self::semantic_token_style(token, palette, styles),
                     ));
                 }
                 current_char = current_char.max(token_end);
@@ -453,8 +615,13 @@ impl Editor {
     /// 将语义 token 类型映射到主题色。
     ///
     /// 映射优先考虑“结构可读性”：关键字/函数/类型/变量保持稳定对比。
-    fn semantic_token_style(token: &LspSemanticToken, palette: ThemePalette) -> Style {
-        let mut style = match token.token_type.as_str() {
+    /// `.order/theme.json` 中配置的覆盖叠加在内置映射之上，修饰符标记最后再应用。
+    fn semantic_token_style(
+        token: &LspSemanticToken,
+        palette: ThemePalette,
+        styles: &SemanticTokenStyles,
+    ) -> Style {
+        let builtin = match token.token_type.as_str() {
             "keyword" => Style::default()
                 .fg(palette.accent)
                 .add_modifier(Modifier::BOLD),
@@ -475,6 +642,7 @@ impl Editor {
             "operator" => Style::default().fg(palette.accent),
             _ => Style::default().fg(palette.fg),
         };
+        let mut style = styles.apply(&token.token_type, builtin);
 
         if token
             .token_modifiers
@@ -864,6 +1032,13 @@ impl Editor {
     }
 
     pub(super) fn render_status(&self, frame: &mut Frame, area: Rect, palette: ThemePalette) {
+        // 命令行输入占用整条状态栏，与 Vim 的底部命令行一致。
+        if self.mode == EditorMode::CommandLine {
+            Paragraph::new(format!(":{}", self.command_input))
+                .style(Style::default().bg(palette.bg).fg(palette.fg))
+                .render(area, frame.buffer_mut());
+            return;
+        }
         let mode = match self.mode {
             EditorMode::Normal => "NORMAL",
             EditorMode::Insert => "INSERT",
@@ -871,8 +1046,15 @@ impl Editor {
             EditorMode::Terminal => "TERM",
             EditorMode::BufferPicker => "BUFFER",
             EditorMode::RenameInput => "RENAME",
+            EditorMode::WillSaveConfirm => "CONFIRM",
+            EditorMode::CapabilitiesView => "CAPS",
+            EditorMode::PeekView => "PEEK",
+            EditorMode::CommandLine => "COMMAND",
+            EditorMode::CallHierarchy => "CALLS",
         };
-        let lsp_indicator = if self.lsp_client.is_running() {
+        let lsp_indicator = if self.lsp_disabled {
+            " off"
+        } else if self.lsp_client.is_running() {
             "●"
         } else {
             "○"
@@ -887,9 +1069,14 @@ impl Editor {
         } else {
             format!(" [{}]", self.lsp_loading_status)
         };
+        let large_file = if self.active_buffer().large_file {
+            " [大文件模式]"
+        } else {
+            ""
+        };
         let text = format!(
-            " {}{}  LSP{}{}  {}",
-            mode, pending, lsp_indicator, loading, self.status_message
+            " {}{}{}  LSP{}{}  {}",
+            mode, pending, large_file, lsp_indicator, loading, self.status_message
         );
         Paragraph::new(text)
             .style(Style::default().bg(palette.bg).fg(palette.ok))
@@ -984,6 +1171,343 @@ impl Editor {
             .render(popup, frame.buffer_mut());
     }
 
+    /// 渲染 `willSaveWaitUntil` 编辑确认弹窗。
+    pub(super) fn render_will_save_confirm_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        palette: ThemePalette,
+    ) {
+        let Some(pending) = self.pending_will_save.as_ref() else {
+            return;
+        };
+
+        let width = min(72, area.width.saturating_sub(4));
+        let height = 7;
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        Clear.render(popup, frame.buffer_mut());
+
+        let file_name = pending
+            .file_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("<unknown>");
+        let language = pending
+            .language
+            .map(|language| language.display_name())
+            .unwrap_or("未知语言");
+        let lines = vec![
+            Line::from(Span::styled(
+                format!("{language} LSP 请求在保存时修改 {file_name}"),
+                Style::default()
+                    .fg(palette.accent)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled(
+                summarize_text_edits(&pending.edits),
+                Style::default().fg(palette.fg),
+            )),
+            Line::from(Span::styled(
+                "y/Enter 应用，n/Esc 放弃",
+                Style::default().fg(palette.dim),
+            )),
+            Line::from(Span::styled(
+                "a 该语言总是应用，s 该语言总是跳过",
+                Style::default().fg(palette.dim),
+            )),
+        ];
+
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title(" willSaveWaitUntil ")
+                    .border_style(Style::default().fg(palette.accent)),
+            )
+            .render(popup, frame.buffer_mut());
+    }
+
+    /// 渲染 LSP 能力面板：左列能力名，右列取值，未支持的能力弱化显示。
+    pub(super) fn render_capabilities_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        palette: ThemePalette,
+    ) {
+        let Some((language, entries)) = self.capabilities_view.as_ref() else {
+            return;
+        };
+
+        let width = min(72, area.width.saturating_sub(4));
+        let height = min(entries.len() as u16 + 4, area.height.saturating_sub(2));
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        if check_size(popup, MIN_POPOVER_WIDTH, MIN_POPOVER_HEIGHT) == SizeCheck::TooSmall {
+            return;
+        }
+        Clear.render(popup, frame.buffer_mut());
+
+        let label_width = entries
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = entries
+            .iter()
+            .map(|(label, value)| {
+                let value_style = if value == "no" || value == "none" || value == "-" {
+                    Style::default().fg(palette.dim)
+                } else {
+                    Style::default().fg(palette.fg)
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{label:<label_width$}  "),
+                        Style::default().fg(palette.accent),
+                    ),
+                    Span::styled(value.clone(), value_style),
+                ])
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(Span::styled(
+            "Esc/q/Enter 关闭",
+            Style::default().fg(palette.dim),
+        )));
+
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title(format!(" {} LSP capabilities ", language.display_name()))
+                    .border_style(Style::default().fg(palette.accent)),
+            )
+            .render(popup, frame.buffer_mut());
+    }
+
+    /// 渲染调用层级浮层：按深度缩进，`▸` 未展开、`▾` 已展开、`…` 加载中，选中行反色。
+    pub(super) fn render_call_hierarchy_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        palette: ThemePalette,
+    ) {
+        let Some(view) = self.call_hierarchy_view.as_ref() else {
+            return;
+        };
+
+        let width = min(90, area.width.saturating_sub(4));
+        let height = min(20, area.height.saturating_sub(4));
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        if check_size(popup, MIN_POPOVER_WIDTH, MIN_POPOVER_HEIGHT) == SizeCheck::TooSmall {
+            return;
+        }
+        Clear.render(popup, frame.buffer_mut());
+
+        // 选中行始终保持在可见范围内。
+        let visible_rows = popup.height.saturating_sub(2).max(1) as usize;
+        let scroll = view.selected.saturating_sub(visible_rows - 1);
+        let lines = view
+            .entries
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(visible_rows)
+            .map(|(index, entry)| {
+                let marker = if entry.loading {
+                    "…"
+                } else if entry.expanded {
+                    "▾"
+                } else {
+                    "▸"
+                };
+                let location = entry
+                    .item
+                    .file_path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&entry.item.file_path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let name_style = if index == view.selected {
+                    Style::default()
+                        .fg(palette.bg)
+                        .bg(palette.accent)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(palette.fg)
+                };
+                Line::from(vec![
+                    Span::raw("  ".repeat(entry.depth)),
+                    Span::styled(format!("{marker} "), Style::default().fg(palette.warn)),
+                    Span::styled(entry.item.name.clone(), name_style),
+                    Span::styled(
+                        format!("  {location}:{}", entry.item.line + 1),
+                        Style::default().fg(palette.dim),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let direction = match view.direction {
+            LspCallDirection::Incoming => "调用者 incoming",
+            LspCallDirection::Outgoing => "被调用者 outgoing",
+        };
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title(format!(" 调用层级：{direction} "))
+                    .title_bottom(
+                        " j/k 移动 · l 展开 · h 折叠 · t 切换方向 · Enter 跳转 · Esc 关闭 ",
+                    )
+                    .border_style(Style::default().fg(palette.accent)),
+            )
+            .render(popup, frame.buffer_mut());
+    }
+
+    /// 渲染定义 peek 浮层：放在光标行下方（下方空间不足时放到上方），定义行高亮。
+    ///
+    /// 浮层只覆盖编辑区的一部分，用户仍能看到光标所在的上下文。
+    // 光标在编辑区内的显示位置 `(相对行, 列)`；软换行时按折行计算，与主光标的落点一致。
+    fn cursor_display_position(&self, editor_inner: Rect) -> (usize, usize) {
+        let buffer = self.active_buffer();
+        let wrap_width = Some((editor_inner.width as usize).saturating_sub(GUTTER_WIDTH))
+            .filter(|width| buffer.soft_wrap && *width > 0);
+        match wrap_width {
+            Some(width) => logical_to_display(
+                &buffer.lines,
+                buffer.scroll_row,
+                buffer.cursor_row,
+                buffer.cursor_col,
+                width,
+            ),
+            None => (
+                buffer.cursor_row.saturating_sub(buffer.scroll_row),
+                buffer.cursor_col,
+            ),
+        }
+    }
+
+    pub(super) fn render_peek_popup(&self, frame: &mut Frame, area: Rect, palette: ThemePalette) {
+        let Some(peek) = self.peek_view.as_ref() else {
+            return;
+        };
+
+        let editor_inner = self.last_editor_inner_area.unwrap_or(area);
+        let (cursor_row, _) = self.cursor_display_position(editor_inner);
+        let cursor_y = editor_inner.y.saturating_add(cursor_row as u16);
+        let height = min(
+            peek.lines.len().min(PEEK_VISIBLE_ROWS) as u16 + 2,
+            editor_inner.height,
+        );
+        let below = cursor_y.saturating_add(1);
+        let y = if below.saturating_add(height) <= editor_inner.bottom() {
+            below
+        } else {
+            cursor_y.saturating_sub(height).max(editor_inner.y)
+        };
+        let popup = Rect {
+            x: editor_inner.x,
+            y,
+            width: editor_inner.width,
+            height,
+        }
+        .intersection(editor_inner);
+        if check_size(popup, MIN_POPOVER_WIDTH, MIN_POPOVER_HEIGHT) == SizeCheck::TooSmall {
+            return;
+        }
+        Clear.render(popup, frame.buffer_mut());
+
+        let number_width = (peek.start_line + peek.lines.len()).to_string().len();
+        let lines = peek
+            .lines
+            .iter()
+            .enumerate()
+            .skip(peek.scroll)
+            .take(PEEK_VISIBLE_ROWS)
+            .map(|(offset, text)| {
+                let line_number = peek.start_line + offset;
+                let text_style = if line_number == peek.target_line {
+                    Style::default()
+                        .fg(palette.fg)
+                        .bg(palette.bg)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(palette.fg)
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>number_width$} ", line_number + 1),
+                        Style::default().fg(palette.dim),
+                    ),
+                    Span::styled(text.clone(), text_style),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .title(format!(" {} ", peek.title))
+                    .title_bottom(" j/k 滚动 · Esc 关闭 ")
+                    .border_style(Style::default().fg(palette.accent)),
+            )
+            .render(popup, frame.buffer_mut());
+    }
+
+    /// 补全项主文本的样式：选中项高亮，已弃用项加删除线。
+    ///
+    /// 弃用项未选中时额外使用弱化色，浏览列表时一眼就能避开；
+    /// 选中后保留高亮底色以便看清当前位置，只靠删除线提示弃用。
+    fn completion_label_style(
+        item: &CompletionDisplayItem,
+        is_selected: bool,
+        palette: ThemePalette,
+    ) -> Style {
+        let style = if is_selected {
+            Style::default()
+                .bg(palette.accent)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        } else if item.deprecated {
+            Style::default().fg(palette.dim)
+        } else {
+            Style::default().fg(palette.fg)
+        };
+        if item.deprecated {
+            style.add_modifier(Modifier::CROSSED_OUT)
+        } else {
+            style
+        }
+    }
+
+    /// 计算补全项的展示文本：`(主文本, 弱化后缀)`。
+    ///
+    /// 服务端提供 `labelDetails` 时按 LSP 3.17 约定展示：
+    /// `label` 紧接 `labelDetails.detail`，`labelDetails.description` 弱化显示；
+    /// 否则回退到旧的 `detail` 作为弱化后缀。
+    fn completion_display_parts(item: &CompletionDisplayItem) -> (String, Option<String>) {
+        if item.label_detail.is_some() || item.label_description.is_some() {
+            let main = format!(
+                "{}{}",
+                item.label,
+                item.label_detail.as_deref().unwrap_or_default()
+            );
+            return (main, item.label_description.clone());
+        }
+        (item.label.clone(), item.detail.clone())
+    }
+
     /// 渲染补全候选列表 popover。
     ///
     /// 在 INSERT 模式下，当 LSP 返回补全候选时显示浮动列表，
@@ -1001,32 +1525,38 @@ impl Editor {
 
         const COMPLETION_VISIBLE_COUNT: usize = 7;
 
-        let buffer = self.active_buffer();
-        let cursor_row = buffer.cursor_row.saturating_sub(buffer.scroll_row);
-        let cursor_col = buffer.cursor_col;
-
         let max_width = 42u16;
         let total_items = self.completion_items.len();
         let visible_count = COMPLETION_VISIBLE_COUNT.min(total_items);
         let max_height = (visible_count as u16).saturating_add(2);
 
         let editor_inner = self.last_editor_inner_area.unwrap_or(area);
+        let (cursor_row, cursor_col) = self.cursor_display_position(editor_inner);
 
         let popup_x = editor_inner
             .x
             .saturating_add(5)
             .saturating_add(cursor_col as u16);
-        let popup_y = editor_inner
-            .y
-            .saturating_add(cursor_row as u16)
-            .saturating_add(1);
+        let cursor_y = editor_inner.y.saturating_add(cursor_row as u16);
+        let popup_y = completion_popup_y(
+            self.config.completion_placement,
+            cursor_y,
+            editor_inner,
+            max_height,
+        );
 
+        // 浮层宽高固定，需裁剪到编辑区内，否则窄终端下会写出缓冲区边界；
+        // 裁剪后仍放不下一行候选时直接不显示，补全仍可通过 Tab 接受。
         let popup = Rect {
             x: popup_x.min(editor_inner.right().saturating_sub(max_width)),
-            y: popup_y.min(editor_inner.bottom().saturating_sub(max_height)),
+            y: popup_y,
             width: max_width,
             height: max_height,
-        };
+        }
+        .intersection(editor_inner);
+        if check_size(popup, MIN_POPOVER_WIDTH, MIN_POPOVER_HEIGHT) == SizeCheck::TooSmall {
+            return;
+        }
 
         Clear.render(popup, frame.buffer_mut());
 
@@ -1038,24 +1568,18 @@ impl Editor {
             let item = &self.completion_items[idx];
             let is_selected = idx == self.completion_selected;
 
-            let style = if is_selected {
-                Style::default()
-                    .bg(palette.accent)
-                    .fg(Color::Black)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(palette.fg)
-            };
+            let style = Self::completion_label_style(item, is_selected, palette);
 
-            let label = if item.label.len() > 28 {
-                format!("{}...", &item.label[..25])
+            let (main_text, dim_text) = Self::completion_display_parts(item);
+            let label = if main_text.chars().count() > 28 {
+                format!("{}...", main_text.chars().take(25).collect::<String>())
             } else {
-                item.label.clone()
+                main_text
             };
 
-            let detail_suffix = item.detail.as_ref().map(|d| {
-                let truncated = if d.len() > 12 {
-                    format!(" {}", &d[..9])
+            let detail_suffix = dim_text.map(|d| {
+                let truncated = if d.chars().count() > 12 {
+                    format!(" {}", d.chars().take(9).collect::<String>())
                 } else {
                     format!(" {}", d)
                 };
@@ -1439,6 +1963,40 @@ impl Editor {
     }
 }
 
+/// 计算补全弹窗的顶部行坐标。
+///
+/// `Auto` 优先放在光标下方，下方放不下而上方放得下时翻到上方；
+/// 两侧都放不下时选择空间更大的一侧。翻转时列表顺序保持不变，
+/// 这样上下键与滚动条的含义在两种位置下一致。
+/// 结果最终限制在编辑区内，强制某一侧且空间不足时允许覆盖光标行。
+fn completion_popup_y(
+    placement: CompletionPlacement,
+    cursor_y: u16,
+    editor_inner: Rect,
+    popup_height: u16,
+) -> u16 {
+    let space_below = editor_inner
+        .bottom()
+        .saturating_sub(cursor_y.saturating_add(1));
+    let space_above = cursor_y.saturating_sub(editor_inner.y);
+    let place_above = match placement {
+        CompletionPlacement::Above => true,
+        CompletionPlacement::Below => false,
+        CompletionPlacement::Auto => {
+            space_below < popup_height && (space_above >= popup_height || space_above > space_below)
+        }
+    };
+
+    let popup_y = if place_above {
+        cursor_y.saturating_sub(popup_height)
+    } else {
+        cursor_y.saturating_add(1)
+    };
+    popup_y
+        .max(editor_inner.y)
+        .min(editor_inner.bottom().saturating_sub(popup_height))
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::types::ThemeName;
@@ -1458,7 +2016,8 @@ mod tests {
             token_type: "keyword".to_string(),
             token_modifiers: vec![],
         };
-        let style = Editor::semantic_token_style(&token, test_palette());
+        let style =
+            Editor::semantic_token_style(&token, test_palette(), &SemanticTokenStyles::default());
         assert!(style.fg.is_some());
     }
 
@@ -1471,7 +2030,8 @@ mod tests {
             token_type: "function".to_string(),
             token_modifiers: vec![],
         };
-        let style = Editor::semantic_token_style(&token, test_palette());
+        let style =
+            Editor::semantic_token_style(&token, test_palette(), &SemanticTokenStyles::default());
         assert!(style.fg.is_some());
     }
 
@@ -1484,7 +2044,8 @@ mod tests {
             token_type: "struct".to_string(),
             token_modifiers: vec![],
         };
-        let style = Editor::semantic_token_style(&token, test_palette());
+        let style =
+            Editor::semantic_token_style(&token, test_palette(), &SemanticTokenStyles::default());
         assert!(style.fg.is_some());
     }
 
@@ -1497,7 +2058,8 @@ mod tests {
             token_type: "function".to_string(),
             token_modifiers: vec!["deprecated".to_string()],
         };
-        let style = Editor::semantic_token_style(&token, test_palette());
+        let style =
+            Editor::semantic_token_style(&token, test_palette(), &SemanticTokenStyles::default());
         assert!(style.add_modifier.contains(Modifier::CROSSED_OUT));
     }
 
@@ -1510,7 +2072,8 @@ mod tests {
             token_type: "property".to_string(),
             token_modifiers: vec!["readonly".to_string()],
         };
-        let style = Editor::semantic_token_style(&token, test_palette());
+        let style =
+            Editor::semantic_token_style(&token, test_palette(), &SemanticTokenStyles::default());
         assert!(style.add_modifier.contains(Modifier::UNDERLINED));
     }
 
@@ -1518,7 +2081,12 @@ mod tests {
     fn test_highlight_line_with_lsp_tokens_empty() {
         let line = "fn main() {}";
         let tokens: Vec<LspSemanticToken> = vec![];
-        let spans = Editor::highlight_line_with_lsp_tokens(line, &tokens, test_palette());
+        let spans = Editor::highlight_line_with_lsp_tokens(
+            line,
+            &tokens,
+            test_palette(),
+            &SemanticTokenStyles::default(),
+        );
         assert_eq!(spans.len(), 1);
         assert_eq!(spans[0].content, line);
     }
@@ -1533,7 +2101,12 @@ mod tests {
             token_type: "keyword".to_string(),
             token_modifiers: vec![],
         }];
-        let spans = Editor::highlight_line_with_lsp_tokens(line, &tokens, test_palette());
+        let spans = Editor::highlight_line_with_lsp_tokens(
+            line,
+            &tokens,
+            test_palette(),
+            &SemanticTokenStyles::default(),
+        );
         assert!(spans.len() >= 2);
         assert_eq!(spans[0].content, "fn");
     }
@@ -1557,7 +2130,12 @@ mod tests {
                 token_modifiers: vec![],
             },
         ];
-        let spans = Editor::highlight_line_with_lsp_tokens(line, &tokens, test_palette());
+        let spans = Editor::highlight_line_with_lsp_tokens(
+            line,
+            &tokens,
+            test_palette(),
+            &SemanticTokenStyles::default(),
+        );
         assert!(spans.len() >= 3);
     }
 
@@ -1571,7 +2149,12 @@ mod tests {
             token_type: "keyword".to_string(),
             token_modifiers: vec![],
         }];
-        let spans = Editor::highlight_line_with_lsp_tokens(line, &tokens, test_palette());
+        let spans = Editor::highlight_line_with_lsp_tokens(
+            line,
+            &tokens,
+            test_palette(),
+            &SemanticTokenStyles::default(),
+        );
         assert!(!spans.is_empty());
         let combined: String = spans.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(combined, line);
@@ -1592,6 +2175,72 @@ mod tests {
         assert!(!Editor::can_use_lsp_semantic_highlight(&buffer));
     }
 
+    #[test]
+    fn test_completion_display_parts_prefers_label_details_over_detail() {
+        let mut item = CompletionDisplayItem {
+            label: "push".to_string(),
+            insert_text: "push".to_string(),
+            detail: Some("fn(&mut self, T)".to_string()),
+            label_detail: Some("(value: T)".to_string()),
+            label_description: Some("Vec<T>".to_string()),
+            deprecated: false,
+        };
+        assert_eq!(
+            Editor::completion_display_parts(&item),
+            ("push(value: T)".to_string(), Some("Vec<T>".to_string()))
+        );
+
+        item.label_detail = None;
+        item.label_description = None;
+        assert_eq!(
+            Editor::completion_display_parts(&item),
+            ("push".to_string(), Some("fn(&mut self, T)".to_string())),
+            "缺少 labelDetails 时应回退 detail"
+        );
+    }
+
+    #[test]
+    fn test_completion_label_style_marks_deprecated_items() {
+        let palette = test_palette();
+        let mut item = CompletionDisplayItem {
+            label: "old_api".to_string(),
+            insert_text: "old_api".to_string(),
+            detail: None,
+            label_detail: None,
+            label_description: None,
+            deprecated: false,
+        };
+        let normal = Editor::completion_label_style(&item, false, palette);
+        assert!(!normal.add_modifier.contains(Modifier::CROSSED_OUT));
+        assert_eq!(normal.fg, Some(palette.fg));
+
+        item.deprecated = true;
+        let deprecated = Editor::completion_label_style(&item, false, palette);
+        assert!(deprecated.add_modifier.contains(Modifier::CROSSED_OUT));
+        assert_eq!(deprecated.fg, Some(palette.dim), "未选中的弃用项应弱化显示");
+
+        let selected = Editor::completion_label_style(&item, true, palette);
+        assert!(selected.add_modifier.contains(Modifier::CROSSED_OUT));
+        assert_eq!(selected.bg, Some(palette.accent), "选中时保留高亮底色");
+    }
+
+    #[test]
+    fn test_can_use_lsp_semantic_highlight_respects_buffer_toggle() {
+        let mut buffer = EditorBuffer::new_empty("test.rs".to_string());
+        buffer.path = Some(std::path::PathBuf::from("/test.rs"));
+        buffer.lsp_tokens_by_line.insert(0, vec![]);
+
+        buffer.semantic_highlight_disabled = true;
+        assert!(!Editor::can_use_lsp_semantic_highlight(&buffer));
+        assert!(
+            !buffer.lsp_tokens_by_line.is_empty(),
+            "关闭语义高亮不应清空已缓存 token"
+        );
+
+        buffer.semantic_highlight_disabled = false;
+        assert!(Editor::can_use_lsp_semantic_highlight(&buffer));
+    }
+
     #[test]
     fn test_can_use_lsp_semantic_highlight_unsupported_language() {
         let mut buffer = EditorBuffer::new_empty("test.xyz".to_string());
@@ -1618,4 +2267,69 @@ mod tests {
         let buffer = EditorBuffer::new_empty("main.rs".to_string());
         assert!(!Editor::is_markdown_buffer(&buffer));
     }
+
+    #[test]
+    fn test_cursor_display_position_follows_soft_wrap() {
+        let mut editor = Editor::new(std::path::PathBuf::from("."));
+        let buffer = editor.active_buffer_mut();
+        buffer.lines = vec!["abcdefghij".to_string(), "xy".to_string()];
+        buffer.cursor_row = 1;
+        buffer.cursor_col = 1;
+        // 正文宽度 = 9 - GUTTER_WIDTH = 4，第一行折成 3 段。
+        let inner = Rect::new(0, 0, 9, 10);
+
+        assert_eq!(editor.cursor_display_position(inner), (1, 1));
+
+        editor.active_buffer_mut().soft_wrap = true;
+        assert_eq!(
+            editor.cursor_display_position(inner),
+            (3, 1),
+            "软换行时浮层应锚定到折行后的光标行"
+        );
+
+        let buffer = editor.active_buffer_mut();
+        buffer.cursor_row = 0;
+        buffer.cursor_col = 6;
+        assert_eq!(editor.cursor_display_position(inner), (1, 2));
+    }
+
+    #[test]
+    fn test_completion_popup_y_auto_flips_above_near_bottom() {
+        let inner = Rect::new(0, 1, 80, 20);
+        // 光标靠近顶部：下方空间充足，放在光标下一行。
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Auto, 3, inner, 9),
+            4
+        );
+        // 光标靠近底部：下方放不下，翻到光标上方且底边紧贴光标行。
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Auto, 18, inner, 9),
+            9
+        );
+    }
+
+    #[test]
+    fn test_completion_popup_y_respects_forced_side_and_clamps() {
+        let inner = Rect::new(0, 1, 80, 20);
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Above, 15, inner, 9),
+            6
+        );
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Below, 15, inner, 9),
+            12,
+            "强制放在下方但空间不足时应限制在编辑区内"
+        );
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Above, 3, inner, 9),
+            1,
+            "强制放在上方但空间不足时不应越过编辑区顶部"
+        );
+        // 两侧都放不下时，Auto 选择空间更大的一侧。
+        let small = Rect::new(0, 0, 80, 10);
+        assert_eq!(
+            completion_popup_y(CompletionPlacement::Auto, 6, small, 9),
+            0
+        );
+    }
 }
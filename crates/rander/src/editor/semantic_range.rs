@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use lsp::LspSemanticToken;
+
+/// 可见区域上下额外请求的行数，滚动少量行时无需立刻重新请求。
+pub(super) const SEMANTIC_RANGE_MARGIN: usize = 50;
+
+/// 计算需要请求语义 token 的行范围 `[start, end)`：可见区域加上下边距。
+pub(super) fn visible_token_range(
+    scroll_row: usize,
+    visible_rows: usize,
+    total_lines: usize,
+    margin: usize,
+) -> (usize, usize) {
+    let start = scroll_row.saturating_sub(margin);
+    let end = scroll_row
+        .saturating_add(visible_rows)
+        .saturating_add(margin)
+        .min(total_lines);
+    (start.min(end), end)
+}
+
+/// 判断已请求的范围是否完整覆盖当前所需范围。
+pub(super) fn range_covers(requested: (usize, usize), needed: (usize, usize)) -> bool {
+    requested.0 <= needed.0 && requested.1 >= needed.1
+}
+
+/// 将范围响应合并进缓冲区的 token 缓存。
+///
+/// 先清掉 `[start, end)` 内的旧 token，再写入新 token；范围外的行保持不变，
+/// 这样来回滚动时已高亮过的区域不会闪回无高亮状态。
+/// 服务端可能返回范围外的 token，这里一并丢弃，避免覆盖不属于本次请求的行。
+pub(super) fn merge_ranged_tokens(
+    all_tokens: &mut Vec<LspSemanticToken>,
+    tokens_by_line: &mut HashMap<usize, Vec<LspSemanticToken>>,
+    start_line: usize,
+    end_line: usize,
+    tokens: Vec<LspSemanticToken>,
+) {
+    let in_range = |line: usize| line >= start_line && line < end_line;
+    all_tokens.retain(|token| !in_range(token.line));
+    tokens_by_line.retain(|line, _| !in_range(*line));
+
+    for token in tokens.into_iter().filter(|token| in_range(token.line)) {
+        tokens_by_line
+            .entry(token.line)
+            .or_default()
+            .push(token.clone());
+        all_tokens.push(token);
+    }
+    for line in start_line..end_line {
+        if let Some(grouped) = tokens_by_line.get_mut(&line) {
+            grouped.sort_by_key(|item| item.start);
+        }
+    }
+    all_tokens.sort_by_key(|token| (token.line, token.start));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lsp::LspSemanticToken;
+
+    use super::{merge_ranged_tokens, range_covers, visible_token_range};
+
+    fn token(line: usize, start: usize, token_type: &str) -> LspSemanticToken {
+        LspSemanticToken {
+            line,
+            start,
+            length: 1,
+            token_type: token_type.to_string(),
+            token_modifiers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_visible_token_range_adds_margin_and_clamps() {
+        assert_eq!(visible_token_range(100, 30, 1000, 50), (50, 180));
+        assert_eq!(visible_token_range(10, 30, 1000, 50), (0, 90));
+        assert_eq!(visible_token_range(980, 30, 1000, 50), (930, 1000));
+        assert_eq!(visible_token_range(0, 30, 0, 50), (0, 0));
+
+        assert!(range_covers((50, 180), (60, 170)));
+        assert!(
+            !range_covers((50, 180), (60, 200)),
+            "滚出已请求范围时需要重新请求"
+        );
+    }
+
+    #[test]
+    fn test_merge_ranged_tokens_replaces_only_lines_in_range() {
+        let mut all_tokens = vec![
+            token(1, 0, "keyword"),
+            token(5, 2, "variable"),
+            token(9, 0, "function"),
+        ];
+        let mut tokens_by_line = HashMap::new();
+        for item in &all_tokens {
+            tokens_by_line
+                .entry(item.line)
+                .or_insert_with(Vec::new)
+                .push(item.clone());
+        }
+
+        merge_ranged_tokens(
+            &mut all_tokens,
+            &mut tokens_by_line,
+            4,
+            8,
+            vec![
+                token(6, 8, "type"),
+                token(6, 1, "keyword"),
+                token(12, 0, "macro"),
+            ],
+        );
+
+        assert!(
+            !tokens_by_line.contains_key(&5),
+            "范围内的旧 token 应被清除"
+        );
+        assert_eq!(
+            tokens_by_line[&6]
+                .iter()
+                .map(|item| item.start)
+                .collect::<Vec<_>>(),
+            vec![1, 8],
+            "同一行的 token 应按起始列排序"
+        );
+        assert_eq!(tokens_by_line[&1][0].token_type, "keyword");
+        assert_eq!(tokens_by_line[&9][0].token_type, "function");
+        assert!(
+            !tokens_by_line.contains_key(&12),
+            "范围外的返回 token 不应写入缓存"
+        );
+        assert_eq!(
+            all_tokens
+                .iter()
+                .map(|item| (item.line, item.start))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (6, 1), (6, 8), (9, 0)]
+        );
+    }
+}
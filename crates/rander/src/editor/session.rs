@@ -1,7 +1,8 @@
-use std::{cmp::min, fs, path::PathBuf};
+use std::{cmp::min, fs, path::PathBuf, time::Instant};
 
 use super::{
     Editor, MAX_TREE_RATIO, MIN_TREE_RATIO, SESSION_FILE,
+    tabs::sort_pinned_first,
     types::{EditorBuffer, PaneFocus, SplitDirection, TabState, ThemeName},
     utils::{escape_text, pane_to_str, parse_pane, parse_split, split_to_str, unescape_text},
 };
@@ -18,11 +19,12 @@ impl Editor {
 
         for tab in &self.tabs {
             lines.push(format!(
-                "TAB\t{}\t{}\t{}\t{}",
+                "TAB\t{}\t{}\t{}\t{}\t{}",
                 escape_text(&tab.title),
                 tab.buffer_index,
                 split_to_str(tab.split),
-                pane_to_str(tab.focus)
+                pane_to_str(tab.focus),
+                tab.pinned as u8
             ));
         }
 
@@ -104,6 +106,9 @@ impl Editor {
                         buffer_index: parts[2].parse::<usize>().unwrap_or(0),
                         split: parse_split(parts[3]),
                         focus: parse_pane(parts[4]),
+                        // 固定标记是后加的第 6 列，旧会话文件缺省为未固定。
+                        pinned: parts.get(5).is_some_and(|value| *value == "1"),
+                        last_focused: Instant::now(),
                     });
                 }
                 "BUF" if parts.len() >= 5 => {
@@ -116,7 +121,10 @@ impl Editor {
                         EditorBuffer::new_empty(name.clone())
                     } else {
                         let file_path = PathBuf::from(path_value.clone());
-                        match EditorBuffer::from_file(&file_path) {
+                        match EditorBuffer::from_file(
+                            &file_path,
+                            self.config.large_file_threshold(),
+                        ) {
                             Ok(mut loaded) => {
                                 loaded.name = name.clone();
                                 loaded
@@ -157,6 +165,8 @@ impl Editor {
                 buffer_index: 0,
                 split: SplitDirection::None,
                 focus: PaneFocus::Primary,
+                pinned: false,
+                last_focused: Instant::now(),
             });
         }
         for tab in &mut tabs {
@@ -169,8 +179,9 @@ impl Editor {
         self.show_tree = show_tree;
         self.theme = theme;
         self.buffers = buffers;
+        let active_tab = min(active_tab, tabs.len().saturating_sub(1));
+        self.active_tab = sort_pinned_first(&mut tabs, active_tab);
         self.tabs = tabs;
-        self.active_tab = min(active_tab, self.tabs.len().saturating_sub(1));
         self.status_message = format!("会话已加载: {}", session_path.display());
     }
 
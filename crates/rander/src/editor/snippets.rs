@@ -0,0 +1,427 @@
+use std::{collections::HashMap, path::Path};
+
+use core::settings::load_settings;
+use lsp::LspLanguage;
+use serde::Deserialize;
+
+use super::{
+    Editor,
+    types::EditorBuffer,
+    utils::{char_count, char_to_byte_index},
+};
+
+/// 单条片段正文：可写成一个字符串，也可按行写成字符串数组。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum SnippetBody {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl SnippetBody {
+    // 统一展开为以 `\n` 分隔的正文。
+    fn into_text(self) -> String {
+        match self {
+            Self::Text(text) => text,
+            Self::Lines(lines) => lines.join("\n"),
+        }
+    }
+}
+
+/// 读取 `.order/snippets/<languageId>.json`，返回触发词到片段正文的映射。
+///
+/// 与 `editor.json` 一样按用户全局 / 项目两层合并，同名触发词以项目为准；
+/// 每次展开时读取，修改片段文件后无需重启编辑器。
+pub(super) fn load_snippets(
+    root: &Path,
+    language: LspLanguage,
+) -> Result<HashMap<String, String>, String> {
+    let file_name = format!("snippets/{}.json", language.language_id());
+    let snippets: HashMap<String, SnippetBody> =
+        load_settings(root, &file_name, &[]).map_err(|error| format!("加载片段失败: {error:#}"))?;
+    Ok(snippets
+        .into_iter()
+        .map(|(trigger, body)| (trigger, body.into_text()))
+        .collect())
+}
+
+/// 片段中的一个 tab stop：所在行、列（按字符计）与占位文本长度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TabStop {
+    pub(super) line: usize,
+    pub(super) col: usize,
+    pub(super) len: usize,
+}
+
+/// 解析后的片段：去掉 `$` 标记后的正文行与按跳转顺序排列的 tab stop。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ParsedSnippet {
+    pub(super) lines: Vec<String>,
+    pub(super) stops: Vec<TabStop>,
+}
+
+/// 解析片段正文中的 `$1`、`${1}` 与 `${1:占位}`。
+///
+/// 跳转顺序为 1、2、3……，`$0` 作为最终位置放在最后；没有 `$0` 时最终停在正文末尾。
+/// 同一编号重复出现时只取第一次；`\$`、`\}`、`\\` 用于输入字面字符。
+pub(super) fn parse_snippet(body: &str) -> ParsedSnippet {
+    let mut lines = vec![String::new()];
+    let mut numbered: Vec<(usize, TabStop)> = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let line = lines.len() - 1;
+        match ch {
+            '\\' if matches!(chars.peek(), Some('$' | '}' | '\\')) => {
+                lines[line].extend(chars.next());
+            }
+            '\n' => lines.push(String::new()),
+            '$' => {
+                let braced = chars.next_if_eq(&'{').is_some();
+                let mut digits = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(digit);
+                }
+                let Ok(number) = digits.parse::<usize>() else {
+                    // 不是 tab stop（如 `$x`），按原样保留。
+                    lines[line].push('$');
+                    if braced {
+                        lines[line].push('{');
+                    }
+                    continue;
+                };
+                let mut placeholder = String::new();
+                if braced {
+                    if chars.next_if_eq(&':').is_some() {
+                        while let Some(inner) = chars.next_if(|inner| *inner != '}') {
+                            if inner == '\\' {
+                                placeholder.extend(chars.next());
+                            } else {
+                                placeholder.push(inner);
+                            }
+                        }
+                    }
+                    chars.next_if_eq(&'}');
+                }
+                let stop = TabStop {
+                    line,
+                    col: char_count(&lines[line]),
+                    len: char_count(&placeholder),
+                };
+                lines[line].push_str(&placeholder);
+                if !numbered.iter().any(|(existing, _)| *existing == number) {
+                    numbered.push((number, stop));
+                }
+            }
+            _ => lines[line].push(ch),
+        }
+    }
+
+    // `$0` 排在最后，其余按编号升序。
+    let has_final = numbered.iter().any(|(number, _)| *number == 0);
+    numbered.sort_by_key(|(number, _)| (*number == 0, *number));
+    let mut stops = numbered
+        .into_iter()
+        .map(|(_, stop)| stop)
+        .collect::<Vec<_>>();
+    if !has_final {
+        let line = lines.len() - 1;
+        stops.push(TabStop {
+            line,
+            col: char_count(&lines[line]),
+            len: 0,
+        });
+    }
+    ParsedSnippet { lines, stops }
+}
+
+/// 光标前的触发词：取光标左侧连续的字母、数字与下划线，返回 (起始列, 触发词)。
+pub(super) fn trigger_before_cursor(line: &str, col: usize) -> Option<(usize, String)> {
+    let chars = line.chars().take(col).collect::<Vec<_>>();
+    let start = chars
+        .iter()
+        .rposition(|ch| !(ch.is_alphanumeric() || *ch == '_'))
+        .map_or(0, |idx| idx + 1);
+    (start < chars.len()).then(|| (start, chars[start..].iter().collect()))
+}
+
+/// 正在进行的片段展开：剩余 tab stop 的绝对位置与待替换的占位文本。
+///
+/// 用户在占位处输入会改变同一行后续位置与后续行号，
+/// 因此记录跳转时的行长与总行数，下次跳转前据此平移剩余位置。
+#[derive(Debug, Clone, Default)]
+pub(super) struct SnippetSession {
+    /// 尚未跳转的 tab stop：(行, 列, 占位长度)。
+    stops: Vec<(usize, usize, usize)>,
+    /// 最近一次跳转到的位置。
+    current: (usize, usize),
+    /// 跳转时当前行的字符数。
+    line_len: usize,
+    /// 跳转时缓冲区的总行数。
+    line_count: usize,
+    /// 光标仍停在占位开头时，首次输入先删掉的占位长度。
+    pending_placeholder: usize,
+}
+
+impl EditorBuffer {
+    /// 用片段替换光标前的触发词，并按正文首行的缩进对齐后续行。
+    ///
+    /// 返回各 tab stop 在缓冲区中的绝对位置 (行, 列, 占位长度)。
+    pub(super) fn insert_snippet(
+        &mut self,
+        trigger_start: usize,
+        snippet: &ParsedSnippet,
+    ) -> Vec<(usize, usize, usize)> {
+        let row = self.cursor_row;
+        let cursor_col = self.cursor_col;
+        let line = &mut self.lines[row];
+        let start = char_to_byte_index(line, trigger_start);
+        let end = char_to_byte_index(line, cursor_col);
+        line.replace_range(start..end, "");
+        let indent = line
+            .chars()
+            .take_while(|ch| ch.is_whitespace())
+            .collect::<String>();
+        self.cursor_col = trigger_start;
+        for (idx, text) in snippet.lines.iter().enumerate() {
+            if idx > 0 {
+                self.insert_newline();
+                self.insert_str(&indent);
+            }
+            self.insert_str(text);
+        }
+        self.modified = true;
+        self.lsp_dirty = true;
+
+        snippet
+            .stops
+            .iter()
+            .map(|stop| {
+                let col = if stop.line == 0 {
+                    trigger_start + stop.col
+                } else {
+                    char_count(&indent) + stop.col
+                };
+                (row + stop.line, col, stop.len)
+            })
+            .collect()
+    }
+}
+
+impl Editor {
+    /// 光标前的单词是当前语言的片段触发词时展开片段，返回是否已展开。
+    pub(super) fn try_expand_snippet(&mut self) -> bool {
+        let buffer = self.active_buffer();
+        let Some(language) =
+            lsp::detect_language_from_path_or_name(buffer.path.as_deref(), &buffer.name)
+        else {
+            return false;
+        };
+        let Some((start, trigger)) =
+            trigger_before_cursor(&buffer.lines[buffer.cursor_row], buffer.cursor_col)
+        else {
+            return false;
+        };
+        let snippets = match load_snippets(&self.root, language) {
+            Ok(snippets) => snippets,
+            Err(error) => {
+                self.status_message = error;
+                return false;
+            }
+        };
+        let Some(body) = snippets.get(&trigger) else {
+            return false;
+        };
+
+        let parsed = parse_snippet(body);
+        let stops = self.active_buffer_mut().insert_snippet(start, &parsed);
+        self.snippet_session = Some(SnippetSession {
+            stops,
+            ..SnippetSession::default()
+        });
+        self.jump_to_next_snippet_stop();
+        if self.snippet_session.is_some() {
+            self.status_message = format!("片段 {trigger}：Tab 跳到下一个位置");
+        }
+        true
+    }
+
+    /// 跳到下一个 tab stop，返回是否处于片段展开中。
+    ///
+    /// 跳到最后一个位置后结束展开，之后 Tab 恢复普通缩进。
+    pub(super) fn jump_to_next_snippet_stop(&mut self) -> bool {
+        let Some(mut session) = self.snippet_session.take() else {
+            return false;
+        };
+        if session.stops.is_empty() {
+            return false;
+        }
+
+        let buffer = self.active_buffer_mut();
+        if session.line_count > 0 {
+            let (current_row, current_col) = session.current;
+            let col_delta = buffer.lines.get(current_row).map_or(0, |line| {
+                char_count(line) as isize - session.line_len as isize
+            });
+            let row_delta = buffer.lines.len() as isize - session.line_count as isize;
+            for (row, col, _) in &mut session.stops {
+                if *row == current_row && *col > current_col {
+                    *col = col.saturating_add_signed(col_delta);
+                } else if *row > current_row {
+                    *row = row.saturating_add_signed(row_delta);
+                }
+            }
+        }
+
+        let (row, col, len) = session.stops.remove(0);
+        buffer.cursor_row = row.min(buffer.lines.len().saturating_sub(1));
+        buffer.cursor_col = col;
+        buffer.ensure_cursor_in_bounds();
+        session.current = (buffer.cursor_row, buffer.cursor_col);
+        session.line_len = char_count(&buffer.lines[buffer.cursor_row]);
+        session.line_count = buffer.lines.len();
+        session.pending_placeholder = len;
+        if !session.stops.is_empty() || len > 0 {
+            self.snippet_session = Some(session);
+        }
+        true
+    }
+
+    /// 光标仍停在占位开头时删除占位文本，使首次输入直接替换默认值。
+    pub(super) fn take_snippet_placeholder(&mut self) -> bool {
+        let Some(session) = self.snippet_session.as_mut() else {
+            return false;
+        };
+        let len = std::mem::take(&mut session.pending_placeholder);
+        let remaining = !session.stops.is_empty();
+        let current = session.current;
+        if !remaining {
+            self.snippet_session = None;
+        }
+        let buffer = self.active_buffer_mut();
+        if len == 0 || (buffer.cursor_row, buffer.cursor_col) != current {
+            return false;
+        }
+        let line = &mut buffer.lines[buffer.cursor_row];
+        let start = char_to_byte_index(line, buffer.cursor_col);
+        let end = char_to_byte_index(line, buffer.cursor_col + len);
+        line.replace_range(start..end, "");
+        buffer.modified = true;
+        buffer.lsp_dirty = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use lsp::LspLanguage;
+
+    use super::{EditorBuffer, TabStop, load_snippets, parse_snippet, trigger_before_cursor};
+
+    #[test]
+    fn test_parse_snippet_placeholders_and_stop_order() {
+        let parsed = parse_snippet("fn ${1:name}(${2}) {\n    $0\n}");
+        assert_eq!(parsed.lines, vec!["fn name() {", "    ", "}"]);
+        assert_eq!(
+            parsed.stops,
+            vec![
+                TabStop {
+                    line: 0,
+                    col: 3,
+                    len: 4
+                },
+                TabStop {
+                    line: 0,
+                    col: 8,
+                    len: 0
+                },
+                TabStop {
+                    line: 1,
+                    col: 4,
+                    len: 0
+                },
+            ],
+            "按 1、2 排序，$0 放在最后"
+        );
+
+        let parsed = parse_snippet("cost \\$5 ${1:a\\}b} $x");
+        assert_eq!(parsed.lines, vec!["cost $5 a}b $x"]);
+        assert_eq!(
+            parsed.stops.last(),
+            Some(&TabStop {
+                line: 0,
+                col: 14,
+                len: 0
+            }),
+            "没有 $0 时最终停在正文末尾"
+        );
+    }
+
+    #[test]
+    fn test_trigger_before_cursor() {
+        assert_eq!(
+            trigger_before_cursor("    let x = fn", 14),
+            Some((12, "fn".to_string()))
+        );
+        assert_eq!(
+            trigger_before_cursor("for_each", 3),
+            Some((0, "for".to_string()))
+        );
+        assert_eq!(
+            trigger_before_cursor("foo(", 4),
+            None,
+            "光标前不是单词时不触发"
+        );
+    }
+
+    #[test]
+    fn test_load_snippets_by_language_trigger() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let root =
+            std::env::temp_dir().join(format!("order-snippets-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(root.join(".order/snippets")).expect("创建临时目录失败");
+        fs::write(
+            root.join(".order/snippets/rust.json"),
+            r##"{ "fn": "fn ${1:name}() {}", "test": ["#[test]", "fn ${1:case}() {", "    $0", "}"] }"##,
+        )
+        .expect("写入片段失败");
+
+        let snippets = load_snippets(&root, LspLanguage::Rust).expect("应能读取片段");
+        assert_eq!(
+            snippets.get("fn").map(String::as_str),
+            Some("fn ${1:name}() {}")
+        );
+        assert_eq!(
+            snippets.get("test").map(String::as_str),
+            Some("#[test]\nfn ${1:case}() {\n    $0\n}"),
+            "数组形式按行拼接"
+        );
+        assert!(
+            load_snippets(&root, LspLanguage::Go)
+                .expect("缺少片段文件时返回空表")
+                .is_empty()
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_insert_snippet_aligns_indent_and_returns_stops() {
+        let mut buffer = EditorBuffer::new_empty("main.rs".to_string());
+        buffer.lines = vec!["    iff".to_string()];
+        buffer.cursor_col = 7;
+        let parsed = parse_snippet("if ${1:cond} {\n    $0\n}");
+
+        let stops = buffer.insert_snippet(4, &parsed);
+        assert_eq!(buffer.lines, vec!["    if cond {", "        ", "    }"]);
+        assert_eq!(stops, vec![(0, 7, 4), (1, 8, 0)]);
+    }
+}
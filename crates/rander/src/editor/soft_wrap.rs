@@ -0,0 +1,300 @@
+use std::cmp::min;
+
+use ratatui::text::Span;
+use unicode_width::UnicodeWidthChar;
+
+use super::{Editor, types::EditorBuffer, utils::char_count};
+
+/// 行号栏宽度：4 位行号 + 1 个空格，软换行正文宽度需扣除这部分。
+pub(super) const GUTTER_WIDTH: usize = 5;
+
+// 单个字符占用的终端列数（控制字符按 0 计，与 ratatui 渲染一致）。
+fn char_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+/// 按显示宽度把一行切成若干显示段，返回每段起始的字符下标（首段恒为 0）。
+///
+/// 每段至少包含一个字符，宽度超过整段的全角字符也不会产生空段；
+/// 切分只影响显示，光标、编辑与 LSP 位置仍使用逻辑行与字符列。
+pub(super) fn wrap_starts(line: &str, width: usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    if width == 0 {
+        return starts;
+    }
+    let mut used = 0;
+    for (idx, ch) in line.chars().enumerate() {
+        let ch_width = char_width(ch);
+        if used > 0 && used + ch_width > width {
+            starts.push(idx);
+            used = 0;
+        }
+        used += ch_width;
+    }
+    starts
+}
+
+/// 逻辑列在该行内对应的显示位置：(显示段序号, 段内显示列)。
+///
+/// 行尾光标恰好落在整段末尾之外时收回到最后一列，避免为它多画一行空白续行。
+pub(super) fn display_position(line: &str, col: usize, width: usize) -> (usize, usize) {
+    let starts = wrap_starts(line, width);
+    let segment = starts.iter().rposition(|start| *start <= col).unwrap_or(0);
+    let display_col = line
+        .chars()
+        .skip(starts[segment])
+        .take(col.saturating_sub(starts[segment]))
+        .map(char_width)
+        .sum::<usize>();
+    (segment, min(display_col, width.saturating_sub(1)))
+}
+
+/// 把逻辑 (row, col) 映射为以 `top_row` 为首行时的显示 (row, col)。
+pub(super) fn logical_to_display(
+    lines: &[String],
+    top_row: usize,
+    row: usize,
+    col: usize,
+    width: usize,
+) -> (usize, usize) {
+    let rows_before = lines[top_row.min(row)..row]
+        .iter()
+        .map(|line| wrap_starts(line, width).len())
+        .sum::<usize>();
+    let (segment, display_col) = display_position(&lines[row], col, width);
+    (rows_before + segment, display_col)
+}
+
+/// 软换行下计算首个可见逻辑行，使光标所在的显示行落在 `visible` 行内。
+///
+/// 每个逻辑行至少占一行显示，所以首行不会早于 `cursor_row + 1 - visible`，
+/// 从这里开始累加，跳转到远处时也不必从旧的首行逐行计算。
+pub(super) fn wrapped_scroll_row(
+    lines: &[String],
+    scroll_row: usize,
+    cursor_row: usize,
+    cursor_col: usize,
+    width: usize,
+    visible: usize,
+) -> usize {
+    if cursor_row < scroll_row {
+        return cursor_row;
+    }
+    let mut top = scroll_row.max((cursor_row + 1).saturating_sub(visible));
+    let heights = lines[top..cursor_row]
+        .iter()
+        .map(|line| wrap_starts(line, width).len())
+        .collect::<Vec<_>>();
+    let (segment, _) = display_position(&lines[cursor_row], cursor_col, width);
+    let mut total = heights.iter().sum::<usize>() + segment + 1;
+    for height in heights {
+        if total <= visible {
+            break;
+        }
+        total -= height;
+        top += 1;
+    }
+    top
+}
+
+/// 按显示段起点把一行的高亮片段切成多行显示（不含行号栏）。
+pub(super) fn split_spans(spans: Vec<Span<'static>>, starts: &[usize]) -> Vec<Vec<Span<'static>>> {
+    let mut segments = vec![Vec::new()];
+    let mut next_start = starts.iter().skip(1).copied().peekable();
+    let mut idx = 0;
+    for span in spans {
+        let mut piece = String::new();
+        for ch in span.content.chars() {
+            if next_start.next_if_eq(&idx).is_some() {
+                if !piece.is_empty() {
+                    let last = segments.len() - 1;
+                    segments[last].push(Span::styled(std::mem::take(&mut piece), span.style));
+                }
+                segments.push(Vec::new());
+            }
+            piece.push(ch);
+            idx += 1;
+        }
+        if !piece.is_empty() {
+            let last = segments.len() - 1;
+            segments[last].push(Span::styled(piece, span.style));
+        }
+    }
+    segments
+}
+
+// 在指定显示段内找到最接近目标显示列的逻辑列，不越过该段末尾。
+fn col_at_display(line: &str, starts: &[usize], segment: usize, display_col: usize) -> usize {
+    let start = starts[segment];
+    let end = starts
+        .get(segment + 1)
+        .map_or_else(|| char_count(line), |next| next - 1);
+    let mut col = start;
+    let mut used = 0;
+    for ch in line.chars().skip(start) {
+        used += char_width(ch);
+        if col >= end || used > display_col {
+            break;
+        }
+        col += 1;
+    }
+    col
+}
+
+impl Editor {
+    /// 当前缓冲区开启软换行时的正文宽度，取自最近一次渲染的聚焦窗格。
+    pub(super) fn soft_wrap_width(&self) -> Option<usize> {
+        if self.tabs.is_empty() || !self.active_buffer().soft_wrap {
+            return None;
+        }
+        self.last_editor_inner_area
+            .map(|inner| (inner.width as usize).saturating_sub(GUTTER_WIDTH))
+            .filter(|width| *width > 0)
+    }
+}
+
+impl EditorBuffer {
+    /// 光标上移一行：开启软换行且已知正文宽度时按显示行移动，否则按逻辑行。
+    pub(super) fn move_up_display(&mut self, width: Option<usize>) {
+        let Some(width) = width.filter(|_| self.soft_wrap) else {
+            self.move_up();
+            return;
+        };
+        self.ensure_cursor_in_bounds();
+        let (segment, display_col) =
+            display_position(&self.lines[self.cursor_row], self.cursor_col, width);
+        if segment > 0 {
+            let starts = wrap_starts(&self.lines[self.cursor_row], width);
+            self.cursor_col = col_at_display(
+                &self.lines[self.cursor_row],
+                &starts,
+                segment - 1,
+                display_col,
+            );
+        } else if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+            let line = &self.lines[self.cursor_row];
+            let starts = wrap_starts(line, width);
+            self.cursor_col = col_at_display(line, &starts, starts.len() - 1, display_col);
+        }
+    }
+
+    /// 光标下移一行：开启软换行且已知正文宽度时按显示行移动，否则按逻辑行。
+    pub(super) fn move_down_display(&mut self, width: Option<usize>) {
+        let Some(width) = width.filter(|_| self.soft_wrap) else {
+            self.move_down();
+            return;
+        };
+        self.ensure_cursor_in_bounds();
+        let line = &self.lines[self.cursor_row];
+        let starts = wrap_starts(line, width);
+        let (segment, display_col) = display_position(line, self.cursor_col, width);
+        if segment + 1 < starts.len() {
+            self.cursor_col = col_at_display(line, &starts, segment + 1, display_col);
+        } else if self.cursor_row + 1 < self.lines.len() {
+            self.cursor_row += 1;
+            let line = &self.lines[self.cursor_row];
+            let starts = wrap_starts(line, width);
+            self.cursor_col = col_at_display(line, &starts, 0, display_col);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{style::Style, text::Span};
+
+    use super::{
+        EditorBuffer, display_position, logical_to_display, split_spans, wrap_starts,
+        wrapped_scroll_row,
+    };
+
+    fn lines(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_wrap_starts_by_display_width() {
+        assert_eq!(wrap_starts("", 4), vec![0]);
+        assert_eq!(wrap_starts("abcdefghij", 4), vec![0, 4, 8]);
+        // 全角字符占两列，放不下时整体移到下一段。
+        assert_eq!(wrap_starts("ab中文字", 4), vec![0, 3]);
+        assert_eq!(wrap_starts("中", 1), vec![0], "过宽字符也不产生空段");
+    }
+
+    #[test]
+    fn test_logical_to_display_under_soft_wrap() {
+        let text = lines(&["abcdefghij", "xy", "0123456789ab"]);
+        assert_eq!(logical_to_display(&text, 0, 0, 0, 4), (0, 0));
+        assert_eq!(logical_to_display(&text, 0, 0, 5, 4), (1, 1));
+        assert_eq!(logical_to_display(&text, 0, 0, 10, 4), (2, 2));
+        // 第 0 行占 3 个显示行，第 1 行从显示行 3 开始。
+        assert_eq!(logical_to_display(&text, 0, 1, 2, 4), (3, 2));
+        assert_eq!(logical_to_display(&text, 0, 2, 9, 4), (6, 1));
+        assert_eq!(
+            logical_to_display(&text, 1, 2, 9, 4),
+            (3, 1),
+            "从滚动首行起算"
+        );
+        // 行尾光标正好在整段之后时收回到最后一列。
+        assert_eq!(display_position("abcd", 4, 4), (0, 3));
+        assert_eq!(display_position("ab中文", 3, 4), (1, 0));
+    }
+
+    #[test]
+    fn test_wrapped_scroll_row_keeps_cursor_visible() {
+        let text = lines(&["abcdefgh", "abcdefgh", "abcdefgh", "ab"]);
+        // 每行占 2 个显示行，3 行可见时光标在第 3 行需要从第 2 行开始显示。
+        assert_eq!(wrapped_scroll_row(&text, 0, 3, 0, 4, 3), 2);
+        assert_eq!(wrapped_scroll_row(&text, 0, 1, 6, 4, 4), 0);
+        assert_eq!(wrapped_scroll_row(&text, 2, 0, 0, 4, 4), 0);
+    }
+
+    #[test]
+    fn test_split_spans_keeps_styles_across_segments() {
+        let spans = vec![
+            Span::styled("abc".to_string(), Style::default()),
+            Span::styled("defg".to_string(), Style::default()),
+        ];
+        let segments = split_spans(spans, &[0, 4]);
+        let texts = segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .iter()
+                    .map(|span| span.content.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec![vec!["abc", "d"], vec!["efg"]]);
+    }
+
+    #[test]
+    fn test_move_by_display_lines_when_wrapped() {
+        let mut buffer = EditorBuffer::new_empty("test".to_string());
+        buffer.lines = lines(&["abcdefghij", "xy"]);
+        buffer.soft_wrap = true;
+        buffer.cursor_col = 1;
+
+        buffer.move_down_display(Some(4));
+        assert_eq!(
+            (buffer.cursor_row, buffer.cursor_col),
+            (0, 5),
+            "同一逻辑行的下一显示段"
+        );
+        buffer.move_down_display(Some(4));
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (0, 9));
+        buffer.move_down_display(Some(4));
+        assert_eq!(
+            (buffer.cursor_row, buffer.cursor_col),
+            (1, 1),
+            "最后一段再下移进入下一逻辑行"
+        );
+        buffer.move_up_display(Some(4));
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (0, 9));
+
+        buffer.soft_wrap = false;
+        buffer.move_up_display(Some(4));
+        assert_eq!(buffer.cursor_row, 0, "关闭软换行时按逻辑行移动");
+    }
+}
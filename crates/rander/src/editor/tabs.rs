@@ -0,0 +1,346 @@
+use std::path::PathBuf;
+
+use super::types::{EditorBuffer, TabState};
+
+/// 最多记住的已关闭文件数量，更早的记录直接丢弃。
+const CLOSED_BUFFER_CAPACITY: usize = 20;
+
+/// 一条已关闭文件的记录：路径与关闭时的光标位置。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ClosedBuffer {
+    pub(super) path: PathBuf,
+    pub(super) cursor_row: usize,
+    pub(super) cursor_col: usize,
+}
+
+impl ClosedBuffer {
+    // 从缓冲区记录关闭信息，未命名缓冲区没有可重新打开的路径，返回 None。
+    pub(super) fn from_buffer(buffer: &EditorBuffer) -> Option<Self> {
+        Some(Self {
+            path: buffer.path.clone()?,
+            cursor_row: buffer.cursor_row,
+            cursor_col: buffer.cursor_col,
+        })
+    }
+
+    /// 把记录的光标位置恢复到重新打开的缓冲区。
+    ///
+    /// 文件可能在关闭期间被外部改短，恢复后按当前内容收紧光标，避免越界。
+    pub(super) fn restore_cursor(&self, buffer: &mut EditorBuffer) {
+        buffer.cursor_row = self.cursor_row;
+        buffer.cursor_col = self.cursor_col;
+        buffer.ensure_cursor_in_bounds();
+    }
+}
+
+/// `tu` 使用的已关闭文件栈，行为与浏览器“重新打开关闭的标签页”一致。
+#[derive(Debug, Default)]
+pub(super) struct ClosedBufferStack {
+    entries: Vec<ClosedBuffer>,
+}
+
+impl ClosedBufferStack {
+    /// 记录一次关闭。
+    ///
+    /// 同一路径只保留最新一条，反复开关同一文件时不会占满栈，
+    /// 重新打开也总是回到最后一次关闭时的位置。
+    pub(super) fn push(&mut self, closed: ClosedBuffer) {
+        self.entries.retain(|entry| entry.path != closed.path);
+        self.entries.push(closed);
+        if self.entries.len() > CLOSED_BUFFER_CAPACITY {
+            self.entries.remove(0);
+        }
+    }
+
+    // 取出最近关闭的一条记录。
+    pub(super) fn pop(&mut self) -> Option<ClosedBuffer> {
+        self.entries.pop()
+    }
+}
+
+/// 固定标签页的数量；固定标签页始终连续排在最左侧。
+pub(super) fn pinned_count(tabs: &[TabState]) -> usize {
+    tabs.iter().take_while(|tab| tab.pinned).count()
+}
+
+/// 把固定标签页稳定地排到最前面，并返回原激活标签页的新下标。
+///
+/// 会话文件可能被手工编辑或来自旧版本，加载后统一整理一次，
+/// 之后的移动与固定操作都依赖“固定标签页连续在前”这一前提。
+pub(super) fn sort_pinned_first(tabs: &mut Vec<TabState>, active: usize) -> usize {
+    let mut indexed = std::mem::take(tabs)
+        .into_iter()
+        .enumerate()
+        .collect::<Vec<_>>();
+    indexed.sort_by_key(|(_, tab)| !tab.pinned);
+    let new_active = indexed
+        .iter()
+        .position(|(index, _)| *index == active)
+        .unwrap_or(0);
+    *tabs = indexed.into_iter().map(|(_, tab)| tab).collect();
+    new_active
+}
+
+/// 将激活标签页向左或向右移动一格，返回移动后的激活下标。
+///
+/// 移动限制在所属分组内：固定标签页不会被挪到普通标签页之后，
+/// 普通标签页也不会越过固定区，保证固定标签页始终在最左侧。
+pub(super) fn move_tab(tabs: &mut [TabState], active: usize, forward: bool) -> usize {
+    if active >= tabs.len() {
+        return active;
+    }
+    let pinned = pinned_count(tabs);
+    let (group_start, group_end) = if tabs[active].pinned {
+        (0, pinned)
+    } else {
+        (pinned, tabs.len())
+    };
+
+    let target = if forward {
+        active + 1
+    } else {
+        active.wrapping_sub(1)
+    };
+    if target < group_start || target >= group_end {
+        return active;
+    }
+    tabs.swap(active, target);
+    target
+}
+
+/// `:bn` / `:bp` 的目标缓冲区下标，到达两端时循环。
+pub(super) fn cycle_buffer_index(current: usize, len: usize, forward: bool) -> usize {
+    if len == 0 {
+        return current;
+    }
+    if forward {
+        (current + 1) % len
+    } else {
+        (current + len - 1) % len
+    }
+}
+
+/// `max_tabs` 超限时选出要淘汰的标签页：最久未激活、未固定且缓冲区没有未保存修改。
+///
+/// 激活标签页不参与淘汰；有未保存修改的缓冲区永远不会被自动关闭，
+/// 全部候选都被排除时返回 `None`，由调用方提示用户手动处理。
+pub(super) fn lru_eviction_candidate(
+    tabs: &[TabState],
+    active: usize,
+    is_modified: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    tabs.iter()
+        .enumerate()
+        .filter(|(index, tab)| *index != active && !tab.pinned && !is_modified(tab.buffer_index))
+        .min_by_key(|(_, tab)| tab.last_focused)
+        .map(|(index, _)| index)
+}
+
+/// 切换激活标签页的固定状态，返回切换后的激活下标。
+///
+/// 固定时移到固定区末尾，取消固定时移到普通区开头，
+/// 这样标签页只跨过分组边界，与其他标签页的相对顺序保持不变。
+pub(super) fn toggle_pin(tabs: &mut Vec<TabState>, active: usize) -> usize {
+    if active >= tabs.len() {
+        return active;
+    }
+    let mut tab = tabs.remove(active);
+    tab.pinned = !tab.pinned;
+    // 移除后剩余固定标签页的数量，恰好就是固定区末尾、也是普通区开头的位置。
+    let target = pinned_count(tabs);
+    tabs.insert(target, tab);
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        CLOSED_BUFFER_CAPACITY, ClosedBuffer, ClosedBufferStack, cycle_buffer_index,
+        lru_eviction_candidate, move_tab, pinned_count, sort_pinned_first, toggle_pin,
+    };
+    use crate::editor::types::{EditorBuffer, PaneFocus, SplitDirection, TabState};
+
+    fn tab(title: &str, pinned: bool) -> TabState {
+        TabState {
+            title: title.to_string(),
+            buffer_index: 0,
+            split: SplitDirection::None,
+            focus: PaneFocus::Primary,
+            pinned,
+            last_focused: Instant::now(),
+        }
+    }
+
+    fn titles(tabs: &[TabState]) -> Vec<&str> {
+        tabs.iter().map(|tab| tab.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_move_tab_tracks_active_and_stays_within_group() {
+        let mut tabs = vec![
+            tab("p1", true),
+            tab("a", false),
+            tab("b", false),
+            tab("c", false),
+        ];
+
+        let active = move_tab(&mut tabs, 1, true);
+        assert_eq!(active, 2);
+        assert_eq!(titles(&tabs), vec!["p1", "b", "a", "c"]);
+
+        let active = move_tab(&mut tabs, active, false);
+        let active = move_tab(&mut tabs, active, false);
+        assert_eq!(active, 1, "普通标签页不能越过固定区");
+        assert_eq!(titles(&tabs), vec!["p1", "a", "b", "c"]);
+
+        assert_eq!(move_tab(&mut tabs, 0, true), 0, "固定标签页不能移入普通区");
+        assert_eq!(move_tab(&mut tabs, 3, true), 3, "到达末尾时保持不动");
+        assert_eq!(titles(&tabs), vec!["p1", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_toggle_pin_keeps_pinned_tabs_first() {
+        let mut tabs = vec![tab("p1", true), tab("a", false), tab("b", false)];
+
+        let active = toggle_pin(&mut tabs, 2);
+        assert_eq!(active, 1);
+        assert_eq!(titles(&tabs), vec!["p1", "b", "a"]);
+        assert_eq!(pinned_count(&tabs), 2);
+
+        let active = toggle_pin(&mut tabs, 0);
+        assert_eq!(active, 1, "取消固定后移到普通区开头");
+        assert_eq!(titles(&tabs), vec!["b", "p1", "a"]);
+        assert_eq!(pinned_count(&tabs), 1);
+
+        let mut loaded = vec![
+            tab("a", false),
+            tab("p1", true),
+            tab("b", false),
+            tab("p2", true),
+        ];
+        let active = sort_pinned_first(&mut loaded, 2);
+        assert_eq!(titles(&loaded), vec!["p1", "p2", "a", "b"]);
+        assert_eq!(active, 3, "整理后应继续指向原激活标签页");
+    }
+
+    fn closed(path: &str, cursor_row: usize) -> ClosedBuffer {
+        ClosedBuffer {
+            path: PathBuf::from(path),
+            cursor_row,
+            cursor_col: 0,
+        }
+    }
+
+    #[test]
+    fn test_closed_buffer_stack_push_pop() {
+        let mut stack = ClosedBufferStack::default();
+        stack.push(closed("a.rs", 1));
+        stack.push(closed("b.rs", 2));
+        stack.push(closed("a.rs", 3));
+
+        assert_eq!(
+            stack.pop(),
+            Some(closed("a.rs", 3)),
+            "应先弹出最近关闭的文件"
+        );
+        assert_eq!(stack.pop(), Some(closed("b.rs", 2)));
+        assert_eq!(stack.pop(), None, "同一路径只保留最新一条");
+
+        for index in 0..CLOSED_BUFFER_CAPACITY + 5 {
+            stack.push(closed(&format!("{index}.rs"), 0));
+        }
+        let mut count = 0;
+        let mut last = None;
+        while let Some(entry) = stack.pop() {
+            count += 1;
+            last = Some(entry);
+        }
+        assert_eq!(count, CLOSED_BUFFER_CAPACITY);
+        assert_eq!(last, Some(closed("5.rs", 0)), "超出容量时丢弃最早的记录");
+
+        let untitled = EditorBuffer::new_empty("untitled-1".to_string());
+        assert!(
+            ClosedBuffer::from_buffer(&untitled).is_none(),
+            "未命名缓冲区不应记录"
+        );
+    }
+
+    #[test]
+    fn test_closed_buffer_restore_cursor() {
+        let mut buffer = EditorBuffer::new_empty("a.rs".to_string());
+        buffer.lines = vec!["first".to_string(), "second".to_string()];
+
+        ClosedBuffer {
+            path: PathBuf::from("a.rs"),
+            cursor_row: 1,
+            cursor_col: 3,
+        }
+        .restore_cursor(&mut buffer);
+        assert_eq!((buffer.cursor_row, buffer.cursor_col), (1, 3));
+
+        ClosedBuffer {
+            path: PathBuf::from("a.rs"),
+            cursor_row: 9,
+            cursor_col: 99,
+        }
+        .restore_cursor(&mut buffer);
+        assert_eq!(
+            (buffer.cursor_row, buffer.cursor_col),
+            (1, 6),
+            "文件变短后光标应收紧到末尾"
+        );
+    }
+
+    #[test]
+    fn test_cycle_buffer_index_wraps_around() {
+        assert_eq!(cycle_buffer_index(0, 3, true), 1);
+        assert_eq!(cycle_buffer_index(2, 3, true), 0, "末尾之后回到开头");
+        assert_eq!(cycle_buffer_index(0, 3, false), 2, "开头之前回到末尾");
+        assert_eq!(cycle_buffer_index(1, 3, false), 0);
+        assert_eq!(cycle_buffer_index(0, 0, true), 0);
+    }
+
+    #[test]
+    fn test_lru_eviction_candidate_skips_modified_pinned_and_active() {
+        let start = Instant::now();
+        let mut tabs = (0..4)
+            .map(|index| TabState {
+                buffer_index: index,
+                last_focused: start + Duration::from_secs(index as u64),
+                ..tab(&format!("t{index}"), false)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lru_eviction_candidate(&tabs, 3, |_| false),
+            Some(0),
+            "选最久未激活的"
+        );
+        assert_eq!(
+            lru_eviction_candidate(&tabs, 3, |buffer| buffer == 0),
+            Some(1),
+            "有未保存修改的缓冲区不参与淘汰"
+        );
+        assert_eq!(
+            lru_eviction_candidate(&tabs, 0, |_| false),
+            Some(1),
+            "激活标签页不参与淘汰"
+        );
+
+        tabs[1].pinned = true;
+        assert_eq!(
+            lru_eviction_candidate(&tabs, 3, |buffer| buffer == 0),
+            Some(2)
+        );
+        assert_eq!(
+            lru_eviction_candidate(&tabs, 3, |buffer| buffer != 3),
+            None,
+            "全部候选都有修改时不淘汰"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use core::settings::load_settings;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// 主题配置文件名（与 `editor.json` 同样按用户全局 / 项目两层叠加）。
+const THEME_CONFIG_FILE: &str = "theme.json";
+
+/// `.order/theme.json` 中的主题配置。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ThemeConfig {
+    /// 按语义 token 类型覆盖样式，键为 LSP token 类型（如 `function`、`keyword`）。
+    semantic_tokens: HashMap<String, SemanticTokenStyleSpec>,
+}
+
+/// 单个 token 类型的样式覆盖：可只写颜色字符串，也可写完整对象。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum SemanticTokenStyleSpec {
+    Color(String),
+    Style {
+        #[serde(default)]
+        fg: Option<String>,
+        #[serde(default)]
+        bg: Option<String>,
+        #[serde(default)]
+        bold: Option<bool>,
+        #[serde(default)]
+        italic: Option<bool>,
+        #[serde(default)]
+        underline: Option<bool>,
+    },
+}
+
+/// 启动时解析好的语义 token 样式覆盖表。
+///
+/// 渲染每一行都会查询，所以颜色字符串在加载时一次性解析成 `Style`，
+/// 而不是在热路径上反复解析。
+#[derive(Debug, Clone, Default)]
+pub(super) struct SemanticTokenStyles {
+    overrides: HashMap<String, Style>,
+}
+
+impl SemanticTokenStyles {
+    /// 加载并解析主题文件中的语义 token 样式；文件不存在时返回空表。
+    pub(super) fn load(root: &Path) -> Result<Self, String> {
+        let config: ThemeConfig = load_settings(root, THEME_CONFIG_FILE, &[])
+            .map_err(|error| format!("加载主题配置失败: {error:#}"))?;
+        Self::from_config(config)
+    }
+
+    // 逐个解析颜色，任一项非法时整体报错并指出对应的 token 类型。
+    fn from_config(config: ThemeConfig) -> Result<Self, String> {
+        let mut overrides = HashMap::new();
+        for (token_type, spec) in config.semantic_tokens {
+            let style = spec
+                .to_style()
+                .map_err(|error| format!("主题配置 semantic_tokens.{token_type}: {error}"))?;
+            overrides.insert(token_type, style);
+        }
+        Ok(Self { overrides })
+    }
+
+    /// 把用户覆盖叠加到内置样式上；未配置的 token 类型原样返回内置样式。
+    ///
+    /// 使用 `Style::patch` 叠加，只写了 `bold` 的覆盖会保留内置颜色，
+    /// 只写了颜色的覆盖也会保留内置的加粗/斜体。
+    pub(super) fn apply(&self, token_type: &str, builtin: Style) -> Style {
+        match self.overrides.get(token_type) {
+            Some(style) => builtin.patch(*style),
+            None => builtin,
+        }
+    }
+}
+
+impl SemanticTokenStyleSpec {
+    // 转换为 ratatui 样式，颜色字符串非法时返回错误。
+    fn to_style(&self) -> Result<Style, String> {
+        match self {
+            Self::Color(color) => Ok(Style::default().fg(parse_color(color)?)),
+            Self::Style {
+                fg,
+                bg,
+                bold,
+                italic,
+                underline,
+            } => {
+                let mut style = Style::default();
+                if let Some(color) = fg {
+                    style = style.fg(parse_color(color)?);
+                }
+                if let Some(color) = bg {
+                    style = style.bg(parse_color(color)?);
+                }
+                for (flag, modifier) in [
+                    (bold, Modifier::BOLD),
+                    (italic, Modifier::ITALIC),
+                    (underline, Modifier::UNDERLINED),
+                ] {
+                    style = match flag {
+                        Some(true) => style.add_modifier(modifier),
+                        Some(false) => style.remove_modifier(modifier),
+                        None => style,
+                    };
+                }
+                Ok(style)
+            }
+        }
+    }
+}
+
+// 解析 `#rrggbb` 或颜色名（如 `red`、`lightblue`）。
+fn parse_color(input: &str) -> Result<Color, String> {
+    Color::from_str(input.trim()).map_err(|_| format!("无法识别的颜色 {input}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier, Style};
+
+    use super::{SemanticTokenStyles, ThemeConfig};
+
+    fn styles(json: &str) -> Result<SemanticTokenStyles, String> {
+        let config: ThemeConfig = serde_json::from_str(json).expect("解析主题配置失败");
+        SemanticTokenStyles::from_config(config)
+    }
+
+    #[test]
+    fn test_semantic_token_override_merges_over_builtin() {
+        let styles = styles(
+            r##"{"semantic_tokens": {
+                "function": "#82aaff",
+                "keyword": {"fg": "#ff0000", "bold": false, "italic": true}
+            }}"##,
+        )
+        .expect("合法配置应解析成功");
+
+        let builtin_function = Style::default().fg(Color::Rgb(1, 2, 3));
+        assert_eq!(
+            styles.apply("function", builtin_function).fg,
+            Some(Color::Rgb(0x82, 0xaa, 0xff))
+        );
+
+        let builtin_keyword = Style::default()
+            .fg(Color::Blue)
+            .add_modifier(Modifier::BOLD);
+        let keyword = styles.apply("keyword", builtin_keyword);
+        assert_eq!(keyword.fg, Some(Color::Rgb(0xff, 0, 0)));
+        assert!(keyword.add_modifier.contains(Modifier::ITALIC));
+        assert!(
+            !keyword.add_modifier.contains(Modifier::BOLD),
+            "显式 bold=false 应去掉内置加粗"
+        );
+
+        let builtin_type = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        assert_eq!(
+            styles.apply("type", builtin_type),
+            builtin_type,
+            "未配置的 token 类型保持内置样式"
+        );
+    }
+
+    #[test]
+    fn test_semantic_token_invalid_color_reports_token_type() {
+        let error =
+            styles(r##"{"semantic_tokens": {"macro": "#zzzzzz"}}"##).expect_err("非法颜色应报错");
+        assert!(
+            error.contains("semantic_tokens.macro"),
+            "错误信息应指出 token 类型"
+        );
+    }
+}
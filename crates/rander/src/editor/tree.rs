@@ -1,35 +1,181 @@
 use std::{
-    collections::BTreeSet,
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
     fs,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use super::{MAX_TREE_ENTRIES, types::TreeEntry, utils::file_name_or};
+use serde::Deserialize;
+
+use super::{MAX_DIR_CHILDREN, types::TreeEntry, utils::file_name_or};
+
+/// 目录树子项的排序方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum TreeSort {
+    /// 目录在前，同类按名称升序（忽略大小写，默认）。
+    #[default]
+    DirsFirst,
+    /// 目录与文件混排，按名称升序（忽略大小写）。
+    Name,
+    /// 按修改时间排序，最近修改的在前；目录与文件混排。
+    Modified,
+}
+
+impl TreeSort {
+    // 按 `DirsFirst -> Name -> Modified` 循环切换。
+    pub(super) fn next(self) -> Self {
+        match self {
+            Self::DirsFirst => Self::Name,
+            Self::Name => Self::Modified,
+            Self::Modified => Self::DirsFirst,
+        }
+    }
+
+    // 状态栏展示用的名称。
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::DirsFirst => "目录在前",
+            Self::Name => "按名称混排",
+            Self::Modified => "按修改时间",
+        }
+    }
+}
+
+/// 读取目录时得到的单个子项。
+#[derive(Debug, Clone)]
+struct ListingEntry {
+    path: PathBuf,
+    is_dir: bool,
+    name: String,
+    /// 读取失败时为 `None`，在按修改时间排序时排到最后。
+    modified: Option<SystemTime>,
+}
+
+/// 单个目录读取后的缓存结果。
+#[derive(Debug, Clone, Default)]
+struct DirListing {
+    /// 排序后的子项，最多 `MAX_DIR_CHILDREN` 项。
+    entries: Vec<ListingEntry>,
+    /// 超出单目录上限而未展示的子项数量。
+    hidden: usize,
+}
+
+/// 目录树懒加载缓存。
+///
+/// 只有被展开的目录才会读取并缓存子项，避免大仓库启动时全量遍历；
+/// 文件操作后由调用方按目录失效，保证展示与磁盘一致。
+#[derive(Debug, Clone, Default)]
+pub(super) struct TreeCache {
+    listings: HashMap<PathBuf, DirListing>,
+    sort: TreeSort,
+}
+
+impl TreeCache {
+    // 当前排序方式。
+    pub(super) fn sort(&self) -> TreeSort {
+        self.sort
+    }
+
+    /// 切换排序方式并清空缓存。
+    ///
+    /// 排序决定了超出上限时哪些子项被隐藏，因此不能只重排已缓存的结果，
+    /// 必须按新顺序重新读取。
+    pub(super) fn set_sort(&mut self, sort: TreeSort) {
+        if self.sort != sort {
+            self.sort = sort;
+            self.listings.clear();
+        }
+    }
+
+    // 使指定目录的缓存失效，下次展开时重新读取。
+    pub(super) fn invalidate(&mut self, dir: &Path) {
+        self.listings.remove(dir);
+    }
+
+    // 清空全部缓存。
+    pub(super) fn clear(&mut self) {
+        self.listings.clear();
+    }
+
+    // 指定目录是否已读取过。
+    #[cfg(test)]
+    fn is_loaded(&self, dir: &Path) -> bool {
+        self.listings.contains_key(dir)
+    }
+
+    // 读取目录子项（命中缓存时不访问磁盘）。
+    fn listing(&mut self, dir: &Path) -> &DirListing {
+        let sort = self.sort;
+        self.listings
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| read_dir_listing(dir, MAX_DIR_CHILDREN, sort))
+    }
+}
 
 // 根据展开状态收集目录树节点。
 pub(super) fn collect_tree_entries(
     root: &Path,
     expanded_dirs: &BTreeSet<PathBuf>,
+    cache: &mut TreeCache,
 ) -> Vec<TreeEntry> {
     let mut entries = Vec::new();
-    collect_tree_entries_recursive(root, 0, expanded_dirs, &mut entries);
+    collect_tree_entries_recursive(root, 0, expanded_dirs, cache, &mut entries);
     entries
 }
 
-// 递归构建目录树。
+// 递归构建目录树，只深入已展开的目录。
 fn collect_tree_entries_recursive(
     path: &Path,
     depth: usize,
     expanded_dirs: &BTreeSet<PathBuf>,
+    cache: &mut TreeCache,
     output: &mut Vec<TreeEntry>,
 ) {
-    if output.len() >= MAX_TREE_ENTRIES {
-        return;
+    let listing = cache.listing(path).clone();
+
+    for entry in listing.entries {
+        let is_dir = entry.is_dir;
+        output.push(TreeEntry {
+            path: entry.path.clone(),
+            depth,
+            is_dir,
+            name: entry.name,
+            more_count: None,
+        });
+
+        if is_dir && expanded_dirs.contains(&entry.path) {
+            collect_tree_entries_recursive(
+                entry.path.as_path(),
+                depth + 1,
+                expanded_dirs,
+                cache,
+                output,
+            );
+        }
+    }
+
+    if listing.hidden > 0 {
+        // 占位节点指向所在目录，在其上按 `h` 可直接折叠该目录。
+        output.push(TreeEntry {
+            path: path.to_path_buf(),
+            depth,
+            is_dir: false,
+            name: format!("+{} more", listing.hidden),
+            more_count: Some(listing.hidden),
+        });
     }
+}
 
+/// 读取单个目录的子项并按 `sort` 排序。
+///
+/// 先完整排序再截断，保证被隐藏的总是排序靠后的条目，
+/// 而不是取决于文件系统返回顺序的任意条目。
+fn read_dir_listing(path: &Path, max_children: usize, sort: TreeSort) -> DirListing {
     let read_dir = match fs::read_dir(path) {
         Ok(rd) => rd,
-        Err(_) => return,
+        Err(_) => return DirListing::default(),
     };
 
     let mut entries = Vec::new();
@@ -43,29 +189,218 @@ fn collect_tree_entries_recursive(
         if name.is_empty() {
             continue;
         }
-        entries.push((entry_path, is_dir, name));
+        // 只有按修改时间排序时才需要元数据，避免默认模式多一次系统调用。
+        let modified = if sort == TreeSort::Modified {
+            entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        } else {
+            None
+        };
+        entries.push(ListingEntry {
+            path: entry_path,
+            is_dir,
+            name,
+            modified,
+        });
     }
 
-    entries.sort_by(|left, right| match (left.1, right.1) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => left.2.cmp(&right.2),
-    });
+    sort_listing_entries(&mut entries, sort);
 
-    for (entry_path, is_dir, name) in entries {
-        if output.len() >= MAX_TREE_ENTRIES {
-            break;
+    let hidden = entries.len().saturating_sub(max_children);
+    entries.truncate(max_children);
+    DirListing { entries, hidden }
+}
+
+/// 按排序方式重排子项。
+///
+/// 名称比较忽略大小写，相同时再按原始名称区分，保证结果稳定；
+/// 修改时间相同时同样回退到名称。
+fn sort_listing_entries(entries: &mut [ListingEntry], sort: TreeSort) {
+    let by_name = |left: &ListingEntry, right: &ListingEntry| {
+        left.name
+            .to_lowercase()
+            .cmp(&right.name.to_lowercase())
+            .then_with(|| left.name.cmp(&right.name))
+    };
+    match sort {
+        TreeSort::DirsFirst => entries.sort_by(|left, right| {
+            right
+                .is_dir
+                .cmp(&left.is_dir)
+                .then_with(|| by_name(left, right))
+        }),
+        TreeSort::Name => entries.sort_by(by_name),
+        TreeSort::Modified => {
+            entries.sort_by(|left, right| match (left.modified, right.modified) {
+                (Some(left_time), Some(right_time)) => right_time
+                    .cmp(&left_time)
+                    .then_with(|| by_name(left, right)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => by_name(left, right),
+            })
         }
+    }
+}
 
-        output.push(TreeEntry {
-            path: entry_path.clone(),
-            depth,
-            is_dir,
-            name,
-        });
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeSet,
+        fs,
+        path::PathBuf,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{
+        ListingEntry, TreeCache, TreeSort, collect_tree_entries, read_dir_listing,
+        sort_listing_entries,
+    };
+
+    fn temp_root(tag: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let root =
+            std::env::temp_dir().join(format!("order-tree-{tag}-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(&root).expect("创建临时目录失败");
+        root
+    }
+
+    #[test]
+    fn test_collect_tree_entries_loads_children_only_on_expand() {
+        let root = temp_root("lazy");
+        let sub = root.join("src");
+        fs::create_dir_all(&sub).expect("创建子目录失败");
+        fs::write(sub.join("main.rs"), "fn main() {}\n").expect("写入文件失败");
+        fs::write(root.join("README.md"), "# demo\n").expect("写入文件失败");
+
+        let mut cache = TreeCache::default();
+        let mut expanded = BTreeSet::new();
+        let collapsed = collect_tree_entries(&root, &expanded, &mut cache);
+        let names = collapsed
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["src", "README.md"]);
+        assert!(!cache.is_loaded(&sub), "未展开的目录不应被读取");
+
+        expanded.insert(sub.clone());
+        let expanded_entries = collect_tree_entries(&root, &expanded, &mut cache);
+        assert!(cache.is_loaded(&sub), "展开后应读取并缓存子目录");
+        let child = expanded_entries
+            .iter()
+            .find(|entry| entry.name == "main.rs")
+            .expect("展开后应包含子文件");
+        assert_eq!(child.depth, 1);
 
-        if is_dir && expanded_dirs.contains(&entry_path) {
-            collect_tree_entries_recursive(entry_path.as_path(), depth + 1, expanded_dirs, output);
+        // 缓存命中时不会看到新文件，失效后才重新读取。
+        fs::write(sub.join("lib.rs"), "").expect("写入文件失败");
+        let cached = collect_tree_entries(&root, &expanded, &mut cache);
+        assert!(!cached.iter().any(|entry| entry.name == "lib.rs"));
+        cache.invalidate(&sub);
+        let refreshed = collect_tree_entries(&root, &expanded, &mut cache);
+        assert!(refreshed.iter().any(|entry| entry.name == "lib.rs"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_read_dir_listing_reports_hidden_children_over_cap() {
+        let root = temp_root("cap");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(root.join(name), "").expect("写入文件失败");
         }
+
+        let listing = read_dir_listing(&root, 2, TreeSort::DirsFirst);
+        let names = listing
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.txt", "b.txt"]);
+        assert_eq!(listing.hidden, 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    // 构造排序用的样例：两个目录、三个文件，名称大小写混杂。
+    fn sample_entries() -> Vec<ListingEntry> {
+        let base = UNIX_EPOCH + Duration::from_secs(1_000);
+        [
+            ("b.txt", false, Some(30)),
+            ("Docs", true, Some(10)),
+            ("a.txt", false, Some(20)),
+            ("src", true, Some(40)),
+            ("README.md", false, None),
+        ]
+        .into_iter()
+        .map(|(name, is_dir, secs)| ListingEntry {
+            path: PathBuf::from(name),
+            is_dir,
+            name: name.to_string(),
+            modified: secs.map(|secs| base + Duration::from_secs(secs)),
+        })
+        .collect()
+    }
+
+    fn sorted_names(sort: TreeSort) -> Vec<String> {
+        let mut entries = sample_entries();
+        sort_listing_entries(&mut entries, sort);
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    #[test]
+    fn test_sort_dirs_first_ignores_case() {
+        assert_eq!(
+            sorted_names(TreeSort::DirsFirst),
+            vec!["Docs", "src", "a.txt", "b.txt", "README.md"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_name_mixes_dirs_and_files() {
+        assert_eq!(
+            sorted_names(TreeSort::Name),
+            vec!["a.txt", "b.txt", "Docs", "README.md", "src"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_modified_puts_newest_first() {
+        assert_eq!(
+            sorted_names(TreeSort::Modified),
+            vec!["src", "b.txt", "a.txt", "Docs", "README.md"],
+            "没有修改时间的条目应排在最后"
+        );
+    }
+
+    #[test]
+    fn test_set_sort_clears_cached_listings() {
+        let root = temp_root("sort");
+        fs::write(root.join("a.txt"), "").expect("写入文件失败");
+        fs::create_dir_all(root.join("m")).expect("创建子目录失败");
+        fs::write(root.join("Z.txt"), "").expect("写入文件失败");
+
+        let mut cache = TreeCache::default();
+        let expanded = BTreeSet::new();
+        let names = |cache: &mut TreeCache| {
+            collect_tree_entries(&root, &expanded, cache)
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&mut cache), vec!["m", "a.txt", "Z.txt"]);
+
+        cache.set_sort(TreeSort::Name);
+        assert!(!cache.is_loaded(&root), "切换排序后应重新读取目录");
+        assert_eq!(names(&mut cache), vec!["a.txt", "m", "Z.txt"]);
+        assert_eq!(TreeSort::DirsFirst.next(), TreeSort::Name);
+        assert_eq!(TreeSort::Modified.next(), TreeSort::DirsFirst);
+
+        let _ = fs::remove_dir_all(&root);
     }
 }
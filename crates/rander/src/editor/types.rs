@@ -3,13 +3,18 @@ use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
+    time::Instant,
 };
 
 use ratatui::style::Color;
 
 use lsp::{LspCompletionItem, LspSemanticToken};
 
-use super::utils::{char_count, char_to_byte_index, file_name_or, is_word_char};
+use super::{
+    config::is_large_file,
+    editorconfig::FormatSettings,
+    utils::{char_count, char_to_byte_index, file_name_or, is_word_char},
+};
 
 // 功能说明：见下方实现。
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,6 +30,18 @@ pub enum EditorMode {
     /// 这里使用独立模式而非复用 NORMAL 命令串，是为了避免把“参数输入”与“命令触发”
     /// 混在一起，降低误触发和命令前缀冲突的概率。
     RenameInput,
+    /// `willSaveWaitUntil` 编辑确认模式。
+    ///
+    /// 与写入审批一致，用独立模式强制用户先做出决定，避免按键误落到缓冲区。
+    WillSaveConfirm,
+    /// LSP 服务端能力面板（只读浮层，任意关闭键返回 NORMAL）。
+    CapabilitiesView,
+    /// 定义 peek 浮层：在当前缓冲区上方只读展示定义附近的代码，关闭后光标位置不变。
+    PeekView,
+    /// `:` 命令行输入模式，用于带参数的编辑器命令（如 `:lsplog <path>`）。
+    CommandLine,
+    /// 调用层级浮层：展开调用者 / 被调用者，选中后跳转。
+    CallHierarchy,
 }
 
 // 功能说明：见下方实现。
@@ -136,6 +153,8 @@ pub(super) struct TreeEntry {
     pub(super) depth: usize,
     pub(super) is_dir: bool,
     pub(super) name: String,
+    /// `Some(n)` 表示这是“+N more”占位节点，`path` 指向被截断的目录。
+    pub(super) more_count: Option<usize>,
 }
 
 /// editor 展示层使用的补全候选。
@@ -143,12 +162,17 @@ pub(super) struct TreeEntry {
 /// 设计为结构体而不是字符串，目的是同时保留：
 /// - `label`：用于 popover 展示；
 /// - `insert_text`：用于真正插入到缓冲区；
-/// - `detail`：用于展示更完整的 LSP 上下文提示。
+/// - `detail`：用于展示更完整的 LSP 上下文提示；
+/// - `label_detail` / `label_description`：来自 `labelDetails`，存在时优先于 `detail` 展示。
 #[derive(Debug, Clone)]
 pub(super) struct CompletionDisplayItem {
     pub(super) label: String,
     pub(super) insert_text: String,
     pub(super) detail: Option<String>,
+    pub(super) label_detail: Option<String>,
+    pub(super) label_description: Option<String>,
+    /// 服务端标记为已弃用，弹窗中以删除线弱化显示。
+    pub(super) deprecated: bool,
 }
 
 // 功能说明：见下方实现。
@@ -188,6 +212,21 @@ pub(super) struct EditorBuffer {
     /// 将 token 预先分组到行级，可以把渲染时复杂度降到 O(当前行 token 数)，
     /// 避免每一帧都全量扫描 token 列表。
     pub(super) lsp_tokens_by_line: HashMap<usize, Vec<LspSemanticToken>>,
+    /// 是否在该缓冲区强制关闭 LSP 语义高亮、回退到 syntect。
+    ///
+    /// 只影响渲染路径选择，不清空已缓存的 token，
+    /// 这样来回切换时无需重新请求即可立刻对比两种高亮效果。
+    pub(super) semantic_highlight_disabled: bool,
+    /// 是否按窗格宽度软换行显示。
+    ///
+    /// 只改变渲染与上下移动（按显示行），逻辑行、字符列与 LSP 位置保持不变；
+    /// 按缓冲区切换，长文本与代码可以在同一会话里分别选择。
+    pub(super) soft_wrap: bool,
+    /// 是否以大文件模式打开。
+    ///
+    /// 打开时按文件大小一次性决定：该模式下只渲染纯文本并跳过 LSP 同步，
+    /// 保留基础的移动与编辑能力。
+    pub(super) large_file: bool,
 }
 
 impl EditorBuffer {
@@ -207,11 +246,15 @@ impl EditorBuffer {
             lsp_completion_items: Vec::new(),
             lsp_semantic_tokens: Vec::new(),
             lsp_tokens_by_line: HashMap::new(),
+            semantic_highlight_disabled: false,
+            soft_wrap: false,
+            large_file: false,
         }
     }
 
-    // 从文件加载缓冲区。
-    pub(super) fn from_file(path: &Path) -> std::io::Result<Self> {
+    // 从文件加载缓冲区；超过 `large_file_bytes` 时进入大文件模式。
+    pub(super) fn from_file(path: &Path, large_file_bytes: u64) -> std::io::Result<Self> {
+        let large_file = is_large_file(fs::metadata(path)?.len(), large_file_bytes);
         let content = fs::read_to_string(path)?;
         let mut lines: Vec<String> = content.lines().map(ToString::to_string).collect();
         if lines.is_empty() {
@@ -231,6 +274,9 @@ impl EditorBuffer {
             lsp_completion_items: Vec::new(),
             lsp_semantic_tokens: Vec::new(),
             lsp_tokens_by_line: HashMap::new(),
+            semantic_highlight_disabled: false,
+            soft_wrap: false,
+            large_file,
         })
     }
 
@@ -394,8 +440,15 @@ impl EditorBuffer {
         self.lsp_dirty = true;
     }
 
-    // 保存缓冲区内容到文件。
-    pub(super) fn save(&mut self, cwd: &Path) -> std::io::Result<PathBuf> {
+    /// 保存缓冲区内容到文件。
+    ///
+    /// 保存前按 `settings` 执行去除行尾空白等变换，并按行尾/字符集设置编码写盘；
+    /// 变换会同步回缓冲区，保证编辑器显示与磁盘内容一致。
+    pub(super) fn save(
+        &mut self,
+        cwd: &Path,
+        settings: &FormatSettings,
+    ) -> std::io::Result<PathBuf> {
         let path = match &self.path {
             Some(path) => path.clone(),
             None => {
@@ -404,7 +457,11 @@ impl EditorBuffer {
                 generated
             }
         };
-        fs::write(&path, self.lines.join("\n"))?;
+        if settings.apply_save_transforms(&mut self.lines) {
+            self.lsp_dirty = true;
+            self.ensure_cursor_in_bounds();
+        }
+        fs::write(&path, settings.encode_lines(&self.lines))?;
         self.modified = false;
         Ok(path)
     }
@@ -417,4 +474,8 @@ pub(super) struct TabState {
     pub(super) buffer_index: usize,
     pub(super) split: SplitDirection,
     pub(super) focus: PaneFocus,
+    /// 是否固定：固定标签页排在最左侧，且不能被 `tc` 关闭。
+    pub(super) pinned: bool,
+    /// 最近一次处于激活状态的时间，`max_tabs` 超限时据此淘汰最久未使用的标签页。
+    pub(super) last_focused: Instant,
 }
@@ -0,0 +1,35 @@
+/// 打字机滚动下的首个可见行：让光标行尽量停在窗格垂直中央。
+///
+/// 靠近文件开头时无法居中，首行停在 0；靠近文件末尾时不再继续滚动，
+/// 避免为了居中在末尾之后露出大片空白。
+pub(super) fn centered_scroll_row(cursor_row: usize, visible: usize, total_lines: usize) -> usize {
+    let max_scroll = total_lines.saturating_sub(visible);
+    cursor_row.saturating_sub(visible / 2).min(max_scroll)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::centered_scroll_row;
+
+    #[test]
+    fn test_centered_scroll_row_keeps_cursor_in_middle() {
+        // 10 行可见，光标行位于第 5 行（下标）处。
+        assert_eq!(centered_scroll_row(50, 10, 100), 45);
+        assert_eq!(centered_scroll_row(51, 10, 100), 46);
+        // 奇数高度时光标正好在中间一行。
+        assert_eq!(centered_scroll_row(50, 9, 100), 46);
+    }
+
+    #[test]
+    fn test_centered_scroll_row_near_file_edges() {
+        assert_eq!(centered_scroll_row(0, 10, 100), 0, "文件开头无法居中");
+        assert_eq!(centered_scroll_row(4, 10, 100), 0);
+        assert_eq!(centered_scroll_row(99, 10, 100), 90, "末尾不滚出空白");
+        assert_eq!(centered_scroll_row(96, 10, 100), 90);
+        assert_eq!(
+            centered_scroll_row(3, 10, 5),
+            0,
+            "文件比窗格短时始终从头显示"
+        );
+    }
+}
@@ -39,9 +39,10 @@ pub(super) fn contains_point(area: Rect, x: u16, y: u16) -> bool {
 // 判断当前输入是否为已知命令前缀。
 pub(super) fn is_normal_command_prefix(prefix: &str) -> bool {
     const COMMANDS: &[&str] = &[
-        "fs", "fl", "sv", "sp", "sh", "sl", "sj", "sk", "tn", "tl", "th", "tb", "tc", "tt", "te",
-        "e", "pi", "pu", "ci", "cu", "w", "q", "fa", "ff", "fh", "fc", "lc", "lr", "lf", "lq",
-        "fb", "[g", "]g", "K",
+        "fs", "fl", "sv", "sp", "sh", "sl", "sj", "sk", "tn", "tl", "th", "tb", "tc", "tu", "tt",
+        "te", "tp", "tH", "tL", "e", "pi", "pu", "ci", "cu", "ch", "w", "q", "fa", "ff", "fh",
+        "fc", "lc", "la", "li", "gp", "lr", "lf", "lq", "fb", "[g", "]g", "[G", "]G", "[r", "]r",
+        "[b", "]b", "K", "dc", "gs", "gw", "tw", "to", "fx", "fmt", "yp", "yl", "yr",
     ];
     COMMANDS.iter().any(|cmd| cmd.starts_with(prefix))
 }
@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use lsp::{LspLanguage, LspTextEdit};
+
+/// 用户针对某种语言记住的 `willSaveWaitUntil` 处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum WillSaveChoice {
+    /// 总是直接应用服务端返回的编辑。
+    Apply,
+    /// 总是丢弃服务端返回的编辑。
+    Skip,
+}
+
+/// 收到 `willSaveWaitUntil` 编辑后的处理决策。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum WillSaveDecision {
+    Apply,
+    Skip,
+    /// 弹出预览，等待用户确认。
+    Prompt,
+}
+
+/// 等待用户确认的 `willSaveWaitUntil` 编辑。
+#[derive(Debug, Clone)]
+pub(super) struct PendingWillSaveEdits {
+    pub(super) file_path: PathBuf,
+    /// 编辑所属语言；无法识别时不支持“记住选择”。
+    pub(super) language: Option<LspLanguage>,
+    pub(super) edits: Vec<LspTextEdit>,
+}
+
+/// 根据预览开关与该语言记住的选择，决定如何处理服务端编辑。
+///
+/// 预览默认关闭以保持原有“保存时静默应用”的行为；
+/// 开启后，已记住选择的语言不再打扰用户，其余语言每次都弹出确认。
+pub(super) fn decide_will_save_action(
+    preview_enabled: bool,
+    remembered: Option<WillSaveChoice>,
+) -> WillSaveDecision {
+    if !preview_enabled {
+        return WillSaveDecision::Apply;
+    }
+
+    match remembered {
+        Some(WillSaveChoice::Apply) => WillSaveDecision::Apply,
+        Some(WillSaveChoice::Skip) => WillSaveDecision::Skip,
+        None => WillSaveDecision::Prompt,
+    }
+}
+
+/// 汇总编辑的数量、类型与涉及行范围，用于确认弹窗展示。
+///
+/// 只展示概要而不是完整 diff：弹窗空间有限，用户需要的是判断
+/// “这次保存会不会被大面积改写”，细节可在应用后直接查看缓冲区。
+pub(super) fn summarize_text_edits(edits: &[LspTextEdit]) -> String {
+    let mut inserts = 0;
+    let mut deletes = 0;
+    let mut replaces = 0;
+    for edit in edits {
+        let empty_range =
+            edit.start_line == edit.end_line && edit.start_character == edit.end_character;
+        match (empty_range, edit.new_text.is_empty()) {
+            (true, _) => inserts += 1,
+            (false, true) => deletes += 1,
+            (false, false) => replaces += 1,
+        }
+    }
+
+    let mut summary = format!(
+        "{} 条编辑（插入 {inserts}、删除 {deletes}、替换 {replaces}）",
+        edits.len()
+    );
+    let first_line = edits.iter().map(|edit| edit.start_line).min();
+    let last_line = edits.iter().map(|edit| edit.end_line).max();
+    if let (Some(first), Some(last)) = (first_line, last_line) {
+        if first == last {
+            summary.push_str(&format!("，涉及第 {} 行", first + 1));
+        } else {
+            summary.push_str(&format!("，涉及第 {}-{} 行", first + 1, last + 1));
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp::LspTextEdit;
+
+    use super::{WillSaveChoice, WillSaveDecision, decide_will_save_action, summarize_text_edits};
+
+    fn edit(start: (usize, usize), end: (usize, usize), new_text: &str) -> LspTextEdit {
+        LspTextEdit {
+            start_line: start.0,
+            start_character: start.1,
+            end_line: end.0,
+            end_character: end.1,
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decide_will_save_action_without_preview_always_applies() {
+        for remembered in [
+            None,
+            Some(WillSaveChoice::Apply),
+            Some(WillSaveChoice::Skip),
+        ] {
+            assert_eq!(
+                decide_will_save_action(false, remembered),
+                WillSaveDecision::Apply,
+                "未开启预览时应保持静默应用"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decide_will_save_action_with_preview_respects_remembered_choice() {
+        assert_eq!(
+            decide_will_save_action(true, None),
+            WillSaveDecision::Prompt
+        );
+        assert_eq!(
+            decide_will_save_action(true, Some(WillSaveChoice::Apply)),
+            WillSaveDecision::Apply
+        );
+        assert_eq!(
+            decide_will_save_action(true, Some(WillSaveChoice::Skip)),
+            WillSaveDecision::Skip
+        );
+    }
+
+    #[test]
+    fn test_summarize_text_edits_counts_edit_kinds_and_lines() {
+        let edits = vec![
+            edit((1, 0), (1, 0), "use std::fmt;\n"),
+            edit((4, 2), (4, 6), ""),
+            edit((9, 0), (9, 4), "    "),
+        ];
+
+        assert_eq!(
+            summarize_text_edits(&edits),
+            "3 条编辑（插入 1、删除 1、替换 1），涉及第 2-10 行"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+
+use super::utils::file_name_or;
+
+/// 失败详情中最多列出的文件数，其余折叠为“等 N 个文件”，避免状态栏被撑满。
+const MAX_LISTED_FAILURES: usize = 3;
+
+/// `WorkspaceEdit` 中单个文件的应用结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct WorkspaceEditFileOutcome {
+    pub(super) path: PathBuf,
+    /// 实际应用的编辑数。
+    pub(super) applied_edits: usize,
+    /// 失败原因；`None` 表示该文件应用成功。
+    pub(super) error: Option<String>,
+}
+
+/// 工作区编辑应用结果：按文件记录成功与失败，计数从中汇总。
+///
+/// 只给出“N 个文件失败”时用户无从下手，保留逐文件结果后，
+/// 状态栏与 `workspace/applyEdit` 回包都能指出具体是哪些文件、因为什么失败。
+#[derive(Debug, Default)]
+pub(super) struct WorkspaceEditApplySummary {
+    pub(super) files: Vec<WorkspaceEditFileOutcome>,
+}
+
+impl WorkspaceEditApplySummary {
+    /// 记录一个文件的应用结果；没有实际应用任何编辑也视为失败。
+    pub(super) fn record(&mut self, path: PathBuf, result: Result<usize, String>) {
+        let (applied_edits, error) = match result {
+            Ok(0) => (0, Some("没有可应用的编辑".to_string())),
+            Ok(applied) => (applied, None),
+            Err(error) => (0, Some(error)),
+        };
+        self.files.push(WorkspaceEditFileOutcome {
+            path,
+            applied_edits,
+            error,
+        });
+    }
+
+    /// 成功应用的文件数。
+    pub(super) fn touched_files(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.error.is_none())
+            .count()
+    }
+
+    /// 成功应用的编辑总数。
+    pub(super) fn applied_edits(&self) -> usize {
+        self.files.iter().map(|file| file.applied_edits).sum()
+    }
+
+    /// 应用失败的文件数。
+    pub(super) fn failed_files(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.error.is_some())
+            .count()
+    }
+
+    /// 失败文件的说明，形如 `a.rs（读取失败: ...）、b.rs（...）`；全部成功时返回 `None`。
+    pub(super) fn failure_details(&self) -> Option<String> {
+        let failed: Vec<_> = self
+            .files
+            .iter()
+            .filter_map(|file| file.error.as_ref().map(|error| (&file.path, error)))
+            .collect();
+        if failed.is_empty() {
+            return None;
+        }
+        let mut parts: Vec<String> = failed
+            .iter()
+            .take(MAX_LISTED_FAILURES)
+            .map(|(path, error)| format!("{}（{}）", file_name_or(path, "<unknown>"), error))
+            .collect();
+        if failed.len() > MAX_LISTED_FAILURES {
+            parts.push(format!("等 {} 个文件", failed.len()));
+        }
+        Some(parts.join("、"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use lsp::{LspTextEdit, LspWorkspaceEdit, LspWorkspaceFileEdit};
+
+    use super::WorkspaceEditApplySummary;
+    use crate::editor::Editor;
+
+    fn insert_at_start(text: &str) -> LspTextEdit {
+        LspTextEdit {
+            start_line: 0,
+            start_character: 0,
+            end_line: 0,
+            end_character: 0,
+            new_text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_workspace_edit_records_per_file_outcomes() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root = std::env::temp_dir().join(format!(
+            "order-workspace-edit-{}-{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&root).expect("创建测试目录失败");
+        let ok_file = root.join("ok.rs");
+        fs::write(&ok_file, "fn main() {}\n").expect("写入测试文件失败");
+        let missing_file = root.join("missing.rs");
+
+        let mut editor = Editor::new(root.clone());
+        let summary = editor.apply_workspace_edit(LspWorkspaceEdit {
+            document_edits: vec![
+                LspWorkspaceFileEdit {
+                    file_path: ok_file.clone(),
+                    edits: vec![insert_at_start("// a\n"), insert_at_start("// b\n")],
+                },
+                LspWorkspaceFileEdit {
+                    file_path: missing_file.clone(),
+                    edits: vec![insert_at_start("// c\n")],
+                },
+            ],
+        });
+
+        assert_eq!(summary.files.len(), 2);
+        assert_eq!(summary.files[0].path, ok_file);
+        assert_eq!(summary.files[0].applied_edits, 2);
+        assert_eq!(summary.files[0].error, None);
+        assert_eq!(summary.files[1].path, missing_file);
+        assert_eq!(summary.files[1].applied_edits, 0);
+        assert!(
+            summary.files[1]
+                .error
+                .as_deref()
+                .is_some_and(|error| error.starts_with("读取失败")),
+            "失败文件应记录原因"
+        );
+        assert_eq!(
+            (
+                summary.touched_files(),
+                summary.applied_edits(),
+                summary.failed_files()
+            ),
+            (1, 2, 1)
+        );
+        assert!(
+            summary
+                .failure_details()
+                .is_some_and(|details| details.starts_with("missing.rs（读取失败")),
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_failure_details_lists_limited_files() {
+        let mut summary = WorkspaceEditApplySummary::default();
+        summary.record(PathBuf::from("src/ok.rs"), Ok(1));
+        assert_eq!(summary.failure_details(), None);
+
+        summary.record(PathBuf::from("src/empty.rs"), Ok(0));
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            summary.record(PathBuf::from(name), Err("写入失败: denied".to_string()));
+        }
+        assert_eq!(summary.failed_files(), 4);
+        assert_eq!(
+            summary.failure_details().as_deref(),
+            Some(
+                "empty.rs（没有可应用的编辑）、a.rs（写入失败: denied）、b.rs（写入失败: denied）、等 4 个文件"
+            )
+        );
+    }
+}
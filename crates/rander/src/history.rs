@@ -7,6 +7,8 @@ use std::{
     collections::HashMap,
     env, fs,
     path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
 };
 
 /// 默认任务 ID。
@@ -239,6 +241,8 @@ pub struct ContextManager {
     memory_file: ContextMemoryFile,
     /// 上下文压缩器。
     compressor: ContextCompressor,
+    /// 已从后台记忆线程应用的最新结果序号。
+    applied_memory_seq: Option<u64>,
 }
 
 impl Default for ContextManager {
@@ -271,6 +275,7 @@ impl ContextManager {
             memory_path,
             memory_file,
             compressor: ContextCompressor::default(),
+            applied_memory_seq: None,
         }
     }
 
@@ -297,6 +302,24 @@ impl ContextManager {
         )
     }
 
+    /// 为后台构建上下文取快照，携带当前已应用的长期记忆。
+    ///
+    /// 快照在主线程发起请求时生成，之后主线程对记忆的更新不影响本次构建，
+    /// 结果与同一时刻直接调用 [`Self::build_history`] 一致。
+    pub fn build_job(
+        &self,
+        current_prompt: &str,
+        messages: Vec<ContextMessage>,
+        limits: ContextModelLimits,
+    ) -> ContextBuildJob {
+        ContextBuildJob {
+            manager: self.clone(),
+            prompt: current_prompt.to_string(),
+            messages,
+            limits,
+        }
+    }
+
     /// 从近期会话中抽取长期记忆并落盘。
     ///
     /// 副作用：
@@ -335,6 +358,56 @@ impl ContextManager {
         write_memory_file(&self.memory_path, &self.memory_file)
     }
 
+    /// 启动长期记忆后台线程，线程持有当前管理器的一份副本。
+    ///
+    /// 启动后记忆更新都应交给返回的 [`MemoryWorker`]，不要再在主线程直接调用
+    /// [`Self::update_long_term_memory`]：后台副本才是写盘的权威状态，
+    /// 两边同时修改会让后写入的一方覆盖另一方的结果。
+    pub fn spawn_memory_worker(&self) -> MemoryWorker {
+        let (job_sender, job_receiver) = mpsc::channel::<(u64, Vec<ContextMessage>)>();
+        let (result_sender, result_receiver) = mpsc::channel::<MemoryUpdate>();
+        let mut worker_manager = self.clone();
+        let handle = thread::spawn(move || {
+            // 单线程按提交顺序处理，保证每次更新都基于上一次的结果。
+            for (seq, messages) in job_receiver {
+                let error = worker_manager
+                    .update_long_term_memory(&messages)
+                    .err()
+                    .map(|error| format!("{error:#}"));
+                let update = MemoryUpdate {
+                    seq,
+                    memory_file: worker_manager.memory_file.clone(),
+                    error,
+                };
+                if result_sender.send(update).is_err() {
+                    break;
+                }
+            }
+        });
+
+        MemoryWorker {
+            jobs: Some(job_sender),
+            results: result_receiver,
+            handle: Some(handle),
+            next_seq: 0,
+        }
+    }
+
+    /// 把后台线程的记忆结果同步回主线程副本，供后续 [`Self::build_history`] 使用。
+    ///
+    /// 只接受比已应用结果更新的序号，迟到的旧结果直接丢弃，
+    /// 返回本次更新携带的写盘错误（如有）。
+    pub fn apply_memory_update(&mut self, update: MemoryUpdate) -> Option<String> {
+        if self
+            .applied_memory_seq
+            .is_none_or(|applied| update.seq > applied)
+        {
+            self.memory_file = update.memory_file;
+            self.applied_memory_seq = Some(update.seq);
+        }
+        update.error
+    }
+
     #[cfg(test)]
     fn new_for_test(task_id: &str, memory_path: PathBuf, compressor: ContextCompressor) -> Self {
         Self {
@@ -342,10 +415,81 @@ impl ContextManager {
             memory_path,
             memory_file: ContextMemoryFile::default(),
             compressor,
+            applied_memory_seq: None,
         }
     }
 }
 
+/// 交给后台线程执行的一次上下文构建。
+#[derive(Debug, Clone)]
+pub struct ContextBuildJob {
+    manager: ContextManager,
+    prompt: String,
+    messages: Vec<ContextMessage>,
+    limits: ContextModelLimits,
+}
+
+impl ContextBuildJob {
+    /// 执行压缩与摘要，返回发送给模型的历史与剩余上下文比例。
+    pub fn run(&self) -> ContextBuildResult {
+        self.manager
+            .build_history(&self.prompt, &self.messages, self.limits)
+    }
+}
+
+/// 后台记忆线程完成一次更新后回传的结果。
+#[derive(Debug)]
+pub struct MemoryUpdate {
+    /// 对应任务的提交序号，单调递增。
+    seq: u64,
+    /// 更新后的完整记忆快照。
+    memory_file: ContextMemoryFile,
+    /// 写盘失败时的错误描述。
+    error: Option<String>,
+}
+
+/// 长期记忆后台线程的句柄。
+///
+/// 记忆抽取与写盘在后台执行，主循环通过 [`Self::try_recv`] 取回结果，
+/// 再交给 [`ContextManager::apply_memory_update`] 应用，避免大会话收尾时卡住界面。
+#[derive(Debug)]
+pub struct MemoryWorker {
+    jobs: Option<Sender<(u64, Vec<ContextMessage>)>>,
+    results: Receiver<MemoryUpdate>,
+    handle: Option<JoinHandle<()>>,
+    next_seq: u64,
+}
+
+impl MemoryWorker {
+    /// 提交一次记忆更新；后台线程已退出时返回 `false`，由调用方回退为同步更新。
+    pub fn submit(&mut self, messages: Vec<ContextMessage>) -> bool {
+        let Some(jobs) = self.jobs.as_ref() else {
+            return false;
+        };
+        if jobs.send((self.next_seq, messages)).is_err() {
+            return false;
+        }
+        self.next_seq += 1;
+        true
+    }
+
+    // 非阻塞地取出一条已完成的结果。
+    pub fn try_recv(&self) -> Option<MemoryUpdate> {
+        self.results.try_recv().ok()
+    }
+
+    /// 停止接收新任务并等待排队中的更新全部写盘，返回尚未应用的结果。
+    ///
+    /// 退出前调用，保证最后一轮对话的记忆不会因进程结束而丢失。
+    pub fn finish(mut self) -> Vec<MemoryUpdate> {
+        self.jobs = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.results.try_iter().collect()
+    }
+}
+
 /// 长期记忆文件结构。
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct ContextMemoryFile {
@@ -1135,6 +1279,123 @@ mod tests {
         );
     }
 
+    #[test]
+    fn build_job_should_match_synchronous_build() {
+        let compressor = ContextCompressor {
+            short_term_rounds: 1,
+            max_short_term_messages: 2,
+            ..ContextCompressor::default()
+        };
+        let mut manager = ContextManager::new_for_test("task-j", temp_memory_path(), compressor);
+        manager
+            .update_long_term_memory(&[context_message(
+                ContextRole::User,
+                "必须使用 UTF-8 编码",
+                true,
+            )])
+            .expect("memory update should succeed");
+        let messages = vec![
+            context_message(ContextRole::User, "请修复解析器", true),
+            context_message(ContextRole::Assistant, "已修复解析器", true),
+            context_message(ContextRole::User, "再补充测试", true),
+            context_message(ContextRole::Assistant, "已补充测试", true),
+        ];
+
+        let sync = manager.build_history("继续", &messages, ContextModelLimits::default());
+        let job = manager.build_job("继续", messages, ContextModelLimits::default());
+        // 取快照后的记忆更新不影响已提交的构建。
+        manager
+            .update_long_term_memory(&[context_message(
+                ContextRole::User,
+                "最终决定采用最小改动方案",
+                true,
+            )])
+            .expect("memory update should succeed");
+        let built = std::thread::spawn(move || job.run())
+            .join()
+            .expect("build thread should not panic");
+
+        assert_eq!(built.history, sync.history);
+        assert_eq!(built.context_remaining, sync.context_remaining);
+
+        if let Some(parent) = manager.memory_path.parent() {
+            let _ = fs::remove_dir_all(parent);
+        }
+    }
+
+    #[test]
+    fn memory_worker_should_match_synchronous_update() {
+        let rounds = vec![
+            vec![context_message(
+                ContextRole::User,
+                "必须使用 UTF-8 编码",
+                true,
+            )],
+            vec![
+                context_message(ContextRole::User, "必须使用 UTF-8 编码", true),
+                context_message(ContextRole::User, "用户偏好：优先修复根因", true),
+            ],
+            vec![context_message(
+                ContextRole::User,
+                "最终决定采用最小改动方案",
+                true,
+            )],
+        ];
+
+        let sync_path = temp_memory_path();
+        let mut sync_manager =
+            ContextManager::new_for_test("task-w", sync_path.clone(), ContextCompressor::default());
+        for messages in &rounds {
+            sync_manager
+                .update_long_term_memory(messages)
+                .expect("sync update should succeed");
+        }
+
+        let worker_path = temp_memory_path();
+        let mut worker_manager = ContextManager::new_for_test(
+            "task-w",
+            worker_path.clone(),
+            ContextCompressor::default(),
+        );
+        let mut worker = worker_manager.spawn_memory_worker();
+        for messages in &rounds {
+            assert!(worker.submit(messages.clone()));
+        }
+        let mut updates = worker.finish();
+        assert_eq!(updates.len(), rounds.len(), "每次提交都应回传一条结果");
+        // 倒序应用：迟到的旧结果不能覆盖已应用的新结果。
+        updates.reverse();
+        for update in updates {
+            assert!(worker_manager.apply_memory_update(update).is_none());
+        }
+
+        let memory_lists = |manager: &ContextManager| {
+            let task = manager
+                .memory_file
+                .tasks
+                .get("task-w")
+                .cloned()
+                .unwrap_or_default();
+            (task.project_rules, task.preferences, task.key_decisions)
+        };
+        assert_eq!(memory_lists(&worker_manager), memory_lists(&sync_manager));
+        assert_eq!(
+            read_memory_file(&worker_path)
+                .expect("worker memory file should be readable")
+                .tasks
+                .get("task-w")
+                .map(|task| task.key_decisions.clone()),
+            Some(vec!["最终决定采用最小改动方案".to_string()]),
+            "后台线程应完成写盘"
+        );
+
+        for path in [sync_path, worker_path] {
+            if let Some(parent) = path.parent() {
+                let _ = fs::remove_dir_all(parent);
+            }
+        }
+    }
+
     #[test]
     fn build_long_term_memory_prompt_should_skip_low_signal_items() {
         let task_memory = TaskMemory {
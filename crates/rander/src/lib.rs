@@ -1,8 +1,17 @@
 pub mod editor;
 pub mod focus_status;
 pub mod history;
+pub mod output_rules;
+pub mod paste_guard;
+pub mod replace_view;
+pub mod sanitize;
+pub mod size_guard;
+pub mod submit_key;
 pub mod tui;
+pub mod validation_gate;
+pub mod validation_view;
 pub mod widget;
+pub mod write_policy;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use core::settings::load_settings;
+use regex::Regex;
+use serde::Deserialize;
+
+/// 输出改写规则文件名（项目配置位于 `.order/`，用户全局配置位于 `~/.config/order/`）。
+const OUTPUT_RULES_FILE: &str = "output_rules.json";
+
+/// `output_rules.json` 的文件结构。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct OutputRulesFile {
+    rules: Vec<OutputRuleEntry>,
+}
+
+/// 一条正则替换规则；`replacement` 支持 `$1`、`${name}` 等捕获组引用。
+#[derive(Debug, Deserialize)]
+struct OutputRuleEntry {
+    pattern: String,
+    #[serde(default)]
+    replacement: String,
+}
+
+/// 已编译的模型回复改写规则，按配置顺序依次应用。
+///
+/// 用于把绝对路径改写为仓库相对路径、遮盖令牌等；
+/// 没有规则文件或规则为空时不做任何处理。
+#[derive(Debug, Default)]
+pub struct OutputRules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl OutputRules {
+    /// 读取并编译规则，同时返回需要提示给用户的警告。
+    ///
+    /// 文件解析失败时整体停用；单条正则无效时跳过该条，其余规则照常生效。
+    pub fn load(workspace_root: &Path) -> (Self, Vec<String>) {
+        match load_settings::<OutputRulesFile>(workspace_root, OUTPUT_RULES_FILE, &[]) {
+            Ok(file) => Self::compile(file.rules),
+            Err(error) => (
+                Self::default(),
+                vec![format!(
+                    "{OUTPUT_RULES_FILE} 解析失败，已停用输出改写: {error}"
+                )],
+            ),
+        }
+    }
+
+    // 编译规则，无效正则记录警告后跳过。
+    fn compile(entries: Vec<OutputRuleEntry>) -> (Self, Vec<String>) {
+        let mut rules = Vec::new();
+        let mut warnings = Vec::new();
+        for (index, entry) in entries.into_iter().enumerate() {
+            match Regex::new(&entry.pattern) {
+                Ok(regex) => rules.push((regex, entry.replacement)),
+                Err(error) => warnings.push(format!(
+                    "{OUTPUT_RULES_FILE} 第 {} 条规则的正则无效，已跳过: {error}",
+                    index + 1
+                )),
+            }
+        }
+        (Self { rules }, warnings)
+    }
+
+    /// 是否没有任何生效的规则。
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 依次应用全部规则，返回改写后的文本。
+    pub fn apply(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |current, (regex, replacement)| {
+                regex
+                    .replace_all(&current, replacement.as_str())
+                    .into_owned()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputRuleEntry, OutputRules};
+
+    fn rule(pattern: &str, replacement: &str) -> OutputRuleEntry {
+        OutputRuleEntry {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_replace_rules_in_order() {
+        let (rules, warnings) = OutputRules::compile(vec![
+            rule(r"/home/\w+/work/order/", ""),
+            rule(r"sk-[A-Za-z0-9]{8,}", "sk-***"),
+            rule(r"(\w+)\.rs:(\d+)", "$1.rs#L$2"),
+        ]);
+        assert!(warnings.is_empty());
+
+        let message = "见 /home/alice/work/order/src/main.rs:12，密钥 sk-abcdef123456 已失效";
+        assert_eq!(
+            rules.apply(message),
+            "见 src/main.rs#L12，密钥 sk-*** 已失效"
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex_is_skipped_with_warning() {
+        let (rules, warnings) =
+            OutputRules::compile(vec![rule("([unclosed", "x"), rule("foo", "bar")]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("第 1 条"), "警告应指出无效规则的位置");
+        assert_eq!(rules.apply("foo ([unclosed"), "bar ([unclosed");
+
+        let (empty, _) = OutputRules::compile(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.apply("原样保留"), "原样保留");
+    }
+}
@@ -0,0 +1,126 @@
+/// 默认的粘贴确认阈值（字符数）。
+///
+/// 两万字符大约是几百行代码，足够覆盖日常粘贴的报错与片段，
+/// 又能拦住误粘贴整份日志或整个文件这类会撑爆上下文的操作。
+pub const DEFAULT_PASTE_CONFIRM_CHARS: usize = 20_000;
+
+/// 超过阈值时确认浮层中的选项，顺序与 [`PasteDecision`] 一致。
+pub const PASTE_CONFIRM_OPTIONS: [&str; 3] = ["1. 全部插入", "2. 截断后插入", "3. 取消"];
+
+/// 用户对大段粘贴的处理选择。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteDecision {
+    Insert,
+    InsertTruncated,
+    Cancel,
+}
+
+impl PasteDecision {
+    /// 由确认浮层中的选中下标得到对应选择，越界时按取消处理。
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::Insert,
+            1 => Self::InsertTruncated,
+            _ => Self::Cancel,
+        }
+    }
+}
+
+/// 从 `ORDER_PASTE_CONFIRM_CHARS` 的取值解析确认阈值，`None` 表示关闭确认。
+///
+/// 未设置或无法解析时回退默认阈值：这是防误操作的保护，
+/// 拼写错误不应意外把它关掉；只有显式写 `0` / `off` 才关闭。
+pub fn paste_confirm_threshold(value: Option<&str>) -> Option<usize> {
+    let Some(value) = value.map(|value| value.trim().to_ascii_lowercase()) else {
+        return Some(DEFAULT_PASTE_CONFIRM_CHARS);
+    };
+    match value.as_str() {
+        "0" | "off" | "false" | "no" => None,
+        _ => Some(
+            value
+                .parse::<usize>()
+                .unwrap_or(DEFAULT_PASTE_CONFIRM_CHARS),
+        ),
+    }
+}
+
+// 粘贴内容的字符数超过阈值时需要确认。
+pub fn paste_needs_confirmation(text: &str, threshold: Option<usize>) -> bool {
+    threshold.is_some_and(|limit| text.chars().count() > limit)
+}
+
+/// 统一粘贴文本的行尾：终端粘贴常带 `\r\n` 或单独的 `\r`，输入框只认 `\n`。
+pub fn normalize_pasted_text(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 按字符数截断粘贴内容。
+///
+/// 截断点之前若有换行，则退回到最后一个完整行，避免把半行代码或半个单词发给模型；
+/// 整段只有一行时才在字符边界处硬截断。
+pub fn truncate_paste(text: &str, limit: usize) -> String {
+    let Some((cut, _)) = text.char_indices().nth(limit) else {
+        return text.to_string();
+    };
+    let head = &text[..cut];
+    match head.rfind('\n') {
+        Some(newline) if newline > 0 => head[..newline].to_string(),
+        _ => head.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DEFAULT_PASTE_CONFIRM_CHARS, PasteDecision, normalize_pasted_text, paste_confirm_threshold,
+        paste_needs_confirmation, truncate_paste,
+    };
+
+    #[test]
+    fn test_paste_confirm_threshold_decision() {
+        assert_eq!(
+            paste_confirm_threshold(None),
+            Some(DEFAULT_PASTE_CONFIRM_CHARS)
+        );
+        assert_eq!(paste_confirm_threshold(Some(" 100 ")), Some(100));
+        assert_eq!(paste_confirm_threshold(Some("off")), None);
+        assert_eq!(paste_confirm_threshold(Some("0")), None);
+        assert_eq!(
+            paste_confirm_threshold(Some("lots")),
+            Some(DEFAULT_PASTE_CONFIRM_CHARS),
+            "无法解析时应回退默认阈值而不是关闭确认"
+        );
+
+        assert!(
+            !paste_needs_confirmation("一二三", Some(3)),
+            "等于阈值不需要确认"
+        );
+        assert!(
+            paste_needs_confirmation("一二三四", Some(3)),
+            "按字符而非字节计数"
+        );
+        assert!(!paste_needs_confirmation(&"x".repeat(100_000), None));
+
+        assert_eq!(PasteDecision::from_index(1), PasteDecision::InsertTruncated);
+        assert_eq!(PasteDecision::from_index(9), PasteDecision::Cancel);
+    }
+
+    #[test]
+    fn test_truncate_paste_keeps_whole_lines() {
+        let text = normalize_pasted_text("line one\r\nline two\rline three");
+        assert_eq!(text, "line one\nline two\nline three");
+
+        assert_eq!(
+            truncate_paste(&text, 12),
+            "line one",
+            "应退回到最后一个完整行"
+        );
+        assert_eq!(truncate_paste(&text, 100), text, "未超过上限时原样返回");
+        assert_eq!(
+            truncate_paste("中文单行内容", 2),
+            "中文",
+            "单行时按字符边界截断"
+        );
+        assert_eq!(truncate_paste("\nabcdef", 3), "\nab");
+    }
+}
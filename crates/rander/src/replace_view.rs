@@ -0,0 +1,326 @@
+use core::replace::ReplaceFileMatch;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// 确认浮层中最多预览的后续文件数量。
+const UPCOMING_PREVIEW_COUNT: usize = 8;
+
+/// `/replace` 的用法提示。
+pub const REPLACE_USAGE: &str = "用法：/replace <查找内容> <替换内容>（含空格或为空时用双引号包裹，如 /replace \"foo bar\" \"\"）";
+
+/// 解析 `/replace` 之后的参数，返回 `(查找内容, 替换内容)`。
+///
+/// 参数按空白切分，双引号包裹的参数可以包含空格或为空，引号内用 `\"`、`\\`
+/// 转义引号与反斜杠。必须恰好两个参数：多余的参数直接报错，
+/// 避免 `/replace foo bar baz` 之类的输入被静默截断后改写整个工作区。
+pub fn parse_replace_arguments(input: &str) -> Result<(String, String), String> {
+    let arguments = split_quoted_arguments(input)?;
+    let [pattern, replacement] = <[String; 2]>::try_from(arguments).map_err(|arguments| {
+        if arguments.len() > 2 {
+            format!(
+                "/replace 只接受两个参数，收到 {} 个；{REPLACE_USAGE}",
+                arguments.len()
+            )
+        } else {
+            REPLACE_USAGE.to_string()
+        }
+    })?;
+    if pattern.is_empty() {
+        return Err("查找内容不能为空".to_string());
+    }
+    Ok((pattern, replacement))
+}
+
+// 按空白切分参数，支持双引号包裹与引号内的 `\"` / `\\` 转义。
+fn split_quoted_arguments(input: &str) -> Result<Vec<String>, String> {
+    let mut arguments = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut argument = String::new();
+        if ch == '"' {
+            chars.next();
+            let mut closed = false;
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '"' => {
+                        closed = true;
+                        break;
+                    }
+                    '\\' => match chars.next() {
+                        Some(escaped @ ('"' | '\\')) => argument.push(escaped),
+                        Some(other) => {
+                            argument.push('\\');
+                            argument.push(other);
+                        }
+                        None => argument.push('\\'),
+                    },
+                    _ => argument.push(ch),
+                }
+            }
+            if !closed {
+                return Err("引号未闭合".to_string());
+            }
+            if chars.peek().is_some_and(|ch| !ch.is_whitespace()) {
+                return Err("引号参数后需要空格分隔".to_string());
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                argument.push(ch);
+                chars.next();
+            }
+        }
+        arguments.push(argument);
+    }
+    Ok(arguments)
+}
+
+/// 用户对当前文件的确认决定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceDecision {
+    /// 替换当前文件。
+    Accept,
+    /// 跳过当前文件。
+    Skip,
+    /// 替换当前及之后的所有文件。
+    AcceptAll,
+    /// 放弃整次替换。
+    Cancel,
+}
+
+/// 逐文件确认结束后的结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaceConfirmOutcome {
+    /// 确认完毕，携带被接受的文件（可能为空）。
+    Finished(Vec<ReplaceFileMatch>),
+    /// 用户放弃了本次替换。
+    Cancelled,
+}
+
+/// `/replace` 逐文件确认浮层状态。
+#[derive(Debug, Clone)]
+pub struct ReplaceConfirmState {
+    pub pattern: String,
+    pub replacement: String,
+    matches: Vec<ReplaceFileMatch>,
+    index: usize,
+    accepted: Vec<ReplaceFileMatch>,
+}
+
+impl ReplaceConfirmState {
+    pub fn new(pattern: String, replacement: String, matches: Vec<ReplaceFileMatch>) -> Self {
+        Self {
+            pattern,
+            replacement,
+            matches,
+            index: 0,
+            accepted: Vec::new(),
+        }
+    }
+
+    /// 浮层标题中的进度文本，例如 `2/5`。
+    pub fn progress_label(&self) -> String {
+        format!(
+            "{}/{}",
+            (self.index + 1).min(self.matches.len()),
+            self.matches.len()
+        )
+    }
+
+    /// 应用一次决定；所有文件处理完或用户放弃时返回最终结果。
+    pub fn apply(&mut self, decision: ReplaceDecision) -> Option<ReplaceConfirmOutcome> {
+        match decision {
+            ReplaceDecision::Cancel => return Some(ReplaceConfirmOutcome::Cancelled),
+            ReplaceDecision::Accept => {
+                if let Some(current) = self.matches.get(self.index) {
+                    self.accepted.push(current.clone());
+                }
+                self.index += 1;
+            }
+            ReplaceDecision::Skip => self.index += 1,
+            ReplaceDecision::AcceptAll => {
+                self.accepted
+                    .extend(self.matches.iter().skip(self.index).cloned());
+                self.index = self.matches.len();
+            }
+        }
+
+        (self.index >= self.matches.len())
+            .then(|| ReplaceConfirmOutcome::Finished(std::mem::take(&mut self.accepted)))
+    }
+
+    /// 构建浮层展示行：当前文件详情 + 后续文件预览。
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(vec![
+            Span::styled("替换 ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format!("`{}`", self.pattern),
+                Style::default().fg(Color::Red),
+            ),
+            Span::styled(" → ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format!("`{}`", self.replacement),
+                Style::default().fg(Color::Green),
+            ),
+            Span::styled(
+                format!("  已接受 {} 个文件", self.accepted.len()),
+                Style::default().fg(Color::Gray),
+            ),
+        ])];
+
+        let Some(current) = self.matches.get(self.index) else {
+            return lines;
+        };
+        lines.push(Line::default());
+        lines.push(Line::from(vec![
+            Span::styled(
+                current.path.clone(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("  {} 处匹配", current.match_count),
+                Style::default().fg(Color::Yellow),
+            ),
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!("  {:>4} | {}", current.first_line.0, current.first_line.1),
+            Style::default().fg(Color::White),
+        )));
+
+        let upcoming = self
+            .matches
+            .iter()
+            .skip(self.index + 1)
+            .take(UPCOMING_PREVIEW_COUNT)
+            .collect::<Vec<_>>();
+        if !upcoming.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                "后续文件：",
+                Style::default().fg(Color::DarkGray),
+            )));
+            for item in upcoming {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}（{} 处）", item.path, item.match_count),
+                    Style::default().fg(Color::Gray),
+                )));
+            }
+            let hidden = self
+                .matches
+                .len()
+                .saturating_sub(self.index + 1 + UPCOMING_PREVIEW_COUNT);
+            if hidden > 0 {
+                lines.push(Line::from(Span::styled(
+                    format!("  …… 另有 {hidden} 个文件"),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::replace::ReplaceFileMatch;
+
+    use super::{
+        REPLACE_USAGE, ReplaceConfirmOutcome, ReplaceConfirmState, ReplaceDecision,
+        parse_replace_arguments,
+    };
+
+    fn file_match(path: &str) -> ReplaceFileMatch {
+        ReplaceFileMatch {
+            path: path.to_string(),
+            match_count: 1,
+            first_line: (1, "old".to_string()),
+            content_hash: 0,
+        }
+    }
+
+    #[test]
+    fn replace_confirm_should_collect_per_file_and_global_decisions() {
+        let mut state = ReplaceConfirmState::new(
+            "old".to_string(),
+            "new".to_string(),
+            ["a.rs", "b.rs", "c.rs", "d.rs"]
+                .into_iter()
+                .map(file_match)
+                .collect(),
+        );
+
+        assert_eq!(state.progress_label(), "1/4");
+        assert_eq!(state.apply(ReplaceDecision::Accept), None);
+        assert_eq!(state.apply(ReplaceDecision::Skip), None);
+        assert_eq!(state.progress_label(), "3/4");
+        let outcome = state.apply(ReplaceDecision::AcceptAll);
+        assert_eq!(
+            outcome,
+            Some(ReplaceConfirmOutcome::Finished(vec![
+                file_match("a.rs"),
+                file_match("c.rs"),
+                file_match("d.rs"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn replace_confirm_cancel_should_discard_accepted_files() {
+        let mut state = ReplaceConfirmState::new(
+            "old".to_string(),
+            "new".to_string(),
+            vec![file_match("a.rs"), file_match("b.rs")],
+        );
+        assert_eq!(state.apply(ReplaceDecision::Accept), None);
+        assert_eq!(
+            state.apply(ReplaceDecision::Cancel),
+            Some(ReplaceConfirmOutcome::Cancelled)
+        );
+    }
+
+    #[test]
+    fn parse_replace_arguments_should_support_quotes_and_reject_extras() {
+        let parsed = |input: &str| parse_replace_arguments(input);
+        assert_eq!(
+            parsed(" foo  bar "),
+            Ok(("foo".to_string(), "bar".to_string()))
+        );
+        assert_eq!(
+            parsed(r#""foo bar" "baz qux""#),
+            Ok(("foo bar".to_string(), "baz qux".to_string())),
+            "引号内可以包含空格"
+        );
+        assert_eq!(
+            parsed(r#"foo """#),
+            Ok(("foo".to_string(), String::new())),
+            "空引号表示替换为空字符串"
+        );
+        assert_eq!(
+            parsed(r#""say \"hi\"" 'x' "#),
+            Ok((r#"say "hi""#.to_string(), "'x'".to_string()))
+        );
+        assert_eq!(
+            parsed(r#"a\b "c\d""#),
+            Ok((r"a\b".to_string(), r"c\d".to_string())),
+            "未转义的反斜杠原样保留"
+        );
+
+        let extra = parsed("foo bar baz").expect_err("多余参数应报错");
+        assert!(extra.contains("收到 3 个"), "{extra}");
+        assert_eq!(parsed("foo"), Err(REPLACE_USAGE.to_string()));
+        assert_eq!(parsed(""), Err(REPLACE_USAGE.to_string()));
+        assert!(parsed(r#""" bar"#).is_err(), "查找内容不能为空");
+        assert!(parsed(r#""foo bar"#).is_err(), "引号未闭合");
+        assert!(parsed(r#""foo"bar baz"#).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+/// 未闭合转义序列最多缓存的字符数，超过后按普通文本处理，避免异常输出吞掉整段回复。
+const MAX_PENDING_CHARS: usize = 256;
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+
+/// 流式输出的控制字符清理器。
+///
+/// 模型或工具偶尔会把 ANSI 转义序列（颜色、光标移动、窗口标题等）混进文本流，
+/// 原样写入 ratatui 缓冲区会打乱整屏布局。转义序列可能被拆在两个增量之间，
+/// 因此末尾未闭合的序列先缓存，等下一个增量到达后再判断。
+#[derive(Debug, Default)]
+pub struct StreamSanitizer {
+    pending: String,
+}
+
+/// 一个转义序列的扫描结果。
+enum EscapeScan {
+    /// 序列完整，下一个待处理字符的下标。
+    Complete(usize),
+    /// 到达输入末尾仍未闭合。
+    Incomplete,
+}
+
+impl StreamSanitizer {
+    /// 清理一段增量，返回可以安全追加到消息中的文本。
+    pub fn push(&mut self, delta: &str) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.push_str(delta);
+        let chars = input.chars().collect::<Vec<_>>();
+
+        let mut output = String::with_capacity(input.len());
+        let mut index = 0;
+        while index < chars.len() {
+            let ch = chars[index];
+            if ch == ESC {
+                match scan_escape(&chars, index) {
+                    EscapeScan::Complete(next) => index = next,
+                    EscapeScan::Incomplete if chars.len() - index <= MAX_PENDING_CHARS => {
+                        self.pending = chars[index..].iter().collect();
+                        break;
+                    }
+                    // 迟迟不闭合的序列多半不是真正的转义，只丢掉 ESC 本身。
+                    EscapeScan::Incomplete => index += 1,
+                }
+                continue;
+            }
+            if ch == '\n' || ch == '\t' || !ch.is_control() {
+                output.push(ch);
+            }
+            index += 1;
+        }
+        output
+    }
+}
+
+/// 一次性清理完整文本（例如从历史文件加载的内容），末尾未闭合的序列直接丢弃。
+pub fn strip_control_sequences(input: &str) -> String {
+    StreamSanitizer::default().push(input)
+}
+
+/// 识别从 `start`（ESC）开始的转义序列。
+///
+/// 覆盖 CSI（`ESC [ ... 终止字节`）、以 BEL 或 `ESC \` 结尾的字符串序列（OSC / DCS 等）
+/// 与其余两三个字符的短序列；CSI 中途遇到非法字节时只丢弃已扫描的前缀。
+fn scan_escape(chars: &[char], start: usize) -> EscapeScan {
+    let Some(&kind) = chars.get(start + 1) else {
+        return EscapeScan::Incomplete;
+    };
+    let mut index = start + 2;
+    match kind {
+        '[' => {
+            while let Some(&ch) = chars.get(index) {
+                match ch {
+                    '\u{20}'..='\u{3f}' => index += 1,
+                    '\u{40}'..='\u{7e}' => return EscapeScan::Complete(index + 1),
+                    _ => return EscapeScan::Complete(index),
+                }
+            }
+            EscapeScan::Incomplete
+        }
+        ']' | 'P' | '_' | '^' | 'X' => {
+            while let Some(&ch) = chars.get(index) {
+                if ch == BEL {
+                    return EscapeScan::Complete(index + 1);
+                }
+                if ch == ESC {
+                    match chars.get(index + 1) {
+                        Some('\\') => return EscapeScan::Complete(index + 2),
+                        Some(_) => {}
+                        None => return EscapeScan::Incomplete,
+                    }
+                }
+                index += 1;
+            }
+            EscapeScan::Incomplete
+        }
+        '\u{20}'..='\u{2f}' => {
+            // 字符集选择等带中间字节的短序列，例如 `ESC ( B`。
+            while let Some(&ch) = chars.get(index) {
+                if !('\u{20}'..='\u{2f}').contains(&ch) {
+                    return EscapeScan::Complete(index + 1);
+                }
+                index += 1;
+            }
+            EscapeScan::Incomplete
+        }
+        _ => EscapeScan::Complete(start + 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamSanitizer, strip_control_sequences};
+
+    #[test]
+    fn strip_control_sequences_should_keep_text_and_newlines() {
+        assert_eq!(
+            strip_control_sequences("\u{1b}[1;31m错误\u{1b}[0m: 行一\n\t行二"),
+            "错误: 行一\n\t行二"
+        );
+        assert_eq!(
+            strip_control_sequences("\u{1b}]0;window title\u{7}hello\u{1b}]8;;url\u{1b}\\ok"),
+            "hellook",
+            "OSC 序列应连同内容一起移除"
+        );
+        assert_eq!(strip_control_sequences("a\r\nb\u{8}c\u{0}"), "a\nbc");
+        assert_eq!(strip_control_sequences("\u{1b}(Bplain"), "plain");
+        assert_eq!(
+            strip_control_sequences("普通文本 [31m 保持不变"),
+            "普通文本 [31m 保持不变"
+        );
+    }
+
+    #[test]
+    fn stream_sanitizer_should_join_sequences_split_across_deltas() {
+        let mut sanitizer = StreamSanitizer::default();
+        assert_eq!(sanitizer.push("前缀\u{1b}[3"), "前缀");
+        assert_eq!(sanitizer.push("2mgreen"), "green");
+        assert_eq!(sanitizer.push("\u{1b}"), "");
+        assert_eq!(sanitizer.push("[0m\n结束"), "\n结束");
+
+        let mut sanitizer = StreamSanitizer::default();
+        let unterminated = format!("\u{1b}]{}", "x".repeat(300));
+        assert!(
+            sanitizer.push(&unterminated).starts_with("]xxx"),
+            "超长未闭合序列应按普通文本输出"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Paragraph, Widget},
+};
+
+/// 整个界面（对话视图或编辑器）可正常渲染的最小宽度。
+pub const MIN_SCREEN_WIDTH: u16 = 24;
+/// 整个界面可正常渲染的最小高度：标题/状态栏各一行，加上带边框的内容区。
+pub const MIN_SCREEN_HEIGHT: u16 = 6;
+/// 编辑器窗格的最小尺寸：边框占去两行两列，内容区至少还要留出行号与一列文本。
+pub const MIN_PANE_WIDTH: u16 = 10;
+pub const MIN_PANE_HEIGHT: u16 = 3;
+/// 浮层（补全列表等）的最小尺寸：边框加至少一行内容。
+pub const MIN_POPOVER_WIDTH: u16 = 12;
+pub const MIN_POPOVER_HEIGHT: u16 = 3;
+
+/// 尺寸检查结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeCheck {
+    /// 区域足够，按正常界面渲染。
+    Fits,
+    /// 区域过小，改为渲染“终端过小”提示。
+    TooSmall,
+}
+
+/// 判断区域是否满足最小尺寸。
+///
+/// 各处渲染函数统一经由这里判断，而不是各自写零宽高检查：
+/// 极小区域下布局切分出的子区域可能为零，继续渲染会错位甚至越界。
+pub fn check_size(area: Rect, min_width: u16, min_height: u16) -> SizeCheck {
+    if area.width < min_width || area.height < min_height {
+        SizeCheck::TooSmall
+    } else {
+        SizeCheck::Fits
+    }
+}
+
+/// 在区域内渲染紧凑的“终端过小”提示。
+///
+/// 空间允许时附带当前尺寸，方便用户判断还需要放大多少；
+/// 连一行都放不下时什么也不画。
+pub fn render_too_small(area: Rect, buf: &mut Buffer) {
+    if area.width == 0 || area.height == 0 {
+        return;
+    }
+    let detailed = format!("终端过小 {}x{}", area.width, area.height);
+    let text = if Line::from(detailed.as_str()).width() <= area.width as usize {
+        detailed
+    } else {
+        "过小".to_string()
+    };
+    let row = Rect {
+        y: area.y + area.height / 2,
+        height: 1,
+        ..area
+    };
+    Paragraph::new(Line::from(text).centered())
+        .style(Style::default().fg(Color::Yellow))
+        .render(row, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{buffer::Buffer, layout::Rect};
+
+    use super::{
+        MIN_PANE_HEIGHT, MIN_PANE_WIDTH, MIN_SCREEN_HEIGHT, MIN_SCREEN_WIDTH, SizeCheck,
+        check_size, render_too_small,
+    };
+
+    #[test]
+    fn check_size_should_fall_back_for_sub_threshold_rects() {
+        let screen = |width, height| Rect::new(0, 0, width, height);
+
+        assert_eq!(
+            check_size(screen(80, 24), MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT),
+            SizeCheck::Fits
+        );
+        assert_eq!(
+            check_size(
+                screen(MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT),
+                MIN_SCREEN_WIDTH,
+                MIN_SCREEN_HEIGHT
+            ),
+            SizeCheck::Fits,
+            "恰好等于阈值时应正常渲染"
+        );
+        assert_eq!(
+            check_size(
+                screen(MIN_SCREEN_WIDTH - 1, 24),
+                MIN_SCREEN_WIDTH,
+                MIN_SCREEN_HEIGHT
+            ),
+            SizeCheck::TooSmall
+        );
+        assert_eq!(
+            check_size(
+                screen(80, MIN_SCREEN_HEIGHT - 1),
+                MIN_SCREEN_WIDTH,
+                MIN_SCREEN_HEIGHT
+            ),
+            SizeCheck::TooSmall
+        );
+        assert_eq!(
+            check_size(screen(0, 0), MIN_PANE_WIDTH, MIN_PANE_HEIGHT),
+            SizeCheck::TooSmall,
+            "零尺寸区域必须走回退提示"
+        );
+    }
+
+    #[test]
+    fn render_too_small_should_not_panic_on_tiny_rects() {
+        for (width, height) in [(0, 0), (1, 1), (3, 1), (30, 2)] {
+            let area = Rect::new(0, 0, width, height);
+            let mut buf = Buffer::empty(area);
+            render_too_small(area, &mut buf);
+        }
+
+        let area = Rect::new(0, 0, 20, 3);
+        let mut buf = Buffer::empty(area);
+        render_too_small(area, &mut buf);
+        let middle_row = (0..area.width)
+            .map(|x| buf[(x, 1)].symbol().to_string())
+            .collect::<String>()
+            .replace(' ', "");
+        assert!(middle_row.contains("终端过小"), "提示应画在区域中间一行");
+    }
+}
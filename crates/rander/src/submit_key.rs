@@ -0,0 +1,128 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// 输入框的提交按键模式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubmitKey {
+    /// `Enter` 提交，`Shift+Enter` / `Ctrl+Enter` 换行（默认）。
+    #[default]
+    Enter,
+    /// `Enter` 换行，`Ctrl+Enter` 或 `Ctrl+D` 提交。
+    CtrlEnter,
+}
+
+/// 输入框中回车类按键对应的动作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKeyAction {
+    /// 提交输入；补全弹窗打开时改为确认补全项。
+    Submit,
+    /// 在光标处插入换行。
+    Newline,
+}
+
+impl SubmitKey {
+    /// 从 `ORDER_SUBMIT_KEY` 的取值解析模式。
+    ///
+    /// 未设置或无法识别时回退 `Enter`，与不了解该配置的用户习惯保持一致。
+    pub fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|value| value.trim().to_ascii_lowercase().replace('-', "_")) {
+            Some(value) if value == "ctrl_enter" => Self::CtrlEnter,
+            _ => Self::Enter,
+        }
+    }
+
+    /// 将按键映射为提交或换行；与回车无关的按键返回 `None`。
+    ///
+    /// 不少终端无法区分 `Ctrl+Enter` 与 `Enter`，
+    /// 因此 `CtrlEnter` 模式额外接受 `Ctrl+D` 作为提交键。
+    pub fn action(self, key: &KeyEvent) -> Option<InputKeyAction> {
+        let with_modifier = key
+            .modifiers
+            .intersects(KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match (self, key.code) {
+            (Self::Enter, KeyCode::Enter) if with_modifier => Some(InputKeyAction::Newline),
+            (Self::Enter, KeyCode::Enter) => Some(InputKeyAction::Submit),
+            (Self::CtrlEnter, KeyCode::Enter) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(InputKeyAction::Submit)
+            }
+            (Self::CtrlEnter, KeyCode::Enter) => Some(InputKeyAction::Newline),
+            (Self::CtrlEnter, KeyCode::Char('d'))
+                if key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                Some(InputKeyAction::Submit)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{InputKeyAction, SubmitKey};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_from_env_value() {
+        assert_eq!(SubmitKey::from_env_value(None), SubmitKey::Enter);
+        assert_eq!(
+            SubmitKey::from_env_value(Some(" Ctrl-Enter ")),
+            SubmitKey::CtrlEnter
+        );
+        assert_eq!(
+            SubmitKey::from_env_value(Some("ctrl_enter")),
+            SubmitKey::CtrlEnter
+        );
+        assert_eq!(
+            SubmitKey::from_env_value(Some("typo")),
+            SubmitKey::Enter,
+            "无法识别时回退默认模式"
+        );
+    }
+
+    #[test]
+    fn test_enter_mode_submits_on_plain_enter() {
+        let mode = SubmitKey::Enter;
+        assert_eq!(
+            mode.action(&key(KeyCode::Enter, KeyModifiers::NONE)),
+            Some(InputKeyAction::Submit)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Enter, KeyModifiers::SHIFT)),
+            Some(InputKeyAction::Newline)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Enter, KeyModifiers::CONTROL)),
+            Some(InputKeyAction::Newline)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            None,
+            "默认模式下 Ctrl+D 不提交"
+        );
+    }
+
+    #[test]
+    fn test_ctrl_enter_mode_submits_on_ctrl_enter_or_ctrl_d() {
+        let mode = SubmitKey::CtrlEnter;
+        assert_eq!(
+            mode.action(&key(KeyCode::Enter, KeyModifiers::NONE)),
+            Some(InputKeyAction::Newline)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Enter, KeyModifiers::CONTROL)),
+            Some(InputKeyAction::Submit)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(InputKeyAction::Submit)
+        );
+        assert_eq!(
+            mode.action(&key(KeyCode::Char('d'), KeyModifiers::NONE)),
+            None
+        );
+    }
+}
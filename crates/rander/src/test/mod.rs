@@ -195,6 +195,26 @@ fn test_required_height() {
     assert_eq!(height, 4); // 1 行文本 + 3（边框等）
 }
 
+#[test]
+fn test_insert_char_literal_slash_skips_completion() {
+    let mut state = InputState::default();
+    state.insert_char_literal('/');
+    assert!(state.literal_slash);
+    assert!(!state.show_completion);
+
+    state.delete_char();
+    state.insert_char('/');
+    assert!(!state.literal_slash, "删掉字面 / 后重新输入恢复命令语义");
+    assert!(state.show_completion);
+}
+
+#[test]
+fn test_required_height_counts_newlines() {
+    let mut state = InputState::default();
+    state.insert_str("first\nsecond\n");
+    assert_eq!(state.required_height(80), 6); // 3 行文本 + 3（边框等）
+}
+
 #[test]
 fn test_toggle_cursor_visibility() {
     let mut state = InputState::default();
@@ -1,8 +1,25 @@
 use crate::{
-    editor::Editor,
+    editor::{ACTIVE_FILE_CONTEXT_BUDGET_CHARS, ActiveFileContext, ChatHandoff, Editor},
     focus_status::{CURRENT_FOCUS, FocusStatus},
-    history::{ContextManager, ContextMessage, ContextModelLimits, ContextRole},
+    history::{
+        ContextBuildJob, ContextManager, ContextMessage, ContextModelLimits, ContextRole,
+        MemoryUpdate, MemoryWorker,
+    },
+    output_rules::OutputRules,
+    paste_guard::{
+        PASTE_CONFIRM_OPTIONS, PasteDecision, normalize_pasted_text, paste_confirm_threshold,
+        paste_needs_confirmation, truncate_paste,
+    },
+    replace_view::{
+        ReplaceConfirmOutcome, ReplaceConfirmState, ReplaceDecision, parse_replace_arguments,
+    },
+    sanitize::{StreamSanitizer, strip_control_sequences},
+    size_guard::{MIN_SCREEN_HEIGHT, MIN_SCREEN_WIDTH, SizeCheck, check_size, render_too_small},
+    submit_key::{InputKeyAction, SubmitKey},
+    validation_gate::{DeferredValidations, ValidationDecision, ValidationMode},
+    validation_view::ValidationViewState,
     widget::input_widget::{InputState, InputWidget},
+    write_policy::{WriteApprovalPolicy, paths_requiring_confirmation},
 };
 use anyhow::{Context, anyhow};
 use chrono::{DateTime, Duration as ChronoDuration, Local, Utc};
@@ -12,18 +29,26 @@ use core::{
     model::{
         capabilities::CapabilityResolver,
         connection::{Connection, ModelStreamEvent, Provider},
-        info::{get_current_model_info, get_current_model_info_from_config},
+        info::{
+            get_current_model_info, get_current_model_info_from_config,
+            resolve_model_config_write_path, serialize_model_config,
+        },
     },
     observability::{
         AgentEvent, log_event_best_effort, new_trace_id, ts, workspace_root_best_effort,
     },
-    safety::{ExecutionGuard, PendingWriteSummary},
-    validation::ValidationPipeline,
+    replace::{ReplaceFileMatch, assemble_replace_plan, search_workspace, stage_replace_plan},
+    replay::{
+        last_tool_trace_id, load_logged_events, read_only_replay_plan, replay_read_only_call,
+        tool_sequence_for_trace,
+    },
+    safety::{ExecutionGuard, PendingWriteSummary, env_flag},
+    validation::{ValidationPipeline, ValidationReport, load_validation_report},
 };
 use crossterm::{
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
-        KeyModifiers, MouseEvent, MouseEventKind,
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind,
     },
     execute,
 };
@@ -69,6 +94,8 @@ struct ChatMessage {
     role: ChatRole,
     /// 消息正文。
     content: String,
+    /// 消息加入对话流的时刻，开启时间戳显示时渲染在角色标签前。
+    created_at: DateTime<Local>,
     /// 是否写入历史文件。
     ///
     /// `/history` 命令回显的历史消息属于临时展示数据，
@@ -77,7 +104,7 @@ struct ChatMessage {
 }
 
 /// 历史文件中的对话条目。
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct HistoryConversation {
     /// 对话角色：`user` / `assistant` / `error`。
     role: String,
@@ -114,6 +141,87 @@ struct HistoryFile {
     records: Vec<HistoryRecord>,
 }
 
+/// `History.json` 的进程内副本。
+///
+/// 每轮对话都要写历史，若每次都先读盘再整体改写，开销会随历史增长线性上升。
+/// 这里以内存副本为准，只在磁盘文件被其他进程改动（修改时间或长度变化）时重新读取，
+/// 会话内容没有变化时也不写盘；磁盘格式与直接读写时完全一致。
+#[derive(Debug, Default)]
+struct HistoryStore {
+    file: HistoryFile,
+    /// 内存副本对应的文件路径；运行目录变化时需要重新加载。
+    loaded_path: Option<PathBuf>,
+    /// 上次读写后磁盘文件的 `(修改时间, 长度)`。
+    disk_stamp: Option<(SystemTime, u64)>,
+}
+
+impl HistoryStore {
+    /// 内存副本过期时从磁盘重新加载，返回本次是否读取了磁盘。
+    fn refresh_if_stale(&mut self, path: &PathBuf) -> anyhow::Result<bool> {
+        let stamp = history_disk_stamp(path);
+        if self.loaded_path.as_ref() == Some(path) && self.disk_stamp == stamp {
+            return Ok(false);
+        }
+        self.file = OrderTui::read_history_file(path)?;
+        self.loaded_path = Some(path.clone());
+        self.disk_stamp = stamp;
+        Ok(true)
+    }
+
+    /// 写入或更新指定会话快照，返回是否实际写盘。
+    fn upsert_session(
+        &mut self,
+        path: &PathBuf,
+        date: String,
+        model: String,
+        session: HistorySession,
+    ) -> anyhow::Result<bool> {
+        self.refresh_if_stale(path)?;
+
+        if let Some(record) = self
+            .file
+            .records
+            .iter_mut()
+            .find(|record| record.date == date && record.model == model)
+        {
+            if let Some(existing_session) = record
+                .history
+                .iter_mut()
+                .find(|history| history.timestamp == session.timestamp)
+            {
+                if existing_session.conversations == session.conversations {
+                    return Ok(false);
+                }
+                existing_session.conversations = session.conversations;
+            } else {
+                record.history.push(session);
+            }
+        } else {
+            self.file.records.push(HistoryRecord {
+                date,
+                model,
+                history: vec![session],
+            });
+        }
+
+        OrderTui::write_history_file(path, &self.file)?;
+        self.disk_stamp = history_disk_stamp(path);
+        Ok(true)
+    }
+
+    /// 丢弃内存副本，下次写入前重新读盘（例如 `/history clear` 之后）。
+    fn invalidate(&mut self) {
+        self.loaded_path = None;
+        self.disk_stamp = None;
+    }
+}
+
+// 读取历史文件的修改时间与长度，文件不存在时返回 `None`。
+fn history_disk_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
 /// 历史选择界面的单条列表项。
 #[derive(Debug, Clone)]
 struct HistoryListItem {
@@ -136,13 +244,121 @@ struct HistoryBrowserState {
     items: Vec<HistoryListItem>,
     /// 当前选中项索引。
     selected: usize,
+    /// 用 `Space` 标记的会话索引，作为对比的另一侧。
+    marked: Option<usize>,
+    /// 并排对比视图；打开时按键只作用于对比视图。
+    compare: Option<HistoryCompareState>,
+}
+
+/// 对比视图中的一列：会话标题、展开后的消息行与该列自己的滚动偏移。
+#[derive(Debug, Clone)]
+struct HistoryCompareColumn {
+    /// 列标题（日期、模型与时间戳）。
+    title: String,
+    /// 按消息展开的原始行，渲染时再按列宽换行。
+    lines: Vec<String>,
+    /// 当前滚动偏移（原始行）。
+    scroll: usize,
+}
+
+impl HistoryCompareColumn {
+    /// 基于历史会话构建一列，每条消息以角色标题开头，消息之间空一行。
+    ///
+    /// `sanitize` 为真时与加载历史一致，先去掉旧历史里残留的转义序列，避免污染终端。
+    fn new(item: &HistoryListItem, sanitize: bool) -> Self {
+        let mut lines = Vec::new();
+        for conversation in &item.conversations {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", conversation.role));
+            if sanitize {
+                let content = strip_control_sequences(&conversation.content);
+                lines.extend(content.lines().map(str::to_string));
+            } else {
+                lines.extend(conversation.content.lines().map(str::to_string));
+            }
+        }
+        Self {
+            title: format!("{} {} {}", item.date, item.model, item.timestamp),
+            lines,
+            scroll: 0,
+        }
+    }
+
+    /// 按行滚动，`delta` 为负表示向上；滚动范围限制在行数内。
+    fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.lines.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+}
+
+/// 历史会话并排对比状态。
+#[derive(Debug, Clone)]
+struct HistoryCompareState {
+    /// 左右两列，左侧为较早的会话。
+    columns: [HistoryCompareColumn; 2],
+    /// 当前接收滚动按键的列（0 为左，1 为右）。
+    focused: usize,
+}
+
+impl HistoryCompareState {
+    /// 对列表中的两个会话建立对比视图。
+    fn new(left: &HistoryListItem, right: &HistoryListItem, sanitize: bool) -> Self {
+        Self {
+            columns: [
+                HistoryCompareColumn::new(left, sanitize),
+                HistoryCompareColumn::new(right, sanitize),
+            ],
+            focused: 0,
+        }
+    }
+}
+
+/// 根据标记项与当前选中项确定对比的两侧，返回 `(左, 右)` 索引。
+///
+/// 列表按时间倒序排列，索引较大的会话更早，放在左侧，
+/// 这样无论先标记哪一个，对比时都是“旧 → 新”的阅读顺序。
+/// 未标记、标记的就是当前项或索引越界时返回 `None`。
+fn history_compare_pair(
+    marked: Option<usize>,
+    selected: usize,
+    len: usize,
+) -> Option<(usize, usize)> {
+    let marked = marked?;
+    if marked == selected || marked >= len || selected >= len {
+        return None;
+    }
+    Some((marked.max(selected), marked.min(selected)))
 }
 
 /// 最近一次失败的摘要信息（用于状态栏展示）。
 #[derive(Debug, Clone)]
 struct FailureSummary {
     trace_id: String,
+    /// 截断后的单行原因，供状态栏与 `/status` 展示。
     reason: String,
+    /// 完整的原始错误（可能多行），供 `/lasterror` 展开查看。
+    detail: String,
+}
+
+impl FailureSummary {
+    // 同时保留截断展示形式与完整错误，避免排查时只剩 80 个字符。
+    fn new(trace_id: &str, error_message: &str) -> Self {
+        Self {
+            trace_id: trace_id.to_string(),
+            reason: shorten_reason(error_message, 80),
+            detail: error_message.trim().to_string(),
+        }
+    }
+
+    // `/lasterror` 展示的完整错误文本。
+    fn expanded_text(&self) -> String {
+        format!(
+            "最近失败详情（trace_id={}）：\n{}",
+            self.trace_id, self.detail
+        )
+    }
 }
 
 /// 后台补全线程向主线程回传的事件。
@@ -152,10 +368,21 @@ struct FailureSummary {
 /// - 模型请求和重试在后台线程执行，主线程只消费事件并刷新界面。
 #[derive(Debug)]
 enum CompletionWorkerEvent {
+    /// 后台构建上下文完成，携带估算的剩余上下文百分比。
+    ContextBuilt(u32),
     Stream(ModelStreamEvent),
     Completed(Result<(), String>),
 }
 
+/// 补全请求使用的对话历史。
+#[derive(Debug)]
+enum ChatHistorySource {
+    /// 已在主线程构建完成（`ORDER_CONTEXT_WORKER=0`）。
+    Ready(Vec<RigMessage>),
+    /// 由补全线程在发送请求前构建。
+    Build(ContextBuildJob),
+}
+
 /// 当前正在进行中的模型请求状态。
 #[derive(Debug)]
 struct ActiveCompletion {
@@ -167,9 +394,81 @@ struct ActiveCompletion {
     received_delta: bool,
     last_tool_progress: Option<String>,
     started_at: Instant,
+    /// 增量文本的控制字符清理状态；关闭清理时为 `None`。
+    sanitizer: Option<StreamSanitizer>,
+}
+
+/// `/replace` 后台线程向主线程回传的事件。
+///
+/// 搜索与暂存都在后台执行，主线程只负责刷新进度与响应 `/cancel`。
+#[derive(Debug)]
+enum ReplaceWorkerEvent {
+    /// 进度文本，覆盖更新到同一条消息。
+    Progress(String),
+    /// 搜索结束。
+    Searched(Result<Vec<ReplaceFileMatch>, String>),
+    /// 暂存结束：成功时携带 trace_id、待确认写入摘要与跳过的文件。
+    Staged(Result<StagedReplace, String>),
+}
+
+/// 替换暂存完成后的结果。
+#[derive(Debug)]
+struct StagedReplace {
+    trace_id: String,
+    summaries: Vec<PendingWriteSummary>,
+    replaced: usize,
+    skipped: Vec<(String, String)>,
+}
+
+/// 正在后台执行的 `/replace` 任务。
+#[derive(Debug)]
+struct ActiveReplace {
+    receiver: Receiver<ReplaceWorkerEvent>,
+    cancel_flag: Arc<AtomicBool>,
+    pattern: String,
+    replacement: String,
+    /// 进度消息在对话流中的位置，进度更新时原地覆盖。
+    progress_message_index: Option<usize>,
 }
 
 const WRITE_APPROVAL_OPTIONS: [&str; 3] = ["1. 同意", "2. 不同意", "3. 同意之后一切修改"];
+/// 启动阶段 Codex 探测的默认超时（秒）。
+///
+/// 启动探测发生在首帧之后、可输入之前，超时越长用户越久无法操作，
+/// 因此比 `/settings` 的显式探测更短。
+const STARTUP_PROBE_TIMEOUT_SECS: u64 = 5;
+/// `/settings` 显式探测的超时（秒），用户主动等待，可以给足时间。
+const SETTINGS_PROBE_TIMEOUT_SECS: u64 = 12;
+
+/// Codex 探测的触发场景，用于选择不同的超时策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProbeContext {
+    /// 启动时自动探测。
+    Startup,
+    /// 用户通过 `/settings` 显式触发。
+    Settings,
+}
+
+/// 首次在未配置模型时发送消息的引导文案。
+const MODEL_SETUP_PROMPT: &str = "尚未配置模型，消息未发送（已放回输入框）。可以：\n1. 输入 /settings 自动探测并写入 Codex 配置\n2. 设置环境变量 CODEX_API_KEY 或 OPENAI_API_KEY 后再执行 /settings\n3. 手动编辑 .order/model.json\n配置完成后回车即可重新发送；本次会话不再重复提示。";
+
+/// 启动探测期间检查取消按键的间隔。
+const PROBE_CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 启动探测被用户按键（Esc / Ctrl+C）取消时返回的错误标记。
+///
+/// 用独立类型而不是错误文案区分“取消”与“探测失败”，调用方据此跳过写配置。
+#[derive(Debug)]
+struct ProbeCancelled;
+
+impl std::fmt::Display for ProbeCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Codex 探测已取消")
+    }
+}
+
+impl std::error::Error for ProbeCancelled {}
+
 /// 当识别为“代码变更请求”时，附加到发送给模型的执行约束提示。
 ///
 /// 目标是把“先口头确认再行动”改为“直接工具执行 + TUI 审批写入”。
@@ -206,6 +505,14 @@ struct WriteApprovalPrompt {
     files: Vec<String>,
 }
 
+/// 超过阈值的粘贴内容在插入输入框前的确认菜单状态。
+#[derive(Debug, Clone)]
+struct PasteConfirmPrompt {
+    /// 已统一行尾的完整粘贴内容。
+    text: String,
+    selected: usize,
+}
+
 pub struct OrderTui<'a> {
     /// 全局退出标记。
     exit: &'a AtomicBool,
@@ -219,8 +526,16 @@ pub struct OrderTui<'a> {
     ///
     /// 负责短期上下文裁剪、中期摘要生成与长期记忆持久化。
     context_manager: ContextManager,
+    /// 长期记忆的后台更新线程，首次写记忆时按需启动。
+    memory_worker: Option<MemoryWorker>,
+    /// 是否把上下文构建与长期记忆更新放到后台线程（`ORDER_CONTEXT_WORKER=0` 时在主线程同步执行）。
+    context_worker_enabled: bool,
     /// 回车后待处理的输入文本。
     pending_command: Option<String>,
+    /// 待处理输入开头的 `/` 是字面输入的，应按普通消息发送而不是解析为命令。
+    pending_command_literal: bool,
+    /// 按过 `Ctrl+V`：下一个按键按字面插入输入框。
+    literal_next: bool,
     /// 与大模型通信的连接。
     ///
     /// 这里使用 `Option` 的原因是延迟初始化：
@@ -234,10 +549,32 @@ pub struct OrderTui<'a> {
     ///
     /// 用于将本次运行期间的消息归并到同一个 `History` 会话节点。
     session_timestamp: String,
+    /// `History.json` 的内存副本，避免每轮对话都整文件读写。
+    history_store: HistoryStore,
+    /// 当前运行会话的起始时刻，用于计算状态栏展示的会话时长。
+    session_started_at: Instant,
+    /// 是否在输入框底部展示当前时间与会话时长（`ORDER_STATUS_CLOCK=0` 可关闭）。
+    show_status_clock: bool,
+    /// 是否在每条消息前展示时间戳（`ORDER_CHAT_TIMESTAMPS=1` 或 `/timestamps` 开启）。
+    ///
+    /// 默认关闭，保持原有对话布局不变。
+    show_message_timestamps: bool,
+    /// 是否把代码块内的空格 / 制表符显示为可见符号（`ORDER_CHAT_WHITESPACE=1` 或 `/whitespace` 开启）。
+    ///
+    /// 只作用于围栏代码块，正文保持原样；仅影响渲染，不改动消息内容。
+    show_code_whitespace: bool,
+    /// 是否清理模型输出中的 ANSI 转义与控制字符（`ORDER_RAW_MODEL_OUTPUT=1` 可关闭）。
+    sanitize_model_output: bool,
     /// 历史选择界面状态。
     ///
     /// 当该字段为 `Some` 时，主界面切换为历史会话列表浏览模式。
     history_browser: Option<HistoryBrowserState>,
+    /// `/validation` 报告浮层状态；有值时主区域展示报告并优先消费按键。
+    validation_view: Option<ValidationViewState>,
+    /// 正在后台执行的 `/replace` 搜索或暂存任务。
+    active_replace: Option<ActiveReplace>,
+    /// `/replace` 逐文件确认浮层状态；有值时优先消费按键。
+    replace_confirm: Option<ReplaceConfirmState>,
     /// 对话区域滚动偏移量。
     ///
     /// 0 表示显示最新消息（底部），大于 0 表示向上滚动的行数。
@@ -250,12 +587,55 @@ pub struct OrderTui<'a> {
     write_approval_prompt: Option<WriteApprovalPrompt>,
     /// 会话级“自动同意后续所有写入”开关。
     approve_all_writes: bool,
+    /// 细粒度写入确认策略（`ORDER_WRITE_APPROVAL_POLICY`），
+    /// 在未开启 `approve_all_writes` 时决定哪些写入可以自动确认。
+    write_approval_policy: WriteApprovalPolicy,
+    /// 从 editor 带回、等待随下一条消息发送的多行上下文。
+    ///
+    /// 输入框只预填充一句指令，完整的诊断与代码片段在发送时追加，
+    /// 避免多行文本挤进单行输入框。
+    pending_chat_context: Option<String>,
+    /// 最近一次退出 editor 时的活动文件（需在 `editor.json` 开启 `auto_context_active_file`）。
+    active_file: Option<ActiveFileContext>,
+    /// 当前活动文件快照是否已随某条消息发出；同一份快照只附带一次。
+    active_file_attached: bool,
     /// 当前是否已开启鼠标捕获。
     ///
     /// 在 Windows 控制台中，未初始化就直接执行 `DisableMouseCapture`
     /// 会触发 `Initial console modes not set`，因此这里显式跟踪状态，
     /// 只在“确实开启过”时再关闭。
     mouse_capture_enabled: bool,
+    /// 当前是否已开启括号粘贴（bracketed paste）。
+    ///
+    /// 开启后整段粘贴以单个事件到达，既能在插入前检查大小，
+    /// 也避免粘贴内容里的换行被当作回车逐行发送。
+    bracketed_paste_enabled: bool,
+    /// 是否在欢迎页展示首次运行引导（工作区尚无 `.order` 目录时展示）。
+    ///
+    /// `Esc` 可关闭；完成配置生成 `.order` 后也会自动隐藏，`ORDER_ONBOARDING=0` 可彻底关闭。
+    show_onboarding: bool,
+    /// 本次会话是否已因“未配置模型”提示过配置方式；只提示一次，之后按普通错误处理。
+    model_setup_prompted: bool,
+    /// 启动 Codex 探测是否正在进行，进行中时状态栏提示可按键跳过。
+    startup_probe_running: bool,
+    /// 粘贴确认阈值（字符数，`ORDER_PASTE_CONFIRM_CHARS`），`None` 表示不确认。
+    paste_confirm_threshold: Option<usize>,
+    /// 等待用户决定如何插入的大段粘贴。
+    paste_confirm: Option<PasteConfirmPrompt>,
+    /// 输入框的提交按键模式（`ORDER_SUBMIT_KEY`）。
+    submit_key: SubmitKey,
+    /// `output_rules.json` 中的回复改写规则，首次完成回复时加载。
+    output_rules: Option<OutputRules>,
+    /// 鉴权失败（401/403）时是否丢弃缓存连接并在下次发送时重新解析凭据（`ORDER_AUTH_RECONNECT=0` 可关闭）。
+    auth_reconnect: bool,
+    /// 确认写入后的验证时机（`ORDER_VALIDATION_MODE`）。
+    validation_mode: ValidationMode,
+    /// 已确认写入、但尚未运行验证的 trace，供 `/validate` 补跑。
+    deferred_validations: DeferredValidations,
+    /// 等待用户决定是否立即验证的 trace_id；有值时优先消费按键。
+    validation_confirm: Option<String>,
+    /// 实际执行验证管线的函数；测试中替换为桩，避免真的运行 cargo 命令。
+    validation_runner: fn(&str, &[String]) -> anyhow::Result<ValidationReport>,
 }
 
 impl Default for OrderTui<'_> {
@@ -267,17 +647,67 @@ impl Default for OrderTui<'_> {
             last_tick: Instant::now(),
             context_remaining: 100,
             context_manager: ContextManager::new(),
+            memory_worker: None,
+            context_worker_enabled: context_worker_enabled(
+                env::var("ORDER_CONTEXT_WORKER").ok().as_deref(),
+            ),
             pending_command: None,
+            pending_command_literal: false,
+            literal_next: false,
             connection: None,
             messages: Vec::new(),
             session_timestamp: now.format("%Y-%-m-%-d %H:%M:%S").to_string(),
+            history_store: HistoryStore::default(),
+            session_started_at: Instant::now(),
+            show_status_clock: status_clock_enabled(env::var("ORDER_STATUS_CLOCK").ok().as_deref()),
+            show_message_timestamps: message_timestamps_enabled(
+                env::var("ORDER_CHAT_TIMESTAMPS").ok().as_deref(),
+            ),
+            show_code_whitespace: env_flag(
+                env::var("ORDER_CHAT_WHITESPACE").ok().as_deref(),
+                false,
+            ),
+            sanitize_model_output: !raw_model_output_enabled(
+                env::var("ORDER_RAW_MODEL_OUTPUT").ok().as_deref(),
+            ),
             history_browser: None,
+            validation_view: None,
+            active_replace: None,
+            replace_confirm: None,
             conversation_scroll: 0,
             last_failure: None,
             active_completion: None,
             write_approval_prompt: None,
             approve_all_writes: false,
+            write_approval_policy: WriteApprovalPolicy::from_env_value(
+                env::var("ORDER_WRITE_APPROVAL_POLICY").ok().as_deref(),
+            ),
+            pending_chat_context: None,
+            active_file: None,
+            active_file_attached: false,
             mouse_capture_enabled: false,
+            bracketed_paste_enabled: false,
+            show_onboarding: should_show_onboarding(
+                &workspace_root_best_effort(),
+                env::var("ORDER_ONBOARDING").ok().as_deref(),
+            ),
+            model_setup_prompted: false,
+            startup_probe_running: false,
+            paste_confirm_threshold: paste_confirm_threshold(
+                env::var("ORDER_PASTE_CONFIRM_CHARS").ok().as_deref(),
+            ),
+            paste_confirm: None,
+            submit_key: SubmitKey::from_env_value(env::var("ORDER_SUBMIT_KEY").ok().as_deref()),
+            output_rules: None,
+            auth_reconnect: auth_reconnect_enabled(
+                env::var("ORDER_AUTH_RECONNECT").ok().as_deref(),
+            ),
+            validation_mode: ValidationMode::from_env_value(
+                env::var("ORDER_VALIDATION_MODE").ok().as_deref(),
+            ),
+            deferred_validations: DeferredValidations::default(),
+            validation_confirm: None,
+            validation_runner: |trace_id, files| ValidationPipeline.run(trace_id, files),
         }
     }
 }
@@ -301,11 +731,14 @@ impl OrderTui<'_> {
 
         // 若启动探测发生阻塞，需要重置闪烁时钟，避免首帧就快速闪烁。
         self.last_tick = Instant::now();
+        self.set_bracketed_paste(true);
 
         // 降低 tick 间隔，保证流式增量渲染时界面刷新更及时。
         let tick_rate = Duration::from_millis(100);
         while !get_exit().load(Ordering::Relaxed) {
             self.poll_active_completion_events();
+            self.poll_active_replace_events();
+            self.poll_context_memory_updates();
             terminal.draw(|frame| self.draw(frame))?;
 
             let timeout = tick_rate
@@ -324,6 +757,10 @@ impl OrderTui<'_> {
                     Event::Mouse(mouse) => {
                         self.handle_mouse_event(&mouse);
                     }
+                    Event::Paste(text) => {
+                        self.handle_paste_event(&text);
+                        self.last_tick = Instant::now();
+                    }
                     _ => {}
                 }
             }
@@ -334,7 +771,9 @@ impl OrderTui<'_> {
             }
         }
 
+        self.finish_memory_worker();
         self.set_mouse_capture(false)?;
+        self.set_bracketed_paste(false);
         terminal.clear()?;
         Ok(())
     }
@@ -354,6 +793,24 @@ impl OrderTui<'_> {
         Ok(())
     }
 
+    /// 切换括号粘贴。
+    ///
+    /// 部分终端（如旧版 Windows 控制台）不支持该模式，失败时保持关闭并静默忽略：
+    /// 粘贴会退化为逐键输入，不影响其它功能。
+    fn set_bracketed_paste(&mut self, enabled: bool) {
+        if self.bracketed_paste_enabled == enabled {
+            return;
+        }
+        let result = if enabled {
+            execute!(std::io::stdout(), EnableBracketedPaste)
+        } else {
+            execute!(std::io::stdout(), DisableBracketedPaste)
+        };
+        if result.is_ok() {
+            self.bracketed_paste_enabled = enabled;
+        }
+    }
+
     /// 启动时尝试启用 Codex，并在可用时落盘到 `.order/model.json`。
     ///
     /// 设计目标：
@@ -382,10 +839,41 @@ impl OrderTui<'_> {
             })
             .unwrap_or_default();
 
+        // 用户显式跳过启动探测时，同样写入默认配置，但不发起任何网络请求，
+        // 让 TUI 立即可交互；token 留空时会在请求时回退读取环境变量。
+        if startup_probe_skipped(env::var("ORDER_SKIP_STARTUP_PROBE").ok().as_deref()) {
+            let config_path = self.model_config_path()?;
+            self.write_model_config_file(&config_path, "codex", &codex_model, &codex_base_url, "")?;
+            self.connection = None;
+
+            self.push_chat_message(
+                ChatRole::Llm,
+                "已按 ORDER_SKIP_STARTUP_PROBE 跳过启动探测，并写入默认 Codex 配置；可随时使用 `/settings force` 重新探测"
+                    .to_string(),
+                false,
+            );
+            terminal.draw(|frame| self.draw(frame))?;
+            return Ok(());
+        }
+
         // 启动阶段希望尽量让 Codex 成为默认模型，但又要区分"无 Key"与"探测失败"两类场景：
         // - 无 Key：仍写入默认配置，便于用户补充 Key 后直接使用；
         // - 探测失败（网络/API 错误）：仍写入默认配置，避免阻塞启动，但给出友好提示。
-        match self.probe_codex_availability(&codex_model, &codex_base_url) {
+        self.startup_probe_running = true;
+        terminal.draw(|frame| self.draw(frame))?;
+        let probe_result =
+            self.probe_codex_availability(&codex_model, &codex_base_url, ProbeContext::Startup);
+        self.startup_probe_running = false;
+        match probe_result {
+            Err(error) if error.is::<ProbeCancelled>() => {
+                // 用户主动跳过：保持现有配置不变，下次启动仍会探测。
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    "已跳过启动时的 Codex 探测，可随时使用 `/settings` 配置模型".to_string(),
+                    false,
+                );
+                terminal.draw(|frame| self.draw(frame))?;
+            }
             Ok(Some(api_key)) => {
                 let config_path = self.model_config_path()?;
                 self.write_model_config_file(
@@ -510,35 +998,78 @@ impl OrderTui<'_> {
             return;
         }
 
+        if self.validation_confirm.is_some() {
+            self.handle_validation_confirm_key_event(key);
+            return;
+        }
+
+        if self.paste_confirm.is_some() {
+            self.handle_paste_confirm_key_event(key);
+            return;
+        }
+
+        if self.replace_confirm.is_some() {
+            self.handle_replace_confirm_key_event(key);
+            return;
+        }
+
+        if self.validation_view.is_some() {
+            self.handle_validation_view_key_event(key);
+            return;
+        }
+
         // 若当前处于历史选择界面，优先消费历史浏览相关按键。
         if self.history_browser.is_some() {
             self.handle_history_browser_key_event(key);
             return;
         }
 
-        match key.code {
-            KeyCode::Enter => {
-                if key.modifiers.contains(KeyModifiers::SHIFT)
-                    && CURRENT_FOCUS == FocusStatus::InputWidget
-                {
-                    // TODO: 后续可支持 Shift+Enter 多行输入。
-                } else if CURRENT_FOCUS == FocusStatus::InputWidget {
-                    if self.input_state.show_completion {
-                        self.input_state.confirm_completion();
-                    } else {
-                        // 回车提交输入内容，由统一入口处理。
-                        //
-                        // 这里先 `trim` 再入队，避免把纯空白字符当成有效输入。
-                        let input = self.input_state.input.trim().to_string();
-                        if !input.is_empty() {
-                            self.pending_command = Some(input);
-                        }
-                        self.input_state.clear();
+        if CURRENT_FOCUS == FocusStatus::InputWidget {
+            if self.literal_next {
+                self.literal_next = false;
+                match key.code {
+                    KeyCode::Char(ch) => self.input_state.insert_char_literal(ch),
+                    KeyCode::Enter => self.input_state.insert_char('\n'),
+                    KeyCode::Tab => self.input_state.insert_char('\t'),
+                    _ => {}
+                }
+                return;
+            }
+            if key.code == KeyCode::Char('v') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                self.literal_next = true;
+                self.input_state.cancel_completion();
+                return;
+            }
+        }
+
+        // 提交与换行的按键取决于 `ORDER_SUBMIT_KEY`，先于普通按键分派处理，
+        // 避免 `Ctrl+D` 被当作字符插入。
+        if let Some(action) = self.submit_key.action(key) {
+            if CURRENT_FOCUS != FocusStatus::InputWidget {
+                self.input_state.clear();
+                return;
+            }
+            match action {
+                InputKeyAction::Submit if self.input_state.show_completion => {
+                    self.input_state.confirm_completion();
+                }
+                InputKeyAction::Submit => {
+                    // 提交输入内容，由统一入口处理。
+                    //
+                    // 这里先 `trim` 再入队，避免把纯空白字符当成有效输入。
+                    let input = self.input_state.input.trim().to_string();
+                    if !input.is_empty() {
+                        self.pending_command_literal = self.input_state.literal_slash;
+                        self.pending_command = Some(input);
                     }
-                } else {
                     self.input_state.clear();
                 }
+                InputKeyAction::Newline => self.input_state.insert_char('\n'),
             }
+            return;
+        }
+
+        match key.code {
             KeyCode::Tab if CURRENT_FOCUS == FocusStatus::InputWidget => {
                 if self.input_state.show_completion {
                     self.input_state.confirm_completion();
@@ -547,6 +1078,10 @@ impl OrderTui<'_> {
             KeyCode::Esc if CURRENT_FOCUS == FocusStatus::InputWidget => {
                 if self.input_state.show_completion {
                     self.input_state.cancel_completion();
+                } else if self.show_onboarding {
+                    self.show_onboarding = false;
+                } else if self.pending_chat_context.is_some() {
+                    self.drop_pending_chat_context();
                 }
             }
             KeyCode::Up if CURRENT_FOCUS == FocusStatus::InputWidget => {
@@ -638,6 +1173,69 @@ impl OrderTui<'_> {
         }
     }
 
+    /// 处理一次括号粘贴。
+    ///
+    /// 只在主对话输入框可编辑时接收；超过阈值的内容先进入确认菜单，
+    /// 避免误粘贴整份日志直接撑爆上下文。
+    fn handle_paste_event(&mut self, text: &str) {
+        if self.write_approval_prompt.is_some()
+            || self.validation_confirm.is_some()
+            || self.paste_confirm.is_some()
+            || self.replace_confirm.is_some()
+            || self.validation_view.is_some()
+            || self.history_browser.is_some()
+            || CURRENT_FOCUS != FocusStatus::InputWidget
+        {
+            return;
+        }
+
+        let text = normalize_pasted_text(text);
+        if paste_needs_confirmation(&text, self.paste_confirm_threshold) {
+            self.paste_confirm = Some(PasteConfirmPrompt { text, selected: 0 });
+        } else {
+            self.input_state.insert_str(&text);
+        }
+    }
+
+    /// 处理大段粘贴确认菜单的按键：`Up` / `Down` 选择，`Enter` 确认，`Esc` 取消。
+    fn handle_paste_confirm_key_event(&mut self, key: &KeyEvent) {
+        let decision = match key.code {
+            KeyCode::Up | KeyCode::Down => {
+                if let Some(prompt) = self.paste_confirm.as_mut() {
+                    let count = PASTE_CONFIRM_OPTIONS.len();
+                    prompt.selected = if key.code == KeyCode::Up {
+                        (prompt.selected + count - 1) % count
+                    } else {
+                        (prompt.selected + 1) % count
+                    };
+                }
+                return;
+            }
+            KeyCode::Enter => match self.paste_confirm.as_ref() {
+                Some(prompt) => PasteDecision::from_index(prompt.selected),
+                None => return,
+            },
+            KeyCode::Esc => PasteDecision::Cancel,
+            _ => return,
+        };
+        let Some(prompt) = self.paste_confirm.take() else {
+            return;
+        };
+        self.apply_paste_decision(prompt.text, decision);
+    }
+
+    // 按用户选择把粘贴内容插入输入框；截断上限与确认阈值一致。
+    fn apply_paste_decision(&mut self, text: String, decision: PasteDecision) {
+        match decision {
+            PasteDecision::Insert => self.input_state.insert_str(&text),
+            PasteDecision::InsertTruncated => {
+                let limit = self.paste_confirm_threshold.unwrap_or(text.len());
+                self.input_state.insert_str(&truncate_paste(&text, limit));
+            }
+            PasteDecision::Cancel => {}
+        }
+    }
+
     /// 处理鼠标事件。
     ///
     /// 说明：
@@ -667,14 +1265,104 @@ impl OrderTui<'_> {
         }
     }
 
+    /// 处理验证报告浮层的按键事件。
+    ///
+    /// 支持按键：`Up`/`Down` 逐行滚动、`PageUp`/`PageDown` 翻页、`Esc`/`q` 关闭。
+    fn handle_validation_view_key_event(&mut self, key: &KeyEvent) {
+        let Some(view) = self.validation_view.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.validation_view = None,
+            KeyCode::Up => view.scroll_by(-1),
+            KeyCode::Down => view.scroll_by(1),
+            KeyCode::PageUp => view.scroll_by(-10),
+            KeyCode::PageDown => view.scroll_by(10),
+            _ => {}
+        }
+    }
+
+    /// 处理 `/replace` 逐文件确认浮层的按键事件。
+    ///
+    /// 支持按键：`y`/`Enter` 替换当前文件、`n` 跳过、`a` 替换剩余全部、`Esc`/`q` 放弃。
+    fn handle_replace_confirm_key_event(&mut self, key: &KeyEvent) {
+        let decision = match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => ReplaceDecision::Accept,
+            KeyCode::Char('n') => ReplaceDecision::Skip,
+            KeyCode::Char('a') => ReplaceDecision::AcceptAll,
+            KeyCode::Esc | KeyCode::Char('q') => ReplaceDecision::Cancel,
+            _ => return,
+        };
+        let Some(state) = self.replace_confirm.as_mut() else {
+            return;
+        };
+        let Some(outcome) = state.apply(decision) else {
+            return;
+        };
+
+        let Some(state) = self.replace_confirm.take() else {
+            return;
+        };
+        match outcome {
+            ReplaceConfirmOutcome::Cancelled => {
+                self.push_chat_message(ChatRole::Llm, "已放弃本次替换".to_string(), false);
+            }
+            ReplaceConfirmOutcome::Finished(accepted) if accepted.is_empty() => {
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    "未选择任何文件，替换已结束".to_string(),
+                    false,
+                );
+            }
+            ReplaceConfirmOutcome::Finished(accepted) => {
+                self.start_replace_staging(state.pattern, state.replacement, accepted);
+            }
+        }
+    }
+
     /// 处理历史选择界面的按键事件。
     ///
     /// 支持按键：
     /// - `Up` / `Down`：移动选择
     /// - `Enter`：加载选中会话到对话区
+    /// - `Space`：标记 / 取消标记当前会话
+    /// - `c`：将标记的会话与当前会话并排对比
     /// - `Esc`：退出历史选择界面
     fn handle_history_browser_key_event(&mut self, key: &KeyEvent) {
+        if self
+            .history_browser
+            .as_ref()
+            .is_some_and(|browser| browser.compare.is_some())
+        {
+            self.handle_history_compare_key_event(key);
+            return;
+        }
+
         match key.code {
+            KeyCode::Char(' ') => {
+                if let Some(browser) = self.history_browser.as_mut() {
+                    browser.marked = if browser.marked == Some(browser.selected) {
+                        None
+                    } else {
+                        Some(browser.selected)
+                    };
+                }
+            }
+            KeyCode::Char('c') => {
+                let Some(browser) = self.history_browser.as_mut() else {
+                    return;
+                };
+                // 未标记或标记的就是当前项时忽略，列表标题已提示操作方式。
+                if let Some((left, right)) =
+                    history_compare_pair(browser.marked, browser.selected, browser.items.len())
+                {
+                    browser.compare = Some(HistoryCompareState::new(
+                        &browser.items[left],
+                        &browser.items[right],
+                        self.sanitize_model_output,
+                    ));
+                }
+            }
             KeyCode::Esc => {
                 self.history_browser = None;
             }
@@ -713,7 +1401,11 @@ impl OrderTui<'_> {
                         false,
                     );
 
-                    for conversation in selected_item.conversations {
+                    for mut conversation in selected_item.conversations {
+                        // 旧版本写入的历史可能带有未清理的转义序列，加载时同样处理。
+                        if self.sanitize_model_output {
+                            conversation.content = strip_control_sequences(&conversation.content);
+                        }
                         match conversation.role.as_str() {
                             "user" => {
                                 self.push_chat_message(ChatRole::User, conversation.content, false)
@@ -743,11 +1435,16 @@ impl OrderTui<'_> {
             return Ok(());
         };
 
-        if command.starts_with('/') {
+        let literal = std::mem::take(&mut self.pending_command_literal);
+        if command.starts_with('/') && !literal {
             self.process_command(&command, terminal)?;
         } else {
             self.process_plain_input(command);
         }
+        // 命令（如 `/settings`）可能刚生成了 `.order`，此后不再需要引导。
+        if self.show_onboarding && !is_first_run(&workspace_root_best_effort()) {
+            self.show_onboarding = false;
+        }
         Ok(())
     }
 
@@ -766,8 +1463,35 @@ impl OrderTui<'_> {
         // 发送新消息时重置滚动，显示最新内容。
         self.conversation_scroll = 0;
 
+        // 首次在未配置模型时发送：给出配置引导并把输入放回输入框，而不是只报错。
+        let model_configured =
+            self.connection.is_some() || !matches!(get_current_model_info(), Ok(None));
+        if self.take_model_setup_prompt(model_configured) {
+            self.input_state.set_input(&input);
+            self.push_chat_message(ChatRole::Error, MODEL_SETUP_PROMPT.to_string(), false);
+            return;
+        }
+
+        let attach_active_file = should_attach_active_file(
+            self.active_file.is_some(),
+            self.active_file_attached,
+            self.pending_chat_context.is_some(),
+            &input,
+        );
+        let mut input = self.attach_pending_chat_context(input);
+        if attach_active_file {
+            input = self.attach_active_file_context(input);
+        }
+
         // 改为后台线程流式执行，主循环继续可响应输入和中断。
-        if let Err(error) = self.start_streaming_completion(input) {
+        let result = self.start_streaming_completion(input);
+        if result.is_ok() {
+            self.pending_chat_context = None;
+            if attach_active_file {
+                self.active_file_attached = true;
+            }
+        }
+        if let Err(error) = result {
             let error_msg = error.to_string();
             if error_msg.contains("API Key 未配置") {
                 self.push_chat_message(
@@ -784,6 +1508,18 @@ impl OrderTui<'_> {
         }
     }
 
+    /// 判断本次发送是否需要展示模型配置引导，并记录已提示过。
+    ///
+    /// 每个会话最多提示一次：用户看过引导仍选择直接发送时，不再反复打断，
+    /// 而是走原有的“未配置模型”错误提示。
+    fn take_model_setup_prompt(&mut self, model_configured: bool) -> bool {
+        if model_configured || self.model_setup_prompted {
+            return false;
+        }
+        self.model_setup_prompted = true;
+        true
+    }
+
     /// 处理 `/` 命令。
     fn process_command(
         &mut self,
@@ -811,6 +1547,13 @@ impl OrderTui<'_> {
             "/cancel" => {
                 if self.active_completion.is_some() {
                     self.cancel_active_completion("已发送取消信号（/cancel）".to_string());
+                } else if let Some(active) = self.active_replace.as_ref() {
+                    active.cancel_flag.store(true, Ordering::Relaxed);
+                    self.push_chat_message(
+                        ChatRole::Llm,
+                        "已发送取消信号，替换将在当前文件处理完后停止".to_string(),
+                        false,
+                    );
                 } else {
                     self.push_chat_message(
                         ChatRole::Llm,
@@ -904,6 +1647,118 @@ impl OrderTui<'_> {
                     );
                 }
             }
+            "/replace" => {
+                // 参数可能包含空格或为空，需要从原始命令行解析，而不是按空白切分。
+                let arguments = command_line
+                    .trim_start()
+                    .strip_prefix(command)
+                    .unwrap_or_default();
+                let (pattern, replacement) = match parse_replace_arguments(arguments) {
+                    Ok(arguments) => arguments,
+                    Err(error) => {
+                        self.push_chat_message(ChatRole::Error, error, false);
+                        return Ok(());
+                    }
+                };
+                if self.active_replace.is_some() || self.replace_confirm.is_some() {
+                    self.push_chat_message(
+                        ChatRole::Error,
+                        "已有进行中的替换，请先完成或使用 /cancel 取消".to_string(),
+                        false,
+                    );
+                    return Ok(());
+                }
+                self.start_replace_search(pattern, replacement);
+            }
+            "/context" => match segments.next() {
+                Some("drop") => self.drop_pending_chat_context(),
+                _ => self.push_chat_message(
+                    ChatRole::Error,
+                    "用法：/context drop（丢弃从 editor 带入、尚未发送的上下文）".to_string(),
+                    false,
+                ),
+            },
+            "/validate" => self.run_deferred_validation(segments.next()),
+            "/validation" => {
+                let argument = segments.next();
+                if argument == Some("retry") {
+                    self.retry_failed_validation(segments.next());
+                    return Ok(());
+                }
+                let workspace_root = workspace_root_best_effort();
+                match load_validation_report(&workspace_root, argument) {
+                    Ok(report) => {
+                        self.validation_view = Some(ValidationViewState::new(&report));
+                    }
+                    Err(error) => self.push_chat_message(
+                        ChatRole::Error,
+                        format!("读取验证报告失败：{error}"),
+                        false,
+                    ),
+                }
+            }
+            "/replay" => {
+                // 参数顺序不限：`run` 表示重放只读工具，其余视为 trace_id。
+                let mut trace_id = None;
+                let mut run = false;
+                for argument in segments.by_ref() {
+                    if argument.eq_ignore_ascii_case("run") {
+                        run = true;
+                    } else {
+                        trace_id = Some(argument.to_string());
+                    }
+                }
+                if let Err(error) = self.show_tool_replay(trace_id.as_deref(), run) {
+                    self.push_chat_message(ChatRole::Error, format!("重放失败：{error}"), false);
+                }
+            }
+            "/timestamps" => {
+                self.show_message_timestamps = match segments.next() {
+                    Some(value) if value.eq_ignore_ascii_case("on") => true,
+                    Some(value) if value.eq_ignore_ascii_case("off") => false,
+                    _ => !self.show_message_timestamps,
+                };
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "消息时间戳已{}",
+                        if self.show_message_timestamps {
+                            "开启"
+                        } else {
+                            "关闭"
+                        }
+                    ),
+                    false,
+                );
+            }
+            "/whitespace" => {
+                self.show_code_whitespace = match segments.next() {
+                    Some(value) if value.eq_ignore_ascii_case("on") => true,
+                    Some(value) if value.eq_ignore_ascii_case("off") => false,
+                    _ => !self.show_code_whitespace,
+                };
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "代码块空白符显示已{}",
+                        if self.show_code_whitespace {
+                            "开启"
+                        } else {
+                            "关闭"
+                        }
+                    ),
+                    false,
+                );
+            }
+            "/lasterror" => match self.last_failure.as_ref() {
+                Some(failure) => {
+                    let text = failure.expanded_text();
+                    self.push_chat_message(ChatRole::Error, text, false);
+                }
+                None => {
+                    self.push_chat_message(ChatRole::Llm, "暂无失败记录".to_string(), false);
+                }
+            },
             "/status" => {
                 if let Err(error) = self.show_status_summary() {
                     self.push_chat_message(
@@ -932,10 +1787,10 @@ impl OrderTui<'_> {
                 match segments.next() {
                     Some(argument) if argument.eq_ignore_ascii_case("clear") => {
                         match self.clear_history_file() {
-                            Ok(()) => {
+                            Ok(path) => {
                                 self.push_chat_message(
                                     ChatRole::Llm,
-                                    "已清空运行目录下的 History.json".to_string(),
+                                    format!("已清空历史文件 {}", path.display()),
                                     false,
                                 );
                             }
@@ -1039,41 +1894,7 @@ impl OrderTui<'_> {
                     false,
                 );
 
-                // 确认写入后自动跑最小验证闭环，并把结果归档到 `.order/reports/<trace_id>/validation.json`。
-                let pipeline = ValidationPipeline::default();
-                match pipeline.run(trace_id, &result.files) {
-                    Ok(report) => {
-                        if report.ok {
-                            self.push_chat_message(
-                                ChatRole::Llm,
-                                format!(
-                                    "自动验证通过（耗时={}ms）。报告已写入 `.order/reports/{}/validation.json`",
-                                    report.duration_ms, report.trace_id
-                                ),
-                                false,
-                            );
-                        } else {
-                            self.push_chat_message(
-                                ChatRole::Error,
-                                format!(
-                                    "自动验证失败（耗时={}ms）。失败命令：{}\n报告已写入 `.order/reports/{}/validation.json`\n{}",
-                                    report.duration_ms,
-                                    report.failed_command.clone().unwrap_or_else(|| "<unknown>".to_string()),
-                                    report.trace_id,
-                                    report.suggestion.clone().unwrap_or_default()
-                                ),
-                                false,
-                            );
-                        }
-                    }
-                    Err(error) => {
-                        self.push_chat_message(
-                            ChatRole::Error,
-                            format!("自动验证执行失败：{error}"),
-                            false,
-                        );
-                    }
-                }
+                self.after_write_approval(trace_id, result.files);
                 true
             }
             Err(error) => {
@@ -1083,6 +1904,143 @@ impl OrderTui<'_> {
         }
     }
 
+    /// 按验证模式处理确认写入后的验证：立即运行、弹出询问或留给 `/validate`。
+    fn after_write_approval(&mut self, trace_id: &str, files: Vec<String>) {
+        match self.validation_mode.decision() {
+            ValidationDecision::Run => self.run_validation(trace_id, &files),
+            ValidationDecision::Prompt => {
+                self.deferred_validations.push(trace_id, files);
+                self.validation_confirm = Some(trace_id.to_string());
+            }
+            ValidationDecision::Defer => {
+                self.deferred_validations.push(trace_id, files);
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!("已跳过自动验证，可用 /validate {trace_id} 运行"),
+                    false,
+                );
+            }
+        }
+    }
+
+    /// 处理验证询问的按键：`y` / `Enter` 立即运行，`n` / `Esc` 留待 `/validate`。
+    fn handle_validation_confirm_key_event(&mut self, key: &KeyEvent) {
+        let run = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => return,
+        };
+        let Some(trace_id) = self.validation_confirm.take() else {
+            return;
+        };
+        if run {
+            self.run_deferred_validation(Some(&trace_id));
+        } else {
+            self.push_chat_message(
+                ChatRole::Llm,
+                format!("已暂缓验证，可用 /validate {trace_id} 运行"),
+                false,
+            );
+        }
+    }
+
+    // 运行暂缓的验证；未指定 trace_id 时取最近一次。
+    fn run_deferred_validation(&mut self, trace_id: Option<&str>) {
+        match self.deferred_validations.take(trace_id) {
+            Some((trace_id, files)) => self.run_validation(&trace_id, &files),
+            None => self.push_chat_message(
+                ChatRole::Error,
+                match trace_id {
+                    Some(trace_id) => format!("没有待运行的验证（trace_id={trace_id}）"),
+                    None => "没有待运行的验证".to_string(),
+                },
+                false,
+            ),
+        }
+    }
+
+    /// 运行最小验证闭环，并把结果归档到 `.order/reports/<trace_id>/validation.json`。
+    fn run_validation(&mut self, trace_id: &str, files: &[String]) {
+        match (self.validation_runner)(trace_id, files) {
+            Ok(report) => {
+                if report.ok {
+                    self.push_chat_message(
+                        ChatRole::Llm,
+                        format!(
+                            "自动验证通过（耗时={}ms）。报告已写入 `.order/reports/{}/validation.json`",
+                            report.duration_ms, report.trace_id
+                        ),
+                        false,
+                    );
+                } else {
+                    self.push_chat_message(
+                        ChatRole::Error,
+                        format!(
+                            "自动验证失败（耗时={}ms）。失败命令：{}\n报告已写入 `.order/reports/{}/validation.json`\n{}",
+                            report.duration_ms,
+                            report.failed_command.clone().unwrap_or_else(|| "<unknown>".to_string()),
+                            report.trace_id,
+                            report.suggestion.clone().unwrap_or_default()
+                        ),
+                        false,
+                    );
+                }
+            }
+            Err(error) => {
+                self.push_chat_message(
+                    ChatRole::Error,
+                    format!("自动验证执行失败：{error}"),
+                    false,
+                );
+            }
+        }
+    }
+
+    /// 只重跑最近（或指定 trace_id）验证报告中失败的命令，并汇报新结果。
+    fn retry_failed_validation(&mut self, trace_id: Option<&str>) {
+        match ValidationPipeline.retry_failed(trace_id) {
+            Ok(report) if report.ok => self.push_chat_message(
+                ChatRole::Llm,
+                format!(
+                    "失败命令重跑通过，此前跳过的命令也已补跑（trace_id={}）。报告已更新 `.order/reports/{}/validation.json`\n{}",
+                    report.trace_id,
+                    report.trace_id,
+                    report.suggestion.unwrap_or_default()
+                ),
+                false,
+            ),
+            Ok(report) if report.failed_command.is_none() => self.push_chat_message(
+                ChatRole::Error,
+                format!(
+                    "失败命令重跑通过，但验证仍不完整（trace_id={}）。报告已更新 `.order/reports/{}/validation.json`\n{}",
+                    report.trace_id,
+                    report.trace_id,
+                    report.suggestion.unwrap_or_default()
+                ),
+                false,
+            ),
+            Ok(report) => self.push_chat_message(
+                ChatRole::Error,
+                format!(
+                    "重跑后仍失败（trace_id={}）。失败命令：{}\n报告已更新 `.order/reports/{}/validation.json`\n{}",
+                    report.trace_id,
+                    report
+                        .failed_command
+                        .clone()
+                        .unwrap_or_else(|| "<unknown>".to_string()),
+                    report.trace_id,
+                    report.suggestion.unwrap_or_default()
+                ),
+                false,
+            ),
+            Err(error) => self.push_chat_message(
+                ChatRole::Error,
+                format!("重跑失败命令失败：{error}"),
+                false,
+            ),
+        }
+    }
+
     /// 执行待确认写入的“拒绝”动作。
     fn reject_pending_writes_by_trace_id(&mut self, trace_id: &str) -> bool {
         if self
@@ -1154,102 +2112,346 @@ impl OrderTui<'_> {
                 "自动同意失败，已回退为手动确认模式".to_string(),
                 false,
             );
+        } else if self.write_approval_policy == WriteApprovalPolicy::Workspace {
+            // 同一 trace 的写入只能整体确认，因此只要有一个文件需要确认就整批弹出菜单，
+            // 并在提示中点明原因，让用户知道为什么这次没有自动确认。
+            let requiring = paths_requiring_confirmation(
+                self.write_approval_policy,
+                summaries.iter().map(|item| item.path.as_str()),
+            );
+            if requiring.is_empty() {
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "检测到待确认写入（trace_id={}，文件数={}），均位于工作区内，已按策略自动确认。",
+                        trace_id,
+                        summaries.len()
+                    ),
+                    false,
+                );
+                if self.approve_pending_writes_by_trace_id(trace_id) {
+                    return;
+                }
+            } else {
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "以下文件位于工作区外或属于敏感文件，需要手动确认：{}",
+                        requiring.join("、")
+                    ),
+                    false,
+                );
+            }
         }
 
         self.open_write_approval_prompt(trace_id, summaries);
     }
 
-    /// 打开写入确认菜单，并带上本次写入的文件摘要。
-    fn open_write_approval_prompt(&mut self, trace_id: &str, summaries: Vec<PendingWriteSummary>) {
-        let files = summaries
-            .iter()
-            .map(|item| item.path.clone())
-            .collect::<Vec<_>>();
-        self.write_approval_prompt = Some(WriteApprovalPrompt {
-            trace_id: trace_id.to_string(),
-            selected: 0,
-            files,
-        });
-        self.push_chat_message(
+    /// 在后台线程中搜索整个工作区，并持续回传已扫描文件数。
+    fn start_replace_search(&mut self, pattern: String, replacement: String) {
+        let (sender, receiver) = mpsc::channel::<ReplaceWorkerEvent>();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let progress_message_index = self.push_chat_message_with_index(
             ChatRole::Llm,
-            format!(
-                "检测到待确认写入（trace_id={}，文件数={}）。请使用 ↑/↓ 选择并按 Enter 确认。",
-                trace_id,
-                summaries.len()
-            ),
+            format!("正在搜索 `{pattern}`……（/cancel 可取消）"),
             false,
         );
-    }
-
-    /// 生成/更新模型配置：优先尝试 Codex。
-    ///
-    /// 规则：
-    /// - 默认不覆盖已有 `.order/model.json`；需要用户显式传入 `force` 才允许覆盖；
-    /// - 仅当探测到 Codex 可正常调用时才写入配置，避免把用户带入“不可用模型”的坑；
-    /// - 写入后清空已缓存的 `Connection`，保证后续请求按新配置重建连接。
-    fn configure_model_with_codex(
-        &mut self,
-        force: bool,
-        terminal: &mut DefaultTerminal,
-    ) -> anyhow::Result<()> {
-        let config_path = self.model_config_path()?;
 
-        if config_path.exists() && !force {
-            self.push_chat_message(
-                ChatRole::Llm,
-                format!(
-                    "检测到已有模型配置：{}；如需覆盖请使用 `/settings force`",
-                    config_path.display()
-                ),
-                false,
+        let worker_cancel = Arc::clone(&cancel_flag);
+        let worker_pattern = pattern.clone();
+        thread::spawn(move || {
+            let workspace_root = workspace_root_best_effort();
+            let result = search_workspace(
+                &workspace_root,
+                &worker_pattern,
+                &worker_cancel,
+                |scanned| {
+                    let _ = sender.send(ReplaceWorkerEvent::Progress(format!(
+                        "正在搜索 `{worker_pattern}`……已扫描 {scanned} 个文件（/cancel 可取消）"
+                    )));
+                },
             );
-            return Ok(());
-        }
+            let _ = sender.send(ReplaceWorkerEvent::Searched(
+                result.map_err(|error| error.to_string()),
+            ));
+        });
 
-        let codex_model = env::var("CODEX_MODEL")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| "gpt-5.3-codex".to_string());
-        let codex_base_url = env::var("CODEX_BASE_URL")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .or_else(|| {
-                env::var("OPENAI_BASE_URL")
-                    .ok()
-                    .filter(|value| !value.trim().is_empty())
-            })
-            .unwrap_or_default();
+        self.active_replace = Some(ActiveReplace {
+            receiver,
+            cancel_flag,
+            pattern,
+            replacement,
+            progress_message_index,
+        });
+    }
 
-        self.push_chat_message(
+    /// 在后台线程中组装替换内容并暂存到同一个 trace_id。
+    fn start_replace_staging(
+        &mut self,
+        pattern: String,
+        replacement: String,
+        accepted: Vec<ReplaceFileMatch>,
+    ) {
+        let (sender, receiver) = mpsc::channel::<ReplaceWorkerEvent>();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let progress_message_index = self.push_chat_message_with_index(
             ChatRole::Llm,
-            format!("正在探测 Codex 可用性（model={codex_model}）..."),
+            format!("正在暂存 {} 个文件的替换……", accepted.len()),
             false,
         );
-        // 先刷新一次界面，让用户看到“探测中”，再开始阻塞等待网络请求。
-        terminal.draw(|frame| self.draw(frame))?;
 
-        let probe_result = self.probe_codex_availability(&codex_model, &codex_base_url);
-        match probe_result {
-            Ok(Some(api_key)) => {
-                self.write_model_config_file(
-                    &config_path,
-                    "codex",
-                    &codex_model,
-                    &codex_base_url,
-                    &api_key,
-                )?;
-                // 清空缓存连接，确保后续发送走新配置。
-                self.connection = None;
+        let worker_cancel = Arc::clone(&cancel_flag);
+        let worker_pattern = pattern.clone();
+        let worker_replacement = replacement.clone();
+        thread::spawn(move || {
+            let workspace_root = workspace_root_best_effort();
+            let plan = assemble_replace_plan(
+                &workspace_root,
+                &accepted,
+                &worker_pattern,
+                &worker_replacement,
+                &worker_cancel,
+            );
+            let result = if plan.cancelled {
+                Err("替换已取消，未暂存任何写入".to_string())
+            } else {
+                let trace_id = new_trace_id();
+                let guard = ExecutionGuard;
+                stage_replace_plan(&guard, &trace_id, &plan.edits)
+                    .map(|summaries| StagedReplace {
+                        trace_id,
+                        summaries,
+                        replaced: plan.edits.iter().map(|edit| edit.replaced).sum(),
+                        skipped: plan.skipped,
+                    })
+                    .map_err(|error| format!("{error:#}"))
+            };
+            let _ = sender.send(ReplaceWorkerEvent::Staged(result));
+        });
 
-                self.push_chat_message(
-                    ChatRole::Llm,
-                    format!(
-                        "Codex 可用：已写入配置 {}，Model 面板将自动更新",
-                        config_path.display()
-                    ),
-                    false,
-                );
-            }
+        self.active_replace = Some(ActiveReplace {
+            receiver,
+            cancel_flag,
+            pattern,
+            replacement,
+            progress_message_index,
+        });
+    }
+
+    /// 消费 `/replace` 后台任务事件：更新进度、进入确认或打开写入确认菜单。
+    fn poll_active_replace_events(&mut self) {
+        let mut buffered = Vec::new();
+        if let Some(active) = self.active_replace.as_ref() {
+            loop {
+                match active.receiver.try_recv() {
+                    Ok(event) => buffered.push(event),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        buffered.push(ReplaceWorkerEvent::Searched(Err(
+                            "后台替换线程异常退出".to_string()
+                        )));
+                        break;
+                    }
+                }
+            }
+        }
+
+        for event in buffered {
+            match event {
+                ReplaceWorkerEvent::Progress(text) => {
+                    let index = self
+                        .active_replace
+                        .as_ref()
+                        .and_then(|active| active.progress_message_index);
+                    if let Some(message) = index.and_then(|index| self.messages.get_mut(index)) {
+                        message.content = text;
+                    }
+                }
+                ReplaceWorkerEvent::Searched(result) => {
+                    let Some(active) = self.active_replace.take() else {
+                        break;
+                    };
+                    self.finish_replace_search(active, result);
+                    break;
+                }
+                ReplaceWorkerEvent::Staged(result) => {
+                    self.active_replace = None;
+                    self.finish_replace_staging(result);
+                    break;
+                }
+            }
+        }
+    }
+
+    // 搜索结束：有结果时进入逐文件确认。
+    fn finish_replace_search(
+        &mut self,
+        active: ActiveReplace,
+        result: Result<Vec<ReplaceFileMatch>, String>,
+    ) {
+        match result {
+            Ok(matches) if matches.is_empty() => {
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!("工作区内未找到 `{}`", active.pattern),
+                    false,
+                );
+            }
+            Ok(matches) => {
+                let total = matches.iter().map(|item| item.match_count).sum::<usize>();
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "找到 {} 个文件共 {total} 处匹配，请逐个确认（y 替换 / n 跳过 / a 全部替换 / Esc 放弃）",
+                        matches.len()
+                    ),
+                    false,
+                );
+                self.replace_confirm = Some(ReplaceConfirmState::new(
+                    active.pattern,
+                    active.replacement,
+                    matches,
+                ));
+            }
+            Err(error) => {
+                self.push_chat_message(ChatRole::Error, format!("搜索失败：{error}"), false);
+            }
+        }
+    }
+
+    // 暂存结束：提示跳过的文件，并复用写入确认菜单做最终确认。
+    fn finish_replace_staging(&mut self, result: Result<StagedReplace, String>) {
+        let staged = match result {
+            Ok(staged) => staged,
+            Err(error) => {
+                self.push_chat_message(ChatRole::Error, error, false);
+                return;
+            }
+        };
+
+        if !staged.skipped.is_empty() {
+            self.push_chat_message(
+                ChatRole::Error,
+                format!(
+                    "以下文件已跳过：\n{}",
+                    staged
+                        .skipped
+                        .iter()
+                        .map(|(path, reason)| format!("- {path}：{reason}"))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                false,
+            );
+        }
+        if staged.summaries.is_empty() {
+            self.push_chat_message(ChatRole::Llm, "没有可替换的文件".to_string(), false);
+            return;
+        }
+
+        self.push_chat_message(
+            ChatRole::Llm,
+            format!(
+                "已暂存 {} 处替换（trace_id={}），确认后可用 /rollback {} 整体回滚",
+                staged.replaced, staged.trace_id, staged.trace_id
+            ),
+            false,
+        );
+        self.open_write_approval_prompt(&staged.trace_id, staged.summaries);
+    }
+
+    /// 打开写入确认菜单，并带上本次写入的文件摘要。
+    fn open_write_approval_prompt(&mut self, trace_id: &str, summaries: Vec<PendingWriteSummary>) {
+        let files = summaries
+            .iter()
+            .map(|item| item.path.clone())
+            .collect::<Vec<_>>();
+        self.write_approval_prompt = Some(WriteApprovalPrompt {
+            trace_id: trace_id.to_string(),
+            selected: 0,
+            files,
+        });
+        self.push_chat_message(
+            ChatRole::Llm,
+            format!(
+                "检测到待确认写入（trace_id={}，文件数={}）。请使用 ↑/↓ 选择并按 Enter 确认。",
+                trace_id,
+                summaries.len()
+            ),
+            false,
+        );
+    }
+
+    /// 生成/更新模型配置：优先尝试 Codex。
+    ///
+    /// 规则：
+    /// - 默认不覆盖已有 `.order/model.json`；需要用户显式传入 `force` 才允许覆盖；
+    /// - 仅当探测到 Codex 可正常调用时才写入配置，避免把用户带入“不可用模型”的坑；
+    /// - 写入后清空已缓存的 `Connection`，保证后续请求按新配置重建连接。
+    fn configure_model_with_codex(
+        &mut self,
+        force: bool,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<()> {
+        let config_path = self.model_config_path()?;
+
+        if config_path.exists() && !force {
+            self.push_chat_message(
+                ChatRole::Llm,
+                format!(
+                    "检测到已有模型配置：{}；如需覆盖请使用 `/settings force`",
+                    config_path.display()
+                ),
+                false,
+            );
+            return Ok(());
+        }
+
+        let codex_model = env::var("CODEX_MODEL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| "gpt-5.3-codex".to_string());
+        let codex_base_url = env::var("CODEX_BASE_URL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| {
+                env::var("OPENAI_BASE_URL")
+                    .ok()
+                    .filter(|value| !value.trim().is_empty())
+            })
+            .unwrap_or_default();
+
+        self.push_chat_message(
+            ChatRole::Llm,
+            format!("正在探测 Codex 可用性（model={codex_model}）..."),
+            false,
+        );
+        // 先刷新一次界面，让用户看到“探测中”，再开始阻塞等待网络请求。
+        terminal.draw(|frame| self.draw(frame))?;
+
+        let probe_result =
+            self.probe_codex_availability(&codex_model, &codex_base_url, ProbeContext::Settings);
+        match probe_result {
+            Ok(Some(api_key)) => {
+                self.write_model_config_file(
+                    &config_path,
+                    "codex",
+                    &codex_model,
+                    &codex_base_url,
+                    &api_key,
+                )?;
+                // 清空缓存连接，确保后续发送走新配置。
+                self.connection = None;
+
+                self.push_chat_message(
+                    ChatRole::Llm,
+                    format!(
+                        "Codex 可用：已写入配置 {}，Model 面板将自动更新",
+                        config_path.display()
+                    ),
+                    false,
+                );
+            }
             Ok(None) => {
                 self.push_chat_message(
                     ChatRole::Error,
@@ -1273,10 +2475,13 @@ impl OrderTui<'_> {
     /// - `Ok(Some(api_key))`：探测请求成功，返回可用的 API Key；
     /// - `Ok(None)`：未发现可用于探测的 API Key，直接跳过；
     /// - `Err(_)`：已尝试调用但失败（含超时、鉴权失败、模型不可用等）。
+    ///
+    /// 超时按 `context` 区分，见 `resolve_probe_timeout`。
     fn probe_codex_availability(
         &self,
         model_name: &str,
         api_url: &str,
+        context: ProbeContext,
     ) -> anyhow::Result<Option<String>> {
         // 优先读取环境变量；若不存在则尝试复用“当前模型配置”的 token。
         //
@@ -1324,25 +2529,34 @@ impl OrderTui<'_> {
             .build()
             .context("创建异步运行时失败")?;
 
+        let timeout =
+            resolve_probe_timeout(context, env::var("ORDER_PROBE_TIMEOUT").ok().as_deref());
         let probe_result = runtime.block_on(async {
             // 使用超时包裹，避免网络异常导致配置流程卡死。
-            tokio::time::timeout(
-                Duration::from_secs(12),
-                connection.response("请只回复 OK".to_string()),
-            )
-            .await
+            let probe =
+                tokio::time::timeout(timeout, connection.response("请只回复 OK".to_string()));
+            match context {
+                // 启动探测阻塞在事件循环之前，这里自行轮询按键，允许用户不等超时直接跳过。
+                ProbeContext::Startup => {
+                    run_cancellable(probe, startup_probe_cancel_requested).await
+                }
+                ProbeContext::Settings => Some(probe.await),
+            }
         });
+        let Some(probe_result) = probe_result else {
+            return Err(ProbeCancelled.into());
+        };
 
         match probe_result {
             Ok(Ok(_)) => Ok(Some(api_key)),
             Ok(Err(error)) => Err(error).context("Codex 探测请求失败"),
-            Err(_) => Err(anyhow!("Codex 探测超时（12s）")),
+            Err(_) => Err(anyhow!("Codex 探测超时（{}s）", timeout.as_secs())),
         }
     }
 
-    /// 写入模型配置文件（UTF-8 JSON + LF）。
+    /// 写入模型配置文件（UTF-8 + LF，按扩展名输出 JSON 或 TOML）。
     ///
-    /// 目前固定写到 `.order/model.json`，并启用 `support_tools`，让 Codex 更像“编码助手”。
+    /// 写到 `model_config_path` 给出的路径，并启用 `support_tools`，让 Codex 更像“编码助手”。
     ///
     /// 同时写入 `default_max_turns`，让用户可直接通过配置调节工具多轮上限。
     fn write_model_config_file(
@@ -1372,8 +2586,7 @@ impl OrderTui<'_> {
             },
             "models": []
         });
-        let mut content = serde_json::to_string_pretty(&value).context("序列化模型配置失败")?;
-        content.push('\n');
+        let content = serialize_model_config(&value, config_path)?;
 
         let report = write_utf8_text_with_report(config_path, &content)
             .with_context(|| format!("写入模型配置失败: {}", config_path.display()))?;
@@ -1385,10 +2598,12 @@ impl OrderTui<'_> {
         Ok(())
     }
 
-    /// 计算模型配置文件路径：运行目录下的 `.order/model.json`。
+    /// 计算模型配置文件路径：运行目录下的 `.order/model.json`，已有 `.order/model.toml` 时沿用 TOML。
     fn model_config_path(&self) -> anyhow::Result<PathBuf> {
         let current_dir = env::current_dir().context("获取运行目录失败")?;
-        Ok(current_dir.join(".order").join("model.json"))
+        Ok(resolve_model_config_write_path(
+            &current_dir.join(".order").join("model.json"),
+        ))
     }
 
     /// 启动一次新的流式补全请求，并把执行交给后台线程。
@@ -1397,7 +2612,7 @@ impl OrderTui<'_> {
     /// 只有请求真正成功结束后才转为持久消息，避免取消/失败污染后续上下文。
     fn start_streaming_completion(&mut self, prompt: String) -> anyhow::Result<()> {
         self.ensure_connection()?;
-        let chat_history = self.build_chat_history_for_llm(&prompt);
+        let chat_history = self.chat_history_source(&prompt);
         // 对“1/继续/同意”等短回复做上下文补全：
         // 若上一轮助手仍在等待写入决策，则本轮继续按“必须落工具”执行，避免反复口头承诺。
         let follow_up_requires_write = self.should_force_write_tool_on_follow_up(&prompt);
@@ -1453,6 +2668,7 @@ impl OrderTui<'_> {
             received_delta: false,
             last_tool_progress: Some("请求已发送，等待首个增量...".to_string()),
             started_at: Instant::now(),
+            sanitizer: self.sanitize_model_output.then(StreamSanitizer::default),
         });
         self.last_failure = None;
         Ok(())
@@ -1465,11 +2681,22 @@ impl OrderTui<'_> {
         prompt: String,
         enforce_tool_execution: bool,
         require_write_tool: bool,
-        history: Vec<RigMessage>,
+        history: ChatHistorySource,
         sender: Sender<CompletionWorkerEvent>,
         cancel_flag: Arc<AtomicBool>,
     ) {
         thread::spawn(move || {
+            let history = match history {
+                ChatHistorySource::Ready(history) => history,
+                ChatHistorySource::Build(job) => {
+                    let result = job.run();
+                    let _ = sender.send(CompletionWorkerEvent::ContextBuilt(
+                        result.context_remaining,
+                    ));
+                    result.history
+                }
+            };
+
             let runtime = match tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()
@@ -2010,6 +3237,48 @@ impl OrderTui<'_> {
         .any(|keyword| normalized.contains(keyword))
     }
 
+    /// 判断错误是否为鉴权失败（401/403 或常见的无效密钥文案）。
+    ///
+    /// 与可重试错误一样只在状态字段附近解析数字，避免 trace_id 中的数字误判。
+    fn is_auth_failure_error(error: &str) -> bool {
+        let normalized = error.to_ascii_lowercase();
+        if ["status code", "status=", "\"status\":", "http "]
+            .iter()
+            .any(|marker| {
+                matches!(
+                    Self::extract_status_after_marker(&normalized, marker),
+                    Some(401 | 403)
+                )
+            })
+        {
+            return true;
+        }
+
+        [
+            "unauthorized",
+            "forbidden",
+            "invalid api key",
+            "invalid_api_key",
+            "incorrect api key",
+            "authentication",
+        ]
+        .iter()
+        .any(|keyword| normalized.contains(keyword))
+    }
+
+    /// 鉴权失败时丢弃缓存的连接，返回是否已丢弃。
+    ///
+    /// 连接创建后会一直携带当时的密钥，密钥轮换后只能重启才生效；
+    /// 丢弃后下次发送由 `ensure_connection` 重新从环境变量、密钥文件与模型配置解析凭据。
+    fn invalidate_connection_on_auth_failure(&mut self, error: &str) -> bool {
+        if !self.auth_reconnect || self.connection.is_none() || !Self::is_auth_failure_error(error)
+        {
+            return false;
+        }
+        self.connection = None;
+        true
+    }
+
     /// 从错误文案中提取“可重试”的 HTTP 状态码。
     ///
     /// 只在常见状态字段附近解析数字，避免被 trace_id 这类无关数字误判触发重试。
@@ -2080,6 +3349,9 @@ impl OrderTui<'_> {
 
         for event in buffered {
             match event {
+                CompletionWorkerEvent::ContextBuilt(context_remaining) => {
+                    self.context_remaining = context_remaining;
+                }
                 CompletionWorkerEvent::Stream(stream_event) => {
                     self.handle_completion_stream_event(stream_event);
                 }
@@ -2095,9 +3367,14 @@ impl OrderTui<'_> {
     fn handle_completion_stream_event(&mut self, event: ModelStreamEvent) {
         match event {
             ModelStreamEvent::Delta { content } => {
-                let (index, received_delta_before) = match self.active_completion.as_ref() {
-                    Some(active) => (active.assistant_message_index, active.received_delta),
-                    None => return,
+                let Some(active) = self.active_completion.as_mut() else {
+                    return;
+                };
+                let (index, received_delta_before) =
+                    (active.assistant_message_index, active.received_delta);
+                let content = match active.sanitizer.as_mut() {
+                    Some(sanitizer) => sanitizer.push(&content),
+                    None => content,
                 };
                 if let Some(message) = self.messages.get_mut(index) {
                     if received_delta_before {
@@ -2129,6 +3406,26 @@ impl OrderTui<'_> {
         }
     }
 
+    /// 对完成的回复应用 `output_rules.json` 中的正则替换。
+    ///
+    /// 规则在首次调用时加载一次，无效规则以错误消息提示后跳过；
+    /// 没有规则文件时不改动回复。
+    fn apply_output_rules(&mut self, message_index: usize) {
+        if self.output_rules.is_none() {
+            let (rules, warnings) = OutputRules::load(&workspace_root_best_effort());
+            self.output_rules = Some(rules);
+            for warning in warnings {
+                self.push_chat_message(ChatRole::Error, warning, false);
+            }
+        }
+        let Some(rules) = self.output_rules.as_ref().filter(|rules| !rules.is_empty()) else {
+            return;
+        };
+        if let Some(message) = self.messages.get_mut(message_index) {
+            message.content = rules.apply(&message.content);
+        }
+    }
+
     /// 结束当前请求并处理成功/失败收尾逻辑。
     fn finalize_active_completion(&mut self, result: Result<(), String>) {
         let Some(active) = self.active_completion.take() else {
@@ -2152,6 +3449,8 @@ impl OrderTui<'_> {
                 {
                     assistant_message.persist_to_history = true;
                 }
+                // 改写放在写入历史之前，历史与后续上下文看到的都是改写后的回复。
+                self.apply_output_rules(active.assistant_message_index);
 
                 if let Err(error) = self.persist_history() {
                     let warning = format!("历史写入失败（请检查文件编码）: {error}");
@@ -2191,16 +3490,20 @@ impl OrderTui<'_> {
                     );
                     self.last_failure = None;
                 } else {
-                    let reason = shorten_reason(&error_message, 80);
-                    self.last_failure = Some(FailureSummary {
-                        trace_id: trace_id.clone(),
-                        reason: reason.clone(),
-                    });
+                    self.last_failure = Some(FailureSummary::new(&trace_id, &error_message));
                     self.push_chat_message(
                         ChatRole::Error,
                         format!("发送失败（trace_id={}）：{}", trace_id, error_message),
                         false,
                     );
+                    if self.invalidate_connection_on_auth_failure(&error_message) {
+                        self.push_chat_message(
+                            ChatRole::Error,
+                            "鉴权失败：已丢弃缓存的模型连接，下次发送时将重新读取密钥与模型配置"
+                                .to_string(),
+                            false,
+                        );
+                    }
                 }
 
                 log_event_best_effort(
@@ -2256,6 +3559,22 @@ impl OrderTui<'_> {
         build_result.history
     }
 
+    /// 准备本次请求的对话历史。
+    ///
+    /// 主线程只取消息与记忆快照，压缩和摘要交给补全线程完成，
+    /// 剩余上下文百分比经 `ContextBuilt` 事件回到主循环；
+    /// `ORDER_CONTEXT_WORKER=0` 时仍在主线程同步构建。
+    fn chat_history_source(&mut self, current_prompt: &str) -> ChatHistorySource {
+        if !self.context_worker_enabled {
+            return ChatHistorySource::Ready(self.build_chat_history_for_llm(current_prompt));
+        }
+        ChatHistorySource::Build(self.context_manager.build_job(
+            current_prompt,
+            self.context_messages_for_manager(),
+            self.current_model_limits(),
+        ))
+    }
+
     /// 将运行时消息转换为上下文管理器可消费的结构。
     fn context_messages_for_manager(&self) -> Vec<ContextMessage> {
         self.messages
@@ -2291,12 +3610,58 @@ impl OrderTui<'_> {
     }
 
     /// 将当前会话增量同步到长期记忆文件。
+    ///
+    /// 默认交给后台线程执行，写盘错误在主循环取回结果时再回显；
+    /// 后台线程不可用或被关闭时回退为同步更新，行为与之前一致。
     fn persist_context_memory(&mut self) -> anyhow::Result<()> {
         let context_messages = self.context_messages_for_manager();
+        if self.context_worker_enabled {
+            let worker = self
+                .memory_worker
+                .get_or_insert_with(|| self.context_manager.spawn_memory_worker());
+            if worker.submit(context_messages.clone()) {
+                return Ok(());
+            }
+            // 线程已退出：先收回它留下的结果，之后都走同步路径。
+            self.finish_memory_worker();
+            self.context_worker_enabled = false;
+        }
         self.context_manager
             .update_long_term_memory(&context_messages)
     }
 
+    /// 取回后台记忆线程已完成的结果并应用到主线程副本。
+    fn poll_context_memory_updates(&mut self) {
+        let mut updates = Vec::new();
+        if let Some(worker) = self.memory_worker.as_ref() {
+            while let Some(update) = worker.try_recv() {
+                updates.push(update);
+            }
+        }
+        for update in updates {
+            self.apply_context_memory_update(update);
+        }
+    }
+
+    /// 等待排队中的记忆更新全部写盘后关闭后台线程。
+    fn finish_memory_worker(&mut self) {
+        let Some(worker) = self.memory_worker.take() else {
+            return;
+        };
+        for update in worker.finish() {
+            self.apply_context_memory_update(update);
+        }
+    }
+
+    // 应用一条后台记忆结果，写盘失败时回显到对话区。
+    fn apply_context_memory_update(&mut self, update: MemoryUpdate) {
+        if let Some(error) = self.context_manager.apply_memory_update(update) {
+            let warning = format!("上下文记忆写入失败（请检查文件编码）: {error}");
+            eprintln!("{warning}");
+            self.push_chat_message(ChatRole::Error, warning, false);
+        }
+    }
+
     /// 向对话流追加一条消息并返回其索引。
     ///
     /// 返回 `None` 表示内容为空白被忽略。
@@ -2316,6 +3681,7 @@ impl OrderTui<'_> {
         self.messages.push(ChatMessage {
             role,
             content: normalized_content,
+            created_at: Local::now(),
             persist_to_history,
         });
         let mut index = self.messages.len().saturating_sub(1);
@@ -2356,48 +3722,31 @@ impl OrderTui<'_> {
     /// 结构与 `docs/History.md` 示例保持一致：
     /// - 顶层包含 `date`、`model`、`History`
     /// - `History` 内每个元素包含 `timestamp` 与 `conversations`
-    fn persist_history(&self) -> anyhow::Result<()> {
+    fn persist_history(&mut self) -> anyhow::Result<()> {
         let path = self.history_file_path()?;
-        let mut file = Self::read_history_file(&path)?;
-
         let today = Local::now().format("%Y-%-m-%-d").to_string();
         let model_name = self.current_model_name_for_history();
-        let conversations = self.history_conversations();
-
         let session = HistorySession {
             timestamp: self.session_timestamp.clone(),
-            conversations,
+            conversations: self.history_conversations(),
         };
 
-        if let Some(record) = file
-            .records
-            .iter_mut()
-            .find(|record| record.date == today && record.model == model_name)
-        {
-            if let Some(existing_session) = record
-                .history
-                .iter_mut()
-                .find(|history| history.timestamp == self.session_timestamp)
-            {
-                existing_session.conversations = session.conversations;
-            } else {
-                record.history.push(session);
-            }
-        } else {
-            file.records.push(HistoryRecord {
-                date: today,
-                model: model_name,
-                history: vec![session],
-            });
-        }
-
-        Self::write_history_file(&path, &file)
+        self.history_store
+            .upsert_session(&path, today, model_name, session)
+            .map(|_| ())
     }
 
-    /// 计算历史文件路径：运行目录下的 `History.json`。
+    /// 计算历史文件路径，规则见 [`resolve_history_location`]。
+    ///
+    /// 新位置尚无文件而运行目录下存在旧 `History.json` 时，会先迁移旧文件。
     fn history_file_path(&self) -> anyhow::Result<PathBuf> {
         let current_dir = std::env::current_dir().context("获取运行目录失败")?;
-        Ok(current_dir.join("History.json"))
+        let location = resolve_history_location(
+            &current_dir,
+            env::var("ORDER_HISTORY_PATH").ok().as_deref(),
+            env::var("ORDER_HISTORY_PER_PROJECT").ok().as_deref(),
+        );
+        Ok(migrate_legacy_history(&location))
     }
 
     /// 读取历史文件，不存在时返回空结构。
@@ -2426,7 +3775,14 @@ impl OrderTui<'_> {
 
     /// 将历史结构以 UTF-8 JSON（pretty）写回磁盘。
     fn write_history_file(path: &PathBuf, file: &HistoryFile) -> anyhow::Result<()> {
-        let mut content =
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("创建历史目录失败: {}", parent.display()))?;
+        }
+        let mut content =
             serde_json::to_string_pretty(&file.records).context("序列化历史记录失败")?;
         content.push('\n');
         let report = write_utf8_text_with_report(path, &content)
@@ -2488,13 +3844,15 @@ impl OrderTui<'_> {
         Ok(value.min(100))
     }
 
-    /// 清空运行目录下的 `History.json`。
+    /// 清空历史文件，返回被清空的文件路径。
     ///
     /// 这里采用写入空数组 `[]` 的方式清空，
     /// 便于后续继续追加历史记录。
-    fn clear_history_file(&self) -> anyhow::Result<()> {
+    fn clear_history_file(&mut self) -> anyhow::Result<PathBuf> {
         let path = self.history_file_path()?;
-        Self::write_history_file(&path, &HistoryFile::default())
+        self.history_store.invalidate();
+        Self::write_history_file(&path, &HistoryFile::default())?;
+        Ok(path)
     }
 
     /// 将 `History.json` 中最近 N 轮会话回显到当前对话区。
@@ -2565,7 +3923,7 @@ impl OrderTui<'_> {
 
     /// 进入历史选择界面。
     ///
-    /// 数据来源：运行目录下 `History.json`。
+    /// 数据来源：[`OrderTui::history_file_path`] 解析出的历史文件。
     fn enter_history_browser(&mut self) -> anyhow::Result<()> {
         let path = self.history_file_path()?;
         let file = Self::read_history_file(&path)?;
@@ -2602,10 +3960,41 @@ impl OrderTui<'_> {
                 .then_with(|| right.timestamp.cmp(&left.timestamp))
         });
 
-        self.history_browser = Some(HistoryBrowserState { items, selected: 0 });
+        self.history_browser = Some(HistoryBrowserState {
+            items,
+            selected: 0,
+            marked: None,
+            compare: None,
+        });
         Ok(())
     }
 
+    /// 处理历史会话对比视图的按键事件。
+    ///
+    /// 两列各自保存滚动偏移，`Tab` / `Left` / `Right` 切换接收滚动的列，
+    /// `Esc` 回到历史列表并保留原有的选中与标记。
+    fn handle_history_compare_key_event(&mut self, key: &KeyEvent) {
+        let Some(browser) = self.history_browser.as_mut() else {
+            return;
+        };
+        let Some(compare) = browser.compare.as_mut() else {
+            return;
+        };
+        let column = &mut compare.columns[compare.focused];
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => browser.compare = None,
+            KeyCode::Tab | KeyCode::BackTab => compare.focused = 1 - compare.focused,
+            KeyCode::Left => compare.focused = 0,
+            KeyCode::Right => compare.focused = 1,
+            KeyCode::Up => column.scroll_by(-1),
+            KeyCode::Down => column.scroll_by(1),
+            KeyCode::PageUp => column.scroll_by(-10),
+            KeyCode::PageDown => column.scroll_by(10),
+            KeyCode::Home => column.scroll = 0,
+            _ => {}
+        }
+    }
+
     /// 获取当前历史选择界面的选中项。
     fn selected_history_item(&self) -> Option<&HistoryListItem> {
         let browser = self.history_browser.as_ref()?;
@@ -2615,7 +4004,7 @@ impl OrderTui<'_> {
     /// 构建历史选择界面的渲染行。
     fn build_history_browser_lines(&self, width: usize) -> Vec<Line<'static>> {
         let mut lines = vec![Line::from(Span::styled(
-            "History Browser: Up/Down 选择，Enter 加载，Esc 返回",
+            "History Browser: Up/Down 选择，Enter 加载，Space 标记，c 对比，Esc 返回",
             Style::default()
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
@@ -2627,7 +4016,12 @@ impl OrderTui<'_> {
 
         for (index, item) in browser.items.iter().enumerate() {
             let is_selected = index == browser.selected;
-            let marker = if is_selected { ">" } else { " " };
+            let marker = match (is_selected, browser.marked == Some(index)) {
+                (true, true) => ">*",
+                (true, false) => "> ",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
             let raw = format!(
                 "{} [{}] {} | {} | {} 条消息",
                 marker, item.date, item.model, item.timestamp, item.message_count
@@ -2802,7 +4196,18 @@ impl OrderTui<'_> {
                 ),
             };
 
-            let wrapped = Self::wrap_message(&message.content, width.saturating_sub(2).max(1));
+            // 时间戳只挂在首行，换行宽度为它预留位置，避免首行超出对话区域。
+            let timestamp = self
+                .show_message_timestamps
+                .then(|| format!("{} ", message.created_at.format("%H:%M:%S")));
+            let timestamp_width = timestamp.as_deref().map_or(0, UnicodeWidthStr::width);
+            let content = if self.show_code_whitespace {
+                reveal_code_block_whitespace(&message.content)
+            } else {
+                message.content.clone()
+            };
+            let wrapped =
+                Self::wrap_message(&content, width.saturating_sub(2 + timestamp_width).max(1));
 
             for (index, segment) in wrapped.into_iter().enumerate() {
                 let content = if index == 0 && prefix.is_empty() {
@@ -2813,6 +4218,17 @@ impl OrderTui<'_> {
                     format!("  {segment}")
                 };
 
+                if let (0, Some(timestamp)) = (index, timestamp.as_deref()) {
+                    lines.push(Self::timestamped_line(
+                        timestamp,
+                        content,
+                        style,
+                        is_right_aligned,
+                        width,
+                    ));
+                    continue;
+                }
+
                 let rendered = if is_right_aligned {
                     let content_width = UnicodeWidthStr::width(content.as_str());
                     let padding = width.saturating_sub(content_width);
@@ -2830,6 +4246,115 @@ impl OrderTui<'_> {
         lines
     }
 
+    /// 组合带时间戳的消息首行：暗色时间戳在前，随后是角色标签与正文。
+    ///
+    /// 右对齐的用户消息把时间戳算进内容宽度一起靠右，
+    /// 使时间戳紧贴正文而不是被推到对话区域最左侧。
+    fn timestamped_line(
+        timestamp: &str,
+        content: String,
+        style: Style,
+        is_right_aligned: bool,
+        width: usize,
+    ) -> Line<'static> {
+        let mut spans = Vec::with_capacity(3);
+        if is_right_aligned {
+            let content_width =
+                UnicodeWidthStr::width(timestamp) + UnicodeWidthStr::width(content.as_str());
+            spans.push(Span::raw(" ".repeat(width.saturating_sub(content_width))));
+        }
+        spans.push(Span::styled(
+            timestamp.to_string(),
+            Style::default().fg(Color::DarkGray),
+        ));
+        spans.push(Span::styled(content, style));
+        Line::from(spans)
+    }
+
+    /// 展示某次请求的工具调用序列，`run` 为真时重放其中的只读工具。
+    ///
+    /// 写入与命令工具只展示、不执行：重放用于复现模型的检查步骤，
+    /// 不能因为调试而再次改动工作区。
+    fn show_tool_replay(&mut self, trace_id: Option<&str>, run: bool) -> anyhow::Result<()> {
+        let workspace_root = workspace_root_best_effort();
+        let events = load_logged_events(&workspace_root)?;
+        let Some(trace_id) = trace_id
+            .map(str::to_string)
+            .or_else(|| last_tool_trace_id(&events))
+        else {
+            self.push_chat_message(
+                ChatRole::Error,
+                "日志中没有任何工具调用记录（.order/logs/）".to_string(),
+                false,
+            );
+            return Ok(());
+        };
+
+        let calls = tool_sequence_for_trace(&events, &trace_id);
+        if calls.is_empty() {
+            self.push_chat_message(
+                ChatRole::Error,
+                format!("trace {trace_id} 没有工具调用记录"),
+                false,
+            );
+            return Ok(());
+        }
+
+        let mut lines = vec![format!(
+            "trace {trace_id} 的工具调用序列（共 {} 次）：",
+            calls.len()
+        )];
+        for (index, call) in calls.iter().enumerate() {
+            let outcome = match (call.ok, call.duration_ms) {
+                (Some(true), Some(duration)) => format!("成功 {duration}ms"),
+                (Some(false), _) => {
+                    format!("失败：{}", call.error.as_deref().unwrap_or("未记录原因"))
+                }
+                _ => "未记录结束".to_string(),
+            };
+            lines.push(format!(
+                "{}. {} {} → {}",
+                index + 1,
+                call.tool,
+                call.args_summary(),
+                outcome
+            ));
+        }
+
+        if run {
+            let plan = read_only_replay_plan(&calls);
+            lines.push(String::new());
+            lines.push(format!(
+                "重放只读工具 {} 次（写入与命令工具不执行）：",
+                plan.len()
+            ));
+            if !plan.is_empty() {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .context("创建异步运行时失败")?;
+                for call in plan {
+                    let result = runtime.block_on(replay_read_only_call(call));
+                    lines.push(match result {
+                        Ok(summary) => {
+                            format!("- {} {} → {summary}", call.tool, call.args_summary())
+                        }
+                        Err(error) => {
+                            format!("- {} {} → 失败：{error}", call.tool, call.args_summary())
+                        }
+                    });
+                }
+            }
+        } else if !read_only_replay_plan(&calls).is_empty() {
+            lines.push(format!(
+                "使用 `/replay {trace_id} run` 重放其中的只读工具。"
+            ));
+        }
+
+        self.push_chat_message(ChatRole::Llm, lines.join("\n"), false);
+        Ok(())
+    }
+
     /// 展示最近 24 小时的结构化日志统计（成功率/耗时/重试率）。
     ///
     /// 统计口径：
@@ -2955,7 +4480,7 @@ impl OrderTui<'_> {
         let approval_mode = if self.approve_all_writes {
             "自动同意（本会话）"
         } else {
-            "手动确认"
+            self.write_approval_policy.label()
         };
         summary.push_str(&format!("\n写入同意策略：{approval_mode}"));
         summary.push_str(&format!("\n日志目录：{}", logs_dir.display()));
@@ -3226,20 +4751,488 @@ impl OrderTui<'_> {
         Paragraph::new(Text::from(lines)).render(inner, buf);
     }
 
+    /// 在主界面上方渲染“是否立即验证”的询问。
+    fn render_validation_confirm_prompt(&self, area: Rect, buf: &mut Buffer) {
+        let Some(trace_id) = self.validation_confirm.as_ref() else {
+            return;
+        };
+
+        let max_width = area.width.saturating_sub(2);
+        let max_height = area.height.saturating_sub(2);
+        if max_width < 24 || max_height < 5 {
+            return;
+        }
+
+        let width = max_width.min(60);
+        let height = 5u16.min(max_height);
+        let popup = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+
+        let block = Block::bordered()
+            .title(" 运行验证？ ")
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let lines = vec![
+            Line::from(format!("写入已确认（trace_id={trace_id}）")),
+            Line::from(""),
+            Line::from(Span::styled(
+                "y/Enter 立即验证    n/Esc 稍后用 /validate 运行",
+                Style::default().fg(Color::Gray),
+            )),
+        ];
+        Paragraph::new(Text::from(lines)).render(inner, buf);
+    }
+
+    /// 在主界面上方渲染大段粘贴确认菜单，展示字符数、行数与开头预览。
+    fn render_paste_confirm_prompt(&self, area: Rect, buf: &mut Buffer) {
+        let Some(prompt) = self.paste_confirm.as_ref() else {
+            return;
+        };
+
+        let max_width = area.width.saturating_sub(2);
+        let max_height = area.height.saturating_sub(2);
+        if max_width < 24 || max_height < 8 {
+            return;
+        }
+
+        let width = max_width.min(78);
+        let height = 13u16.min(max_height);
+        let popup = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(popup, buf);
+
+        let block = Block::bordered()
+            .title(" 粘贴确认 ")
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+
+        let char_count = prompt.text.chars().count();
+        let limit = self.paste_confirm_threshold.unwrap_or(char_count);
+        let mut lines = vec![
+            Line::from(format!(
+                "粘贴内容 {char_count} 字符 / {} 行，超过阈值 {limit} 字符",
+                prompt.text.lines().count()
+            )),
+            Line::from(""),
+            Line::from("内容预览："),
+        ];
+        for preview in prompt.text.lines().take(3) {
+            lines.push(Line::from(Span::styled(
+                format!("  {preview}"),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        lines.push(Line::from(""));
+
+        for (index, option) in PASTE_CONFIRM_OPTIONS.iter().enumerate() {
+            let selected = index == prompt.selected;
+            let prefix = if selected { ">" } else { " " };
+            let style = if selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            lines.push(Line::from(Span::styled(
+                format!("{prefix} {option}"),
+                style,
+            )));
+        }
+
+        lines.push(Line::from(Span::styled(
+            "↑/↓ 选择，Enter 确认，Esc 取消",
+            Style::default().fg(Color::DarkGray),
+        )));
+
+        let max_lines = inner.height as usize;
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+        }
+
+        Paragraph::new(Text::from(lines)).render(inner, buf);
+    }
+
     /// 进入 editor 子界面，退出后回到主界面。
     fn launch_editor(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
         // editor 依赖鼠标拖拽与滚轮交互，因此进入 editor 前临时开启鼠标捕获。
         self.set_mouse_capture(true)?;
+        // editor 只处理按键事件，关闭括号粘贴让粘贴内容仍按逐键输入进入缓冲区。
+        self.set_bracketed_paste(false);
         let mut editor = Editor::default();
         let run_result = editor.run(terminal);
         // 返回主界面后恢复“可框选文本”的默认行为。
         let restore_result = self.set_mouse_capture(false);
+        self.set_bracketed_paste(true);
         restore_result?;
         run_result?;
         terminal.clear()?;
         self.last_tick = Instant::now();
+
+        // 每次退出都刷新活动文件：关闭配置后返回 `None`，也顺带清掉旧快照。
+        self.active_file = editor.active_file_context();
+        self.active_file_attached = false;
+        if let Some(handoff) = editor.take_chat_handoff() {
+            self.apply_chat_handoff(handoff);
+        }
         Ok(())
     }
+
+    /// 接收 editor 交回的预填充内容：指令进入输入框，上下文等待随消息发送。
+    fn apply_chat_handoff(&mut self, handoff: ChatHandoff) {
+        self.input_state.set_input(&handoff.instruction);
+        self.pending_chat_context = Some(handoff.context);
+        self.push_chat_message(
+            ChatRole::Llm,
+            format!("已从 editor 带入 {}，编辑指令后回车发送", handoff.summary),
+            false,
+        );
+    }
+
+    /// 丢弃从 editor 带入、尚未发送的上下文，避免它被附加到无关的下一条消息上。
+    fn drop_pending_chat_context(&mut self) {
+        let message = if self.pending_chat_context.take().is_some() {
+            "已丢弃从 editor 带入的上下文"
+        } else {
+            "没有待发送的 editor 上下文"
+        };
+        self.push_chat_message(ChatRole::Llm, message.to_string(), false);
+    }
+
+    // 生成输入框底部的时钟标签；关闭时返回 `None`。
+    fn status_clock_label(&self, width: u16) -> Option<String> {
+        if !self.show_status_clock {
+            return None;
+        }
+        build_status_clock_label(
+            &Local::now().format("%H:%M").to_string(),
+            self.session_started_at.elapsed(),
+            width,
+        )
+    }
+
+    /// 将 editor 带回的上下文拼接到用户输入之后。
+    ///
+    /// 这里只读取不清空：请求启动失败（如未配置 Key）时用户重试仍能带上上下文，
+    /// 真正发出请求后再由调用方清空。
+    fn attach_pending_chat_context(&self, input: String) -> String {
+        match self.pending_chat_context.as_deref() {
+            Some(context) => format!("{input}\n\n{context}"),
+            None => input,
+        }
+    }
+
+    // 把活动文件按预算追加到输入之后；预算放不下时原样返回。
+    fn attach_active_file_context(&self, input: String) -> String {
+        match self
+            .active_file
+            .as_ref()
+            .and_then(|file| file.render_within_budget(ACTIVE_FILE_CONTEXT_BUDGET_CHARS))
+        {
+            Some(context) => format!("{input}\n\n{context}"),
+            None => input,
+        }
+    }
+}
+
+/// 按触发场景选择 Codex 探测超时。
+///
+/// 规则：
+/// - `Startup`：默认 `STARTUP_PROBE_TIMEOUT_SECS`，可用 `ORDER_PROBE_TIMEOUT`（秒）覆盖；
+/// - `Settings`：固定 `SETTINGS_PROBE_TIMEOUT_SECS`，不受环境变量影响，
+///   因为该变量的目的是缩短启动阻塞，而不是削弱用户主动发起的探测。
+///
+/// 环境变量值非法（非正整数）时回退默认值，避免一次笔误让启动卡住或立即超时。
+fn resolve_probe_timeout(context: ProbeContext, env_value: Option<&str>) -> Duration {
+    match context {
+        ProbeContext::Startup => {
+            let seconds = env_value
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(STARTUP_PROBE_TIMEOUT_SECS);
+            Duration::from_secs(seconds)
+        }
+        ProbeContext::Settings => Duration::from_secs(SETTINGS_PROBE_TIMEOUT_SECS),
+    }
+}
+
+/// 等待 `future` 完成，同时每隔 `PROBE_CANCEL_POLL_INTERVAL` 检查一次取消信号。
+///
+/// 返回值：`future` 先完成时返回其输出；检测到取消时丢弃 `future` 并返回 `None`。
+async fn run_cancellable<F: Future>(
+    future: F,
+    mut cancel_requested: impl FnMut() -> bool,
+) -> Option<F::Output> {
+    tokio::pin!(future);
+    loop {
+        if cancel_requested() {
+            return None;
+        }
+        tokio::select! {
+            output = &mut future => return Some(output),
+            _ = tokio::time::sleep(PROBE_CANCEL_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// 非阻塞读取启动探测期间的按键，判断用户是否要求跳过。
+///
+/// 探测期间主循环尚未运行，其他按键不会被处理，这里直接丢弃，
+/// 避免探测结束后被当作输入重放。
+fn startup_probe_cancel_requested() -> bool {
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        if let Ok(Event::Key(key)) = event::read()
+            && key.kind == KeyEventKind::Press
+            && is_probe_cancel_key(&key)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+// 判断按键是否为跳过探测的 Esc / Ctrl+C。
+fn is_probe_cancel_key(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Esc
+        || (matches!(key.code, KeyCode::Char('c') | KeyCode::Char('C'))
+            && key.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+// 判断是否跳过启动探测（`ORDER_SKIP_STARTUP_PROBE`）；未设置时默认不跳过。
+fn startup_probe_skipped(env_value: Option<&str>) -> bool {
+    env_flag(env_value, false)
+}
+
+/// 历史文件名；旧版本固定写在运行目录根部。
+const HISTORY_FILE_NAME: &str = "History.json";
+
+/// 解析出的历史文件位置。
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HistoryLocation {
+    /// 本次应读写的历史文件。
+    path: PathBuf,
+    /// 需要迁移的旧文件：新位置尚无文件、而运行目录下存在旧 `History.json` 时才有值。
+    legacy: Option<PathBuf>,
+}
+
+/// 按优先级解析历史文件位置。
+///
+/// 1. `ORDER_HISTORY_PATH`：显式指定文件，相对路径按运行目录解析；
+/// 2. 项目级目录 `.order/history/History.json`：`ORDER_HISTORY_PER_PROJECT` 为真值，
+///    或该目录已经存在（启用一次后保持生效，无需每次都设置环境变量）；
+/// 3. 运行目录下的 `History.json`，与旧版本行为一致。
+fn resolve_history_location(
+    cwd: &Path,
+    env_path: Option<&str>,
+    per_project_env: Option<&str>,
+) -> HistoryLocation {
+    let legacy_path = cwd.join(HISTORY_FILE_NAME);
+    let project_dir = cwd.join(".order").join("history");
+
+    let path = if let Some(custom) = env_path.map(str::trim).filter(|value| !value.is_empty()) {
+        cwd.join(custom)
+    } else if history_per_project_enabled(per_project_env) || project_dir.is_dir() {
+        project_dir.join(HISTORY_FILE_NAME)
+    } else {
+        legacy_path.clone()
+    };
+
+    let legacy =
+        (path != legacy_path && !path.exists() && legacy_path.is_file()).then_some(legacy_path);
+    HistoryLocation { path, legacy }
+}
+
+/// 把旧位置的历史文件迁移到新位置，返回实际应使用的路径。
+///
+/// 优先移动文件，跨设备等原因无法移动时退回复制；都失败时继续读写旧文件，
+/// 保证历史不会因为迁移失败而“消失”。
+fn migrate_legacy_history(location: &HistoryLocation) -> PathBuf {
+    let Some(legacy) = location.legacy.as_ref() else {
+        return location.path.clone();
+    };
+    if let Some(parent) = location.path.parent()
+        && let Err(error) = fs::create_dir_all(parent)
+    {
+        eprintln!(
+            "history migration warning: 创建 {} 失败: {error}",
+            parent.display()
+        );
+        return legacy.clone();
+    }
+    if fs::rename(legacy, &location.path).is_ok() || fs::copy(legacy, &location.path).is_ok() {
+        return location.path.clone();
+    }
+    eprintln!(
+        "history migration warning: 无法迁移 {}，继续使用旧位置",
+        legacy.display()
+    );
+    legacy.clone()
+}
+
+// 判断 `ORDER_HISTORY_PER_PROJECT` 是否为真值（1/true/yes/on）。
+fn history_per_project_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, false)
+}
+
+// 判断 `ORDER_RAW_MODEL_OUTPUT` 是否开启（保留原始控制字符）；未设置时默认关闭。
+fn raw_model_output_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, false)
+}
+
+// 判断 `ORDER_CHAT_TIMESTAMPS` 是否开启；未设置时默认关闭。
+fn message_timestamps_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, false)
+}
+
+/// 把围栏代码块内的空格替换为 `·`、制表符替换为 `→`，围栏行与正文保持不变。
+///
+/// 用于审阅 YAML、Python 等对空白敏感的输出；只在渲染时调用，消息原文不受影响。
+fn reveal_code_block_whitespace(content: &str) -> String {
+    let mut in_code_block = false;
+    content
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+                return line.to_string();
+            }
+            if !in_code_block {
+                return line.to_string();
+            }
+            line.chars()
+                .map(|ch| match ch {
+                    ' ' => '·',
+                    '\t' => '→',
+                    other => other,
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 判断本条消息是否自动附带活动文件。
+///
+/// 以下情况不附带：
+/// - 同一份快照已经随之前的消息发出，重复附带只会挤占上下文；
+/// - editor 带回的诊断上下文已包含相关代码片段；
+/// - 用户自己贴了代码块，说明已经手动提供了上下文。
+fn should_attach_active_file(
+    has_active_file: bool,
+    already_attached: bool,
+    has_pending_context: bool,
+    input: &str,
+) -> bool {
+    has_active_file && !already_attached && !has_pending_context && !input.contains("```")
+}
+
+// 判断 `ORDER_CONTEXT_WORKER` 是否开启；未设置时默认开启。
+fn context_worker_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, true)
+}
+
+/// 判断是否为首次运行：工作区下还没有 `.order` 配置目录。
+fn is_first_run(workspace_root: &Path) -> bool {
+    !workspace_root.join(".order").exists()
+}
+
+/// 判断是否展示首次运行引导。
+///
+/// `ORDER_ONBOARDING` 设为 0/false/no/off 时始终不展示，其余情况仅在首次运行时展示。
+fn should_show_onboarding(workspace_root: &Path, env_value: Option<&str>) -> bool {
+    env_flag(env_value, true) && is_first_run(workspace_root)
+}
+
+// 生成欢迎页的首次运行引导内容。
+fn onboarding_banner_lines() -> Vec<Line<'static>> {
+    let hint = |key: &'static str, text: &'static str| {
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            Span::styled(format!("{key:<10}"), Style::default().fg(Color::Cyan)),
+            Span::styled(text, Style::default().fg(Color::Gray)),
+        ])
+    };
+    vec![
+        Line::from(Span::styled(
+            "First time here? Quick start:",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        hint(
+            "/settings",
+            "configure a model (or set CODEX_API_KEY / OPENAI_API_KEY)",
+        ),
+        hint("/editor", "open the built-in editor"),
+        hint(
+            ".order/",
+            "project config lives here; user config in ~/.config/order/",
+        ),
+        Line::from(Span::styled(
+            "Press Esc to dismiss",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ]
+}
+
+// 判断 `ORDER_AUTH_RECONNECT` 是否开启；未设置时默认开启。
+fn auth_reconnect_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, true)
+}
+
+// 判断 `ORDER_STATUS_CLOCK` 是否开启；未设置时默认开启。
+fn status_clock_enabled(env_value: Option<&str>) -> bool {
+    env_flag(env_value, true)
+}
+
+/// 将会话时长格式化为 `H:MM:SS`。
+///
+/// 小时位不补零也不按天折返，长会话（超过 24 小时）依然能直观读出总时长。
+fn format_session_duration(elapsed: Duration) -> String {
+    let total = elapsed.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
+
+/// 按输入框宽度生成时钟标签。
+///
+/// 宽度足够时同时展示当前时间与会话时长；较窄时只保留会话时长；
+/// 再窄则不展示，避免挤占底部边框或在窄终端上被截断成难以辨认的片段。
+fn build_status_clock_label(now: &str, elapsed: Duration, width: u16) -> Option<String> {
+    const FULL_MIN_WIDTH: u16 = 40;
+    const COMPACT_MIN_WIDTH: u16 = 24;
+
+    let duration = format_session_duration(elapsed);
+    if width >= FULL_MIN_WIDTH {
+        Some(format!("{now} · 会话 {duration}"))
+    } else if width >= COMPACT_MIN_WIDTH {
+        Some(duration)
+    } else {
+        None
+    }
 }
 
 /// 截断错误原因，避免状态栏被长文本撑爆。
@@ -3266,16 +5259,141 @@ fn shorten_reason(text: &str, max_chars: usize) -> String {
 
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone;
+
     use super::*;
 
     fn chat_message(role: ChatRole, content: &str, persist_to_history: bool) -> ChatMessage {
         ChatMessage {
             role,
             content: content.to_string(),
+            created_at: Local::now(),
             persist_to_history,
         }
     }
 
+    fn history_item(timestamp: &str, contents: &[&str]) -> HistoryListItem {
+        HistoryListItem {
+            date: "2026-10-17".to_string(),
+            model: "gpt".to_string(),
+            timestamp: timestamp.to_string(),
+            message_count: contents.len(),
+            conversations: contents
+                .iter()
+                .map(|content| HistoryConversation {
+                    role: "user".to_string(),
+                    content: content.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn history_compare_pair_should_put_older_session_on_the_left() {
+        assert_eq!(history_compare_pair(Some(0), 2, 3), Some((2, 0)));
+        assert_eq!(
+            history_compare_pair(Some(2), 0, 3),
+            Some((2, 0)),
+            "先标记哪一个都不影响左右顺序"
+        );
+        assert_eq!(history_compare_pair(None, 1, 3), None, "未标记时不对比");
+        assert_eq!(history_compare_pair(Some(1), 1, 3), None, "同一会话不对比");
+        assert_eq!(
+            history_compare_pair(Some(5), 1, 3),
+            None,
+            "标记越界时不对比"
+        );
+    }
+
+    #[test]
+    fn history_compare_columns_should_scroll_independently() {
+        let mut tui = OrderTui {
+            history_browser: Some(HistoryBrowserState {
+                items: vec![
+                    history_item("10:00", &["new"]),
+                    history_item("09:00", &["a\nb\nc", "d"]),
+                ],
+                selected: 0,
+                marked: None,
+                compare: None,
+            }),
+            ..OrderTui::default()
+        };
+        let press = |tui: &mut OrderTui, code: KeyCode| {
+            tui.handle_history_browser_key_event(&KeyEvent::new(code, KeyModifiers::NONE));
+        };
+
+        press(&mut tui, KeyCode::Char('c'));
+        assert!(
+            tui.history_browser.as_ref().unwrap().compare.is_none(),
+            "未标记时不应打开对比"
+        );
+        press(&mut tui, KeyCode::Char(' '));
+        press(&mut tui, KeyCode::Down);
+        press(&mut tui, KeyCode::Char('c'));
+        let compare = tui
+            .history_browser
+            .as_ref()
+            .unwrap()
+            .compare
+            .as_ref()
+            .unwrap();
+        assert_eq!(compare.columns[0].title, "2026-10-17 gpt 09:00");
+        assert_eq!(
+            compare.columns[0].lines,
+            vec!["[user]", "a", "b", "c", "", "[user]", "d"]
+        );
+
+        press(&mut tui, KeyCode::Down);
+        press(&mut tui, KeyCode::Down);
+        press(&mut tui, KeyCode::Tab);
+        press(&mut tui, KeyCode::PageDown);
+        let compare = tui
+            .history_browser
+            .as_ref()
+            .unwrap()
+            .compare
+            .as_ref()
+            .unwrap();
+        assert_eq!(compare.columns[0].scroll, 2, "左列保留自己的偏移");
+        assert_eq!(compare.columns[1].scroll, 1, "右列滚动限制在行数内");
+
+        press(&mut tui, KeyCode::Left);
+        press(&mut tui, KeyCode::PageUp);
+        let compare = tui
+            .history_browser
+            .as_ref()
+            .unwrap()
+            .compare
+            .as_ref()
+            .unwrap();
+        assert_eq!(compare.columns[0].scroll, 0);
+        assert_eq!(compare.columns[1].scroll, 1);
+
+        press(&mut tui, KeyCode::Esc);
+        let browser = tui.history_browser.as_ref().unwrap();
+        assert!(browser.compare.is_none(), "Esc 只关闭对比视图");
+        assert_eq!((browser.selected, browser.marked), (1, Some(0)));
+    }
+
+    #[test]
+    fn history_compare_columns_should_strip_control_sequences() {
+        let item = history_item("09:00", &["\x1b[31mred\x1b[0m\ttext"]);
+
+        let column = HistoryCompareColumn::new(&item, true);
+        assert_eq!(
+            column.lines,
+            vec!["[user]", "red\ttext"],
+            "对比视图不应输出转义序列"
+        );
+
+        let raw = HistoryCompareColumn::new(&item, false);
+        assert_eq!(
+            raw.lines[1], "\x1b[31mred\x1b[0m\ttext",
+            "关闭清理时保留原文"
+        );
+    }
+
     #[test]
     fn build_chat_history_should_skip_current_prompt_duplicate() {
         let mut tui = OrderTui::default();
@@ -3387,6 +5505,137 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn is_auth_failure_error_should_match_401_403_and_key_errors() {
+        assert!(OrderTui::is_auth_failure_error("401 unauthorized"));
+        assert!(OrderTui::is_auth_failure_error(
+            "CompletionError: ProviderError: Invalid status code 403 Forbidden"
+        ));
+        assert!(OrderTui::is_auth_failure_error(
+            "{\"error\":{\"code\":\"invalid_api_key\"}}"
+        ));
+        assert!(!OrderTui::is_auth_failure_error(
+            "CompletionError: ProviderError: Invalid status code 500 Internal Server Error"
+        ));
+        assert!(!OrderTui::is_auth_failure_error(
+            "请求超时（trace_id=401403）"
+        ));
+    }
+
+    #[test]
+    fn write_approval_should_defer_validation_unless_auto() {
+        // 桩验证：直接返回通过的报告，不在测试里真的运行 cargo。
+        fn passing_validation(
+            trace_id: &str,
+            _files: &[String],
+        ) -> anyhow::Result<ValidationReport> {
+            Ok(ValidationReport {
+                trace_id: trace_id.to_string(),
+                started_at_unix_ms: 0,
+                duration_ms: 0,
+                ok: true,
+                stages: Vec::new(),
+                failed_command: None,
+                suggestion: None,
+                plan: Vec::new(),
+            })
+        }
+        let files = vec!["crates/rander/src/tui.rs".to_string()];
+        let last_message =
+            |tui: &OrderTui| tui.messages.last().map(|message| message.content.clone());
+
+        let mut manual = OrderTui {
+            validation_mode: ValidationMode::Manual,
+            validation_runner: passing_validation,
+            ..OrderTui::default()
+        };
+        manual.after_write_approval("trace-manual", files.clone());
+        assert!(manual.validation_confirm.is_none(), "manual 模式不弹出询问");
+        assert!(manual.deferred_validations.contains("trace-manual"));
+        assert!(
+            last_message(&manual).is_some_and(|content| content.contains("/validate trace-manual"))
+        );
+
+        manual.run_deferred_validation(None);
+        assert!(
+            last_message(&manual).is_some_and(|content| content.contains("自动验证通过")),
+            "/validate 不带参数时运行最近一次暂缓的验证"
+        );
+        assert!(
+            !manual.deferred_validations.contains("trace-manual"),
+            "运行后应从待验证列表移除"
+        );
+
+        let mut ask = OrderTui {
+            validation_mode: ValidationMode::Ask,
+            validation_runner: passing_validation,
+            ..OrderTui::default()
+        };
+        ask.after_write_approval("trace-ask", files);
+        assert_eq!(ask.validation_confirm.as_deref(), Some("trace-ask"));
+        ask.handle_validation_confirm_key_event(&KeyEvent::new(
+            KeyCode::Char('n'),
+            KeyModifiers::NONE,
+        ));
+        assert!(ask.validation_confirm.is_none());
+        assert!(
+            ask.deferred_validations.contains("trace-ask"),
+            "选择稍后时保留记录，供 /validate 触发"
+        );
+
+        ask.run_deferred_validation(Some("trace-ask"));
+        assert!(last_message(&ask).is_some_and(|content| content.contains("自动验证通过")));
+        assert!(!ask.deferred_validations.contains("trace-ask"));
+
+        ask.run_deferred_validation(Some("trace-ask"));
+        assert!(
+            ask.messages
+                .last()
+                .is_some_and(|message| matches!(message.role, ChatRole::Error)
+                    && message.content.contains("没有待运行的验证")),
+            "同一 trace 的验证只能运行一次"
+        );
+    }
+
+    #[test]
+    fn auth_failure_should_invalidate_cached_connection() {
+        let connection = || {
+            Connection::new(
+                Provider::OpenAI,
+                "https://example.invalid/v1".to_string(),
+                "old-key".to_string(),
+                "gpt".to_string(),
+                true,
+                None,
+                None,
+            )
+        };
+        let mut tui = OrderTui {
+            connection: Some(connection()),
+            auth_reconnect: true,
+            ..OrderTui::default()
+        };
+
+        assert!(!tui.invalidate_connection_on_auth_failure("503 Service Unavailable"));
+        assert!(tui.connection.is_some(), "非鉴权错误不应丢弃连接");
+        assert!(tui.invalidate_connection_on_auth_failure("status code 401 Unauthorized"));
+        assert!(
+            tui.connection.is_none(),
+            "鉴权失败后应丢弃连接，下次发送重新解析凭据"
+        );
+
+        let mut disabled = OrderTui {
+            connection: Some(connection()),
+            auth_reconnect: false,
+            ..OrderTui::default()
+        };
+        assert!(!disabled.invalidate_connection_on_auth_failure("401 unauthorized"));
+        assert!(disabled.connection.is_some(), "关闭后保持原有连接");
+
+        assert!(auth_reconnect_enabled(None));
+        assert!(!auth_reconnect_enabled(Some("0")));
+    }
+
     #[test]
     fn extract_retryable_http_status_should_only_accept_retryable_codes() {
         assert_eq!(
@@ -3561,6 +5810,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ctrl_v_should_insert_slash_without_command_completion() {
+        let mut tui = OrderTui {
+            submit_key: SubmitKey::Enter,
+            ..OrderTui::default()
+        };
+        let press = |tui: &mut OrderTui, code: KeyCode, modifiers: KeyModifiers| {
+            tui.handle_key_event(&KeyEvent::new(code, modifiers));
+        };
+
+        press(&mut tui, KeyCode::Char('v'), KeyModifiers::CONTROL);
+        press(&mut tui, KeyCode::Char('/'), KeyModifiers::NONE);
+        press(&mut tui, KeyCode::Char('h'), KeyModifiers::NONE);
+        assert_eq!(tui.input_state.input, "/h");
+        assert!(
+            !tui.input_state.show_completion,
+            "字面输入的 / 不弹出命令补全"
+        );
+
+        press(&mut tui, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(tui.pending_command.as_deref(), Some("/h"));
+        assert!(tui.pending_command_literal, "提交时按普通消息处理");
+
+        press(&mut tui, KeyCode::Char('/'), KeyModifiers::NONE);
+        assert!(tui.input_state.show_completion, "之后的按键恢复常规行为");
+    }
+
     #[test]
     fn write_approval_prompt_enter_on_reject_should_not_enable_approve_all() {
         let mut tui = OrderTui::default();
@@ -3596,10 +5872,519 @@ mod tests {
             "应给出已开启会话级自动同意的提示"
         );
     }
+
+    #[test]
+    fn large_paste_should_wait_for_confirmation_and_truncate_on_request() {
+        let mut tui = OrderTui {
+            paste_confirm_threshold: Some(10),
+            ..OrderTui::default()
+        };
+
+        tui.handle_paste_event("short");
+        assert_eq!(tui.input_state.input, "short", "未超过阈值应直接插入");
+        assert!(tui.paste_confirm.is_none());
+
+        tui.input_state.clear();
+        tui.handle_paste_event("first\r\nsecond line\r\nthird");
+        assert!(tui.paste_confirm.is_some(), "超过阈值应弹出确认");
+        assert!(tui.input_state.input.is_empty(), "确认前不应写入输入框");
+
+        tui.handle_key_event(&KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        tui.handle_key_event(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(tui.paste_confirm.is_none());
+        assert_eq!(tui.input_state.input, "first", "截断插入应保留完整行");
+        assert_eq!(tui.input_state.cursor_position, 5);
+
+        tui.input_state.clear();
+        tui.handle_paste_event(&"x".repeat(20));
+        tui.handle_key_event(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(tui.paste_confirm.is_none());
+        assert!(tui.input_state.input.is_empty(), "取消后不应插入任何内容");
+    }
+
+    #[test]
+    fn failure_summary_should_keep_full_error_alongside_short_reason() {
+        let long_line = "连接被重置 ".repeat(30);
+        let error_message = format!("{long_line}\ncaused by: tls handshake eof\n  at retry 3");
+        let failure = FailureSummary::new("trace-9", &error_message);
+
+        assert!(failure.reason.chars().count() <= 80, "状态栏展示应截断");
+        assert!(!failure.reason.contains("caused by"), "状态栏只展示首行");
+        assert_eq!(failure.detail, error_message.trim(), "完整错误应原样保留");
+
+        let expanded = failure.expanded_text();
+        assert!(expanded.contains("trace_id=trace-9"));
+        assert!(expanded.contains("  at retry 3"), "展开内容应包含多行细节");
+    }
+
+    #[test]
+    fn resolve_probe_timeout_should_differ_between_startup_and_settings() {
+        assert_eq!(
+            resolve_probe_timeout(ProbeContext::Startup, None),
+            Duration::from_secs(STARTUP_PROBE_TIMEOUT_SECS)
+        );
+        assert_eq!(
+            resolve_probe_timeout(ProbeContext::Settings, None),
+            Duration::from_secs(SETTINGS_PROBE_TIMEOUT_SECS)
+        );
+        assert!(
+            resolve_probe_timeout(ProbeContext::Startup, None)
+                < resolve_probe_timeout(ProbeContext::Settings, None),
+            "启动探测超时应短于 /settings 探测"
+        );
+    }
+
+    #[test]
+    fn resolve_probe_timeout_should_apply_env_override_only_on_startup() {
+        assert_eq!(
+            resolve_probe_timeout(ProbeContext::Startup, Some(" 2 ")),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            resolve_probe_timeout(ProbeContext::Settings, Some("2")),
+            Duration::from_secs(SETTINGS_PROBE_TIMEOUT_SECS),
+            "/settings 探测不应受 ORDER_PROBE_TIMEOUT 影响"
+        );
+        // 非法值回退默认，避免 0 秒立即超时。
+        for invalid in ["0", "abc", ""] {
+            assert_eq!(
+                resolve_probe_timeout(ProbeContext::Startup, Some(invalid)),
+                Duration::from_secs(STARTUP_PROBE_TIMEOUT_SECS)
+            );
+        }
+    }
+
+    #[test]
+    fn startup_probe_skipped_should_accept_truthy_values() {
+        assert!(startup_probe_skipped(Some("1")));
+        assert!(startup_probe_skipped(Some(" TRUE ")));
+        assert!(!startup_probe_skipped(Some("0")));
+        assert!(!startup_probe_skipped(None));
+    }
+
+    #[test]
+    fn model_setup_prompt_should_trigger_once_per_session() {
+        let mut tui = OrderTui::default();
+        assert!(!tui.take_model_setup_prompt(true), "已配置模型时不应提示");
+        assert!(tui.take_model_setup_prompt(false), "首次未配置模型时应提示");
+        assert!(!tui.take_model_setup_prompt(false), "同一会话不应重复提示");
+    }
+
+    #[test]
+    fn run_cancellable_should_short_circuit_on_cancel_signal() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("创建异步运行时失败");
+
+        // 取消信号优先于尚未完成的探测，探测结果不再被处理。
+        let mut polls = 0;
+        let cancelled = runtime.block_on(run_cancellable(std::future::pending::<()>(), || {
+            polls += 1;
+            polls >= 3
+        }));
+        assert_eq!(cancelled, None);
+        assert_eq!(polls, 3, "应按间隔轮询直到收到取消信号");
+
+        let completed = runtime.block_on(run_cancellable(async { 42 }, || false));
+        assert_eq!(completed, Some(42));
+
+        assert!(is_probe_cancel_key(&KeyEvent::new(
+            KeyCode::Esc,
+            KeyModifiers::NONE
+        )));
+        assert!(is_probe_cancel_key(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_probe_cancel_key(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::NONE
+        )));
+    }
+
+    #[test]
+    fn format_session_duration_should_roll_over_minutes_and_hours() {
+        assert_eq!(format_session_duration(Duration::from_secs(0)), "0:00:00");
+        assert_eq!(format_session_duration(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(format_session_duration(Duration::from_secs(60)), "0:01:00");
+        assert_eq!(
+            format_session_duration(Duration::from_secs(3599)),
+            "0:59:59"
+        );
+        assert_eq!(
+            format_session_duration(Duration::from_secs(3600)),
+            "1:00:00"
+        );
+        assert_eq!(
+            format_session_duration(Duration::from_secs(25 * 3600 + 61)),
+            "25:01:01",
+            "超过 24 小时不应折返"
+        );
+    }
+
+    #[test]
+    fn conversation_lines_should_prefix_dimmed_timestamp_when_enabled() {
+        let created_at = Local
+            .with_ymd_and_hms(2026, 2, 8, 9, 5, 7)
+            .single()
+            .expect("构造时间失败");
+        let tui = OrderTui {
+            show_message_timestamps: true,
+            messages: vec![
+                ChatMessage {
+                    created_at,
+                    ..chat_message(ChatRole::Llm, "你好", false)
+                },
+                ChatMessage {
+                    created_at,
+                    ..chat_message(ChatRole::User, "hi", false)
+                },
+            ],
+            ..OrderTui::default()
+        };
+
+        let lines = tui.build_conversation_lines(20);
+        let llm_first = &lines[0];
+        assert_eq!(llm_first.spans[0].content, "09:05:07 ");
+        assert_eq!(
+            llm_first.spans[0].style.fg,
+            Some(Color::DarkGray),
+            "时间戳应以暗色显示"
+        );
+        assert_eq!(llm_first.spans[1].content, "LLM: 你好");
+
+        let user_first = &lines[2];
+        assert_eq!(
+            user_first
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<String>(),
+            format!("{}09:05:07 hi", " ".repeat(9)),
+            "用户消息连同时间戳整体右对齐"
+        );
+        assert_eq!(user_first.width(), 20);
+    }
+
+    #[test]
+    fn reveal_code_block_whitespace_should_only_touch_code_blocks() {
+        let content = "先看 a b\n```yaml\nkey:\n  value: 1\n\tx: y\n```\n结尾 c d";
+        assert_eq!(
+            reveal_code_block_whitespace(content),
+            "先看 a b\n```yaml\nkey:\n··value:·1\n→x:·y\n```\n结尾 c d"
+        );
+
+        let mut tui = OrderTui {
+            show_message_timestamps: false,
+            show_code_whitespace: true,
+            ..OrderTui::default()
+        };
+        tui.push_chat_message(ChatRole::Llm, "a b\n```\n  x\n```".to_string(), false);
+        let rendered = tui
+            .build_conversation_lines(40)
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(rendered[0], "LLM: a b", "正文中的空格不应被替换");
+        assert_eq!(rendered[2], "  ··x");
+    }
+
+    #[test]
+    fn conversation_lines_without_timestamps_should_match_original_layout() {
+        let tui = OrderTui {
+            show_message_timestamps: false,
+            messages: vec![
+                chat_message(ChatRole::Llm, "你好", false),
+                chat_message(ChatRole::User, "hi", false),
+            ],
+            ..OrderTui::default()
+        };
+
+        let llm_style = Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD);
+        let user_style = Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD);
+        assert_eq!(
+            tui.build_conversation_lines(10),
+            vec![
+                Line::from(Span::styled("LLM: 你好", llm_style)),
+                Line::from(""),
+                Line::from(Span::styled(format!("{}hi", " ".repeat(8)), user_style)),
+                Line::from(""),
+            ]
+        );
+        assert!(!message_timestamps_enabled(None), "默认不显示时间戳");
+        assert!(message_timestamps_enabled(Some("on")));
+    }
+
+    #[test]
+    fn onboarding_should_show_only_before_order_dir_exists() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let root =
+            std::env::temp_dir().join(format!("order-onboarding-{}-{nanos}", std::process::id()));
+        fs::create_dir_all(&root).expect("创建临时工作区失败");
+
+        assert!(is_first_run(&root));
+        assert!(should_show_onboarding(&root, None));
+        assert!(
+            !should_show_onboarding(&root, Some("off")),
+            "显式关闭时不应展示"
+        );
+
+        fs::create_dir_all(root.join(".order")).expect("创建 .order 目录失败");
+        assert!(!is_first_run(&root));
+        assert!(!should_show_onboarding(&root, None), "已有配置时应隐藏引导");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn build_status_clock_label_should_adapt_to_width() {
+        let elapsed = Duration::from_secs(65);
+        assert_eq!(
+            build_status_clock_label("09:30", elapsed, 80).as_deref(),
+            Some("09:30 · 会话 0:01:05")
+        );
+        assert_eq!(
+            build_status_clock_label("09:30", elapsed, 30).as_deref(),
+            Some("0:01:05")
+        );
+        assert_eq!(build_status_clock_label("09:30", elapsed, 10), None);
+        assert!(status_clock_enabled(None));
+        assert!(!status_clock_enabled(Some("off")));
+    }
+
+    #[test]
+    fn apply_chat_handoff_should_prefill_input_and_attach_context() {
+        let mut tui = OrderTui {
+            show_onboarding: false,
+            ..OrderTui::default()
+        };
+        tui.apply_chat_handoff(ChatHandoff {
+            instruction: "请修复 `a.rs` 中的 1 条诊断问题".to_string(),
+            context: "诊断详情".to_string(),
+            summary: "1 条诊断及代码上下文".to_string(),
+        });
+
+        assert_eq!(tui.input_state.input, "请修复 `a.rs` 中的 1 条诊断问题");
+        assert_eq!(
+            tui.attach_pending_chat_context("修一下".to_string()),
+            "修一下\n\n诊断详情"
+        );
+        // 上下文在真正发出请求前保持可用，避免启动失败后丢失。
+        assert!(tui.pending_chat_context.is_some());
+
+        // 不想发送时可以用 Esc 丢弃，之后的消息不再附带。
+        tui.handle_key_event(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert!(tui.pending_chat_context.is_none());
+        assert_eq!(
+            tui.attach_pending_chat_context("无关的问题".to_string()),
+            "无关的问题"
+        );
+
+        tui.apply_chat_handoff(ChatHandoff {
+            instruction: "请修复".to_string(),
+            context: "诊断详情".to_string(),
+            summary: "1 条诊断".to_string(),
+        });
+        tui.drop_pending_chat_context();
+        assert!(tui.pending_chat_context.is_none());
+        assert_eq!(
+            tui.messages.last().map(|message| message.content.as_str()),
+            Some("已丢弃从 editor 带入的上下文")
+        );
+    }
+
+    #[test]
+    fn active_file_should_attach_once_and_skip_with_other_context() {
+        assert!(should_attach_active_file(true, false, false, "解释一下"));
+        assert!(!should_attach_active_file(false, false, false, "解释一下"));
+        assert!(!should_attach_active_file(true, true, false, "解释一下"));
+        assert!(!should_attach_active_file(true, false, true, "解释一下"));
+        assert!(!should_attach_active_file(
+            true,
+            false,
+            false,
+            "看这段\n```rust\nfn a() {}\n```"
+        ));
+
+        let mut tui = OrderTui::default();
+        assert_eq!(tui.attach_active_file_context("问".to_string()), "问");
+        tui.active_file = Some(ActiveFileContext {
+            label: "src/a.rs".to_string(),
+            lines: vec!["fn a() {}".to_string()],
+            focus_row: 0,
+        });
+        let input = tui.attach_active_file_context("问".to_string());
+        assert!(input.starts_with("问\n\n当前活动文件 `src/a.rs`"));
+        assert!(input.contains("1 | fn a() {}"));
+    }
+
+    #[test]
+    fn history_store_should_append_turns_without_rereading_file() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!(
+            "order-history-store-{}-{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).expect("创建临时目录失败");
+        let path = dir.join("History.json");
+        let session = |contents: &[&str]| HistorySession {
+            timestamp: "2026-2-8 10:00:00".to_string(),
+            conversations: contents
+                .iter()
+                .map(|content| HistoryConversation {
+                    role: "user".to_string(),
+                    content: (*content).to_string(),
+                })
+                .collect(),
+        };
+        let mut store = HistoryStore::default();
+
+        let written = store
+            .upsert_session(&path, "2026-2-8".into(), "m".into(), session(&["第一问"]))
+            .expect("首次写入失败");
+        assert!(written);
+        assert!(
+            !store.refresh_if_stale(&path).expect("检查缓存失败"),
+            "自己写入后缓存仍然有效，不应重新读盘"
+        );
+
+        let written = store
+            .upsert_session(
+                &path,
+                "2026-2-8".into(),
+                "m".into(),
+                session(&["第一问", "第二问"]),
+            )
+            .expect("追加写入失败");
+        assert!(written);
+        assert!(!store.refresh_if_stale(&path).expect("检查缓存失败"));
+        let unchanged = store
+            .upsert_session(
+                &path,
+                "2026-2-8".into(),
+                "m".into(),
+                session(&["第一问", "第二问"]),
+            )
+            .expect("重复写入失败");
+        assert!(!unchanged, "会话内容未变化时不应写盘");
+
+        let on_disk = OrderTui::read_history_file(&path).expect("读取历史文件失败");
+        assert_eq!(on_disk.records.len(), 1);
+        assert_eq!(on_disk.records[0].history[0].conversations.len(), 2);
+
+        fs::write(&path, "[]\n").expect("模拟其他进程改写失败");
+        assert!(
+            store.refresh_if_stale(&path).expect("检查缓存失败"),
+            "磁盘文件被外部改写后应重新读取"
+        );
+        assert!(store.file.records.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn history_temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!(
+            "order-history-{label}-{}-{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).expect("创建临时目录失败");
+        dir
+    }
+
+    #[test]
+    fn resolve_history_location_should_follow_precedence() {
+        let cwd = history_temp_dir("precedence");
+        let project_path = cwd.join(".order").join("history").join("History.json");
+
+        assert_eq!(
+            resolve_history_location(&cwd, None, None).path,
+            cwd.join("History.json"),
+            "未配置时沿用运行目录下的旧位置"
+        );
+        assert_eq!(
+            resolve_history_location(&cwd, None, Some("1")).path,
+            project_path
+        );
+        assert_eq!(
+            resolve_history_location(&cwd, Some("logs/chat.json"), Some("1")).path,
+            cwd.join("logs").join("chat.json"),
+            "ORDER_HISTORY_PATH 优先级最高"
+        );
+        assert_eq!(
+            resolve_history_location(&cwd, Some("  "), None).path,
+            cwd.join("History.json"),
+            "空白路径视为未设置"
+        );
+
+        fs::create_dir_all(cwd.join(".order").join("history")).expect("创建项目历史目录失败");
+        assert_eq!(
+            resolve_history_location(&cwd, None, None).path,
+            project_path,
+            "项目历史目录已存在时自动启用"
+        );
+
+        let _ = fs::remove_dir_all(&cwd);
+    }
+
+    #[test]
+    fn resolve_history_location_should_migrate_legacy_file() {
+        let cwd = history_temp_dir("legacy");
+        let legacy = cwd.join("History.json");
+        fs::write(&legacy, "[]\n").expect("写入旧历史文件失败");
+
+        let location = resolve_history_location(&cwd, None, Some("on"));
+        assert_eq!(location.legacy.as_ref(), Some(&legacy));
+        let path = migrate_legacy_history(&location);
+        assert_eq!(path, location.path);
+        assert!(path.is_file(), "旧文件应被迁移到项目历史目录");
+        assert!(!legacy.exists());
+        assert_eq!(
+            resolve_history_location(&cwd, None, None).legacy,
+            None,
+            "迁移完成后不再回退到旧位置"
+        );
+
+        fs::write(&legacy, "[]\n").expect("写入旧历史文件失败");
+        assert_eq!(
+            resolve_history_location(&cwd, None, None).legacy,
+            None,
+            "新位置已有文件时不覆盖"
+        );
+        assert_eq!(
+            resolve_history_location(&cwd, Some("History.json"), None),
+            HistoryLocation {
+                path: legacy.clone(),
+                legacy: None,
+            },
+            "显式指向旧位置时无需迁移"
+        );
+
+        let _ = fs::remove_dir_all(&cwd);
+    }
 }
 
 impl Widget for &OrderTui<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        // 终端过小时对话视图与输入框都无法完整展示，只给出提示，等待窗口放大。
+        if check_size(area, MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT) == SizeCheck::TooSmall {
+            render_too_small(area, buf);
+            return;
+        }
         let input_height = self.input_state.required_height(area.width);
         let layout = Layout::vertical([Constraint::Min(0), Constraint::Length(input_height)]);
         let [main_area, input_area] = layout.areas(area);
@@ -3613,12 +6398,131 @@ impl Widget for &OrderTui<'_> {
                 "进行中({}s) {} {}",
                 elapsed, active.trace_id, progress
             ))
+        } else if self.startup_probe_running {
+            Some("正在探测 Codex 可用性，按 Esc / Ctrl+C 跳过".to_string())
+        } else if self.pending_chat_context.is_some() {
+            Some(
+                "已带入 editor 上下文，将随下一条消息发送（Esc 或 /context drop 丢弃）".to_string(),
+            )
         } else {
-            self.last_failure
-                .as_ref()
-                .map(|item| format!("最近失败: {} {}", item.trace_id, item.reason))
+            self.last_failure.as_ref().map(|item| {
+                format!(
+                    "最近失败: {} {}（/lasterror 查看详情）",
+                    item.trace_id, item.reason
+                )
+            })
         };
 
+        // 替换确认浮层优先渲染，逐文件展示待替换内容。
+        if let Some(confirm) = self.replace_confirm.as_ref() {
+            let confirm_block = Block::bordered()
+                .title(format!(" Replace {} ", confirm.progress_label()))
+                .title_bottom(Line::from(" y 替换  n 跳过  a 全部替换  Esc 取消 ").right_aligned())
+                .border_style(Style::default().fg(Color::DarkGray));
+            Paragraph::new(Text::from(confirm.lines()))
+                .block(confirm_block)
+                .wrap(ratatui::widgets::Wrap { trim: false })
+                .render(main_area, buf);
+
+            let mut widget = InputWidget::new(&self.input_state);
+            widget.set_context_remaining(self.context_remaining);
+            if let Some(ref message) = status_message {
+                widget.set_status_message(message.clone());
+            }
+            widget.clone().render(input_area, buf);
+            self.render_write_approval_prompt(main_area, buf);
+            return;
+        }
+
+        // 验证报告浮层优先渲染，覆盖整个主区域以便完整查看输出。
+        if let Some(view) = self.validation_view.as_ref() {
+            let report_block = Block::bordered()
+                .title(format!(" Validation {} ", view.trace_id))
+                .title_bottom(Line::from(" ↑/↓ 滚动  PgUp/PgDn 翻页  Esc 关闭 ").right_aligned())
+                .border_style(Style::default().fg(Color::DarkGray));
+            Paragraph::new(Text::from(view.lines.clone()))
+                .block(report_block)
+                .wrap(ratatui::widgets::Wrap { trim: false })
+                .scroll((view.scroll.min(u16::MAX as usize) as u16, 0))
+                .render(main_area, buf);
+
+            let mut widget = InputWidget::new(&self.input_state);
+            widget.set_context_remaining(self.context_remaining);
+            if let Some(ref message) = status_message {
+                widget.set_status_message(message.clone());
+            }
+            widget.clone().render(input_area, buf);
+            self.render_write_approval_prompt(main_area, buf);
+            return;
+        }
+
+        // 历史会话对比视图：左右两列各自滚动。
+        if let Some(compare) = self
+            .history_browser
+            .as_ref()
+            .and_then(|browser| browser.compare.as_ref())
+        {
+            let [left_area, right_area] =
+                Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .areas(main_area);
+            for (index, (column, column_area)) in compare
+                .columns
+                .iter()
+                .zip([left_area, right_area])
+                .enumerate()
+            {
+                let border_color = if index == compare.focused {
+                    Color::Cyan
+                } else {
+                    Color::DarkGray
+                };
+                let column_block = Block::bordered()
+                    .title(format!(" {} ", column.title))
+                    .title_bottom(
+                        Line::from(format!(
+                            " {}/{} ",
+                            (column.scroll + 1).min(column.lines.len()),
+                            column.lines.len()
+                        ))
+                        .right_aligned(),
+                    )
+                    .border_style(Style::default().fg(border_color));
+                let column_inner = column_block.inner(column_area);
+                column_block.render(column_area, buf);
+                if column_inner.width == 0 || column_inner.height == 0 {
+                    continue;
+                }
+                // 滚动以原始行为单位，从偏移处开始按列宽换行，直到填满该列。
+                let max_visible = column_inner.height as usize;
+                let mut lines = Vec::new();
+                for raw in column.lines.iter().skip(column.scroll) {
+                    if raw.is_empty() {
+                        lines.push(Line::from(""));
+                    } else {
+                        lines.extend(
+                            OrderTui::wrap_message(raw, column_inner.width as usize)
+                                .into_iter()
+                                .map(Line::from),
+                        );
+                    }
+                    if lines.len() >= max_visible {
+                        break;
+                    }
+                }
+                lines.truncate(max_visible);
+                Paragraph::new(Text::from(lines)).render(column_inner, buf);
+            }
+
+            let mut widget = InputWidget::new(&self.input_state);
+            widget.set_context_remaining(self.context_remaining);
+            widget.set_status_message(
+                "对比：Tab/←/→ 切换列，↑/↓ PgUp/PgDn 滚动，Esc 返回列表".to_string(),
+            );
+            widget.clone().render(input_area, buf);
+            self.render_write_approval_prompt(main_area, buf);
+            return;
+        }
+
         // 历史选择界面优先渲染。
         if self.history_browser.is_some() {
             let history_block = Block::bordered()
@@ -3641,6 +6545,9 @@ impl Widget for &OrderTui<'_> {
             if let Some(ref message) = status_message {
                 widget.set_status_message(message.clone());
             }
+            if let Some(label) = self.status_clock_label(input_area.width) {
+                widget.set_clock_label(label);
+            }
             widget.clone().render(input_area, buf);
             self.render_write_approval_prompt(main_area, buf);
             return;
@@ -3687,19 +6594,41 @@ impl Widget for &OrderTui<'_> {
             if let Some(ref message) = status_message {
                 widget.set_status_message(message.clone());
             }
+            if let Some(label) = self.status_clock_label(input_area.width) {
+                widget.set_clock_label(label);
+            }
             widget.clone().render(input_area, buf);
             self.render_write_approval_prompt(main_area, buf);
             return;
         }
 
+        let onboarding_lines = if self.show_onboarding {
+            onboarding_banner_lines()
+        } else {
+            Vec::new()
+        };
+        // 引导关闭时高度为 0，欢迎页其余布局保持不变。
+        let onboarding_height = if onboarding_lines.is_empty() {
+            0
+        } else {
+            onboarding_lines.len() as u16 + 3
+        };
         let main_layout = Layout::vertical([
             Constraint::Length(1),
             Constraint::Length(1),
+            Constraint::Length(onboarding_height),
             Constraint::Length(3),
             Constraint::Length(1),
             Constraint::Min(0),
         ]);
-        let [welcome_area, _, model_area, _, commands_area] = main_layout.areas(main_area);
+        let [
+            welcome_area,
+            _,
+            onboarding_area,
+            model_area,
+            _,
+            commands_area,
+        ] = main_layout.areas(main_area);
 
         let welcome_text = Text::from(vec![Line::from(vec![Span::styled(
             format!("Welcome to Order   Version {}", env!("CARGO_PKG_VERSION")),
@@ -3709,6 +6638,14 @@ impl Widget for &OrderTui<'_> {
         )])]);
         Paragraph::new(welcome_text).render(welcome_area, buf);
 
+        if !onboarding_lines.is_empty() {
+            let [banner_area, _] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                .areas(onboarding_area);
+            Paragraph::new(Text::from(onboarding_lines))
+                .block(Block::bordered().border_style(Style::default().fg(Color::Yellow)))
+                .render(banner_area, buf);
+        }
+
         let model_label = if let Ok(Some(model_info)) = get_current_model_info() {
             // 显示 provider + model，便于用户快速确认当前走的是哪条连接链路。
             format!("{}/{}", model_info.provider_name, model_info.model_name)
@@ -3744,6 +6681,38 @@ impl Widget for &OrderTui<'_> {
             ("/rules", "Edit project rules"),
             ("/settings", "Configure settings"),
             ("/status", "Check system status"),
+            (
+                "/validation",
+                "Show validation report by trace_id (or latest); /validation retry [trace_id]",
+            ),
+            (
+                "/context",
+                "Drop the editor context waiting to be sent; /context drop",
+            ),
+            (
+                "/validate",
+                "Run deferred validation by trace_id (or latest)",
+            ),
+            (
+                "/replace",
+                "Project-wide replace; usage: /replace <pattern> <replacement> (quote to include spaces)",
+            ),
+            (
+                "/replay",
+                "Show tool calls of a trace (or latest); /replay [trace_id] run",
+            ),
+            (
+                "/timestamps",
+                "Toggle message timestamps; /timestamps on|off",
+            ),
+            (
+                "/whitespace",
+                "Show spaces/tabs in code blocks; /whitespace on|off",
+            ),
+            (
+                "/lasterror",
+                "Show the full error and trace_id of the latest failure",
+            ),
             (
                 "/capability",
                 "Capability cache reset; usage: /capability reset ...",
@@ -3770,7 +6739,12 @@ impl Widget for &OrderTui<'_> {
         if let Some(ref message) = status_message {
             widget.set_status_message(message.clone());
         }
+        if let Some(label) = self.status_clock_label(input_area.width) {
+            widget.set_clock_label(label);
+        }
         widget.clone().render(input_area, buf);
         self.render_write_approval_prompt(main_area, buf);
+        self.render_paste_confirm_prompt(main_area, buf);
+        self.render_validation_confirm_prompt(main_area, buf);
     }
 }
@@ -0,0 +1,125 @@
+/// 确认写入后的验证时机。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// 确认写入后立即运行验证（默认）。
+    #[default]
+    Auto,
+    /// 确认写入后先询问是否运行，选择稍后时可用 `/validate` 补跑。
+    Ask,
+    /// 不自动运行，只记录待验证的 trace，由 `/validate` 手动触发。
+    Manual,
+}
+
+/// 确认写入后对验证采取的动作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationDecision {
+    /// 立即运行。
+    Run,
+    /// 暂缓并弹出询问。
+    Prompt,
+    /// 暂缓，等待 `/validate`。
+    Defer,
+}
+
+impl ValidationMode {
+    /// 从 `ORDER_VALIDATION_MODE` 的取值解析模式。
+    ///
+    /// 未设置或无法识别时回退 `Auto`，保持确认写入后自动验证的既有行为。
+    pub fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|value| value.trim().to_ascii_lowercase()) {
+            Some(value) if value == "ask" => Self::Ask,
+            Some(value) if value == "manual" || value == "off" => Self::Manual,
+            _ => Self::Auto,
+        }
+    }
+
+    /// 确认写入后应采取的动作。
+    pub fn decision(self) -> ValidationDecision {
+        match self {
+            Self::Auto => ValidationDecision::Run,
+            Self::Ask => ValidationDecision::Prompt,
+            Self::Manual => ValidationDecision::Defer,
+        }
+    }
+}
+
+/// 已确认写入、但尚未运行验证的 trace 列表，按确认顺序排列。
+#[derive(Debug, Clone, Default)]
+pub struct DeferredValidations {
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl DeferredValidations {
+    /// 记录一次待验证的写入；同一 trace 重复记录时以最新一次为准并移到末尾。
+    pub fn push(&mut self, trace_id: &str, files: Vec<String>) {
+        self.entries.retain(|(id, _)| id != trace_id);
+        self.entries.push((trace_id.to_string(), files));
+    }
+
+    /// 取出指定 trace（未指定时取最近一次）的待验证记录。
+    pub fn take(&mut self, trace_id: Option<&str>) -> Option<(String, Vec<String>)> {
+        let index = match trace_id {
+            Some(trace_id) => self.entries.iter().position(|(id, _)| id == trace_id)?,
+            None => self.entries.len().checked_sub(1)?,
+        };
+        Some(self.entries.remove(index))
+    }
+
+    /// 是否存在指定 trace 的待验证记录。
+    pub fn contains(&self, trace_id: &str) -> bool {
+        self.entries.iter().any(|(id, _)| id == trace_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeferredValidations, ValidationDecision, ValidationMode};
+
+    #[test]
+    fn test_validation_mode_decision() {
+        assert_eq!(ValidationMode::from_env_value(None), ValidationMode::Auto);
+        assert_eq!(
+            ValidationMode::from_env_value(Some(" ASK ")),
+            ValidationMode::Ask
+        );
+        assert_eq!(
+            ValidationMode::from_env_value(Some("manual")),
+            ValidationMode::Manual
+        );
+        assert_eq!(
+            ValidationMode::from_env_value(Some("typo")),
+            ValidationMode::Auto,
+            "无法识别时保持自动验证"
+        );
+
+        assert_eq!(ValidationMode::Auto.decision(), ValidationDecision::Run);
+        assert_eq!(ValidationMode::Ask.decision(), ValidationDecision::Prompt);
+        assert_eq!(ValidationMode::Manual.decision(), ValidationDecision::Defer);
+    }
+
+    #[test]
+    fn test_deferred_validations_take_by_trace_or_latest() {
+        let mut deferred = DeferredValidations::default();
+        assert!(deferred.take(None).is_none());
+
+        deferred.push("t1", vec!["crates/core/src/lib.rs".to_string()]);
+        deferred.push("t2", vec!["README.md".to_string()]);
+        deferred.push("t1", vec!["crates/rander/src/tui.rs".to_string()]);
+        assert!(deferred.contains("t2"));
+
+        assert_eq!(
+            deferred.take(None),
+            Some((
+                "t1".to_string(),
+                vec!["crates/rander/src/tui.rs".to_string()]
+            )),
+            "重复记录的 trace 以最新一次为准"
+        );
+        assert!(deferred.take(Some("missing")).is_none());
+        assert_eq!(
+            deferred.take(Some("t2")).map(|(id, _)| id),
+            Some("t2".to_string())
+        );
+        assert!(deferred.take(None).is_none(), "取出后不应再次触发");
+    }
+}
@@ -0,0 +1,234 @@
+use core::validation::{CommandReport, ValidationReport};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// 每条命令 stdout/stderr 在报告视图中最多展示的末尾行数。
+///
+/// 报告本身已截断到末尾 4000 字符，这里再按行收紧，
+/// 让多条命令的结果能在一屏内大致浏览，失败原因通常也集中在末尾。
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// `/validation` 报告浮层状态。
+#[derive(Debug, Clone)]
+pub struct ValidationViewState {
+    /// 报告对应的 trace_id，用于浮层标题。
+    pub trace_id: String,
+    /// 预先格式化好的报告行。
+    pub lines: Vec<Line<'static>>,
+    /// 当前滚动偏移（行）。
+    pub scroll: usize,
+}
+
+impl ValidationViewState {
+    /// 基于报告构建浮层状态。
+    pub fn new(report: &ValidationReport) -> Self {
+        Self {
+            trace_id: report.trace_id.clone(),
+            lines: format_validation_report(report),
+            scroll: 0,
+        }
+    }
+
+    /// 按行滚动，`delta` 为负表示向上；滚动范围限制在报告行数内。
+    pub fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.lines.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+}
+
+/// 将验证报告格式化为逐命令的展示行。
+///
+/// 结构：概要（结果/耗时/失败命令/建议） -> 按阶段列出命令 ->
+/// 每条命令的退出码、耗时与输出末尾，便于直接在 TUI 中定位失败原因。
+pub fn format_validation_report(report: &ValidationReport) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let (result_text, result_color) = if report.ok {
+        ("通过", Color::Green)
+    } else {
+        ("失败", Color::Red)
+    };
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!("trace_id={} ", report.trace_id),
+            Style::default().fg(Color::Gray),
+        ),
+        Span::styled(
+            format!("结果：{result_text}"),
+            Style::default()
+                .fg(result_color)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!("  总耗时 {}ms", report.duration_ms),
+            Style::default().fg(Color::Gray),
+        ),
+    ]));
+    if let Some(command) = report.failed_command.as_deref() {
+        lines.push(Line::from(Span::styled(
+            format!("失败命令：`{command}`"),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    if let Some(suggestion) = report.suggestion.as_deref() {
+        lines.push(Line::from(Span::styled(
+            format!("建议：{suggestion}"),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+
+    for stage in &report.stages {
+        lines.push(Line::default());
+        lines.push(Line::from(Span::styled(
+            format!("[{}] {} 条命令", stage.name, stage.commands.len()),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for command in &stage.commands {
+            push_command_lines(&mut lines, command);
+        }
+    }
+
+    lines
+}
+
+// 追加单条命令的状态行与输出末尾。
+fn push_command_lines(lines: &mut Vec<Line<'static>>, command: &CommandReport) {
+    let (mark, color) = if command.ok {
+        ("✓", Color::Green)
+    } else {
+        ("✗", Color::Red)
+    };
+    let exit_code = command
+        .exit_code
+        .map(|code| code.to_string())
+        .unwrap_or_else(|| "无（被信号终止）".to_string());
+    lines.push(Line::from(vec![
+        Span::styled(format!("  {mark} "), Style::default().fg(color)),
+        Span::styled(command.command.clone(), Style::default().fg(Color::White)),
+        Span::styled(
+            format!("  exit={exit_code}  {}ms", command.duration_ms),
+            Style::default().fg(Color::Gray),
+        ),
+    ]));
+
+    for (label, text) in [
+        ("stdout", command.stdout_tail.as_str()),
+        ("stderr", command.stderr_tail.as_str()),
+    ] {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let output_lines = text.lines().collect::<Vec<_>>();
+        let skipped = output_lines.len().saturating_sub(OUTPUT_TAIL_LINES);
+        let header = if skipped > 0 {
+            format!("    {label}（省略前 {skipped} 行）：")
+        } else {
+            format!("    {label}：")
+        };
+        lines.push(Line::from(Span::styled(
+            header,
+            Style::default().fg(Color::DarkGray),
+        )));
+        for output_line in &output_lines[skipped..] {
+            lines.push(Line::from(Span::styled(
+                format!("      {output_line}"),
+                Style::default().fg(Color::Gray),
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::validation::{CommandReport, StageReport, ValidationReport};
+
+    use super::{OUTPUT_TAIL_LINES, ValidationViewState, format_validation_report};
+
+    fn command(command: &str, ok: bool, stdout: &str, stderr: &str) -> CommandReport {
+        CommandReport {
+            command: command.to_string(),
+            ok,
+            exit_code: Some(if ok { 0 } else { 101 }),
+            duration_ms: 1200,
+            stdout_tail: stdout.to_string(),
+            stderr_tail: stderr.to_string(),
+        }
+    }
+
+    fn to_plain_lines(report: &ValidationReport) -> Vec<String> {
+        format_validation_report(report)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn format_validation_report_should_list_each_command_with_status() {
+        let long_stderr = (1..=OUTPUT_TAIL_LINES + 5)
+            .map(|index| format!("error line {index}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let report = ValidationReport {
+            trace_id: "trace-1".to_string(),
+            started_at_unix_ms: 0,
+            duration_ms: 3400,
+            ok: false,
+            stages: vec![StageReport {
+                name: "minimal".to_string(),
+                commands: vec![
+                    command("cargo test -p core", true, "test result: ok", ""),
+                    command("cargo test -p rander", false, "", &long_stderr),
+                ],
+            }],
+            failed_command: Some("cargo test -p rander".to_string()),
+            suggestion: Some("请复现失败命令".to_string()),
+            plan: Vec::new(),
+        };
+
+        let lines = to_plain_lines(&report);
+        assert_eq!(lines[0], "trace_id=trace-1 结果：失败  总耗时 3400ms");
+        assert_eq!(lines[1], "失败命令：`cargo test -p rander`");
+        assert!(lines.contains(&"[minimal] 2 条命令".to_string()));
+        assert!(lines.contains(&"  ✓ cargo test -p core  exit=0  1200ms".to_string()));
+        assert!(lines.contains(&"  ✗ cargo test -p rander  exit=101  1200ms".to_string()));
+        assert!(lines.contains(&"      test result: ok".to_string()));
+        // 空 stdout 不展示；过长 stderr 只保留末尾并提示省略行数。
+        assert_eq!(
+            lines.iter().filter(|line| line.contains("stdout")).count(),
+            1
+        );
+        assert!(lines.contains(&"    stderr（省略前 5 行）：".to_string()));
+        assert!(!lines.contains(&"      error line 5".to_string()));
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some("      error line 25")
+        );
+    }
+
+    #[test]
+    fn validation_view_scroll_should_stay_within_report() {
+        let report = ValidationReport {
+            trace_id: "trace-2".to_string(),
+            started_at_unix_ms: 0,
+            duration_ms: 1,
+            ok: true,
+            stages: Vec::new(),
+            failed_command: None,
+            suggestion: Some("验证通过".to_string()),
+            plan: Vec::new(),
+        };
+        let mut view = ValidationViewState::new(&report);
+        view.scroll_by(-3);
+        assert_eq!(view.scroll, 0);
+        view.scroll_by(10);
+        assert_eq!(view.scroll, view.lines.len() - 1);
+    }
+}
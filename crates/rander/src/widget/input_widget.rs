@@ -22,6 +22,38 @@ pub const AVAILABLE_COMMANDS: &[(&str, &str)] = &[
     ("/rules", "Edit project rules"),
     ("/settings", "Configure settings"),
     ("/status", "Check system status"),
+    (
+        "/validation",
+        "Show validation report by trace_id (or latest)",
+    ),
+    (
+        "/context",
+        "Drop the editor context waiting to be sent; /context drop",
+    ),
+    (
+        "/validate",
+        "Run deferred validation by trace_id (or latest)",
+    ),
+    (
+        "/replace",
+        "Project-wide replace; usage: /replace <pattern> <replacement> (quote to include spaces)",
+    ),
+    (
+        "/replay",
+        "Show tool calls of a trace (or latest); /replay [trace_id] run",
+    ),
+    (
+        "/timestamps",
+        "Toggle message timestamps; /timestamps on|off",
+    ),
+    (
+        "/whitespace",
+        "Show spaces/tabs in code blocks; /whitespace on|off",
+    ),
+    (
+        "/lasterror",
+        "Show the full error and trace_id of the latest failure",
+    ),
     (
         "/capability",
         "Capability cache reset; usage: /capability reset ...",
@@ -52,6 +84,8 @@ pub struct InputState {
     pub filtered_commands: Vec<(String, String)>,
     /// 补全列表的滚动偏移量（可见区域的起始索引）。
     pub completion_scroll_offset: usize,
+    /// 开头的 `/` 是否经 `Ctrl+V` 字面输入：不弹出命令补全，提交时按普通消息发送。
+    pub literal_slash: bool,
 }
 
 impl Default for InputState {
@@ -64,6 +98,7 @@ impl Default for InputState {
             completion_selected: 0,
             filtered_commands: Vec::new(),
             completion_scroll_offset: 0,
+            literal_slash: false,
         }
     }
 }
@@ -89,7 +124,16 @@ impl InputState {
     ///
     /// 根据当前输入过滤可用的命令，并在输入以 '/' 开头时显示补全弹窗。
     fn update_completion(&mut self) {
-        if self.input.starts_with('/') && self.input.len() > 1 {
+        // 字面输入的 `/` 被删掉后，重新输入的 `/` 恢复命令语义。
+        if !self.input.starts_with('/') {
+            self.literal_slash = false;
+        }
+        if self.literal_slash {
+            self.show_completion = false;
+            self.filtered_commands.clear();
+            self.completion_selected = 0;
+            self.completion_scroll_offset = 0;
+        } else if self.input.starts_with('/') && self.input.len() > 1 {
             let filter = &self.input[1..].to_lowercase();
             self.filtered_commands = AVAILABLE_COMMANDS
                 .iter()
@@ -129,6 +173,24 @@ impl InputState {
         self.update_completion();
     }
 
+    /// 按字面插入一个字符（`Ctrl+V` 之后的按键）。
+    ///
+    /// 与 [`InputState::insert_char`] 的区别是：插在开头的 `/` 不会被当作命令前缀。
+    pub fn insert_char_literal(&mut self, new_char: char) {
+        if new_char == '/' && self.cursor_position == 0 {
+            self.literal_slash = true;
+        }
+        self.insert_char(new_char);
+    }
+
+    /// 在当前光标位置插入一段文本（用于粘贴），光标移到插入内容之后。
+    pub fn insert_str(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.input.insert_str(index, text);
+        self.cursor_position = self.clamp_cursor(self.cursor_position + text.chars().count());
+        self.update_completion();
+    }
+
     /// 计算当前光标位置的字节索引。
     ///
     /// Rust 字符串是 UTF-8 编码的，所以字符索引 != 字节索引。
@@ -183,8 +245,19 @@ impl InputState {
         self.cursor_visible = visible;
     }
 
+    /// 用给定文本替换输入内容，并把光标移到末尾。
+    ///
+    /// 用于从 editor 等其它界面预填充输入框，用户仍可在发送前继续编辑。
+    pub fn set_input(&mut self, text: &str) {
+        self.literal_slash = false;
+        self.input = text.to_string();
+        self.cursor_position = self.input.chars().count();
+        self.update_completion();
+    }
+
     /// 清除输入文本并将光标位置重置为 0。
     pub fn clear(&mut self) {
+        self.literal_slash = false;
         self.input.clear();
         self.cursor_position = 0;
         self.show_completion = false;
@@ -276,22 +349,31 @@ impl InputState {
         }
 
         let prompt_width = 4; // ">>> "
-        let input_width: usize = self
-            .input
-            .chars()
-            .map(|c| if c.is_ascii() { 1 } else { 2 })
-            .sum();
         let cursor_extra_width = if self.cursor_position >= self.input.chars().count() {
             1
         } else {
             0
         };
-        let total_width = prompt_width + input_width + cursor_extra_width;
 
-        let lines = total_width.div_ceil(available_width as usize);
-        let lines = lines.max(1) as u16;
+        // 换行符把输入分成多个逻辑行，每行再按可用宽度折行。
+        let logical_lines: Vec<&str> = self.input.split('\n').collect();
+        let last_index = logical_lines.len() - 1;
+        let lines: usize = logical_lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let mut width: usize = line.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+                if index == 0 {
+                    width += prompt_width;
+                }
+                if index == last_index {
+                    width += cursor_extra_width;
+                }
+                width.div_ceil(available_width as usize).max(1)
+            })
+            .sum();
 
-        lines + 3
+        lines as u16 + 3
     }
 
     /// 计算补全弹窗的高度。
@@ -346,6 +428,7 @@ pub struct InputWidget<'a> {
     state: &'a InputState,
     context_remaining: Option<u32>,
     status_message: Option<String>,
+    clock_label: Option<String>,
 }
 
 impl<'a> InputWidget<'a> {
@@ -355,6 +438,7 @@ impl<'a> InputWidget<'a> {
             state,
             context_remaining: None,
             status_message: None,
+            clock_label: None,
         }
     }
 
@@ -370,6 +454,12 @@ impl<'a> InputWidget<'a> {
         self
     }
 
+    /// 设置时钟/会话时长标签（显示在输入框底部边框右侧）。
+    pub fn set_clock_label(&mut self, label: impl Into<String>) -> &mut Self {
+        self.clock_label = Some(label.into());
+        self
+    }
+
     /// 计算补全弹窗的布局区域。
     ///
     /// 弹窗显示在输入框上方，宽度根据内容自适应。
@@ -425,6 +515,17 @@ impl<'a> Widget for InputWidget<'a> {
             );
         }
 
+        if let Some(label) = self.clock_label.as_deref() {
+            // 放在底部边框：顶部左右两侧已被状态与上下文占用，底部不会与其重叠。
+            input_block = input_block.title_bottom(
+                Line::from(vec![Span::styled(
+                    format!(" {label} "),
+                    Style::default().fg(Color::DarkGray),
+                )])
+                .right_aligned(),
+            );
+        }
+
         let mut input_spans = vec![Span::styled(
             ">>> ",
             Style::default().fg(Color::Green).bold(),
@@ -437,7 +538,13 @@ impl<'a> Widget for InputWidget<'a> {
 
         if self.state.cursor_visible {
             let (cursor_char, right_rest) = if let Some(c) = right.chars().next() {
-                (c.to_string(), &right[c.len_utf8()..])
+                // 光标停在换行符上时先画一个空格作为光标块，再换行。
+                let shown = if c == '\n' {
+                    " \n".to_string()
+                } else {
+                    c.to_string()
+                };
+                (shown, &right[c.len_utf8()..])
             } else {
                 (" ".to_string(), "")
             };
@@ -450,7 +557,7 @@ impl<'a> Widget for InputWidget<'a> {
             input_spans.push(Span::raw(right));
         }
 
-        let input_text = Line::from(input_spans);
+        let input_text = split_spans_at_newlines(input_spans);
 
         Paragraph::new(input_text)
             .block(input_block)
@@ -567,3 +674,23 @@ impl<'a> InputWidget<'a> {
         }
     }
 }
+
+/// 按换行符把输入区的 span 拆成多行，保留各段原有样式。
+fn split_spans_at_newlines(spans: Vec<Span<'_>>) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut current = Vec::new();
+    for span in spans {
+        let style = span.style;
+        let mut parts = span.content.split('\n').peekable();
+        while let Some(part) = parts.next() {
+            if !part.is_empty() {
+                current.push(Span::styled(part.to_string(), style));
+            }
+            if parts.peek().is_some() {
+                lines.push(Line::from(std::mem::take(&mut current)));
+            }
+        }
+    }
+    lines.push(Line::from(current));
+    lines
+}
@@ -0,0 +1,209 @@
+use std::path::{Component, Path};
+
+/// 写入目标路径的分类结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePathClass {
+    /// 位于工作区内的普通文件，可按策略自动确认。
+    InWorkspace,
+    /// 位于工作区之外（绝对路径或通过 `..` 越界），必须人工确认。
+    OutsideWorkspace,
+    /// 命中敏感文件名单（如 `.env`、`secrets.*`），必须人工确认。
+    Sensitive,
+}
+
+/// 会话写入确认策略。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteApprovalPolicy {
+    /// 每次写入都弹出确认菜单（默认）。
+    #[default]
+    Manual,
+    /// 工作区内的非敏感文件自动确认，其余仍需确认。
+    Workspace,
+}
+
+impl WriteApprovalPolicy {
+    /// 从 `ORDER_WRITE_APPROVAL_POLICY` 的取值解析策略。
+    ///
+    /// 未设置或无法识别时回退 `Manual`：写入确认是安全边界，
+    /// 拼写错误不应意外放宽为自动确认。
+    pub fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|value| value.trim().to_ascii_lowercase()) {
+            Some(value) if value == "workspace" => Self::Workspace,
+            _ => Self::Manual,
+        }
+    }
+
+    /// 返回策略在状态面板中的展示文本。
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Manual => "手动确认",
+            Self::Workspace => "工作区内自动确认（敏感文件与工作区外仍需确认）",
+        }
+    }
+}
+
+/// 默认敏感文件名单（按文件名匹配，支持 `*` 通配，大小写不敏感）。
+///
+/// 这些文件通常保存密钥或凭据，即使位于工作区内也应由用户亲自确认。
+const SENSITIVE_FILE_PATTERNS: [&str; 8] = [
+    ".env",
+    ".env.*",
+    "secrets.*",
+    "secret.*",
+    "*.pem",
+    "*.key",
+    "id_rsa*",
+    "id_ed25519*",
+];
+
+/// 对写入目标路径（工作区相对路径）进行分类。
+///
+/// 先判断是否越界再判断敏感名单：越界写入无论文件名如何都必须确认。
+/// `.git` 与 `.order` 目录下的文件也视为敏感：前者是仓库元数据，
+/// 后者保存模型凭据与验证、格式化等会被执行的命令配置。
+pub fn classify_write_path(path: &str) -> WritePathClass {
+    let path = Path::new(path.trim());
+    let mut depth: usize = 0;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return WritePathClass::OutsideWorkspace,
+            Component::ParentDir => {
+                if depth == 0 {
+                    return WritePathClass::OutsideWorkspace;
+                }
+                depth -= 1;
+            }
+            Component::Normal(name) => {
+                if name.eq_ignore_ascii_case(".git") || name.eq_ignore_ascii_case(".order") {
+                    return WritePathClass::Sensitive;
+                }
+                depth += 1;
+            }
+            Component::CurDir => {}
+        }
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if SENSITIVE_FILE_PATTERNS
+        .iter()
+        .any(|pattern| wildcard_match(pattern, &file_name))
+    {
+        return WritePathClass::Sensitive;
+    }
+
+    WritePathClass::InWorkspace
+}
+
+/// 筛选出在当前策略下仍需人工确认的路径。
+///
+/// 返回值为空表示整批写入都可以自动确认。
+pub fn paths_requiring_confirmation<'a>(
+    policy: WriteApprovalPolicy,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let paths = paths.into_iter();
+    match policy {
+        WriteApprovalPolicy::Manual => paths.collect(),
+        WriteApprovalPolicy::Workspace => paths
+            .filter(|path| classify_write_path(path) != WritePathClass::InWorkspace)
+            .collect(),
+    }
+}
+
+// 仅支持 `*` 的简单通配匹配。
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(remaining) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=remaining.len())
+                .filter(|index| remaining.is_char_boundary(*index))
+                .any(|index| wildcard_match(rest, &remaining[index..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        WriteApprovalPolicy, WritePathClass, classify_write_path, paths_requiring_confirmation,
+    };
+
+    #[test]
+    fn classify_write_path_should_accept_regular_workspace_files() {
+        for path in ["src/main.rs", "./README.md", "crates/a/../b/lib.rs"] {
+            assert_eq!(
+                classify_write_path(path),
+                WritePathClass::InWorkspace,
+                "{path} 应视为工作区内文件"
+            );
+        }
+    }
+
+    #[test]
+    fn classify_write_path_should_reject_paths_outside_workspace() {
+        for path in ["../other/main.rs", "src/../../etc/passwd", "/etc/hosts"] {
+            assert_eq!(
+                classify_write_path(path),
+                WritePathClass::OutsideWorkspace,
+                "{path} 应视为工作区外路径"
+            );
+        }
+    }
+
+    #[test]
+    fn classify_write_path_should_flag_sensitive_files() {
+        for path in [
+            ".env",
+            "config/.env.local",
+            "Secrets.toml",
+            "deploy/server.pem",
+            ".ssh/id_rsa.pub",
+            ".git/config",
+            ".order/validation.toml",
+            ".order/model.json",
+            "./.order/reports/run.json",
+        ] {
+            assert_eq!(
+                classify_write_path(path),
+                WritePathClass::Sensitive,
+                "{path} 应视为敏感文件"
+            );
+        }
+        assert_eq!(
+            classify_write_path("src/environment.rs"),
+            WritePathClass::InWorkspace,
+            "仅名称相近的普通文件不应误判"
+        );
+    }
+
+    #[test]
+    fn paths_requiring_confirmation_should_follow_policy() {
+        let paths = ["src/lib.rs", ".env", "../x.rs"];
+        assert_eq!(
+            paths_requiring_confirmation(WriteApprovalPolicy::Manual, paths),
+            vec!["src/lib.rs", ".env", "../x.rs"]
+        );
+        assert_eq!(
+            paths_requiring_confirmation(WriteApprovalPolicy::Workspace, paths),
+            vec![".env", "../x.rs"]
+        );
+        assert_eq!(
+            WriteApprovalPolicy::from_env_value(Some(" Workspace ")),
+            WriteApprovalPolicy::Workspace
+        );
+        assert_eq!(
+            WriteApprovalPolicy::from_env_value(Some("typo")),
+            WriteApprovalPolicy::Manual
+        );
+    }
+}